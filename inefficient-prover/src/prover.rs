@@ -2,10 +2,12 @@ use ark_bls12_381::{Fr, G1Affine, G1Projective, G2Affine, G2Projective, Bls12_38
 use ark_ec::{CurveGroup, VariableBaseMSM, AffineRepr, pairing::Pairing};
 use ark_ff::{UniformRand, Zero, One, PrimeField};
 use ark_poly::{EvaluationDomain, Radix2EvaluationDomain, univariate::DensePolynomial, Polynomial, DenseUVPolynomial};
+use ark_std::rand::{rngs::StdRng, Rng, SeedableRng};
 use ark_std::test_rng;
-use ark_serialize::CanonicalSerialize;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
 use rayon::prelude::*;
 use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
 use std::time::Instant;
 
 /// n = 2^17 as specified for production
@@ -54,22 +56,54 @@ pub struct Setup {
 
 impl Setup {
     pub fn new(config: Config) -> Self {
+        Self::build(config, &mut test_rng(), |_, two_n| {
+            // Random polynomial evaluations, generated in parallel
+            (0..two_n)
+                .into_par_iter()
+                .map(|_| {
+                    let mut local_rng = test_rng();
+                    Fr::rand(&mut local_rng)
+                })
+                .collect()
+        })
+    }
+
+    /// Setup drawing τ, the generators and the c_i from `rng`, so a run can
+    /// be reproduced from its seed
+    ///
+    /// The c_i are still generated in parallel, each from its own RNG seeded
+    /// by one value drawn from `rng`.
+    pub fn from_rng<R: Rng>(config: Config, rng: &mut R) -> Self {
+        Self::build(config, rng, |rng, two_n| {
+            let seed: u64 = rng.gen();
+            (0..two_n)
+                .into_par_iter()
+                .map(|i| {
+                    let mut local_rng = StdRng::seed_from_u64(seed.wrapping_add(i as u64));
+                    Fr::rand(&mut local_rng)
+                })
+                .collect()
+        })
+    }
+
+    /// Setup with τ and the generators drawn from `rng`, in that order, and
+    /// the 2n c_i from `c_eval`
+    fn build<R: Rng>(config: Config, rng: &mut R, c_eval: impl FnOnce(&mut R, usize) -> Vec<Fr>) -> Self {
         println!("Starting setup phase for n = 2^{}...", config.log_n);
         let start = Instant::now();
         
-        let mut rng = test_rng();
         let two_n = config.two_n();
         
         // 1. Generate random τ ∈ Fr
-        let tau = Fr::rand(&mut rng);
+        let tau = Fr::rand(rng);
         
         // 2. Generate powers of τ efficiently using parallel windowing
         println!("Computing powers of τ...");
         let tau_powers = Self::compute_powers_parallel(tau, two_n);
         
         // 3. Generate random G ∈ G1 and H ∈ G2
-        let g1 = G1Projective::rand(&mut rng);
-        let g2 = G2Projective::rand(&mut rng);
+        let g1 = G1Projective::rand(rng);
+        let g2 = G2Projective::rand(rng);
         
         // 4. Compute SRS in monomial basis using parallel scalar multiplication
         println!("Computing SRS in monomial basis...");
@@ -89,14 +123,8 @@ impl Setup {
         let domain = Radix2EvaluationDomain::<Fr>::new(two_n).unwrap();
         let srs_lagrange = Self::monomial_to_lagrange(&srs_monomial, &domain);
         
-        // 6. Generate random polynomial evaluations in parallel
-        let c_eval: Vec<Fr> = (0..two_n)
-            .into_par_iter()
-            .map(|_| {
-                let mut local_rng = test_rng();
-                Fr::rand(&mut local_rng)
-            })
-            .collect();
+        // 6. Generate random polynomial evaluations
+        let c_eval = c_eval(rng, two_n);
         
         // 7. Compute G2 elements for verification
         let tau_g2 = (g2 * tau).into_affine();
@@ -131,7 +159,7 @@ impl Setup {
         
         // For larger counts, use windowing approach
         let window_size = 256;
-        let num_windows = (count + window_size - 1) / window_size;
+        let num_windows = count.div_ceil(window_size);
         
         // Compute tau^window_size, tau^(2*window_size), etc.
         let tau_window = {
@@ -196,6 +224,25 @@ pub struct OpeningProof {
     pub proof: G1Affine,
 }
 
+impl OpeningProof {
+    /// Compressed encoding: point, evaluation, then the proof element
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.point.serialize_compressed(&mut writer)?;
+        self.evaluation.serialize_compressed(&mut writer)?;
+        self.proof.serialize_compressed(&mut writer)
+    }
+
+    /// Read a proof written by [`OpeningProof::write`], checking the point
+    /// is on the curve and in the right subgroup
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(OpeningProof {
+            point: Fr::deserialize_compressed(&mut reader)?,
+            evaluation: Fr::deserialize_compressed(&mut reader)?,
+            proof: G1Affine::deserialize_compressed(&mut reader)?,
+        })
+    }
+}
+
 /// Prover - generates witness and commitment
 pub struct Prover {
     setup: Setup,
@@ -205,17 +252,25 @@ impl Prover {
     pub fn new(setup: Setup) -> Self {
         Prover { setup }
     }
+
+    pub fn config(&self) -> &Config {
+        &self.setup.config
+    }
     
     pub fn prove(&self) -> (G1Affine, Vec<Fr>) {
+        self.prove_with_rng(&mut test_rng())
+    }
+
+    /// [`Prover::prove`] with the witness drawn from `rng`
+    pub fn prove_with_rng<R: Rng>(&self, rng: &mut R) -> (G1Affine, Vec<Fr>) {
         println!("Starting prover phase...");
         let start = Instant::now();
         
-        let mut rng = test_rng();
         let n = self.setup.config.n();
         let two_n = self.setup.config.two_n();
         
         // 1. Generate witness: random x_i ∈ Fr for i = 0, 1, ..., n-1
-        let x_values: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        let x_values: Vec<Fr> = (0..n).map(|_| Fr::rand(rng)).collect();
         
         // 2. Compute f_i = Hash(x_i)
         let f_values: Vec<Fr> = x_values
@@ -263,6 +318,28 @@ impl Prover {
         (commitment.into_affine(), hadamard_product)
     }
     
+    /// Commit to caller-supplied evaluations over the 2n-point domain
+    ///
+    /// `evals` is zero-padded to 2n; the padded vector is returned alongside
+    /// the commitment, as with [`Prover::prove`], for use with
+    /// [`Prover::create_opening_proof`]. Opening at the i-th domain element
+    /// yields `evals[i]`.
+    pub fn commit_evaluations(&self, evals: &[Fr]) -> (G1Affine, Vec<Fr>) {
+        let two_n = self.setup.config.two_n();
+        assert!(evals.len() <= two_n, "{} evaluations exceed the domain size {}", evals.len(), two_n);
+
+        let mut padded = evals.to_vec();
+        padded.resize(two_n, Fr::zero());
+
+        let srs_lagrange_affine: Vec<G1Affine> = self.setup.srs_lagrange_g1
+            .par_iter()
+            .map(|p| p.into_affine())
+            .collect();
+        let commitment = Self::efficient_msm(&srs_lagrange_affine, &padded);
+
+        (commitment.into_affine(), padded)
+    }
+
     /// Create an opening proof for a specific evaluation point
     pub fn create_opening_proof(
         &self,
@@ -323,6 +400,10 @@ impl Verifier {
     pub fn new(setup: Setup) -> Self {
         Verifier { setup }
     }
+
+    pub fn setup(&self) -> &Setup {
+        &self.setup
+    }
     
     /// Verify an opening proof using pairing check
     pub fn verify_opening(
@@ -62,4 +62,69 @@ fn test_production_size() {
     let config = Config::production();
     assert_eq!(config.n(), 1 << 17);
     assert_eq!(config.two_n(), 2 << 17);
-} 
\ No newline at end of file
+}
+
+#[test]
+fn test_commit_evaluations_opens_at_domain_points() {
+    let setup = Setup::new(Config { log_n: 4 });
+    let prover = Prover::new(setup.clone());
+    let values: Vec<Fr> = (1..=5u64).map(Fr::from).collect();
+    let (commitment, evals) = prover.commit_evaluations(&values);
+    assert_eq!(evals.len(), setup.config.two_n());
+
+    let domain = Radix2EvaluationDomain::<Fr>::new(setup.config.two_n()).unwrap();
+    let opening = prover.create_opening_proof(&evals, domain.element(3));
+    assert_eq!(opening.evaluation, Fr::from(4u64));
+
+    let verifier = Verifier::new(setup);
+    assert!(verifier.verify_opening(&commitment, &opening));
+}
+
+#[test]
+fn test_seeded_runs_are_reproducible() {
+    use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+    let run = |seed: u64| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let setup = Setup::from_rng(Config { log_n: 3 }, &mut rng);
+        Prover::new(setup).prove_with_rng(&mut rng).0
+    };
+    assert_eq!(run(7), run(7));
+    assert_ne!(run(7), run(8));
+}
+
+#[test]
+fn test_unseeded_runs_match_baseline() {
+    use ark_serialize::CanonicalSerialize;
+
+    // Outputs of `Setup::new` and `Prover::prove` for n = 2^3 from before the
+    // seeded variants existed; the unseeded paths must not change them
+    fn hex<T: CanonicalSerialize>(t: &T) -> String {
+        let mut bytes = Vec::new();
+        t.serialize_compressed(&mut bytes).unwrap();
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+    let setup = Setup::new(Config { log_n: 3 });
+    assert_eq!(hex(&setup.tau_g2), "aec75b7848db3eacb0068a38379fc73aaee4488f2a12207b70757a5d56567b18939e9a02961001e1ca277c9f84b7ee13193de85d4f349d23486b4c2037f4a04f37c5d07499a59f02b84205926ead8b0cb07433774f65e72233fc566196cbf918");
+    assert_eq!(hex(&setup.srs_monomial_g1[1]), "aa9cb862c3611b1188df5225d184a167fbb2b089f393fdd75f5320a75705be317b03773ee54f738cfa2cd19812135b29");
+    for c in &setup.c_eval {
+        assert_eq!(hex(c), "88c522e40e4d57abd3386ff6cb2c5496d767606488f3c9f9494cd363741d4e67");
+    }
+    let (commitment, evals) = Prover::new(setup).prove();
+    assert_eq!(hex(&commitment), "aba5c9fcd95a4ce3b167ef8c2571664a43b338cba67c161f558e01d3bca07707a0111e0a85611007c10eae0f6fbe0784");
+    assert_eq!(hex(&evals[3]), "58903ddac6a7360ef3a549fe96bdd43eaccccd97265bc6163f9381ba5b981f1d");
+}
+
+#[test]
+fn test_opening_proof_round_trip() {
+    let setup = Setup::new(Config { log_n: 2 });
+    let prover = Prover::new(setup.clone());
+    let (commitment, evals) = prover.commit_evaluations(&[Fr::from(3u64)]);
+    let proof = prover.create_opening_proof(&evals, Fr::from(9u64));
+
+    let mut bytes = Vec::new();
+    proof.write(&mut bytes).unwrap();
+    let read = OpeningProof::read(&bytes[..]).unwrap();
+    assert!(Verifier::new(setup).verify_opening(&commitment, &read));
+    assert!(OpeningProof::read(&bytes[..bytes.len() - 1]).is_err());
+}
@@ -0,0 +1,191 @@
+//! WebAssembly bindings for the modulo machine, for browser demos and
+//! JavaScript test tooling.
+//!
+//! GMP does not compile to `wasm32`, so this crate wraps
+//! [`BackendMachine`] on the `modulo-machine` crate's pure-Rust
+//! [`NumBigint`] backend: the machine's default configuration (async reset to
+//! zero, `x mod P` latched on each rising edge). Tests cross-check it against
+//! the GMP-backed `ModuloMachine`.
+//!
+//! Operands and outputs cross the JavaScript boundary as hex strings, with an
+//! optional `0x` prefix on input and always `0x`-prefixed on output. Operands
+//! wider than the 300-bit bus are rejected rather than reduced. Build
+//! with `wasm-pack build --target web modulo-machine-wasm`:
+//!
+//! ```js
+//! import init, { ModuloMachine } from "./pkg/modulo_machine_wasm.js";
+//! await init();
+//! const m = new ModuloMachine();
+//! m.tick(false, false, "0x1f");
+//! m.tick(true, false, "0x1f"); // "0x1f"
+//! m.processValues(["0x5", m.prime]); // ["0x5", "0x0"]
+//! ```
+
+use modulo_machine::backend::NumBigint;
+use modulo_machine::{Backend, BackendMachine, X_BITS};
+use num_bigint::BigUint;
+use wasm_bindgen::prelude::*;
+
+pub use modulo_machine::P_STR;
+
+/// The machine on the pure-Rust backend
+pub type Machine = BackendMachine<NumBigint>;
+
+/// Parse hex digits with an optional `0x` prefix
+pub fn parse_hex(s: &str) -> Result<BigUint, String> {
+    let s = s.trim();
+    let digits = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
+    NumBigint::parse(digits, 16).ok_or_else(|| format!("`{}` is not a hex number", s))
+}
+
+/// Parse a hex operand that fits the 300-bit X bus
+pub fn parse_operand(s: &str) -> Result<BigUint, String> {
+    let x = parse_hex(s)?;
+    if NumBigint::bits(&x) > X_BITS {
+        return Err(format!("operand is {} bits, wider than the {}-bit bus", NumBigint::bits(&x), X_BITS));
+    }
+    Ok(x)
+}
+
+/// `0x`-prefixed lowercase hex
+pub fn to_hex(x: &BigUint) -> String {
+    format!("0x{:x}", x)
+}
+
+/// The machine as a JavaScript class
+#[wasm_bindgen(js_name = ModuloMachine)]
+pub struct WasmModuloMachine(Machine);
+
+impl Default for WasmModuloMachine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen(js_class = ModuloMachine)]
+impl WasmModuloMachine {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self(Machine::new())
+    }
+
+    /// A machine reducing modulo the hex modulus `p`
+    #[wasm_bindgen(js_name = withModulus)]
+    pub fn with_modulus(p: &str) -> Result<WasmModuloMachine, JsError> {
+        let p = parse_hex(p).map_err(|e| JsError::new(&e))?;
+        Machine::with_modulus(p).map(Self).map_err(|e| JsError::new(&e))
+    }
+
+    /// Apply one clock cycle and return the output
+    pub fn tick(&mut self, clk: bool, reset: bool, x: &str) -> Result<String, JsError> {
+        let x = parse_operand(x).map_err(|e| JsError::new(&e))?;
+        Ok(to_hex(self.0.tick(clk, reset, &x)))
+    }
+
+    /// Apply `clk[i]`, `reset[i]`, `x[i]` for every `i`, returning the output
+    /// after each tick; all three arrays must have the same length
+    #[wasm_bindgen(js_name = processBatch)]
+    pub fn process_batch(&mut self, clk: &[u8], reset: &[u8], x: Vec<String>) -> Result<Vec<String>, JsError> {
+        if clk.len() != x.len() || reset.len() != x.len() {
+            return Err(JsError::new("clk, reset and x must have the same length"));
+        }
+        let x = x.iter().map(|x| parse_operand(x)).collect::<Result<Vec<_>, _>>().map_err(|e| JsError::new(&e))?;
+        Ok(x.iter()
+            .enumerate()
+            .map(|(i, x)| to_hex(self.0.tick(clk[i] != 0, reset[i] != 0, x)))
+            .collect())
+    }
+
+    /// Latch each value on its own rising edge, returning one output per value
+    #[wasm_bindgen(js_name = processValues)]
+    pub fn process_values(&mut self, values: Vec<String>) -> Result<Vec<String>, JsError> {
+        let values = values.iter().map(|x| parse_operand(x)).collect::<Result<Vec<_>, _>>().map_err(|e| JsError::new(&e))?;
+        Ok(values
+            .iter()
+            .map(|x| {
+                self.0.tick(false, false, x);
+                to_hex(self.0.tick(true, false, x))
+            })
+            .collect())
+    }
+
+    pub fn reset(&mut self) {
+        self.0.reset();
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn output(&self) -> String {
+        to_hex(self.0.output())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn prime(&self) -> String {
+        to_hex(self.0.modulus())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use modulo_machine::{InputWidthPolicy, ModuloMachine};
+    use rug::Integer;
+
+    #[test]
+    fn test_matches_gmp_model() {
+        // The GMP model rejects over-wide operands the way the bindings do
+        let mut reference = ModuloMachine::new();
+        reference.set_input_width_policy(InputWidthPolicy::Error);
+        let mut machine = Machine::new();
+        assert_eq!(machine.modulus().to_string(), reference.get_prime().to_string());
+
+        // Deterministic 300-bit values plus the edges around P and the bus
+        let p = reference.get_prime().clone();
+        let mut values = vec![Integer::new(), Integer::from(&p - 1u32), p.clone(), Integer::from(&p + 1u32), (Integer::from(1) << 300) - 1u32, Integer::from(1) << 300];
+        let mut state = Integer::from(0x5eed_u32);
+        for _ in 0..32 {
+            state = (state * 6364136223846793005u64 + 1442695040888963407u64).keep_bits(300);
+            values.push(state.clone());
+        }
+        for (i, x) in values.iter().enumerate() {
+            let reset = i % 7 == 6;
+            let Ok(big) = parse_operand(&x.to_string_radix(16)) else {
+                // Neither latches it; both hold the previous output
+                assert_eq!(x.significant_bits(), 301);
+                let held = reference.get_output().clone();
+                assert!(reference.tick_checked(false, reset, x).is_ok());
+                assert!(reference.tick_checked(true, reset, x).is_err());
+                assert_eq!(*reference.get_output(), held);
+                assert_eq!(machine.output().to_string(), held.to_string());
+                continue;
+            };
+            for clk in [false, true] {
+                let expected = reference.tick(clk, reset, x).to_string_radix(16);
+                assert_eq!(to_hex(machine.tick(clk, reset, &big)), format!("0x{}", expected));
+            }
+        }
+    }
+
+    #[test]
+    fn test_hex_and_modulus_validation() {
+        assert_eq!(parse_hex("0x1F").unwrap(), BigUint::from(31u32));
+        assert_eq!(parse_hex("ff").unwrap(), BigUint::from(255u32));
+        for bad in ["", "0x", "-1", "0xg", "1 2"] {
+            assert!(parse_hex(bad).is_err(), "{:?}", bad);
+        }
+        assert!(parse_operand(&format!("{:x}", (BigUint::from(1u32) << 300) - 1u32)).is_ok());
+        assert!(parse_operand(&format!("{:x}", BigUint::from(1u32) << 300)).is_err());
+        assert!(Machine::with_modulus(BigUint::from(16u32)).is_err());
+        assert!(Machine::with_modulus(BigUint::from(1u32) << 256).is_err());
+
+        let mut machine = Machine::with_modulus(BigUint::from(97u32)).unwrap();
+        assert_eq!(*machine.tick(true, false, &BigUint::from(256u32)), BigUint::from(62u32));
+        // Held until the next rising edge, cleared by reset
+        assert_eq!(*machine.tick(true, false, &BigUint::from(1u32)), BigUint::from(62u32));
+        assert_eq!(*machine.tick(true, true, &BigUint::from(1u32)), BigUint::ZERO);
+
+        let mut wasm = WasmModuloMachine::new();
+        assert_eq!(wasm.process_values(vec!["0x5".into(), wasm.prime()]).unwrap_or_default(), ["0x5", "0x0"]);
+        assert_eq!(wasm.process_batch(&[0, 1], &[0, 0], vec!["7".into(), "7".into()]).unwrap_or_default(), ["0x0", "0x7"]);
+        assert_eq!(wasm.output(), "0x7");
+    }
+}
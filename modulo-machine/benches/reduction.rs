@@ -0,0 +1,183 @@
+//! Reduction-path benchmarks: single-tick latency and batch throughput for
+//! every [`ReductionStrategy`] across operand widths, and the bare division
+//! step with and without a cached reciprocal.
+//!
+//! Usage: `cargo bench -p modulo-machine --bench reduction [-- <filter>]`,
+//! e.g. `-- tick/barrett` to time one strategy. Criterion keeps the previous
+//! run's results under `target/criterion/`, so a regression shows up as a
+//! reported change against the last run on the same machine.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use modulo_machine::{FixedReducer, ModuloMachine, PreinvReducer, ReductionStrategy, SimdLevel};
+use rug::Assign;
+use rug::integer::Order;
+use rug::Integer;
+use std::hint::black_box;
+
+/// Operand widths: below, at and above the 256-bit modulus, up to the bus
+const WIDTHS: [u32; 4] = [64, 128, 256, 300];
+
+const STRATEGIES: [(&str, ReductionStrategy); 5] = [
+    ("gmp", ReductionStrategy::Gmp),
+    ("barrett", ReductionStrategy::Barrett),
+    ("shift-subtract", ReductionStrategy::ShiftSubtract),
+    ("constant-time", ReductionStrategy::ConstantTime),
+    ("preinverted", ReductionStrategy::Preinverted),
+];
+
+/// Values per batch in the throughput group
+const BATCH: usize = 1024;
+
+/// Deterministic operands of exactly `bits` bits, so every strategy sees the
+/// same stimulus from run to run
+fn operands(bits: u32, count: usize) -> Vec<Integer> {
+    let mut state = 0x9E37_79B9_7F4A_7C15u64 ^ bits as u64;
+    let mut next = || {
+        // xorshift64*
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    };
+
+    (0..count)
+        .map(|_| {
+            let limbs: Vec<u64> = (0..bits.div_ceil(64)).map(|_| next()).collect();
+            let mut x = Integer::from_digits(&limbs, Order::Lsf).keep_bits(bits);
+            x.set_bit(bits - 1, true);
+            x
+        })
+        .collect()
+}
+
+fn limbs(x: &Integer) -> [u64; 5] {
+    let mut limbs = [0; 5];
+    x.write_digits(&mut limbs, Order::Lsf);
+    limbs
+}
+
+fn machine(strategy: ReductionStrategy) -> ModuloMachine {
+    let mut machine = ModuloMachine::new();
+    assert!(machine.set_reduction_strategy(strategy), "{:?} does not support the configured modulus", strategy);
+    machine
+}
+
+/// One full clock cycle per iteration: a low tick, then the rising edge that
+/// reduces and latches the operand
+fn tick_latency(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tick");
+    for (name, strategy) in STRATEGIES {
+        for bits in WIDTHS {
+            let inputs = operands(bits, 64);
+            let mut machine = machine(strategy);
+            let mut i = 0;
+            group.bench_with_input(BenchmarkId::new(name, bits), &inputs, |b, inputs| {
+                b.iter(|| {
+                    let x = &inputs[i % inputs.len()];
+                    i += 1;
+                    machine.tick(false, false, x);
+                    black_box(machine.tick(true, false, black_box(x)));
+                })
+            });
+        }
+    }
+
+    // The same cycle through the fixed-limb API, with no Integers to build
+    let inputs: Vec<[u64; 5]> = operands(300, 64).iter().map(limbs).collect();
+    let mut machine = ModuloMachine::new();
+    let mut i = 0;
+    group.bench_function(BenchmarkId::new("gmp-limbs", 300), |b| {
+        b.iter(|| {
+            let x = &inputs[i % inputs.len()];
+            i += 1;
+            machine.tick_limbs(false, false, x);
+            black_box(machine.tick_limbs(true, false, black_box(x)));
+        })
+    });
+    group.finish();
+}
+
+/// `process_values` over a batch of operands, and `reduce_batch_parallel` over
+/// the same batch, reported in values per second
+fn batch_throughput(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batch");
+    group.throughput(Throughput::Elements(BATCH as u64));
+    for (name, strategy) in STRATEGIES {
+        for bits in WIDTHS {
+            let inputs = operands(bits, BATCH);
+            let mut machine = machine(strategy);
+            group.bench_with_input(BenchmarkId::new(name, bits), &inputs, |b, inputs| {
+                b.iter(|| {
+                    // Hand the outputs back so the arena, not the allocator, is timed
+                    let results = machine.process_values(black_box(inputs));
+                    machine.recycle(results);
+                })
+            });
+        }
+    }
+
+    // Stateless reduction of the same batch across every core
+    for bits in WIDTHS {
+        let inputs = operands(bits, BATCH);
+        let machine = ModuloMachine::new();
+        group.bench_with_input(BenchmarkId::new("gmp-parallel", bits), &inputs, |b, inputs| {
+            b.iter(|| black_box(machine.reduce_batch_parallel(black_box(inputs))))
+        });
+    }
+
+    // The fixed-limb reducer for the spec P, one operand at a time and per
+    // vector register
+    let inputs: Vec<[u64; 5]> = operands(300, BATCH).iter().map(limbs).collect();
+    let mut out = vec![[0; 4]; BATCH];
+    for (name, level) in [("fixed-scalar", SimdLevel::Scalar), ("fixed-avx2", SimdLevel::Avx2), ("fixed-avx512", SimdLevel::Avx512)] {
+        if level > SimdLevel::detect() {
+            continue;
+        }
+        group.bench_function(BenchmarkId::new(name, 300), |b| {
+            b.iter(|| FixedReducer::P.reduce_batch_at(level, black_box(&inputs), &mut out))
+        });
+    }
+    group.finish();
+}
+
+/// `x mod P` for the 300-bit worst case alone: GMP's division, which sets up
+/// the divisor's reciprocal on every call, against the reciprocal cached by
+/// [`PreinvReducer`]
+fn division(c: &mut Criterion) {
+    let mut group = c.benchmark_group("reduce");
+    let inputs = operands(300, 64);
+    let p = ModuloMachine::new().get_prime().clone();
+    let preinv = PreinvReducer::new(&p);
+    let mut out = Integer::with_capacity(256);
+    let mut i = 0;
+    group.bench_function(BenchmarkId::new("gmp-division", 300), |b| {
+        b.iter(|| {
+            let x = &inputs[i % inputs.len()];
+            i += 1;
+            out.assign(black_box(x) % &p);
+            black_box(&out);
+        })
+    });
+    group.bench_function(BenchmarkId::new("preinverted", 300), |b| {
+        b.iter(|| {
+            let x = &inputs[i % inputs.len()];
+            i += 1;
+            preinv.reduce_into(black_box(x), &mut out);
+            black_box(&out);
+        })
+    });
+
+    // The division alone, without converting to and from Integer limbs
+    let limb_inputs: Vec<[u64; 5]> = inputs.iter().map(limbs).collect();
+    group.bench_function(BenchmarkId::new("preinverted-limbs", 300), |b| {
+        b.iter(|| {
+            let x = &limb_inputs[i % limb_inputs.len()];
+            i += 1;
+            black_box(preinv.reduce_limbs(black_box(x)))
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, tick_latency, batch_throughput, division);
+criterion_main!(benches);
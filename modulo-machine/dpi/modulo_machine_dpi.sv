@@ -0,0 +1,17 @@
+// DPI-C imports for the modulo-machine golden model.
+//
+// Build the library with
+//   cargo rustc --release --lib --features dpi --crate-type cdylib
+// and load it into the simulator, e.g. with -sv_lib libmodulo_machine.
+package modulo_machine_dpi;
+  // Create a machine with the specification's modulus
+  import "DPI-C" function chandle mm_new();
+  // Destroy a machine; null is ignored
+  import "DPI-C" function void mm_free(input chandle m);
+  // Return to the power-on state
+  import "DPI-C" function void mm_reset(input chandle m);
+  // Apply one tick
+  import "DPI-C" function void mm_tick(input chandle m, input bit clk, input bit reset, input bit [299:0] x);
+  // Output register, least significant byte first
+  import "DPI-C" function void mm_get_output_bytes(input chandle m, output byte unsigned o[32]);
+endpackage
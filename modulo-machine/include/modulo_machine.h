@@ -0,0 +1,108 @@
+#ifndef MODULO_MACHINE_H
+#define MODULO_MACHINE_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Bytes written by [`mm_machine_output`]
+#define MM_OUTPUT_BYTES 32
+
+// Result of an FFI call
+typedef enum MmStatus {
+  MM_STATUS_OK = 0,
+  // A required pointer argument was null
+  MM_STATUS_NULL_POINTER = 1,
+  // An active edge saw an operand wider than the 300-bit X bus under
+  // [`MmWidthPolicy::Error`]; the tick was applied and the output held
+  MM_STATUS_INPUT_TOO_WIDE = 2,
+  // The modulus is not odd, at least 3 and at most 256 bits
+  MM_STATUS_INVALID_MODULUS = 3,
+  // The output buffer is shorter than [`MM_OUTPUT_BYTES`]
+  MM_STATUS_BUFFER_TOO_SMALL = 4,
+} MmStatus;
+
+// What an active edge does with an operand wider than the 300-bit X bus,
+// as [`InputWidthPolicy`]
+typedef enum MmWidthPolicy {
+  // Reduce the full value
+  MM_WIDTH_POLICY_REDUCE = 0,
+  // Keep the low 300 bits
+  MM_WIDTH_POLICY_WRAP = 1,
+  // Clamp to `2^300 - 1`
+  MM_WIDTH_POLICY_SATURATE = 2,
+  // Hold the output and return [`MmStatus::InputTooWide`]; the default
+  MM_WIDTH_POLICY_ERROR = 3,
+} MmWidthPolicy;
+
+// Opaque handle to a machine
+typedef struct MmMachine MmMachine;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Create a machine into `*out`
+//
+// A null `modulus` (with `modulus_len` 0) selects the specification's P;
+// otherwise `modulus` holds `modulus_len` big-endian bytes. Over-wide
+// operands are rejected until [`mm_machine_set_input_width_policy`] says
+// otherwise.
+//
+// # Safety
+//
+// `out` must be valid for writes and `modulus` valid for `modulus_len`
+// reads. Free the machine with [`mm_machine_destroy`].
+MmStatus mm_machine_create(const uint8_t *modulus, size_t modulus_len, MmMachine **out);
+
+// Destroy a machine; null is ignored
+//
+// # Safety
+//
+// `m` must be null or a machine from [`mm_machine_create`] not already
+// destroyed.
+void mm_machine_destroy(MmMachine *m);
+
+// Return the machine to its power-on state
+//
+// # Safety
+//
+// `m` must be null or a live machine.
+MmStatus mm_machine_reset(MmMachine *m);
+
+// Choose what active edges do with operands wider than the 300-bit X bus
+//
+// # Safety
+//
+// `m` must be null or a live machine.
+MmStatus mm_machine_set_input_width_policy(MmMachine *m, MmWidthPolicy policy);
+
+// Apply one tick with the operand `x` of `x_len` big-endian bytes
+//
+// An over-wide operand is handled by the machine's width policy.
+//
+// # Safety
+//
+// `m` must be null or a live machine, and `x` valid for `x_len` reads.
+MmStatus mm_machine_tick(MmMachine *m, bool clk, bool reset, const uint8_t *x, size_t x_len);
+
+// Write the 256-bit output register to `out` as [`MM_OUTPUT_BYTES`]
+// big-endian bytes, two's complement if a signed mode made it negative
+//
+// # Safety
+//
+// `m` must be null or a live machine, and `out` valid for `out_len` writes.
+MmStatus mm_machine_output(const MmMachine *m, uint8_t *out, size_t out_len);
+
+// Static, NUL-terminated description of `status`
+const char *mm_status_message(MmStatus status);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* MODULO_MACHINE_H */
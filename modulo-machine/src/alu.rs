@@ -0,0 +1,174 @@
+//! Opcodes for driving the machine as a small modular ALU.
+//!
+//! [`ModuloMachine::tick_op`](crate::ModuloMachine::tick_op) latches the
+//! selected result on a rising edge. `Op::Mod` is the machine's ordinary
+//! datapath and honours the width policy, lazy reduction and reduction
+//! strategy; the two-operand ops always produce a canonical result in
+//! `[0, P)`.
+//!
+//! `Op::ModExp` is multi-cycle: the edge that issues it loads the operands,
+//! and each following rising edge processes one exponent bit
+//! (left-to-right square-and-multiply). The machine reports
+//! [`busy`](crate::ModuloMachine::busy) meanwhile and pulses
+//! [`done`](crate::ModuloMachine::done) for the edge that latches the result,
+//! so an exponent of `n` bits takes `n` edges after the issuing one.
+
+use crate::arith;
+use rug::Integer;
+use std::fmt;
+use std::str::FromStr;
+
+/// Operation selected by the opcode input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Op {
+    /// `a mod P`; `b` is ignored
+    #[default]
+    Mod,
+    /// `(a + b) mod P`
+    AddMod,
+    /// `(a - b) mod P`
+    SubMod,
+    /// `(a · b) mod P`
+    MulMod,
+    /// `a^|b| mod P`, one exponent bit per cycle
+    ModExp,
+    /// `a^-1 mod P`; `b` is ignored. Latches 0 when `a` has no inverse, the
+    /// `0^-1 = 0` convention of Fermat-based inverters
+    Inv,
+}
+
+impl Op {
+    pub const ALL: [Op; 6] = [Op::Mod, Op::AddMod, Op::SubMod, Op::MulMod, Op::ModExp, Op::Inv];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Op::Mod => "mod",
+            Op::AddMod => "add_mod",
+            Op::SubMod => "sub_mod",
+            Op::MulMod => "mul_mod",
+            Op::ModExp => "mod_exp",
+            Op::Inv => "inv",
+        }
+    }
+
+    /// Golden result of the operation, in `[0, p)`
+    pub fn apply(self, a: &Integer, b: &Integer, p: &Integer) -> Integer {
+        let raw = match self {
+            Op::Mod => a.clone(),
+            Op::AddMod => Integer::from(a + b),
+            Op::SubMod => Integer::from(a - b),
+            Op::MulMod => Integer::from(a * b),
+            Op::ModExp => {
+                let base = Op::Mod.apply(a, p, p);
+                return Integer::from(base.pow_mod_ref(&Integer::from(b.abs_ref()), p).unwrap());
+            }
+            Op::Inv => return arith::mod_inverse(a, p).unwrap_or_default(),
+        };
+        let mut r = raw % p;
+        if r < 0 {
+            r += p;
+        }
+        r
+    }
+}
+
+/// State of an exponentiation in flight
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct ModExpUnit {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_int"))]
+    base: Integer,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_int"))]
+    exponent: Integer,
+    /// Exponent bits still to process
+    remaining: u32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_int"))]
+    acc: Integer,
+}
+
+impl ModExpUnit {
+    /// Load the operands, as the issuing edge does
+    pub(crate) fn start(a: &Integer, b: &Integer, p: &Integer) -> Self {
+        let exponent = Integer::from(b.abs_ref());
+        Self {
+            base: Op::Mod.apply(a, p, p),
+            remaining: exponent.significant_bits(),
+            exponent,
+            acc: Integer::from(1u32) % p,
+        }
+    }
+
+    /// Process one exponent bit; returns the result once every bit is done
+    pub(crate) fn step(&mut self, p: &Integer) -> Option<&Integer> {
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            self.acc = Integer::from(self.acc.square_ref()) % p;
+            if self.exponent.get_bit(self.remaining) {
+                self.acc = Integer::from(&self.acc * &self.base) % p;
+            }
+        }
+        (self.remaining == 0).then_some(&self.acc)
+    }
+
+    pub(crate) fn finished(&self) -> bool {
+        self.remaining == 0
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// An opcode name that is not one of [`Op::ALL`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownOp(pub String);
+
+impl fmt::Display for UnknownOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown opcode '{}'", self.0)
+    }
+}
+
+impl std::error::Error for UnknownOp {}
+
+impl FromStr for Op {
+    type Err = UnknownOp;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Op::ALL.into_iter().find(|op| op.name() == s).ok_or_else(|| UnknownOp(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_is_canonical() {
+        let p = Integer::from(97u32);
+        let (a, b) = (Integer::from(90u32), Integer::from(20u32));
+        assert_eq!(Op::Mod.apply(&Integer::from(200u32), &b, &p), 6);
+        assert_eq!(Op::AddMod.apply(&a, &b, &p), 13);
+        assert_eq!(Op::SubMod.apply(&b, &a, &p), 27);
+        assert_eq!(Op::MulMod.apply(&a, &b, &p), 1800 % 97);
+        assert_eq!(Op::Mod.apply(&Integer::from(-1), &b, &p), 96);
+        assert_eq!(Op::Inv.apply(&a, &b, &p), 83);
+        assert_eq!(Op::Inv.apply(&p, &b, &p), 0);
+        assert_eq!(Op::ModExp.apply(&Integer::from(3u32), &b, &p), Integer::from(3u32).pow_mod(&b, &p).unwrap());
+
+        let mut unit = ModExpUnit::start(&Integer::from(3u32), &b, &p);
+        let mut cycles = 1;
+        while unit.step(&p).is_none() {
+            cycles += 1;
+        }
+        assert_eq!(cycles, b.significant_bits());
+        assert_eq!(unit.acc, Op::ModExp.apply(&Integer::from(3u32), &b, &p));
+
+        for op in Op::ALL {
+            assert_eq!(op.name().parse(), Ok(op));
+        }
+        assert_eq!("div".parse::<Op>(), Err(UnknownOp("div".to_string())));
+    }
+}
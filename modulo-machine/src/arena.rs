@@ -0,0 +1,96 @@
+use rug::Integer;
+
+/// Free list of pre-sized Integers reused across ticks and batches
+///
+/// Long simulations spend a surprising share of their time in the allocator
+/// when every result is a fresh `Integer`. Handing spent results back with
+/// [`IntegerArena::give`] lets the next batch reuse their limb buffers.
+#[derive(Debug, Default)]
+pub struct IntegerArena {
+    free: Vec<Integer>,
+    capacity_bits: usize,
+}
+
+/// A clone starts empty: spare Integers hold nothing worth copying
+impl Clone for IntegerArena {
+    fn clone(&self) -> Self {
+        Self::new(self.capacity_bits)
+    }
+}
+
+impl IntegerArena {
+    /// Create an empty arena whose fresh Integers reserve `capacity_bits`
+    pub fn new(capacity_bits: usize) -> Self {
+        Self {
+            free: Vec::new(),
+            capacity_bits,
+        }
+    }
+
+    /// Create an arena with `count` Integers already allocated
+    pub fn with_preallocated(capacity_bits: usize, count: usize) -> Self {
+        Self {
+            free: (0..count).map(|_| Integer::with_capacity(capacity_bits)).collect(),
+            capacity_bits,
+        }
+    }
+
+    /// Take a spare Integer, allocating a new one only when the arena is empty
+    ///
+    /// The value of a recycled Integer is unspecified; callers overwrite it.
+    pub fn take(&mut self) -> Integer {
+        self.free
+            .pop()
+            .unwrap_or_else(|| Integer::with_capacity(self.capacity_bits))
+    }
+
+    /// Return an Integer for reuse
+    pub fn give(&mut self, x: Integer) {
+        self.free.push(x);
+    }
+
+    /// Return every Integer in `xs` for reuse
+    pub fn give_all<I: IntoIterator<Item = Integer>>(&mut self, xs: I) {
+        self.free.extend(xs);
+    }
+
+    /// Bits reserved by freshly allocated Integers
+    pub fn capacity_bits(&self) -> usize {
+        self.capacity_bits
+    }
+
+    /// Number of spare Integers currently held
+    pub fn len(&self) -> usize {
+        self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.free.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rug::Assign;
+
+    #[test]
+    fn test_take_reuses_given_integers() {
+        let mut arena = IntegerArena::with_preallocated(256, 2);
+        assert_eq!(arena.len(), 2);
+
+        let a = arena.take();
+        let b = arena.take();
+        assert!(arena.is_empty());
+
+        // An empty arena still hands out fresh Integers
+        let c = arena.take();
+        arena.give_all([a, b, c]);
+        assert_eq!(arena.len(), 3);
+
+        let mut x = arena.take();
+        x.assign(7);
+        assert_eq!(x, 7);
+        assert_eq!(arena.len(), 2);
+    }
+}
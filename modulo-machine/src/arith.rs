@@ -0,0 +1,219 @@
+//! Modular arithmetic helpers for building expected values in testbenches.
+
+use rug::integer::IsPrime;
+use rug::Integer;
+
+/// Miller–Rabin rounds used when a helper needs `p` to be prime
+pub(crate) const PRIMALITY_REPS: u32 = 30;
+
+/// Square root of `x` modulo an odd prime `p` (Tonelli–Shanks)
+///
+/// Returns `None` when `x` is a quadratic non-residue, or when `p` fails a
+/// Miller–Rabin test (square roots modulo a composite need its factorization,
+/// and Tonelli–Shanks would not terminate). Otherwise returns the
+/// smaller of the two roots `r` and `p - r`; callers that need a particular
+/// root (e.g. by parity for point decompression) take `p - r` as required.
+pub fn mod_sqrt(x: &Integer, p: &Integer) -> Option<Integer> {
+    if p.is_even() || p.is_probably_prime(PRIMALITY_REPS) == IsPrime::No {
+        return None;
+    }
+
+    let mut a = Integer::from(x % p);
+    if a < 0 {
+        a += p;
+    }
+    if a == 0 {
+        return Some(a);
+    }
+    if a.legendre(p) != 1 {
+        return None;
+    }
+
+    // p - 1 = q * 2^s with q odd
+    let p_minus_one = Integer::from(p - 1u32);
+    let s = p_minus_one.find_one(0).expect("p must be greater than 1");
+    let q = Integer::from(&p_minus_one >> s);
+
+    // Any quadratic non-residue z seeds the 2-power part
+    let mut z = Integer::from(2u32);
+    while z.legendre(p) != -1 {
+        z += 1u32;
+    }
+
+    let pow = |base: &Integer, exp: &Integer| Integer::from(base.pow_mod_ref(exp, p).unwrap());
+    let mut m = s;
+    let mut c = pow(&z, &q);
+    let mut t = pow(&a, &q);
+    let mut r = pow(&a, &(Integer::from(&q + 1u32) >> 1u32));
+
+    while t != 1 {
+        // Least i with t^(2^i) = 1; 0 < i < m since t has order dividing 2^(m-1)
+        let mut i = 0;
+        let mut t2i = t.clone();
+        while t2i != 1 {
+            t2i = Integer::from(t2i.square_ref()) % p;
+            i += 1;
+        }
+
+        let b = pow(&c, &(Integer::from(1u32) << (m - i - 1)));
+        m = i;
+        c = Integer::from(b.square_ref()) % p;
+        t = Integer::from(&t * &c) % p;
+        r = Integer::from(&r * &b) % p;
+    }
+
+    let other = Integer::from(p - &r);
+    Some(if other < r { other } else { r })
+}
+
+/// Inverse of `x` modulo `p` by the extended Euclidean algorithm
+///
+/// Works for composite moduli too; returns `None` when `x` shares a factor
+/// with `p` (including `x = 0 mod p`). The result is in `[1, p)`.
+pub fn mod_inverse(x: &Integer, p: &Integer) -> Option<Integer> {
+    if *p <= 1 {
+        return None;
+    }
+    let mut a = Integer::from(x % p);
+    if a < 0 {
+        a += p;
+    }
+
+    // Invariant: s·x = r (mod p) for both rows
+    let (mut r0, mut r1) = (p.clone(), a);
+    let (mut s0, mut s1) = (Integer::new(), Integer::from(1u32));
+    while r1 != 0 {
+        let q = Integer::from(&r0 / &r1);
+        let r2 = &r0 - Integer::from(&q * &r1);
+        let s2 = &s0 - Integer::from(&q * &s1);
+        (r0, r1) = (r1, r2);
+        (s0, s1) = (s1, s2);
+    }
+    if r0 != 1 {
+        return None;
+    }
+    if s0 < 0 {
+        s0 += p;
+    }
+    Some(s0)
+}
+
+/// One modular multiplication with its double-width intermediate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModMul {
+    /// Unreduced product `a * b`, up to twice the modulus width for canonical
+    /// operands: what a multiplier stage hands to the reducer
+    pub product: Integer,
+    /// `product mod p`, in `[0, p)`
+    pub reduced: Integer,
+}
+
+/// `(a * b) mod p` for every pair, keeping the intermediate products
+pub fn mod_mul_batch(pairs: &[(Integer, Integer)], p: &Integer) -> Vec<ModMul> {
+    pairs
+        .iter()
+        .map(|(a, b)| {
+            let product = Integer::from(a * b);
+            let mut reduced = Integer::from(&product % p);
+            if reduced < 0 {
+                reduced += p;
+            }
+            ModMul { product, reduced }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::P_STR;
+
+    /// BLS12-381 scalar field order; p - 1 has 2-adicity 32, which exercises
+    /// many Tonelli–Shanks iterations
+    const BLS12_381_R: &str =
+        "52435875175126190479447740508185965837690552500527637822603658699938581184513";
+
+    #[test]
+    fn test_mod_sqrt() {
+        let p = Integer::from_str_radix(BLS12_381_R, 10).unwrap();
+
+        for v in [1u64, 2, 12345, u64::MAX] {
+            let square = Integer::from(Integer::from(v).square_ref()) % &p;
+            let root = mod_sqrt(&square, &p).expect("squares are residues");
+            assert_eq!(Integer::from(root.square_ref()) % &p, square);
+            assert!(root == v || Integer::from(&p - &root) == v);
+        }
+        assert_eq!(mod_sqrt(&Integer::new(), &p), Some(Integer::new()));
+
+        // Non-residues and negative inputs
+        let mut z = Integer::from(2u32);
+        while z.legendre(&p) != -1 {
+            z += 1u32;
+        }
+        assert_eq!(mod_sqrt(&z, &p), None);
+        let root = mod_sqrt(&Integer::from(-4), &p).unwrap();
+        assert_eq!(Integer::from(root.square_ref()) % &p, Integer::from(&p - 4u32));
+    }
+
+    #[test]
+    fn test_mod_sqrt_composite_modulus() {
+        // The specification's P is not prime (it has the factor 2447)
+        let p = Integer::from_str_radix(P_STR, 10).unwrap();
+        assert!(p.is_divisible_u(2447));
+        assert_eq!(mod_sqrt(&Integer::from(4u32), &p), None);
+    }
+
+    #[test]
+    fn test_mod_sqrt_small_primes() {
+        // Exhaustive over primes with different 2-adic orders of p - 1
+        for p in [3u32, 5, 13, 17, 41, 97, 257] {
+            let p = Integer::from(p);
+            for x in 0..p.to_u32().unwrap() {
+                let x = Integer::from(x);
+                let is_square = (0..p.to_u32().unwrap())
+                    .any(|r| Integer::from(r * r) % &p == x);
+                match mod_sqrt(&x, &p) {
+                    Some(r) => assert_eq!(Integer::from(&r * &r) % &p, x),
+                    None => assert!(!is_square, "missed root of {} mod {}", x, p),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_mod_inverse() {
+        let p = Integer::from_str_radix(P_STR, 10).unwrap();
+        for x in [Integer::from(1u32), Integer::from(2u32), Integer::from(&p - 1u32), Integer::from(-5), Integer::from(&p * 3u32) + 7u32] {
+            let inv = mod_inverse(&x, &p).unwrap();
+            assert!(inv > 0 && inv < p);
+            let mut product = Integer::from(&x * &inv) % &p;
+            if product < 0 {
+                product += &p;
+            }
+            assert_eq!(product, 1);
+        }
+        // P is composite: multiples of its factor have no inverse
+        assert_eq!(mod_inverse(&Integer::from(2447u32), &p), None);
+        assert_eq!(mod_inverse(&Integer::new(), &p), None);
+        assert_eq!(mod_inverse(&p, &p), None);
+    }
+
+    #[test]
+    fn test_mod_mul_batch() {
+        let p = Integer::from_str_radix(P_STR, 10).unwrap();
+        let max = Integer::from(&p - 1u32);
+        let pairs = [
+            (Integer::from(3u32), Integer::from(5u32)),
+            (max.clone(), max.clone()),
+            (Integer::from(-2), Integer::from(7u32)),
+        ];
+        let results = mod_mul_batch(&pairs, &p);
+
+        assert_eq!(results[0], ModMul { product: Integer::from(15u32), reduced: Integer::from(15u32) });
+        // (P - 1)^2 = 1 mod P, with a product twice the modulus width
+        assert_eq!(results[1].product.significant_bits(), 2 * p.significant_bits());
+        assert_eq!(results[1].reduced, 1);
+        assert_eq!(results[2].product, -14);
+        assert_eq!(results[2].reduced, Integer::from(&p - 14u32));
+    }
+}
@@ -0,0 +1,276 @@
+//! AXI-Lite register map in front of the machine, for co-simulating
+//! firmware drivers.
+//!
+//! CTRL at `0x00` takes self-clearing START (bit 0) and RESET (bit 1) bits;
+//! STATUS at `0x04` reports DONE (bit 0). The 300-bit operand is written as
+//! ten 32-bit words from `0x10` and the 256-bit result read as eight from
+//! `0x40`, least significant word first; the output words read a negative
+//! result as two's complement. Unaligned accesses and writes to read-only
+//! registers answer SLVERR, unmapped addresses DECERR.
+
+use crate::{ModuloMachine, X_BITS};
+use rug::integer::Order;
+use rug::Integer;
+use std::fmt;
+
+/// Control register: bit 0 starts a reduction, bit 1 resets the output register.
+/// Both bits are self-clearing.
+pub const REG_CTRL: u32 = 0x00;
+/// Status register (read-only): bit 0 is DONE, set once a reduction has completed.
+pub const REG_STATUS: u32 = 0x04;
+/// First of the input word registers, least significant word first.
+pub const REG_X_BASE: u32 = 0x10;
+/// First of the output word registers (read-only), least significant word first.
+pub const REG_O_BASE: u32 = 0x40;
+
+/// Number of 32-bit words needed to hold the 300-bit input
+pub const X_WORDS: usize = 10;
+/// Number of 32-bit words needed to hold the 256-bit output
+pub const O_WORDS: usize = 8;
+
+pub const CTRL_START: u32 = 1 << 0;
+pub const CTRL_RESET: u32 = 1 << 1;
+pub const STATUS_DONE: u32 = 1 << 0;
+
+/// AXI-Lite error responses (OKAY is represented by `Ok`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AxiError {
+    /// Slave error: unaligned access or write to a read-only register
+    SlvErr,
+    /// Decode error: no register at this address
+    DecErr,
+}
+
+impl fmt::Display for AxiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AxiError::SlvErr => write!(f, "SLVERR"),
+            AxiError::DecErr => write!(f, "DECERR"),
+        }
+    }
+}
+
+impl std::error::Error for AxiError {}
+
+/// ModuloMachine behind a modeled AXI-Lite slave register map
+///
+/// | Offset        | Register        | Access |
+/// |---------------|-----------------|--------|
+/// | `0x00`        | CTRL            | W      |
+/// | `0x04`        | STATUS          | R      |
+/// | `0x10`-`0x34` | X\[0\]..X\[9\]  | R/W    |
+/// | `0x40`-`0x5C` | O\[0\]..O\[7\]  | R      |
+///
+/// Firmware loads the input words, writes `CTRL_START` and polls `STATUS_DONE`
+/// before reading the result words.
+pub struct AxiLiteSlave {
+    machine: ModuloMachine,
+    x_words: [u32; X_WORDS],
+    status: u32,
+    /// Scratch register for assembling X from the input words
+    x: Integer,
+}
+
+impl AxiLiteSlave {
+    /// Wrap a fresh machine
+    pub fn new() -> Self {
+        Self::with_machine(ModuloMachine::new())
+    }
+
+    /// Wrap an existing machine
+    pub fn with_machine(machine: ModuloMachine) -> Self {
+        Self {
+            machine,
+            x_words: [0; X_WORDS],
+            status: 0,
+            x: Integer::new(),
+        }
+    }
+
+    /// Access the wrapped machine
+    pub fn machine(&self) -> &ModuloMachine {
+        &self.machine
+    }
+
+    /// Perform an AXI-Lite read transaction
+    pub fn axi_read(&self, addr: u32) -> Result<u32, AxiError> {
+        if !addr.is_multiple_of(4) {
+            return Err(AxiError::SlvErr);
+        }
+
+        if let Some(index) = Self::x_index(addr) {
+            return Ok(self.x_words[index]);
+        }
+        if let Some(index) = Self::o_index(addr) {
+            return Ok(self.output_word(index));
+        }
+
+        match addr {
+            REG_CTRL => Ok(0), // self-clearing bits always read back as zero
+            REG_STATUS => Ok(self.status),
+            _ => Err(AxiError::DecErr),
+        }
+    }
+
+    /// Perform an AXI-Lite write transaction
+    pub fn axi_write(&mut self, addr: u32, data: u32) -> Result<(), AxiError> {
+        if !addr.is_multiple_of(4) {
+            return Err(AxiError::SlvErr);
+        }
+
+        if let Some(index) = Self::x_index(addr) {
+            self.x_words[index] = data;
+            return Ok(());
+        }
+        if Self::o_index(addr).is_some() {
+            return Err(AxiError::SlvErr);
+        }
+
+        match addr {
+            REG_CTRL => {
+                if data & CTRL_RESET != 0 {
+                    // Directly rather than through a reset tick, which a
+                    // synchronous-reset machine would ignore with the clock low
+                    self.machine.reset();
+                    self.status = 0;
+                }
+                if data & CTRL_START != 0 {
+                    self.start();
+                }
+                Ok(())
+            }
+            REG_STATUS => Err(AxiError::SlvErr),
+            _ => Err(AxiError::DecErr),
+        }
+    }
+
+    /// Latch X from the input registers and drive one full clock cycle
+    fn start(&mut self) {
+        self.x = Integer::from_digits(&self.x_words, Order::Lsf);
+        // Bits above the X bus are dropped like unconnected wires
+        self.x.keep_bits_mut(X_BITS);
+
+        self.machine.cycle(&self.x);
+        self.status |= STATUS_DONE;
+    }
+
+    /// Word `index` of the 256-bit output, in two's complement if a signed
+    /// mode left it negative
+    fn output_word(&self, index: usize) -> u32 {
+        let limb = self.machine.output_to_limbs()[index / 2];
+        (limb >> (32 * (index % 2))) as u32
+    }
+
+    fn x_index(addr: u32) -> Option<usize> {
+        let index = addr.checked_sub(REG_X_BASE)? as usize / 4;
+        (index < X_WORDS).then_some(index)
+    }
+
+    fn o_index(addr: u32) -> Option<usize> {
+        let index = addr.checked_sub(REG_O_BASE)? as usize / 4;
+        (index < O_WORDS).then_some(index)
+    }
+}
+
+impl Default for AxiLiteSlave {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ResetKind;
+
+    fn write_x(slave: &mut AxiLiteSlave, x: &Integer) {
+        let words = x.to_digits::<u32>(Order::Lsf);
+        for i in 0..X_WORDS {
+            let word = words.get(i).copied().unwrap_or(0);
+            slave.axi_write(REG_X_BASE + 4 * i as u32, word).unwrap();
+        }
+    }
+
+    fn read_output(slave: &AxiLiteSlave) -> Integer {
+        let words: Vec<u32> = (0..O_WORDS)
+            .map(|i| slave.axi_read(REG_O_BASE + 4 * i as u32).unwrap())
+            .collect();
+        Integer::from_digits(&words, Order::Lsf)
+    }
+
+    #[test]
+    fn test_driver_flow() {
+        let mut slave = AxiLiteSlave::new();
+        let p = slave.machine().get_prime().clone();
+        let x = ModuloMachine::create_large_input(299, 123456789);
+
+        write_x(&mut slave, &x);
+        assert_eq!(slave.axi_read(REG_STATUS).unwrap() & STATUS_DONE, 0);
+
+        slave.axi_write(REG_CTRL, CTRL_START).unwrap();
+        assert_eq!(slave.axi_read(REG_STATUS).unwrap() & STATUS_DONE, STATUS_DONE);
+        assert_eq!(read_output(&slave), Integer::from(&x % &p));
+
+        // Back-to-back starts each see a fresh rising edge
+        write_x(&mut slave, &Integer::from(12345u32));
+        slave.axi_write(REG_CTRL, CTRL_START).unwrap();
+        assert_eq!(read_output(&slave), 12345);
+    }
+
+    #[test]
+    fn test_reset_clears_output_and_done() {
+        let mut slave = AxiLiteSlave::new();
+        write_x(&mut slave, &Integer::from(777u32));
+        slave.axi_write(REG_CTRL, CTRL_START).unwrap();
+        assert_eq!(read_output(&slave), 777);
+
+        slave.axi_write(REG_CTRL, CTRL_RESET).unwrap();
+        assert_eq!(read_output(&slave), 0);
+        assert_eq!(slave.axi_read(REG_STATUS).unwrap(), 0);
+        // Input registers are not affected by the datapath reset
+        assert_eq!(slave.axi_read(REG_X_BASE).unwrap(), 777);
+
+        // A machine with synchronous reset is cleared by the register too
+        let mut machine = ModuloMachine::new();
+        machine.set_reset_kind(ResetKind::Sync);
+        let mut slave = AxiLiteSlave::with_machine(machine);
+        write_x(&mut slave, &Integer::from(777u32));
+        slave.axi_write(REG_CTRL, CTRL_START).unwrap();
+        slave.axi_write(REG_CTRL, CTRL_RESET).unwrap();
+        assert_eq!(read_output(&slave), 0);
+        assert_eq!(slave.axi_read(REG_STATUS).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_negative_output_reads_as_twos_complement() {
+        // Truncated signed mode keeps the sign of a negative operand
+        let mut machine = ModuloMachine::new();
+        machine.cycle(&Integer::from(-5));
+        let slave = AxiLiteSlave::with_machine(machine);
+        assert_eq!(slave.axi_read(REG_O_BASE).unwrap(), 0xFFFF_FFFB);
+        for i in 1..O_WORDS as u32 {
+            assert_eq!(slave.axi_read(REG_O_BASE + 4 * i).unwrap(), 0xFFFF_FFFF);
+        }
+        assert_eq!(read_output(&slave), (Integer::from(1) << 256u32) - 5u32);
+    }
+
+    #[test]
+    fn test_error_responses() {
+        let mut slave = AxiLiteSlave::new();
+        assert_eq!(slave.axi_read(0x02), Err(AxiError::SlvErr));
+        assert_eq!(slave.axi_read(0x100), Err(AxiError::DecErr));
+        assert_eq!(slave.axi_write(REG_STATUS, 1), Err(AxiError::SlvErr));
+        assert_eq!(slave.axi_write(REG_O_BASE, 1), Err(AxiError::SlvErr));
+        assert_eq!(slave.axi_write(0x38, 1), Err(AxiError::DecErr));
+    }
+
+    #[test]
+    fn test_input_truncated_to_bus_width() {
+        let mut slave = AxiLiteSlave::new();
+        // Bits 300..319 of the top word don't exist on the 300-bit bus
+        slave.axi_write(REG_X_BASE + 4 * 9, 0xFFFF_F000).unwrap();
+        slave.axi_write(REG_X_BASE, 5).unwrap();
+        slave.axi_write(REG_CTRL, CTRL_START).unwrap();
+        assert_eq!(read_output(&slave), 5);
+    }
+}
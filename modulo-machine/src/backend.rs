@@ -0,0 +1,387 @@
+//! Big-integer backends, chosen at compile time.
+//!
+//! The crate is built on `rug`, which needs GMP: painful on Windows MSVC,
+//! impossible on `wasm32`, and a chore when cross-compiling. A [`Backend`]
+//! names the handful of non-negative integer operations the machine's default
+//! configuration needs (async reset to zero, `x mod P` latched on each rising
+//! edge), and [`BackendMachine`] implements that machine once, generically:
+//!
+//! | Feature     | Backend       | Integer type            |
+//! |-------------|---------------|-------------------------|
+//! | `gmp`       | [`Gmp`]       | `rug::Integer`          |
+//! | `pure-rust` | [`NumBigint`] | `num_bigint::BigUint`   |
+//!
+//! `gmp` is on by default and also builds everything else in the crate. To
+//! drop GMP, build with `--no-default-features --features pure-rust`; only
+//! this module remains. [`DefaultBackend`] is `Gmp` whenever `gmp` is
+//! enabled, so existing builds are unchanged.
+//!
+//! ```
+//! use modulo_machine::{Backend, BackendMachine, DefaultBackend};
+//!
+//! let mut machine = BackendMachine::<DefaultBackend>::new();
+//! let x = DefaultBackend::parse("1f", 16).unwrap();
+//! machine.tick(false, false, &x);
+//! assert_eq!(DefaultBackend::to_string_radix(machine.tick(true, false, &x), 10), "31");
+//! ```
+//!
+//! Both backends run the same conformance tests, and with both features on a
+//! cross-check drives identical stimulus through each and through
+//! [`ModuloMachine`](crate::ModuloMachine).
+
+use crate::P_STR;
+use std::fmt;
+
+#[cfg(not(any(feature = "gmp", feature = "pure-rust")))]
+compile_error!("modulo-machine needs a big-integer backend: enable the `gmp` or `pure-rust` feature");
+
+/// Non-negative big-integer arithmetic for [`BackendMachine`]
+///
+/// Every implementation must give identical results for identical inputs;
+/// the shared tests in this module check that they do.
+pub trait Backend {
+    /// Integer type; only non-negative values are ever constructed
+    type Int: Clone + fmt::Debug + PartialEq + PartialOrd;
+
+    /// Short name for reports
+    const NAME: &'static str;
+
+    fn zero() -> Self::Int;
+
+    /// Big-endian magnitude; an empty slice is zero
+    fn from_bytes_be(bytes: &[u8]) -> Self::Int;
+
+    /// Minimal big-endian magnitude: no leading zero bytes, empty for zero
+    fn to_bytes_be(x: &Self::Int) -> Vec<u8>;
+
+    /// Parse unsigned digits in `radix` (2 to 36), with no sign, prefix or
+    /// separators
+    fn parse(s: &str, radix: u32) -> Option<Self::Int>;
+
+    /// Lowercase digits in `radix` (2 to 36), `"0"` for zero
+    fn to_string_radix(x: &Self::Int, radix: u32) -> String;
+
+    /// Bits needed to represent `x`; 0 for zero
+    fn bits(x: &Self::Int) -> u32;
+
+    /// `x mod m` for `m > 0`
+    fn rem(x: &Self::Int, m: &Self::Int) -> Self::Int;
+}
+
+/// Whether `s` is a non-empty run of digits valid in `radix`, so backends
+/// agree on what they accept whatever their own parsers allow
+fn valid_digits(s: &str, radix: u32) -> bool {
+    (2..=36).contains(&radix) && !s.is_empty() && s.chars().all(|c| c.is_digit(radix))
+}
+
+/// `rug`/GMP integers (`gmp` feature)
+#[cfg(feature = "gmp")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Gmp;
+
+#[cfg(feature = "gmp")]
+impl Backend for Gmp {
+    type Int = rug::Integer;
+
+    const NAME: &'static str = "gmp";
+
+    fn zero() -> rug::Integer {
+        rug::Integer::new()
+    }
+
+    fn from_bytes_be(bytes: &[u8]) -> rug::Integer {
+        rug::Integer::from_digits(bytes, rug::integer::Order::Msf)
+    }
+
+    fn to_bytes_be(x: &rug::Integer) -> Vec<u8> {
+        x.to_digits(rug::integer::Order::Msf)
+    }
+
+    fn parse(s: &str, radix: u32) -> Option<rug::Integer> {
+        if !valid_digits(s, radix) {
+            return None;
+        }
+        rug::Integer::from_str_radix(s, radix as i32).ok()
+    }
+
+    fn to_string_radix(x: &rug::Integer, radix: u32) -> String {
+        x.to_string_radix(radix as i32)
+    }
+
+    fn bits(x: &rug::Integer) -> u32 {
+        x.significant_bits()
+    }
+
+    fn rem(x: &rug::Integer, m: &rug::Integer) -> rug::Integer {
+        rug::Integer::from(x % m)
+    }
+}
+
+/// Pure-Rust `num-bigint` integers (`pure-rust` feature)
+#[cfg(feature = "pure-rust")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NumBigint;
+
+#[cfg(feature = "pure-rust")]
+impl Backend for NumBigint {
+    type Int = num_bigint::BigUint;
+
+    const NAME: &'static str = "num-bigint";
+
+    fn zero() -> num_bigint::BigUint {
+        num_bigint::BigUint::default()
+    }
+
+    fn from_bytes_be(bytes: &[u8]) -> num_bigint::BigUint {
+        num_bigint::BigUint::from_bytes_be(bytes)
+    }
+
+    fn to_bytes_be(x: &num_bigint::BigUint) -> Vec<u8> {
+        if x.bits() == 0 {
+            return Vec::new();
+        }
+        x.to_bytes_be()
+    }
+
+    fn parse(s: &str, radix: u32) -> Option<num_bigint::BigUint> {
+        if !valid_digits(s, radix) {
+            return None;
+        }
+        num_bigint::BigUint::parse_bytes(s.as_bytes(), radix)
+    }
+
+    fn to_string_radix(x: &num_bigint::BigUint, radix: u32) -> String {
+        x.to_str_radix(radix)
+    }
+
+    fn bits(x: &num_bigint::BigUint) -> u32 {
+        x.bits() as u32
+    }
+
+    fn rem(x: &num_bigint::BigUint, m: &num_bigint::BigUint) -> num_bigint::BigUint {
+        x % m
+    }
+}
+
+/// The backend this build uses: [`Gmp`] with the `gmp` feature, otherwise
+/// [`NumBigint`]
+#[cfg(feature = "gmp")]
+pub type DefaultBackend = Gmp;
+/// The backend this build uses: [`Gmp`] with the `gmp` feature, otherwise
+/// [`NumBigint`]
+#[cfg(all(feature = "pure-rust", not(feature = "gmp")))]
+pub type DefaultBackend = NumBigint;
+
+/// The machine's default configuration on any [`Backend`]: async reset to
+/// zero, `x mod P` latched on each rising edge
+pub struct BackendMachine<B: Backend = DefaultBackend> {
+    p: B::Int,
+    output: B::Int,
+    clk_prev: bool,
+}
+
+impl<B: Backend> BackendMachine<B> {
+    /// A machine reducing modulo the specification's P
+    pub fn new() -> Self {
+        Self::build(B::parse(P_STR, 10).expect("Failed to parse prime P"))
+    }
+
+    /// A machine reducing modulo `p`: odd, at least 3 and at most 256 bits
+    pub fn with_modulus(p: B::Int) -> Result<Self, String> {
+        let bits = B::bits(&p);
+        if !(2..=256).contains(&bits) || B::rem(&p, &B::from_bytes_be(&[2])) == B::zero() {
+            return Err("modulus must be odd, at least 3 and at most 256 bits".to_string());
+        }
+        Ok(Self::build(p))
+    }
+
+    fn build(p: B::Int) -> Self {
+        Self {
+            p,
+            output: B::zero(),
+            clk_prev: false,
+        }
+    }
+
+    /// Apply one clock cycle and return the output after it
+    pub fn tick(&mut self, clk: bool, reset: bool, x: &B::Int) -> &B::Int {
+        if reset {
+            self.reset();
+            return &self.output;
+        }
+        if clk && !self.clk_prev {
+            self.output = B::rem(x, &self.p);
+        }
+        self.clk_prev = clk;
+        &self.output
+    }
+
+    /// Latch each value on its own rising edge and collect the outputs
+    pub fn process_values(&mut self, values: &[B::Int]) -> Vec<B::Int> {
+        values
+            .iter()
+            .map(|x| {
+                self.tick(false, false, x);
+                self.tick(true, false, x).clone()
+            })
+            .collect()
+    }
+
+    /// Return to the power-on state
+    pub fn reset(&mut self) {
+        self.output = B::zero();
+        self.clk_prev = false;
+    }
+
+    pub fn output(&self) -> &B::Int {
+        &self.output
+    }
+
+    pub fn modulus(&self) -> &B::Int {
+        &self.p
+    }
+}
+
+impl<B: Backend> Default for BackendMachine<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: Backend> Clone for BackendMachine<B> {
+    fn clone(&self) -> Self {
+        Self {
+            p: self.p.clone(),
+            output: self.output.clone(),
+            clk_prev: self.clk_prev,
+        }
+    }
+}
+
+impl<B: Backend> fmt::Debug for BackendMachine<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BackendMachine")
+            .field("backend", &B::NAME)
+            .field("output", &B::to_string_radix(&self.output, 16))
+            .field("clk_prev", &self.clk_prev)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// xorshift64* operands of up to `bytes` bytes, as big-endian bytes so
+    /// every backend can build them
+    fn operands(count: usize, bytes: usize) -> Vec<Vec<u8>> {
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        (0..count)
+            .map(|i| {
+                let len = i % (bytes + 1);
+                (0..len)
+                    .map(|_| {
+                        state ^= state >> 12;
+                        state ^= state << 25;
+                        state ^= state >> 27;
+                        (state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The conformance suite every backend must pass
+    macro_rules! backend_tests {
+        ($($name:ident: $backend:ty,)*) => {$(
+            mod $name {
+                use super::*;
+                type B = $backend;
+
+                fn int(s: &str) -> <B as Backend>::Int {
+                    B::parse(s, 10).unwrap()
+                }
+
+                #[test]
+                fn test_conversions() {
+                    assert_eq!(B::to_bytes_be(&B::zero()), Vec::<u8>::new());
+                    assert_eq!(B::from_bytes_be(&[]), B::zero());
+                    assert_eq!(B::to_bytes_be(&B::from_bytes_be(&[0, 0, 1, 2])), [1, 2]);
+                    assert_eq!(B::to_string_radix(&int("255"), 16), "ff");
+                    assert_eq!(B::to_string_radix(&B::zero(), 2), "0");
+                    assert_eq!(B::bits(&int("256")), 9);
+                    assert_eq!(B::bits(&B::zero()), 0);
+                    assert_eq!(B::parse("Zz", 36), Some(int("1295")));
+                    for bad in ["", "-1", "+1", "1_000", "0x10", "12a", " 1"] {
+                        assert_eq!(B::parse(bad, 10), None, "{:?}", bad);
+                    }
+                    assert_eq!(B::parse("1", 37), None);
+                }
+
+                #[test]
+                fn test_machine() {
+                    let mut machine = BackendMachine::<B>::new();
+                    let p = machine.modulus().clone();
+                    assert_eq!(B::to_string_radix(&p, 10), P_STR);
+                    assert_eq!(machine.output(), &B::zero());
+
+                    // A wide operand latches its remainder, on the edge only
+                    let big = B::from_bytes_be(&[B::to_bytes_be(&p), vec![0]].concat());
+                    let expected = B::rem(&big, &p);
+                    machine.tick(false, false, &big);
+                    assert_eq!(machine.tick(true, false, &big), &expected);
+                    assert_eq!(machine.tick(true, false, &int("9")), &expected);
+                    assert_eq!(machine.tick(true, true, &int("9")), &B::zero());
+                    assert_eq!(machine.tick(true, false, &int("9")), &int("9"));
+                    assert_eq!(machine.process_values(&[int("5"), p.clone()]), [int("5"), B::zero()]);
+
+                    assert!(BackendMachine::<B>::with_modulus(int("97")).is_ok());
+                    for bad in [int("1"), int("2"), int("96"), B::from_bytes_be(&[1; 33])] {
+                        assert!(BackendMachine::<B>::with_modulus(bad).is_err());
+                    }
+                    let mut small = BackendMachine::<B>::with_modulus(int("97")).unwrap();
+                    assert_eq!(small.process_values(&[int("1000")]), [int("30")]);
+                }
+            }
+        )*};
+    }
+
+    #[cfg(feature = "gmp")]
+    backend_tests! {
+        gmp: Gmp,
+    }
+
+    #[cfg(feature = "pure-rust")]
+    backend_tests! {
+        num_bigint: NumBigint,
+    }
+
+    #[cfg(all(feature = "gmp", feature = "pure-rust"))]
+    #[test]
+    fn test_backends_agree() {
+        let values = operands(500, 40);
+        let mut reference = crate::ModuloMachine::new();
+        let mut gmp = BackendMachine::<Gmp>::new();
+        let mut num = BackendMachine::<NumBigint>::new();
+        for (i, bytes) in values.iter().enumerate() {
+            let (clk, reset) = (!i.is_multiple_of(2), i.is_multiple_of(37));
+            let expected = reference.tick(clk, reset, &Gmp::from_bytes_be(bytes)).clone();
+            assert_eq!(gmp.tick(clk, reset, &Gmp::from_bytes_be(bytes)), &expected);
+            let num_out = num.tick(clk, reset, &NumBigint::from_bytes_be(bytes));
+            assert_eq!(NumBigint::to_bytes_be(num_out), Gmp::to_bytes_be(&expected), "tick {}", i);
+        }
+    }
+
+    #[test]
+    fn test_default_backend_reduces_wide_operands() {
+        type B = DefaultBackend;
+        let mut machine = BackendMachine::<B>::new();
+        let p = machine.modulus().clone();
+        for bytes in operands(200, 40) {
+            let x = B::from_bytes_be(&bytes);
+            let out = machine.process_values(std::slice::from_ref(&x)).remove(0);
+            assert!(out < p);
+            assert_eq!(out, B::rem(&x, &p));
+        }
+    }
+}
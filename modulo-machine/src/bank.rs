@@ -0,0 +1,98 @@
+//! An array of machines sharing one clock and reset, like a SIMD reduction
+//! unit with one reducer per lane.
+
+use crate::ModuloMachine;
+use rayon::prelude::*;
+use rug::Integer;
+
+/// K machines driven in lockstep, each with its own input
+///
+/// Lanes are independent apart from the shared clock and reset, so a tick
+/// processes them in parallel on rayon's global pool. Lanes may be configured
+/// differently, e.g. with different moduli via
+/// [`ModuloMachineBank::from_machines`].
+pub struct ModuloMachineBank {
+    lanes: Vec<ModuloMachine>,
+}
+
+impl ModuloMachineBank {
+    /// Create a bank of `k` default machines
+    pub fn new(k: usize) -> Self {
+        Self::from_machines((0..k).map(|_| ModuloMachine::new()).collect())
+    }
+
+    /// Create a bank from already configured machines, one per lane
+    pub fn from_machines(lanes: Vec<ModuloMachine>) -> Self {
+        Self { lanes }
+    }
+
+    /// Number of lanes
+    pub fn len(&self) -> usize {
+        self.lanes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lanes.is_empty()
+    }
+
+    /// Process one clock cycle on every lane
+    /// - clk, reset: shared by all lanes
+    /// - inputs: one value per lane
+    ///
+    /// Returns every lane's output, in lane order
+    pub fn tick(&mut self, clk: bool, reset: bool, inputs: &[Integer]) -> Vec<Integer> {
+        assert_eq!(inputs.len(), self.lanes.len(), "one input per lane");
+        self.lanes
+            .par_iter_mut()
+            .zip(inputs)
+            .map(|(machine, x)| machine.tick(clk, reset, x).clone())
+            .collect()
+    }
+
+    /// Reset every lane
+    pub fn reset(&mut self) {
+        self.lanes.iter_mut().for_each(ModuloMachine::reset);
+    }
+
+    /// Current output of every lane
+    pub fn outputs(&self) -> Vec<&Integer> {
+        self.lanes.iter().map(ModuloMachine::get_output).collect()
+    }
+
+    pub fn lane(&self, index: usize) -> &ModuloMachine {
+        &self.lanes[index]
+    }
+
+    pub fn lane_mut(&mut self, index: usize) -> &mut ModuloMachine {
+        &mut self.lanes[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Preset;
+
+    #[test]
+    fn test_lanes_in_lockstep() {
+        let mut bank = ModuloMachineBank::from_machines(Preset::ALL.iter().map(|&preset| ModuloMachine::for_preset(preset)).collect());
+        let inputs: Vec<Integer> = (0..bank.len()).map(|lane| ModuloMachine::create_large_input(299, lane as u64)).collect();
+
+        bank.tick(false, false, &inputs);
+        let outputs = bank.tick(true, false, &inputs);
+        for (lane, (x, output)) in inputs.iter().zip(&outputs).enumerate() {
+            assert_eq!(*output, Integer::from(x % bank.lane(lane).get_prime()));
+        }
+        assert_eq!(bank.outputs(), outputs.iter().collect::<Vec<_>>());
+
+        // Reset reaches every lane
+        let outputs = bank.tick(false, true, &inputs);
+        assert!(outputs.iter().all(|output| *output == 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "one input per lane")]
+    fn test_input_count_must_match() {
+        ModuloMachineBank::new(3).tick(true, false, &[Integer::new()]);
+    }
+}
@@ -0,0 +1,104 @@
+//! Barrett reduction on GMP integers.
+//!
+//! Replaces the division in `x mod P` with two multiplications and shifts by
+//! a constant computed once per modulus. Unlike [`crate::CtReducer`] it works
+//! for any modulus the machine accepts and makes no constant-time promise:
+//! the final correction loop runs zero to three times.
+
+use crate::X_BITS;
+use rug::{Assign, Integer};
+
+/// Barrett constant and shifts for one modulus
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BarrettReducer {
+    p: Integer,
+    /// `floor(2^n / p)` with `n = max(300, 2·bits(p))`
+    mu: Integer,
+    /// Bits of `x` dropped before multiplying by `mu`, `bits(p) - 1`
+    shift_in: u32,
+    /// Bits dropped from the product, `n - bits(p) + 1`
+    shift_out: u32,
+    /// Widest operand the estimate is valid for, `n`
+    max_bits: u32,
+}
+
+impl BarrettReducer {
+    /// Precompute the constant for `p`, which must be positive
+    pub fn new(p: &Integer) -> Self {
+        assert!(*p > 0, "Barrett reduction needs a positive modulus");
+        let k = p.significant_bits();
+        let n = X_BITS.max(2 * k);
+        let mu = Integer::from(Integer::u_pow_u(2, n)) / p;
+        Self {
+            p: p.clone(),
+            mu,
+            shift_in: k - 1,
+            shift_out: n - k + 1,
+            max_bits: n,
+        }
+    }
+
+    pub fn modulus(&self) -> &Integer {
+        &self.p
+    }
+
+    /// The precomputed `floor(2^n / p)`
+    pub fn mu(&self) -> &Integer {
+        &self.mu
+    }
+
+    /// Write `x mod p` into `out`
+    ///
+    /// Operands that are negative or wider than the constant was computed
+    /// for fall back to GMP's division.
+    pub fn reduce_into(&self, x: &Integer, out: &mut Integer) {
+        if *x < 0 || x.significant_bits() > self.max_bits {
+            out.assign(x % &self.p);
+            return;
+        }
+        // The quotient estimate is at most three below floor(x / p)
+        let mut qp = Integer::from(x >> self.shift_in);
+        qp *= &self.mu;
+        qp >>= self.shift_out;
+        qp *= &self.p;
+        out.assign(x - &qp);
+        while *out >= self.p {
+            *out -= &self.p;
+        }
+    }
+
+    pub fn reduce(&self, x: &Integer) -> Integer {
+        let mut out = Integer::new();
+        self.reduce_into(x, &mut out);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ModuloMachine, Preset, P_STR};
+
+    #[test]
+    fn test_matches_gmp() {
+        let mut moduli = vec![Integer::from_str_radix(P_STR, 10).unwrap(), Integer::from(3u32), Integer::from(1_000_003u32)];
+        moduli.extend(Preset::ALL.iter().map(|preset| preset.modulus()));
+        for p in &moduli {
+            let reducer = BarrettReducer::new(p);
+            let cases = [
+                Integer::new(),
+                Integer::from(p - 1u32),
+                p.clone(),
+                Integer::from(p * 3u32) - 1u32,
+                ModuloMachine::create_large_input(299, 123456789),
+                ModuloMachine::create_large_input(300, 0) - 1u32,
+                // Outside the estimate's range: GMP fallback
+                Integer::from(-7),
+                Integer::from(1) << 700u32,
+            ];
+            for x in &cases {
+                assert_eq!(reducer.reduce(x), Integer::from(x % p), "{} mod {}", x, p);
+            }
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Times every reduction backend over identical stimulus and prints a table.
+//!
+//! Usage: `cargo run --release --bin backend-bench [-- <cycles>]`
+
+use modulo_machine::{ModuloMachine, ReductionStrategy};
+use rug::Integer;
+use std::time::{Duration, Instant};
+
+const DEFAULT_CYCLES: usize = 200_000;
+
+const BACKENDS: [(&str, ReductionStrategy); 5] = [
+    ("gmp", ReductionStrategy::Gmp),
+    ("barrett (gmp)", ReductionStrategy::Barrett),
+    ("shift-subtract (gmp)", ReductionStrategy::ShiftSubtract),
+    ("constant-time (pure Rust)", ReductionStrategy::ConstantTime),
+    ("preinverted (cached reciprocal)", ReductionStrategy::Preinverted),
+];
+
+/// Deterministic 300-bit operands so every backend sees the same stimulus
+fn stimulus(count: usize) -> Vec<Integer> {
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    let mut next = || {
+        // xorshift64*
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    };
+
+    (0..count)
+        .map(|_| {
+            let limbs = [next(), next(), next(), next(), next()];
+            Integer::from_digits(&limbs, rug::integer::Order::Lsf).keep_bits(300)
+        })
+        .collect()
+}
+
+fn run(strategy: ReductionStrategy, inputs: &[Integer]) -> (Duration, Integer) {
+    let mut machine = ModuloMachine::new();
+    assert!(
+        machine.set_reduction_strategy(strategy),
+        "{:?} does not support the configured modulus",
+        strategy
+    );
+
+    // Fold every output into a checksum so backends can be cross-checked
+    let mut checksum = Integer::new();
+    let start = Instant::now();
+    for x in inputs {
+        machine.tick(false, false, x);
+        checksum ^= machine.tick(true, false, x);
+    }
+    (start.elapsed(), checksum)
+}
+
+fn main() {
+    let cycles = match std::env::args().nth(1) {
+        Some(arg) => arg.parse().unwrap_or_else(|_| {
+            eprintln!("usage: backend-bench [cycles]");
+            std::process::exit(2);
+        }),
+        None => DEFAULT_CYCLES,
+    };
+
+    println!("Backend Benchmark");
+    println!("=================");
+    println!("{} cycles of 300-bit operands mod P", cycles);
+    println!();
+
+    let inputs = stimulus(cycles);
+    let results: Vec<(&str, Duration, Integer)> = BACKENDS
+        .iter()
+        .map(|&(name, strategy)| {
+            let (elapsed, checksum) = run(strategy, &inputs);
+            (name, elapsed, checksum)
+        })
+        .collect();
+
+    let baseline = results[0].1.as_secs_f64();
+    println!(
+        "{:<28} {:>12} {:>10} {:>14} {:>9}",
+        "backend", "total", "ns/cycle", "cycles/s", "relative"
+    );
+    for (name, elapsed, _) in &results {
+        let secs = elapsed.as_secs_f64();
+        println!(
+            "{:<28} {:>12.2?} {:>10.1} {:>14.0} {:>8.2}x",
+            name,
+            elapsed,
+            secs * 1e9 / cycles.max(1) as f64,
+            cycles as f64 / secs,
+            secs / baseline
+        );
+    }
+
+    let agree = results.iter().all(|(_, _, checksum)| *checksum == results[0].2);
+    println!();
+    if agree {
+        println!("✅ All backends produced identical outputs");
+    } else {
+        println!("❌ Backend outputs differ");
+        std::process::exit(1);
+    }
+}
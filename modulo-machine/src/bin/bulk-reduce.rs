@@ -0,0 +1,149 @@
+//! Streams a file of operands through the machine without holding it in
+//! memory, for multi-gigabyte vector files.
+//!
+//! Usage: `bulk-reduce --input PATH [--output PATH] [--modulus N] [--progress N]`
+//!
+//! Each non-blank line of the input (`-` for stdin) is one operand, decimal or
+//! `0x` hex, latched on its own rising edge. Results go to `--output` (stdout
+//! by default), one hex value per line. Progress every `--progress` operands
+//! and a final summary (throughput, per-operand latency and the SHA-256 of
+//! the results) go to stderr, so two runs can be compared by digest alone.
+
+use modulo_machine::ModuloMachine;
+use rug::Integer;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process;
+use std::time::{Duration, Instant};
+
+const DEFAULT_PROGRESS: u64 = 1_000_000;
+
+fn usage() -> ! {
+    eprintln!("usage: bulk-reduce --input PATH [--output PATH] [--modulus N] [--progress N]");
+    process::exit(2);
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("❌ {}", message);
+    process::exit(1);
+}
+
+/// Decimal or `0x` hex, digits only
+fn parse_operand(s: &str) -> Option<Integer> {
+    let (radix, digits) = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => (16, hex),
+        None => (10, s),
+    };
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    Integer::from_str_radix(digits, radix as i32).ok()
+}
+
+/// Per-operand latency, without keeping every sample
+struct Latency {
+    min: Duration,
+    max: Duration,
+    total: Duration,
+}
+
+impl Latency {
+    fn record(&mut self, sample: Duration) {
+        self.min = self.min.min(sample);
+        self.max = self.max.max(sample);
+        self.total += sample;
+    }
+}
+
+fn rate(count: u64, elapsed: Duration) -> f64 {
+    count as f64 / elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+}
+
+fn main() {
+    let mut input = None;
+    let mut output = None;
+    let mut modulus = None;
+    let mut progress = DEFAULT_PROGRESS;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--input" => input = Some(value),
+            "--output" => output = Some(value),
+            "--modulus" => modulus = Some(parse_operand(&value).unwrap_or_else(|| usage())),
+            "--progress" => progress = value.parse().ok().filter(|&n| n > 0).unwrap_or_else(|| usage()),
+            _ => usage(),
+        }
+    }
+    let input = input.unwrap_or_else(|| usage());
+
+    let mut reader: Box<dyn BufRead> = if input == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(&input).unwrap_or_else(|e| fail(format!("{}: {}", input, e)))))
+    };
+    let mut writer: Box<dyn Write> = match &output {
+        Some(path) => Box::new(BufWriter::new(File::create(path).unwrap_or_else(|e| fail(format!("{}: {}", path, e))))),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let mut machine = match modulus {
+        Some(p) => ModuloMachine::with_modulus(p).unwrap_or_else(|e| fail(format!("bad modulus: {}", e))),
+        None => ModuloMachine::new(),
+    };
+
+    let mut hasher = Sha256::new();
+    let mut latency = Latency {
+        min: Duration::MAX,
+        max: Duration::ZERO,
+        total: Duration::ZERO,
+    };
+    let mut count = 0u64;
+    let mut line = String::new();
+    let mut line_number = 0u64;
+    let start = Instant::now();
+    loop {
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => line_number += 1,
+            Err(e) => fail(format!("{}: {}", input, e)),
+        }
+        let text = line.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let x = parse_operand(text).unwrap_or_else(|| fail(format!("{}: line {}: not an operand: {}", input, line_number, text)));
+
+        let tick_start = Instant::now();
+        machine.tick(false, false, &x);
+        let result = machine.tick(true, false, &x);
+        latency.record(tick_start.elapsed());
+
+        let result = format!("{}\n", result.to_string_radix(16));
+        hasher.update(result.as_bytes());
+        writer.write_all(result.as_bytes()).unwrap_or_else(|e| fail(format!("writing results: {}", e)));
+        count += 1;
+
+        if count.is_multiple_of(progress) {
+            eprintln!("… {} operands, {:.0} ops/s", count, rate(count, start.elapsed()));
+        }
+    }
+    writer.flush().unwrap_or_else(|e| fail(format!("writing results: {}", e)));
+    let elapsed = start.elapsed();
+
+    let digest: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+    eprintln!("Operands:   {}", count);
+    eprintln!("Elapsed:    {:.3} s", elapsed.as_secs_f64());
+    eprintln!("Throughput: {:.0} ops/s", rate(count, elapsed));
+    if count > 0 {
+        eprintln!(
+            "Latency:    min {:?}, mean {:?}, max {:?}",
+            latency.min,
+            Duration::from_secs_f64(latency.total.as_secs_f64() / count as f64),
+            latency.max
+        );
+    }
+    eprintln!("Digest:     sha256:{}", digest);
+}
@@ -0,0 +1,60 @@
+//! Generates a versioned golden test-vector archive for hardware CI.
+//!
+//! Usage: `vector-archive [--seed N] [--random N] [--out PATH] [--sv PATH]`
+//!
+//! `--sv` also writes the vectors as a SystemVerilog package
+//! (`modulo_vectors_pkg`).
+
+use modulo_machine::vectors;
+use std::fs::File;
+use std::io::BufWriter;
+use std::process;
+
+const DEFAULT_SEED: u64 = 1;
+const DEFAULT_RANDOM: usize = 1000;
+
+fn usage() -> ! {
+    eprintln!("usage: vector-archive [--seed N] [--random N] [--out PATH] [--sv PATH]");
+    process::exit(2);
+}
+
+fn main() {
+    let mut seed = DEFAULT_SEED;
+    let mut random = DEFAULT_RANDOM;
+    let mut out = None;
+    let mut sv = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--seed" => seed = value.parse().unwrap_or_else(|_| usage()),
+            "--random" => random = value.parse().unwrap_or_else(|_| usage()),
+            "--out" => out = Some(value),
+            "--sv" => sv = Some(value),
+            _ => usage(),
+        }
+    }
+
+    let set = vectors::generate(seed, random);
+    let out = out.unwrap_or_else(|| format!("{}.tar", set.archive_name()));
+
+    let file = File::create(&out).unwrap_or_else(|e| {
+        eprintln!("❌ Cannot create {}: {}", out, e);
+        process::exit(1);
+    });
+    if let Err(e) = set.write_archive(BufWriter::new(file)) {
+        eprintln!("❌ Failed to write {}: {}", out, e);
+        process::exit(1);
+    }
+
+    if let Some(sv) = sv {
+        if let Err(e) = std::fs::write(&sv, set.sv_package("modulo_vectors_pkg")) {
+            eprintln!("❌ Failed to write {}: {}", sv, e);
+            process::exit(1);
+        }
+        println!("Wrote SystemVerilog package to {}", sv);
+    }
+
+    println!("Wrote {} vectors (format v{}, seed {}) to {}", set.vectors.len(), vectors::FORMAT_VERSION, seed, out);
+}
@@ -0,0 +1,276 @@
+//! One place to configure a machine before it runs.
+//!
+//! ```
+//! use modulo_machine::{ModuloMachineBuilder, Preset, ReductionStrategy};
+//!
+//! let machine = ModuloMachineBuilder::new()
+//!     .preset(Preset::Bn254Fr)
+//!     .strategy(ReductionStrategy::Barrett)
+//!     .strict(true)
+//!     .build()
+//!     .unwrap();
+//! ```
+//!
+//! Every knob defaults to what [`ModuloMachine::new`] uses. [`build`]
+//! rejects combinations the machine would otherwise quietly resolve one way
+//! or the other.
+//!
+//! With the `serde` feature the builder doubles as a configuration file
+//! format: every field is optional and unknown fields are rejected.
+//!
+//! [`build`]: ModuloMachineBuilder::build
+
+use crate::{
+    Datapath, EdgeMode, InputWidthPolicy, ModuloMachine, ModulusError, Preset, ReductionStrategy, ResetKind, ResetValue,
+    SignedMode, P_STR,
+};
+use rug::Integer;
+use std::fmt;
+
+/// Reason a configuration cannot be built
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// The modulus fails [`crate::validate_machine_modulus`]
+    Modulus(ModulusError),
+    /// The strategy cannot reduce by the configured modulus
+    UnsupportedStrategy(ReductionStrategy),
+    /// Strict mode rejects wide inputs, so a width policy other than
+    /// `Reduce` would never apply
+    StrictWithWidthPolicy(InputWidthPolicy),
+    /// Lazy reduction latches values below the threshold as-is, so it must
+    /// not exceed the 256-bit output register
+    LazyThresholdTooWide { bits: u32 },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::Modulus(e) => write!(f, "invalid modulus: {}", e),
+            BuildError::UnsupportedStrategy(strategy) => {
+                write!(f, "{:?} reduction does not support the configured modulus", strategy)
+            }
+            BuildError::StrictWithWidthPolicy(policy) => {
+                write!(f, "strict mode overrides the {:?} input width policy", policy)
+            }
+            BuildError::LazyThresholdTooWide { bits } => {
+                write!(f, "lazy threshold of {} bits exceeds the 256-bit output register", bits)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl From<ModulusError> for BuildError {
+    fn from(e: ModulusError) -> Self {
+        BuildError::Modulus(e)
+    }
+}
+
+/// Builder for a configured [`ModuloMachine`]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct ModuloMachineBuilder {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_int::option"))]
+    modulus: Option<Integer>,
+    /// Left to the machine when unset: folding for special-form moduli, GMP
+    /// otherwise
+    strategy: Option<ReductionStrategy>,
+    datapath: Datapath,
+    edge: EdgeMode,
+    reset_kind: ResetKind,
+    signed_mode: SignedMode,
+    width_policy: InputWidthPolicy,
+    strict: bool,
+    reset_value: ResetValue,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_int::option"))]
+    lazy_threshold: Option<Integer>,
+    pipeline_depth: usize,
+    time_step: Option<u64>,
+}
+
+impl ModuloMachineBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reduce by `p` instead of the specification's P
+    pub fn modulus(mut self, p: Integer) -> Self {
+        self.modulus = Some(p);
+        self
+    }
+
+    pub fn preset(self, preset: Preset) -> Self {
+        self.modulus(preset.modulus())
+    }
+
+    pub fn strategy(mut self, strategy: ReductionStrategy) -> Self {
+        self.strategy = Some(strategy);
+        self
+    }
+
+    pub fn datapath(mut self, datapath: Datapath) -> Self {
+        self.datapath = datapath;
+        self
+    }
+
+    /// Clock transitions that latch
+    pub fn edge(mut self, edge: EdgeMode) -> Self {
+        self.edge = edge;
+        self
+    }
+
+    pub fn reset_kind(mut self, kind: ResetKind) -> Self {
+        self.reset_kind = kind;
+        self
+    }
+
+    pub fn signed_mode(mut self, mode: SignedMode) -> Self {
+        self.signed_mode = mode;
+        self
+    }
+
+    pub fn width_policy(mut self, policy: InputWidthPolicy) -> Self {
+        self.width_policy = policy;
+        self
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn reset_value(mut self, reset_value: ResetValue) -> Self {
+        self.reset_value = reset_value;
+        self
+    }
+
+    pub fn lazy_reduction(mut self, threshold: Integer) -> Self {
+        self.lazy_threshold = Some(threshold);
+        self
+    }
+
+    /// Edges between sampling an input and latching its result
+    pub fn pipeline_depth(mut self, depth: usize) -> Self {
+        self.pipeline_depth = depth;
+        self
+    }
+
+    pub fn time_step(mut self, units: u64) -> Self {
+        self.time_step = Some(units);
+        self
+    }
+
+    /// Validate the configuration and create the machine, in its reset state
+    pub fn build(self) -> Result<ModuloMachine, BuildError> {
+        if self.strict && self.width_policy != InputWidthPolicy::Reduce {
+            return Err(BuildError::StrictWithWidthPolicy(self.width_policy));
+        }
+        if let Some(threshold) = &self.lazy_threshold {
+            let bits = threshold.significant_bits();
+            if bits > 256 {
+                return Err(BuildError::LazyThresholdTooWide { bits });
+            }
+        }
+
+        let p = self
+            .modulus
+            .unwrap_or_else(|| Integer::from_str_radix(P_STR, 10).expect("Failed to parse prime P"));
+        let mut machine = ModuloMachine::with_modulus(p)?;
+        if let Some(strategy) = self.strategy {
+            if !machine.set_reduction_strategy(strategy) {
+                return Err(BuildError::UnsupportedStrategy(strategy));
+            }
+        }
+        machine.set_datapath(self.datapath);
+        machine.set_edge_mode(self.edge);
+        machine.set_reset_kind(self.reset_kind);
+        machine.set_signed_mode(self.signed_mode);
+        machine.set_input_width_policy(self.width_policy);
+        machine.set_strict(self.strict);
+        machine.set_reset_value(self.reset_value);
+        machine.set_lazy_reduction(self.lazy_threshold);
+        machine.set_pipeline_depth(self.pipeline_depth);
+        if let Some(units) = self.time_step {
+            machine.set_time_step(units);
+        }
+        machine.reset();
+        Ok(machine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_and_validate() {
+        let machine = ModuloMachineBuilder::new()
+            .preset(Preset::Goldilocks)
+            .strategy(ReductionStrategy::Barrett)
+            .signed_mode(SignedMode::Euclidean)
+            .edge(EdgeMode::Falling)
+            .reset_kind(ResetKind::Sync)
+            .reset_value(ResetValue::Custom(Integer::from(9u32)))
+            .strict(true)
+            .time_step(10)
+            .pipeline_depth(3)
+            .build()
+            .unwrap();
+        assert_eq!(machine.get_prime(), &Preset::Goldilocks.modulus());
+        assert_eq!(machine.reduction_strategy(), ReductionStrategy::Barrett);
+        assert_eq!(machine.signed_mode(), SignedMode::Euclidean);
+        assert_eq!(machine.edge_mode(), EdgeMode::Falling);
+        assert_eq!(machine.reset_kind(), ResetKind::Sync);
+        assert_eq!(*machine.get_output(), 9);
+        assert!(machine.is_strict());
+        assert_eq!(machine.time_step(), 10);
+        assert_eq!(machine.pipeline_depth(), 3);
+
+        let default = ModuloMachineBuilder::new().build().unwrap();
+        assert_eq!(default.get_prime(), ModuloMachine::new().get_prime());
+
+        // Without a strategy the machine picks one for the modulus
+        let mersenne = (Integer::from(1) << 127u32) - 1u32;
+        let machine = ModuloMachineBuilder::new().modulus(mersenne.clone()).build().unwrap();
+        assert_eq!(machine.reduction_strategy(), ReductionStrategy::Folding);
+        let machine = ModuloMachineBuilder::new().modulus(mersenne).strategy(ReductionStrategy::Gmp).build().unwrap();
+        assert_eq!(machine.reduction_strategy(), ReductionStrategy::Gmp);
+
+        let err = |builder: ModuloMachineBuilder| builder.build().err().unwrap();
+        assert_eq!(err(ModuloMachineBuilder::new().modulus(Integer::from(8u32))), BuildError::Modulus(ModulusError::Even));
+        assert_eq!(
+            err(ModuloMachineBuilder::new().preset(Preset::Goldilocks).strategy(ReductionStrategy::ConstantTime)),
+            BuildError::UnsupportedStrategy(ReductionStrategy::ConstantTime)
+        );
+        assert_eq!(
+            err(ModuloMachineBuilder::new().strict(true).width_policy(InputWidthPolicy::Wrap)),
+            BuildError::StrictWithWidthPolicy(InputWidthPolicy::Wrap)
+        );
+        assert_eq!(
+            err(ModuloMachineBuilder::new().lazy_reduction(Integer::from(1) << 256u32)),
+            BuildError::LazyThresholdTooWide { bits: 257 }
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_config_from_json() {
+        let config = r#"{ "modulus": "0xffffffff00000001", "strategy": "barrett", "datapath": { "mac": "3" }, "reset_value": "all_ones" }"#;
+        let builder: ModuloMachineBuilder = serde_json::from_str(config).unwrap();
+        let machine = builder.clone().build().unwrap();
+        assert_eq!(machine.get_prime(), &Preset::Goldilocks.modulus());
+        assert_eq!(machine.reduction_strategy(), ReductionStrategy::Barrett);
+        assert_eq!(machine.datapath(), &Datapath::Mac(Integer::from(3u32)));
+        assert_eq!(*machine.reset_value(), ResetValue::AllOnes);
+
+        let json = serde_json::to_string(&builder).unwrap();
+        assert!(json.contains(r#""modulus":"18446744069414584321""#));
+        assert!(serde_json::from_str::<ModuloMachineBuilder>(r#"{ "strategy": "fast" }"#).is_err());
+        assert!(serde_json::from_str::<ModuloMachineBuilder>(r#"{ "modulo": "7" }"#).is_err());
+    }
+}
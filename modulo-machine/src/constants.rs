@@ -0,0 +1,68 @@
+//! Modulus constants in the encodings hardware and firmware need.
+//!
+//! Generated once from the decimal value and checked against it by the tests
+//! below, so nobody has to re-derive limb order or byte order by hand.
+
+use crate::P_STR;
+
+/// One modulus in every encoding, fixed at compile time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModulusConstants {
+    /// Decimal string
+    pub decimal: &'static str,
+    /// `0x`-prefixed lowercase hex
+    pub hex: &'static str,
+    /// 64-bit limbs, least significant first
+    pub limbs_le: [u64; 4],
+    /// Bytes, most significant first
+    pub bytes_be: [u8; 32],
+    /// Barrett constant `floor(2^512 / p)` as 64-bit limbs, least significant
+    /// first (one bit wider than the modulus, hence five limbs)
+    pub barrett_mu_le: [u64; 5],
+}
+
+/// The specification's modulus P
+pub const P: ModulusConstants = ModulusConstants {
+    decimal: P_STR,
+    hex: "0xe7eb417862865b8ff6fa5c28e93008d69368f209ad2757cc370682fe26bdc75d",
+    limbs_le: [
+        0x370682fe26bdc75d,
+        0x9368f209ad2757cc,
+        0xf6fa5c28e93008d6,
+        0xe7eb417862865b8f,
+    ],
+    bytes_be: [
+        0xe7, 0xeb, 0x41, 0x78, 0x62, 0x86, 0x5b, 0x8f, 0xf6, 0xfa, 0x5c, 0x28, 0xe9, 0x30, 0x08, 0xd6,
+        0x93, 0x68, 0xf2, 0x09, 0xad, 0x27, 0x57, 0xcc, 0x37, 0x06, 0x82, 0xfe, 0x26, 0xbd, 0xc7, 0x5d,
+    ],
+    barrett_mu_le: [
+        0x670b5c00558f50e9,
+        0x93a0578c5d889b4e,
+        0x851e6105114f39f5,
+        0x1a94da6b4beeafe5,
+        0x1,
+    ],
+};
+
+#[cfg(all(test, feature = "gmp"))]
+mod tests {
+    use super::*;
+    use rug::integer::Order;
+    use rug::Integer;
+
+    fn check(constants: &ModulusConstants) {
+        let p = Integer::from_str_radix(constants.decimal, 10).unwrap();
+        let hex = constants.hex.strip_prefix("0x").unwrap();
+        assert_eq!(Integer::from_str_radix(hex, 16).unwrap(), p);
+        assert_eq!(Integer::from_digits(&constants.limbs_le, Order::Lsf), p);
+        assert_eq!(Integer::from_digits(&constants.bytes_be, Order::Msf), p);
+
+        let mu = Integer::from(Integer::u_pow_u(2, 512)) / &p;
+        assert_eq!(Integer::from_digits(&constants.barrett_mu_le, Order::Lsf), mu);
+    }
+
+    #[test]
+    fn test_encodings_agree() {
+        check(&P);
+    }
+}
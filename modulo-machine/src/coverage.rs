@@ -0,0 +1,390 @@
+//! Functional coverage of a run, for coverage-closure evidence.
+//!
+//! A [`Coverage`] collector watches each tick's pins and output and counts
+//! hits in four groups of bins:
+//!
+//! - `input_width`: bit width of each operand latched on a rising edge, in
+//!   buckets relative to the modulus width, up to the full 300-bit bus
+//! - `input_value`: latched operands at or near 0, P, 2P and `2^300 - 1`,
+//!   and which side of P and 2P they fall on
+//! - `output`: the output after each edge, by width and at 0, 1 and just
+//!   below P
+//! - `events`: reset on a rising edge, reset with the clock high, reset held
+//!   across ticks, the first edge after a reset, and the clock held high
+//!
+//! After a run, [`Coverage::percent`] gives the share of bins hit and
+//! [`Coverage::uncovered`] names the rest; the `Display` impl and
+//! [`Coverage::to_json`] render the whole report. Collectors from parallel
+//! runs combine with [`Coverage::merge`].
+
+use crate::model::ModuloModel;
+use crate::{TickInput, X_BITS};
+use rug::Integer;
+use serde_json::{json, Value};
+use std::fmt;
+
+/// Distance from 0, P, 2P and the bus maximum that counts as "near"
+pub const DEFAULT_WINDOW: u32 = 16;
+
+const INPUT_VALUE_BINS: [&str; 10] = [
+    "zero",
+    "near_zero",
+    "less_than_p",
+    "just_below_p",
+    "p",
+    "just_above_p",
+    "between_p_and_2p",
+    "near_2p",
+    "at_least_2p",
+    "near_bus_max",
+];
+const OUTPUT_VALUE_BINS: [&str; 3] = ["one", "just_below_p", "p_minus_1"];
+const EVENT_BINS: [&str; 5] = [
+    "reset_on_rising_edge",
+    "reset_with_clk_high",
+    "reset_held",
+    "edge_after_reset",
+    "clk_held_high",
+];
+
+/// One bin and how often it was hit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverBin {
+    pub name: String,
+    pub hits: u64,
+}
+
+/// A named set of bins
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverGroup {
+    pub name: &'static str,
+    pub bins: Vec<CoverBin>,
+}
+
+impl CoverGroup {
+    fn new(name: &'static str, bins: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            name,
+            bins: bins.into_iter().map(|name| CoverBin { name, hits: 0 }).collect(),
+        }
+    }
+
+    fn hit(&mut self, bin: usize) {
+        self.bins[bin].hits += 1;
+    }
+
+    fn hit_named(&mut self, name: &str) {
+        let bin = self.bins.iter().position(|b| b.name == name).expect("bin exists");
+        self.hit(bin);
+    }
+
+    /// Bins hit at least once
+    pub fn covered(&self) -> usize {
+        self.bins.iter().filter(|b| b.hits > 0).count()
+    }
+
+    pub fn percent(&self) -> f64 {
+        100.0 * self.covered() as f64 / self.bins.len() as f64
+    }
+}
+
+/// Width buckets: zero, then quarters of `1..=top` (skipping empty ones)
+fn width_buckets(top: u32) -> Vec<(u32, u32)> {
+    let mut buckets = vec![(0, 0)];
+    for i in 0..4 {
+        let (lo, hi) = (1 + top * i / 4, top * (i + 1) / 4);
+        if lo <= hi {
+            buckets.push((lo, hi));
+        }
+    }
+    buckets
+}
+
+fn bucket_name(&(lo, hi): &(u32, u32)) -> String {
+    if lo == hi {
+        format!("{}_bits", lo)
+    } else {
+        format!("{}..={}_bits", lo, hi)
+    }
+}
+
+/// Bins observed inputs, outputs and reset/clock events
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    p: Integer,
+    two_p: Integer,
+    bus_max: Integer,
+    window: u32,
+    input_buckets: Vec<(u32, u32)>,
+    output_buckets: Vec<(u32, u32)>,
+    input_width: CoverGroup,
+    input_value: CoverGroup,
+    output: CoverGroup,
+    events: CoverGroup,
+    /// Clock as the machine saw it, cleared by reset
+    clk_prev: bool,
+    /// Clock pin level on the previous tick
+    clk_pin_prev: bool,
+    reset_prev: bool,
+    edges_since_reset: u64,
+}
+
+impl Coverage {
+    /// Bins for reduction modulo `p`, with [`DEFAULT_WINDOW`]
+    pub fn new(p: &Integer) -> Self {
+        Self::with_window(p, DEFAULT_WINDOW)
+    }
+
+    /// Bins for reduction modulo `p`, where "near" means within `window`
+    pub fn with_window(p: &Integer, window: u32) -> Self {
+        let p_bits = p.significant_bits();
+        // Operands narrower than P, exactly P's width, wider, and the full bus
+        let mut input_buckets = width_buckets(p_bits - 1);
+        input_buckets.push((p_bits, p_bits));
+        if p_bits < X_BITS - 1 {
+            input_buckets.push((p_bits + 1, X_BITS - 1));
+        }
+        input_buckets.push((X_BITS, X_BITS));
+        let output_buckets = width_buckets(p_bits);
+
+        let input_width = CoverGroup::new("input_width", input_buckets.iter().map(bucket_name));
+        let input_value = CoverGroup::new("input_value", INPUT_VALUE_BINS.iter().map(|s| s.to_string()));
+        let output = CoverGroup::new("output", output_buckets.iter().map(bucket_name).chain(OUTPUT_VALUE_BINS.iter().map(|s| s.to_string())));
+        let events = CoverGroup::new("events", EVENT_BINS.iter().map(|s| s.to_string()));
+        Self {
+            p: p.clone(),
+            two_p: Integer::from(p * 2u32),
+            bus_max: (Integer::from(1) << X_BITS) - 1u32,
+            window,
+            input_buckets,
+            output_buckets,
+            input_width,
+            input_value,
+            output,
+            events,
+            clk_prev: false,
+            clk_pin_prev: false,
+            reset_prev: false,
+            edges_since_reset: 0,
+        }
+    }
+
+    /// Record one tick: the pins driven and the output after it
+    pub fn observe(&mut self, clk: bool, reset: bool, x: &Integer, output: &Integer) {
+        let rising = clk && !self.clk_prev;
+        if reset {
+            if clk && !self.clk_pin_prev {
+                self.events.hit_named("reset_on_rising_edge");
+            }
+            if clk {
+                self.events.hit_named("reset_with_clk_high");
+            }
+            if self.reset_prev {
+                self.events.hit_named("reset_held");
+            }
+        } else if rising {
+            if self.edges_since_reset == 0 {
+                self.events.hit_named("edge_after_reset");
+            }
+            self.edges_since_reset += 1;
+            self.sample_input(x);
+            self.sample_output(output);
+        } else if clk {
+            self.events.hit_named("clk_held_high");
+        }
+        if reset {
+            self.edges_since_reset = 0;
+        }
+        self.clk_prev = clk && !reset;
+        self.clk_pin_prev = clk;
+        self.reset_prev = reset;
+    }
+
+    /// Apply `stimulus` to `model`, observing every tick, and return the
+    /// outputs
+    pub fn run<M: ModuloModel>(&mut self, model: &mut M, stimulus: &[TickInput]) -> Vec<Integer> {
+        stimulus
+            .iter()
+            .map(|t| {
+                let output = model.tick(t.clk, t.reset, &t.x);
+                self.observe(t.clk, t.reset, &t.x, &output);
+                output
+            })
+            .collect()
+    }
+
+    fn sample_input(&mut self, x: &Integer) {
+        let bits = x.significant_bits();
+        // Over-wide operands are the width policy's concern, not a bucket
+        if let Some(bin) = self.input_buckets.iter().position(|&(lo, hi)| (lo..=hi).contains(&bits)) {
+            self.input_width.hit(bin);
+        }
+
+        let near = |a: &Integer, b: &Integer| Integer::from(a - b).abs() <= self.window;
+        let mut bins = Vec::new();
+        if *x == 0 {
+            bins.push("zero");
+        } else if *x <= self.window {
+            bins.push("near_zero");
+        }
+        match x.cmp(&self.p) {
+            std::cmp::Ordering::Less => {
+                bins.push("less_than_p");
+                if near(x, &self.p) {
+                    bins.push("just_below_p");
+                }
+            }
+            std::cmp::Ordering::Equal => bins.push("p"),
+            std::cmp::Ordering::Greater => {
+                if near(x, &self.p) {
+                    bins.push("just_above_p");
+                }
+                bins.push(if *x < self.two_p { "between_p_and_2p" } else { "at_least_2p" });
+            }
+        }
+        if near(x, &self.two_p) {
+            bins.push("near_2p");
+        }
+        if near(x, &self.bus_max) && *x <= self.bus_max {
+            bins.push("near_bus_max");
+        }
+        for bin in bins {
+            self.input_value.hit_named(bin);
+        }
+    }
+
+    fn sample_output(&mut self, output: &Integer) {
+        let bits = output.significant_bits();
+        if let Some(bin) = self.output_buckets.iter().position(|&(lo, hi)| (lo..=hi).contains(&bits)) {
+            self.output.hit(bin);
+        }
+        if *output == 1 {
+            self.output.hit_named("one");
+        }
+        if *output < self.p && Integer::from(&self.p - output) <= self.window {
+            self.output.hit_named("just_below_p");
+            if Integer::from(&self.p - output) == 1 {
+                self.output.hit_named("p_minus_1");
+            }
+        }
+    }
+
+    pub fn groups(&self) -> [&CoverGroup; 4] {
+        [&self.input_width, &self.input_value, &self.output, &self.events]
+    }
+
+    /// Share of all bins hit, 0 to 100
+    pub fn percent(&self) -> f64 {
+        let (covered, total) = self.groups().iter().fold((0, 0), |(c, t), g| (c + g.covered(), t + g.bins.len()));
+        100.0 * covered as f64 / total as f64
+    }
+
+    /// `group.bin` names of bins never hit
+    pub fn uncovered(&self) -> Vec<String> {
+        self.groups()
+            .iter()
+            .flat_map(|g| g.bins.iter().filter(|b| b.hits == 0).map(move |b| format!("{}.{}", g.name, b.name)))
+            .collect()
+    }
+
+    /// Add `other`'s hits to this collector's
+    ///
+    /// # Panics
+    ///
+    /// If the collectors were built for different moduli or windows.
+    pub fn merge(&mut self, other: &Coverage) {
+        assert!(self.p == other.p && self.window == other.window, "merging coverage of different bins");
+        for (mine, theirs) in [
+            (&mut self.input_width, &other.input_width),
+            (&mut self.input_value, &other.input_value),
+            (&mut self.output, &other.output),
+            (&mut self.events, &other.events),
+        ] {
+            for (bin, other_bin) in mine.bins.iter_mut().zip(&theirs.bins) {
+                bin.hits += other_bin.hits;
+            }
+        }
+    }
+
+    /// `{percent, groups: {name: {percent, bins: {name: hits}}}}`
+    pub fn to_json(&self) -> Value {
+        let groups: serde_json::Map<String, Value> = self
+            .groups()
+            .iter()
+            .map(|g| {
+                let bins: serde_json::Map<String, Value> = g.bins.iter().map(|b| (b.name.clone(), json!(b.hits))).collect();
+                (g.name.to_string(), json!({ "percent": g.percent(), "bins": bins }))
+            })
+            .collect();
+        json!({ "percent": self.percent(), "groups": groups })
+    }
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "coverage: {:.1}%", self.percent())?;
+        for group in self.groups() {
+            write!(f, "\n  {} ({}/{}, {:.1}%)", group.name, group.covered(), group.bins.len(), group.percent())?;
+            for bin in &group.bins {
+                let mark = if bin.hits > 0 { ' ' } else { '!' };
+                write!(f, "\n   {} {:<24} {}", mark, bin.name, bin.hits)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectors::corner_cases;
+    use crate::ModuloMachine;
+
+    fn edges(values: &[Integer]) -> Vec<TickInput> {
+        values.iter().flat_map(|x| [TickInput::new(false, false, x.clone()), TickInput::new(true, false, x.clone())]).collect()
+    }
+
+    #[test]
+    fn test_corner_cases_close_value_bins() {
+        let mut machine = ModuloMachine::new();
+        let mut coverage = Coverage::new(machine.get_prime());
+        let values: Vec<Integer> = corner_cases(machine.get_prime()).into_iter().map(|(_, x)| x).collect();
+        coverage.run(&mut machine, &edges(&values));
+
+        assert_eq!(coverage.input_width.percent(), 100.0, "{}", coverage);
+        assert_eq!(coverage.input_value.percent(), 100.0, "{}", coverage);
+        assert_eq!(coverage.output.percent(), 100.0, "{}", coverage);
+        // Only the first edge follows power-on
+        assert_eq!(coverage.uncovered(), ["events.reset_on_rising_edge", "events.reset_with_clk_high", "events.reset_held", "events.clk_held_high"]);
+        assert!(coverage.percent() < 100.0);
+        assert!(coverage.to_string().contains("! reset_held"));
+    }
+
+    #[test]
+    fn test_events_and_merge() {
+        let mut machine = ModuloMachine::new();
+        let x = Integer::from(5);
+        let tick = |clk, reset| TickInput::new(clk, reset, x.clone());
+        let mut coverage = Coverage::new(machine.get_prime());
+        coverage.run(&mut machine, &[tick(true, true), tick(true, true), tick(false, false), tick(true, false), tick(true, false)]);
+        assert!(coverage.uncovered().iter().all(|bin| !bin.starts_with("events.")), "{:?}", coverage.uncovered());
+        assert_eq!(coverage.events.bins[0].hits, 1);
+
+        let mut merged = Coverage::new(machine.get_prime());
+        merged.merge(&coverage);
+        merged.merge(&coverage);
+        assert_eq!(merged.events.bins[1].hits, 4);
+        let json = merged.to_json();
+        assert_eq!(json["groups"]["events"]["percent"], 100.0);
+        assert_eq!(json["groups"]["input_value"]["bins"]["less_than_p"], 2);
+    }
+
+    #[test]
+    fn test_small_modulus_buckets() {
+        let coverage = Coverage::new(&Integer::from(97));
+        let names: Vec<&str> = coverage.input_width.bins.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, ["0_bits", "1_bits", "2..=3_bits", "4_bits", "5..=6_bits", "7_bits", "8..=299_bits", "300_bits"]);
+        let names: Vec<&str> = coverage.output.bins.iter().take(5).map(|b| b.name.as_str()).collect();
+        assert_eq!(names, ["0_bits", "1_bits", "2..=3_bits", "4..=5_bits", "6..=7_bits"]);
+    }
+}
@@ -0,0 +1,216 @@
+//! CSV stimulus and results, for spreadsheet-driven test plans.
+//!
+//! Files have a header row naming the columns `cycle`, `clk`, `reset`, `x`
+//! and, optionally, `expected`, in any order. `clk` and `reset` are `0`/`1`
+//! or `true`/`false`; `x` and `expected` are decimal or `0x`-prefixed hex.
+//! Rows with an `expected` value make [`run`] a self-checking comparison;
+//! an empty cell leaves that cycle unchecked.
+//!
+//! [`write_results`] exports a run in the same layout with the model's
+//! output in the `expected` column, so the export is itself a self-checking
+//! vector file.
+
+use crate::{ModuloMachine, TickInput};
+use rug::Integer;
+use std::io::{self, BufRead, Write};
+
+/// One stimulus row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvRow {
+    pub cycle: u64,
+    pub tick: TickInput,
+    pub expected: Option<Integer>,
+}
+
+/// A checked cycle whose output differed from `expected`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvMismatch {
+    pub cycle: u64,
+    pub expected: Integer,
+    pub actual: Integer,
+}
+
+/// Outputs of a CSV run and the cycles that failed their check
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CsvReport {
+    /// Output after each row, in file order
+    pub outputs: Vec<Integer>,
+    /// Rows with an `expected` value
+    pub checked: usize,
+    pub mismatches: Vec<CsvMismatch>,
+}
+
+impl CsvReport {
+    pub fn is_clean(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+const COLUMNS: [&str; 5] = ["cycle", "clk", "reset", "x", "expected"];
+
+/// Read every row; blank lines are skipped
+pub fn read_csv<R: BufRead>(reader: R) -> io::Result<Vec<CsvRow>> {
+    let mut lines = reader.lines().enumerate();
+    let header = loop {
+        match lines.next() {
+            Some((_, line)) => {
+                let line = line?;
+                if !line.trim().is_empty() {
+                    break line;
+                }
+            }
+            None => return Ok(Vec::new()),
+        }
+    };
+
+    // Position of each known column; `expected` may be absent
+    let names: Vec<String> = header.split(',').map(|name| name.trim().to_ascii_lowercase()).collect();
+    let mut positions = [None; 5];
+    for (column, position) in COLUMNS.iter().zip(&mut positions) {
+        *position = names.iter().position(|name| name == column);
+    }
+    if let Some(missing) = COLUMNS[..4].iter().zip(&positions).find(|(_, position)| position.is_none()) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("header: missing column `{}`", missing.0)));
+    }
+    let [Some(cycle), Some(clk), Some(reset), Some(x), expected] = positions else {
+        unreachable!("required columns were checked above");
+    };
+
+    let mut rows = Vec::new();
+    for (i, line) in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let invalid = |column: &str| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: bad `{}`", i + 1, column));
+        let cells: Vec<&str> = line.split(',').map(str::trim).collect();
+        let cell = |position: usize| cells.get(position).copied().unwrap_or("");
+
+        let expected = match expected.map(cell) {
+            None | Some("") => None,
+            Some(text) => Some(integer(text).ok_or_else(|| invalid("expected"))?),
+        };
+        rows.push(CsvRow {
+            cycle: cell(cycle).parse().map_err(|_| invalid("cycle"))?,
+            tick: TickInput::new(
+                bit(cell(clk)).ok_or_else(|| invalid("clk"))?,
+                bit(cell(reset)).ok_or_else(|| invalid("reset"))?,
+                integer(cell(x)).ok_or_else(|| invalid("x"))?,
+            ),
+            expected,
+        });
+    }
+    Ok(rows)
+}
+
+/// Tick `machine` through `rows`, comparing each output with the row's
+/// `expected` value where there is one
+pub fn run(machine: &mut ModuloMachine, rows: &[CsvRow]) -> CsvReport {
+    let mut report = CsvReport::default();
+    for row in rows {
+        let actual = machine.tick(row.tick.clk, row.tick.reset, &row.tick.x).clone();
+        if let Some(expected) = &row.expected {
+            report.checked += 1;
+            if *expected != actual {
+                report.mismatches.push(CsvMismatch {
+                    cycle: row.cycle,
+                    expected: expected.clone(),
+                    actual: actual.clone(),
+                });
+            }
+        }
+        report.outputs.push(actual);
+    }
+    report
+}
+
+/// Write `cycle,clk,reset,x,expected` rows with the output as `expected`,
+/// cycles numbered from 0
+pub fn write_results<'a, W: Write>(
+    mut writer: W,
+    entries: impl IntoIterator<Item = (&'a TickInput, &'a Integer)>,
+) -> io::Result<()> {
+    writeln!(writer, "{}", COLUMNS.join(","))?;
+    for (cycle, (t, output)) in entries.into_iter().enumerate() {
+        writeln!(writer, "{},{},{},{},{}", cycle, t.clk as u8, t.reset as u8, hex(&t.x), hex(output))?;
+    }
+    Ok(())
+}
+
+fn bit(s: &str) -> Option<bool> {
+    match s {
+        "0" | "false" => Some(false),
+        "1" | "true" => Some(true),
+        _ => None,
+    }
+}
+
+fn integer(s: &str) -> Option<Integer> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let (radix, body) = match digits.strip_prefix("0x") {
+        Some(hex) => (16, hex),
+        None => (10, digits),
+    };
+    if body.is_empty() || !body.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    let magnitude = Integer::from_str_radix(body, radix as i32).ok()?;
+    Some(if negative { -magnitude } else { magnitude })
+}
+
+fn hex(x: &Integer) -> String {
+    if *x < 0 {
+        format!("-0x{}", Integer::from(-x).to_string_radix(16))
+    } else {
+        format!("0x{}", x.to_string_radix(16))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_checking_run() {
+        let p = ModuloMachine::new().get_prime().clone();
+        let text = format!(
+            "x, clk, reset, cycle, expected\n0, 0, 1, 0, 0\n{0}, 0, 0, 1,\n{0}, 1, 0, 2, 9\n\n0x10, true, false, 3, 9\n",
+            Integer::from(&p + 9u32)
+        );
+        let rows = read_csv(text.as_bytes()).unwrap();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[1].expected, None);
+        assert_eq!(rows[3].tick, TickInput::new(true, false, Integer::from(16u32)));
+
+        // Cycle 3 holds 9 (no new edge), so every check passes
+        let report = run(&mut ModuloMachine::new(), &rows);
+        assert!(report.is_clean());
+        assert_eq!(report.checked, 3);
+
+        // Export, then feed the export back as a self-checking vector
+        let ticks: Vec<TickInput> = rows.iter().map(|row| row.tick.clone()).collect();
+        let mut exported = Vec::new();
+        write_results(&mut exported, ticks.iter().zip(&report.outputs)).unwrap();
+        let rows = read_csv(&exported[..]).unwrap();
+        assert!(rows.iter().all(|row| row.expected.is_some()));
+        assert!(run(&mut ModuloMachine::new(), &rows).is_clean());
+
+        // A wrong expectation is reported by cycle
+        let wrong = "cycle,clk,reset,x,expected\n7,1,0,5,6\n";
+        let report = run(&mut ModuloMachine::new(), &read_csv(wrong.as_bytes()).unwrap());
+        assert_eq!(report.mismatches, [CsvMismatch { cycle: 7, expected: Integer::from(6), actual: Integer::from(5) }]);
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!(read_csv("cycle,clk,x\n0,1,5\n".as_bytes()).unwrap_err().to_string().contains("`reset`"));
+        for bad in ["0,2,0,5", "0,1,0,zz", "a,1,0,5", "0,1,0,0x"] {
+            let text = format!("cycle,clk,reset,x\n{}\n", bad);
+            assert!(read_csv(text.as_bytes()).unwrap_err().to_string().starts_with("line 2"), "{}", bad);
+        }
+        assert_eq!(read_csv("".as_bytes()).unwrap(), []);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::fixed::FixedReducer;
+use rug::integer::Order;
+use rug::Integer;
+
+pub use crate::fixed::{P_LIMBS, X_LIMBS};
+
+/// Constant-time Barrett reducer for moduli in `[2^192, 2^256)`
+///
+/// A [`FixedReducer`] built from an `Integer` modulus: the classic base-2^64
+/// Barrett reduction (HAC 14.42) with a precomputed `mu = floor(2^512 / P)`,
+/// followed by exactly two masked conditional subtractions. The instruction
+/// sequence does not depend on the operand, unlike GMP's division which skips
+/// work for short inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CtReducer {
+    inner: FixedReducer,
+}
+
+impl CtReducer {
+    /// Precompute the Barrett constant for `p`
+    ///
+    /// Returns `None` unless `p` is odd and its top limb is occupied, which
+    /// keeps the quotient estimate within two subtractions of exact.
+    pub fn new(p: &Integer) -> Option<Self> {
+        if p.is_even() || p.significant_bits() <= 192 || p.significant_bits() > 256 {
+            return None;
+        }
+
+        let mut p_limbs = [0u64; P_LIMBS];
+        p.write_digits(&mut p_limbs, Order::Lsf);
+        Some(Self {
+            inner: FixedReducer::new(p_limbs)?,
+        })
+    }
+
+    /// Compute `x mod P` for a 320-bit little-endian operand in constant time
+    pub fn reduce(&self, x: &[u64; X_LIMBS]) -> [u64; P_LIMBS] {
+        self.inner.reduce(x)
+    }
+
+    /// Reduce an Integer operand through the constant-time path
+    ///
+    /// Returns `None` for negative operands or ones wider than 320 bits, which
+    /// have no fixed-limb representation.
+    pub fn reduce_integer(&self, x: &Integer) -> Option<Integer> {
+        let limbs = to_limbs(x)?;
+        Some(Integer::from_digits(&self.reduce(&limbs), Order::Lsf))
+    }
+}
+
+/// Convert a non-negative Integer of at most 320 bits to fixed limbs
+pub fn to_limbs(x: &Integer) -> Option<[u64; X_LIMBS]> {
+    if *x < 0 || x.significant_bits() > 64 * X_LIMBS as u32 {
+        return None;
+    }
+    let mut limbs = [0u64; X_LIMBS];
+    x.write_digits(&mut limbs, Order::Lsf);
+    Some(limbs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ModuloMachine, P_STR};
+    use std::time::Instant;
+
+    fn p() -> Integer {
+        Integer::from_str_radix(P_STR, 10).unwrap()
+    }
+
+    #[test]
+    fn test_matches_gmp() {
+        let p = p();
+        let reducer = CtReducer::new(&p).unwrap();
+        let max = Integer::from(Integer::u_pow_u(2, 320)) - 1u32;
+        let cases = [
+            Integer::new(),
+            Integer::from(1u32),
+            Integer::from(&p - 1u32),
+            p.clone(),
+            Integer::from(&p + 1u32),
+            Integer::from(&p * 3u32) - 1u32,
+            ModuloMachine::create_large_input(300, 0) - 1u32,
+            ModuloMachine::create_large_input(299, 123456789),
+            max,
+        ];
+        for x in &cases {
+            assert_eq!(reducer.reduce_integer(x).unwrap(), Integer::from(x % &p), "x = {}", x);
+        }
+    }
+
+    #[test]
+    fn test_rejects_unsupported_inputs() {
+        assert!(CtReducer::new(&Integer::from(1_000_003u32)).is_none());
+        assert!(CtReducer::new(&(p() * 2u32)).is_none());
+
+        let reducer = CtReducer::new(&p()).unwrap();
+        assert!(reducer.reduce_integer(&Integer::from(-5)).is_none());
+        assert!(reducer.reduce_integer(&ModuloMachine::create_large_input(320, 0)).is_none());
+    }
+
+    #[test]
+    fn test_timing_independent_of_operand() {
+        // Compare the fastest batch for a tiny operand against the fastest for
+        // a full-width one. Noise from preemption and other tests only ever
+        // adds time, so the minimum over many interleaved batches tracks the
+        // true cost, and the tolerance leaves room for whatever noise is left;
+        // GMP's division on the same pair differs by far more.
+        let reducer = CtReducer::new(&p()).unwrap();
+        let small = [1u64, 0, 0, 0, 0];
+        let large = to_limbs(&(ModuloMachine::create_large_input(300, 0) - 1u32)).unwrap();
+
+        let time = |x: &[u64; X_LIMBS]| {
+            let start = Instant::now();
+            for _ in 0..200 {
+                std::hint::black_box(reducer.reduce(std::hint::black_box(x)));
+            }
+            start.elapsed().as_nanos() as f64
+        };
+
+        let (mut small_min, mut large_min) = (f64::INFINITY, f64::INFINITY);
+        for _ in 0..500 {
+            small_min = small_min.min(time(&small));
+            large_min = large_min.min(time(&large));
+        }
+
+        let ratio = large_min / small_min;
+        assert!((0.5..2.0).contains(&ratio), "minimum batch time ratio {:.3}", ratio);
+    }
+}
@@ -0,0 +1,121 @@
+//! SystemVerilog DPI-C entry points (`dpi` feature).
+//!
+//! Build a shared library for the simulator with
+//! `cargo rustc --release --lib --features dpi --crate-type cdylib`, and
+//! import the functions with `dpi/modulo_machine_dpi.sv`:
+//!
+//! ```systemverilog
+//! chandle m = mm_new();
+//! byte unsigned o[32];
+//! mm_tick(m, 1'b1, 1'b0, x);
+//! mm_get_output_bytes(m, o);
+//! ```
+//!
+//! A machine is an opaque `chandle`. `x` crosses as the simulator's
+//! canonical `bit [299:0]` representation (ten 32-bit words, least
+//! significant first); the output comes back as 32 bytes, least significant
+//! first, two's complement if a signed mode made it negative.
+
+use crate::{ModuloMachine, X_BITS};
+use rug::integer::Order;
+use rug::Integer;
+
+/// 32-bit words in a `bit [299:0]` argument
+const X_WORDS: usize = 10;
+/// Bytes in the output
+const O_BYTES: usize = 32;
+
+/// Create a machine with the specification's modulus; free it with
+/// [`mm_free`]
+#[no_mangle]
+pub extern "C" fn mm_new() -> *mut ModuloMachine {
+    Box::into_raw(Box::new(ModuloMachine::new()))
+}
+
+/// Destroy a machine created by [`mm_new`]; null is ignored
+///
+/// # Safety
+///
+/// `m` must be null or a pointer from [`mm_new`] not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn mm_free(m: *mut ModuloMachine) {
+    if !m.is_null() {
+        drop(Box::from_raw(m));
+    }
+}
+
+/// Return the machine to its power-on state
+///
+/// # Safety
+///
+/// `m` must be a live pointer from [`mm_new`].
+#[no_mangle]
+pub unsafe extern "C" fn mm_reset(m: *mut ModuloMachine) {
+    (*m).reset();
+}
+
+/// Apply one tick; `clk` and `reset` are DPI `svBit`s (0 or 1)
+///
+/// # Safety
+///
+/// `m` must be a live pointer from [`mm_new`] and `x` must point to ten
+/// readable 32-bit words.
+#[no_mangle]
+pub unsafe extern "C" fn mm_tick(m: *mut ModuloMachine, clk: u8, reset: u8, x: *const u32) {
+    let words = std::slice::from_raw_parts(x, X_WORDS);
+    let x = Integer::from(Integer::from_digits(words, Order::Lsf).keep_bits_ref(X_BITS));
+    (*m).tick(clk & 1 != 0, reset & 1 != 0, &x);
+}
+
+/// Copy the 256-bit output register into `out`, least significant byte first
+///
+/// # Safety
+///
+/// `m` must be a live pointer from [`mm_new`] and `out` must point to 32
+/// writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mm_get_output_bytes(m: *const ModuloMachine, out: *mut u8) {
+    let out = std::slice::from_raw_parts_mut(out, O_BYTES);
+    output_bytes((*m).get_output(), out);
+}
+
+fn output_bytes(output: &Integer, out: &mut [u8]) {
+    Integer::from(output.keep_bits_ref(256)).write_digits(out, Order::Lsf);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(x: &Integer) -> [u32; X_WORDS] {
+        let mut words = [0; X_WORDS];
+        x.write_digits(&mut words, Order::Lsf);
+        words
+    }
+
+    #[test]
+    fn test_tick_and_read_output() {
+        let p = ModuloMachine::new().get_prime().clone();
+        let x = (Integer::from(1) << 299) + 0x1234u32;
+        let expected = Integer::from(&x % &p);
+        unsafe {
+            let m = mm_new();
+            mm_tick(m, 0, 0, words(&x).as_ptr());
+            mm_tick(m, 1, 0, words(&x).as_ptr());
+            let mut out = [0u8; O_BYTES];
+            mm_get_output_bytes(m, out.as_mut_ptr());
+            assert_eq!(Integer::from_digits(&out, Order::Lsf), expected);
+
+            mm_reset(m);
+            mm_get_output_bytes(m, out.as_mut_ptr());
+            assert_eq!(out, [0; O_BYTES]);
+            mm_free(m);
+            mm_free(std::ptr::null_mut());
+        }
+
+        // Negative outputs come back as two's complement
+        let mut out = [0u8; O_BYTES];
+        output_bytes(&Integer::from(-1), &mut out);
+        assert_eq!(out, [0xff; O_BYTES]);
+    }
+}
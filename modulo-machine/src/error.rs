@@ -0,0 +1,34 @@
+//! Errors reported by the machine itself.
+
+use std::fmt;
+
+/// Reason a tick was rejected by [`ModuloMachine::tick_checked`], or an
+/// input could not be created
+///
+/// [`ModuloMachine::tick_checked`]: crate::ModuloMachine::tick_checked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MachineError {
+    /// Input wider than the 300-bit X bus
+    InputTooWide { bits: u32 },
+    /// Negative input, which the unsigned X bus cannot carry (strict mode)
+    NegativeInput,
+    /// Radix outside `2..=36`
+    InvalidRadix { radix: i32 },
+    /// Text that is not a number in the requested radix
+    InvalidDigits,
+}
+
+impl fmt::Display for MachineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MachineError::InputTooWide { bits } => {
+                write!(f, "input is {} bits, wider than the 300-bit X bus", bits)
+            }
+            MachineError::NegativeInput => write!(f, "input is negative; the X bus is unsigned"),
+            MachineError::InvalidRadix { radix } => write!(f, "radix {} is outside 2..=36", radix),
+            MachineError::InvalidDigits => write!(f, "input is not a number in the given radix"),
+        }
+    }
+}
+
+impl std::error::Error for MachineError {}
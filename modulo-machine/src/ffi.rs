@@ -0,0 +1,279 @@
+//! Stable C ABI for embedding the model in C and C++ simulators (`ffi`
+//! feature).
+//!
+//! Build a shared or static library with
+//! `cargo rustc --release --lib --features ffi --crate-type cdylib` (or
+//! `staticlib`) and include `include/modulo_machine.h`. The header is
+//! generated from this module with `cbindgen --config cbindgen.toml -o
+//! include/modulo_machine.h`; regenerate it after changing any signature.
+//!
+//! A machine is an opaque `MmMachine *`. Operands and outputs cross as
+//! big-endian byte arrays, the same layout as
+//! [`constants::P`](crate::constants::P)'s bytes. Every fallible call
+//! returns an [`MmStatus`]; [`mm_status_message`] describes it.
+
+use crate::{validate_machine_modulus, InputWidthPolicy, ModuloMachine};
+use rug::integer::Order;
+use rug::Integer;
+use std::ffi::c_char;
+
+/// Bytes written by [`mm_machine_output`]
+pub const MM_OUTPUT_BYTES: usize = 32;
+
+/// Result of an FFI call
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MmStatus {
+    Ok = 0,
+    /// A required pointer argument was null
+    NullPointer = 1,
+    /// An active edge saw an operand wider than the 300-bit X bus under
+    /// [`MmWidthPolicy::Error`]; the tick was applied and the output held
+    InputTooWide = 2,
+    /// The modulus is not odd, at least 3 and at most 256 bits
+    InvalidModulus = 3,
+    /// The output buffer is shorter than [`MM_OUTPUT_BYTES`]
+    BufferTooSmall = 4,
+}
+
+/// What an active edge does with an operand wider than the 300-bit X bus,
+/// as [`InputWidthPolicy`]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MmWidthPolicy {
+    /// Reduce the full value
+    Reduce = 0,
+    /// Keep the low 300 bits
+    Wrap = 1,
+    /// Clamp to `2^300 - 1`
+    Saturate = 2,
+    /// Hold the output and return [`MmStatus::InputTooWide`]; the default
+    Error = 3,
+}
+
+impl From<MmWidthPolicy> for InputWidthPolicy {
+    fn from(policy: MmWidthPolicy) -> Self {
+        match policy {
+            MmWidthPolicy::Reduce => InputWidthPolicy::Reduce,
+            MmWidthPolicy::Wrap => InputWidthPolicy::Wrap,
+            MmWidthPolicy::Saturate => InputWidthPolicy::Saturate,
+            MmWidthPolicy::Error => InputWidthPolicy::Error,
+        }
+    }
+}
+
+/// Opaque handle to a machine
+pub struct MmMachine(ModuloMachine);
+
+/// `len` big-endian bytes at `ptr`; null is only allowed when `len` is 0
+unsafe fn read_bytes(ptr: *const u8, len: usize) -> Option<Integer> {
+    if len == 0 {
+        return Some(Integer::new());
+    }
+    if ptr.is_null() {
+        return None;
+    }
+    Some(Integer::from_digits(std::slice::from_raw_parts(ptr, len), Order::Msf))
+}
+
+/// Create a machine into `*out`
+///
+/// A null `modulus` (with `modulus_len` 0) selects the specification's P;
+/// otherwise `modulus` holds `modulus_len` big-endian bytes. Over-wide
+/// operands are rejected until [`mm_machine_set_input_width_policy`] says
+/// otherwise.
+///
+/// # Safety
+///
+/// `out` must be valid for writes and `modulus` valid for `modulus_len`
+/// reads. Free the machine with [`mm_machine_destroy`].
+#[no_mangle]
+pub unsafe extern "C" fn mm_machine_create(modulus: *const u8, modulus_len: usize, out: *mut *mut MmMachine) -> MmStatus {
+    if out.is_null() {
+        return MmStatus::NullPointer;
+    }
+    let mut machine = if modulus.is_null() && modulus_len == 0 {
+        ModuloMachine::new()
+    } else {
+        let Some(p) = read_bytes(modulus, modulus_len) else {
+            return MmStatus::NullPointer;
+        };
+        if validate_machine_modulus(&p).is_err() {
+            return MmStatus::InvalidModulus;
+        }
+        ModuloMachine::with_modulus(p).expect("modulus was validated")
+    };
+    machine.set_input_width_policy(InputWidthPolicy::Error);
+    *out = Box::into_raw(Box::new(MmMachine(machine)));
+    MmStatus::Ok
+}
+
+/// Destroy a machine; null is ignored
+///
+/// # Safety
+///
+/// `m` must be null or a machine from [`mm_machine_create`] not already
+/// destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn mm_machine_destroy(m: *mut MmMachine) {
+    if !m.is_null() {
+        drop(Box::from_raw(m));
+    }
+}
+
+/// Return the machine to its power-on state
+///
+/// # Safety
+///
+/// `m` must be null or a live machine.
+#[no_mangle]
+pub unsafe extern "C" fn mm_machine_reset(m: *mut MmMachine) -> MmStatus {
+    match m.as_mut() {
+        Some(m) => {
+            m.0.reset();
+            MmStatus::Ok
+        }
+        None => MmStatus::NullPointer,
+    }
+}
+
+/// Choose what active edges do with operands wider than the 300-bit X bus
+///
+/// # Safety
+///
+/// `m` must be null or a live machine.
+#[no_mangle]
+pub unsafe extern "C" fn mm_machine_set_input_width_policy(m: *mut MmMachine, policy: MmWidthPolicy) -> MmStatus {
+    match m.as_mut() {
+        Some(m) => {
+            m.0.set_input_width_policy(policy.into());
+            MmStatus::Ok
+        }
+        None => MmStatus::NullPointer,
+    }
+}
+
+/// Apply one tick with the operand `x` of `x_len` big-endian bytes
+///
+/// An over-wide operand is handled by the machine's width policy.
+///
+/// # Safety
+///
+/// `m` must be null or a live machine, and `x` valid for `x_len` reads.
+#[no_mangle]
+pub unsafe extern "C" fn mm_machine_tick(m: *mut MmMachine, clk: bool, reset: bool, x: *const u8, x_len: usize) -> MmStatus {
+    let (Some(m), Some(x)) = (m.as_mut(), read_bytes(x, x_len)) else {
+        return MmStatus::NullPointer;
+    };
+    match m.0.tick_checked(clk, reset, &x) {
+        Ok(_) => MmStatus::Ok,
+        // Strict mode is not reachable from C, so only the width policy fails
+        Err(_) => MmStatus::InputTooWide,
+    }
+}
+
+/// Write the 256-bit output register to `out` as [`MM_OUTPUT_BYTES`]
+/// big-endian bytes, two's complement if a signed mode made it negative
+///
+/// # Safety
+///
+/// `m` must be null or a live machine, and `out` valid for `out_len` writes.
+#[no_mangle]
+pub unsafe extern "C" fn mm_machine_output(m: *const MmMachine, out: *mut u8, out_len: usize) -> MmStatus {
+    let Some(m) = m.as_ref() else {
+        return MmStatus::NullPointer;
+    };
+    if out.is_null() {
+        return MmStatus::NullPointer;
+    }
+    if out_len < MM_OUTPUT_BYTES {
+        return MmStatus::BufferTooSmall;
+    }
+    std::slice::from_raw_parts_mut(out, MM_OUTPUT_BYTES).copy_from_slice(&m.0.output_to_bytes_be());
+    MmStatus::Ok
+}
+
+/// Static, NUL-terminated description of `status`
+#[no_mangle]
+pub extern "C" fn mm_status_message(status: MmStatus) -> *const c_char {
+    let message: &'static [u8] = match status {
+        MmStatus::Ok => b"ok\0",
+        MmStatus::NullPointer => b"null pointer argument\0",
+        MmStatus::InputTooWide => b"input wider than the 300-bit X bus\0",
+        MmStatus::InvalidModulus => b"modulus must be odd, at least 3 and at most 256 bits\0",
+        MmStatus::BufferTooSmall => b"output buffer shorter than 32 bytes\0",
+    };
+    message.as_ptr().cast()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::X_BITS;
+    use std::ffi::CStr;
+    use std::ptr;
+
+    #[test]
+    fn test_round_trip() {
+        let p = ModuloMachine::new().get_prime().clone();
+        let x = Integer::from(&p + 0x1234u32).to_digits::<u8>(Order::Msf);
+        let mut out = [0u8; MM_OUTPUT_BYTES];
+        unsafe {
+            let mut m = ptr::null_mut();
+            assert_eq!(mm_machine_create(ptr::null(), 0, &mut m), MmStatus::Ok);
+            assert_eq!(mm_machine_tick(m, false, false, x.as_ptr(), x.len()), MmStatus::Ok);
+            assert_eq!(mm_machine_tick(m, true, false, x.as_ptr(), x.len()), MmStatus::Ok);
+            assert_eq!(mm_machine_output(m, out.as_mut_ptr(), out.len()), MmStatus::Ok);
+            assert_eq!(out[30..], [0x12, 0x34]);
+            assert!(out[..30].iter().all(|&b| b == 0));
+
+            assert_eq!(mm_machine_output(m, out.as_mut_ptr(), 31), MmStatus::BufferTooSmall);
+            // Over-wide operands are rejected on the edge by default, and
+            // follow the policy once one is chosen
+            let wide = [0xffu8; 38];
+            assert_eq!(mm_machine_tick(m, false, false, wide.as_ptr(), wide.len()), MmStatus::Ok);
+            assert_eq!(mm_machine_tick(m, true, false, wide.as_ptr(), wide.len()), MmStatus::InputTooWide);
+            assert_eq!(mm_machine_output(m, out.as_mut_ptr(), out.len()), MmStatus::Ok);
+            assert_eq!(out[30..], [0x12, 0x34]);
+            assert_eq!(mm_machine_set_input_width_policy(m, MmWidthPolicy::Wrap), MmStatus::Ok);
+            assert_eq!(mm_machine_tick(m, false, false, wide.as_ptr(), wide.len()), MmStatus::Ok);
+            assert_eq!(mm_machine_tick(m, true, false, wide.as_ptr(), wide.len()), MmStatus::Ok);
+            let wrapped = Integer::from(Integer::from_digits(&wide, Order::Msf).keep_bits_ref(X_BITS)) % &p;
+            assert_eq!(mm_machine_output(m, out.as_mut_ptr(), out.len()), MmStatus::Ok);
+            assert_eq!(Integer::from_digits(&out, Order::Msf), wrapped);
+            assert_eq!(mm_machine_set_input_width_policy(ptr::null_mut(), MmWidthPolicy::Wrap), MmStatus::NullPointer);
+            assert_eq!(mm_machine_tick(m, false, false, ptr::null(), 1), MmStatus::NullPointer);
+            assert_eq!(mm_machine_reset(m), MmStatus::Ok);
+            assert_eq!(mm_machine_output(m, out.as_mut_ptr(), out.len()), MmStatus::Ok);
+            assert_eq!(out, [0; MM_OUTPUT_BYTES]);
+            mm_machine_destroy(m);
+
+            // Custom moduli are validated
+            let (mut m, even) = (ptr::null_mut(), [0x10u8]);
+            assert_eq!(mm_machine_create(even.as_ptr(), 1, &mut m), MmStatus::InvalidModulus);
+            assert!(m.is_null());
+            assert_eq!(mm_machine_create([0x61u8].as_ptr(), 1, &mut m), MmStatus::Ok);
+            let x = [0x01u8, 0x00];
+            mm_machine_tick(m, true, false, x.as_ptr(), x.len());
+            mm_machine_output(m, out.as_mut_ptr(), out.len());
+            // 256 mod 97
+            assert_eq!(out[31], 62);
+            mm_machine_destroy(m);
+            mm_machine_reset(ptr::null_mut());
+        }
+        let message = unsafe { CStr::from_ptr(mm_status_message(MmStatus::InputTooWide)) };
+        assert_eq!(message.to_str().unwrap(), "input wider than the 300-bit X bus");
+    }
+
+    #[test]
+    fn test_header_declares_every_function() {
+        let header = include_str!("../include/modulo_machine.h");
+        let source = include_str!("ffi.rs");
+        let exported = source.lines().filter_map(|line| line.split("extern \"C\" fn ").nth(1)).map(|rest| &rest[..rest.find('(').unwrap()]);
+        for name in exported {
+            assert!(header.contains(&format!("{}(", name)), "{} missing from the header", name);
+        }
+        assert!(header.contains("#define MM_OUTPUT_BYTES 32"));
+        assert!(header.contains("MM_WIDTH_POLICY_ERROR = 3"));
+    }
+}
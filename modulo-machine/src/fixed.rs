@@ -0,0 +1,242 @@
+//! `no_std` golden model on fixed-size limbs.
+//!
+//! With default features off the crate is `#![no_std]`, needs no allocator,
+//! and this module (with [`constants`](crate::constants)) is what builds, so
+//! the model can run on an embedded validation board next to the silicon it
+//! models:
+//!
+//! ```toml
+//! modulo-machine = { version = "0.1", default-features = false }
+//! ```
+//!
+//! Operands are `[u64; 5]` (320 bits, room for the 300-bit X bus) and results
+//! `[u64; 4]`, both least significant limb first. [`FixedReducer`] is the
+//! hand-written Barrett reduction behind [`CtReducer`](crate::CtReducer):
+//! fixed iteration counts and masked conditional subtractions, so it is
+//! constant-time as well as allocation-free. [`FixedMachine`] wraps it in the
+//! machine's default configuration: async reset to zero, `x mod P` latched on
+//! each rising edge, operands reduced in full. Its constructor is `const`, so
+//! firmware can keep the machine in a `static`.
+//!
+//! ```
+//! use modulo_machine::FixedMachine;
+//!
+//! let mut machine = FixedMachine::new();
+//! let x = [31, 0, 0, 0, 0];
+//! machine.tick(false, false, &x);
+//! assert_eq!(machine.tick(true, false, &x), &[31, 0, 0, 0]);
+//! ```
+
+use crate::{constants, limbs, simd, X_BITS};
+
+/// Operand limbs: 320 bits, enough for the 300-bit X bus
+pub const X_LIMBS: usize = X_BITS.div_ceil(64) as usize;
+/// Modulus and result limbs: 256 bits
+pub const P_LIMBS: usize = 4;
+
+/// Allocation-free Barrett reducer for moduli in `[2^192, 2^256)`
+///
+/// The classic base-2^64 Barrett reduction (HAC 14.42) with `mu =
+/// floor(2^512 / P)`, followed by exactly two masked conditional
+/// subtractions. The instruction sequence does not depend on the operand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedReducer {
+    pub(crate) p: [u64; P_LIMBS],
+    /// `p` widened to the operand size for the final subtractions
+    p_wide: [u64; X_LIMBS],
+    mu: [u64; X_LIMBS],
+    /// Barrett constant for the vector kernels in [`simd`](crate::simd)
+    pub(crate) lane_mu: simd::LaneMu,
+}
+
+impl FixedReducer {
+    /// Reducer for the specification's P, from the precomputed constants
+    pub const P: FixedReducer = {
+        let p = constants::P.limbs_le;
+        FixedReducer {
+            p,
+            p_wide: [p[0], p[1], p[2], p[3], 0],
+            mu: constants::P.barrett_mu_le,
+            lane_mu: simd::lane_mu(&p),
+        }
+    };
+
+    /// Compute the Barrett constant for `p`, least significant limb first
+    ///
+    /// Returns `None` unless `p` is odd and its top limb is occupied, which
+    /// keeps the quotient estimate within two subtractions of exact.
+    pub fn new(p: [u64; P_LIMBS]) -> Option<Self> {
+        if p[0] & 1 == 0 || p[3] == 0 {
+            return None;
+        }
+        let p_wide = [p[0], p[1], p[2], p[3], 0];
+        Some(Self {
+            p,
+            p_wide,
+            mu: barrett_mu(&p_wide),
+            lane_mu: simd::lane_mu(&p),
+        })
+    }
+
+    pub fn modulus(&self) -> &[u64; P_LIMBS] {
+        &self.p
+    }
+
+    /// Compute `x mod P` for a 320-bit operand in constant time
+    pub fn reduce(&self, x: &[u64; X_LIMBS]) -> [u64; P_LIMBS] {
+        // q1 = floor(x / b^(k-1)) with b = 2^64, k = 4
+        let q1 = [x[3], x[4]];
+        let q2: [u64; 7] = limbs::mul(&q1, &self.mu);
+        // q3 = floor(q2 / b^(k+1))
+        let q3 = [q2[5], q2[6]];
+        let q3p: [u64; 6] = limbs::mul(&q3, &self.p);
+
+        // r = (x - q3·P) mod b^(k+1), known to be below 3P
+        let r2 = [q3p[0], q3p[1], q3p[2], q3p[3], q3p[4]];
+        let (mut r, _) = limbs::sub(x, &r2);
+        r = limbs::cond_sub(&r, &self.p_wide);
+        r = limbs::cond_sub(&r, &self.p_wide);
+
+        [r[0], r[1], r[2], r[3]]
+    }
+}
+
+/// `floor(2^512 / p)` by binary long division; below `2^320` because the top
+/// limb of `p` is occupied
+fn barrett_mu(p: &[u64; X_LIMBS]) -> [u64; X_LIMBS] {
+    let mut r = [0u64; X_LIMBS];
+    let mut q = [0u64; X_LIMBS];
+    for bit in (0..=512).rev() {
+        // r < p < 2^256 before the shift, so it fits in five limbs after
+        let mut carry = (bit == 512) as u64;
+        for limb in r.iter_mut() {
+            let next = *limb >> 63;
+            *limb = (*limb << 1) | carry;
+            carry = next;
+        }
+        let (d, borrow) = limbs::sub(&r, p);
+        if borrow == 0 {
+            r = d;
+            q[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+    q
+}
+
+/// The machine's default configuration on fixed limbs; see the
+/// [module docs](self)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedMachine {
+    reducer: FixedReducer,
+    output: [u64; P_LIMBS],
+    clk_prev: bool,
+}
+
+impl FixedMachine {
+    /// A machine reducing modulo the specification's P
+    pub const fn new() -> Self {
+        Self {
+            reducer: FixedReducer::P,
+            output: [0; P_LIMBS],
+            clk_prev: false,
+        }
+    }
+
+    /// A machine reducing modulo `p`, under the limits of
+    /// [`FixedReducer::new`]
+    pub fn with_modulus(p: [u64; P_LIMBS]) -> Option<Self> {
+        Some(Self {
+            reducer: FixedReducer::new(p)?,
+            ..Self::new()
+        })
+    }
+
+    /// Apply one clock cycle and return the output after it
+    pub fn tick(&mut self, clk: bool, reset: bool, x: &[u64; X_LIMBS]) -> &[u64; P_LIMBS] {
+        if reset {
+            self.reset();
+            return &self.output;
+        }
+        if clk && !self.clk_prev {
+            self.output = self.reducer.reduce(x);
+        }
+        self.clk_prev = clk;
+        &self.output
+    }
+
+    /// Return to the power-on state
+    pub fn reset(&mut self) {
+        self.output = [0; P_LIMBS];
+        self.clk_prev = false;
+    }
+
+    pub fn output(&self) -> &[u64; P_LIMBS] {
+        &self.output
+    }
+
+    pub fn modulus(&self) -> &[u64; P_LIMBS] {
+        self.reducer.modulus()
+    }
+}
+
+impl Default for FixedMachine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_barrett_constant() {
+        assert_eq!(FixedReducer::new(constants::P.limbs_le), Some(FixedReducer::P));
+        assert_eq!(FixedReducer::new([2, 0, 0, 1]), None);
+        assert_eq!(FixedReducer::new([u64::MAX, u64::MAX, u64::MAX, 0]), None);
+        // 2^512 / (2^256 - 1) = 2^256 + 1
+        assert_eq!(FixedReducer::new([u64::MAX; 4]).unwrap().mu, [1, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_machine() {
+        let mut machine = FixedMachine::new();
+        let p = *machine.modulus();
+        let p_wide = [p[0], p[1], p[2], p[3], 0];
+        assert_eq!(machine.tick(true, false, &p_wide), &[0; 4]);
+        assert_eq!(machine.tick(true, false, &[9, 0, 0, 0, 0]), &[0; 4]);
+        machine.tick(false, false, &[9, 0, 0, 0, 0]);
+        assert_eq!(machine.tick(true, false, &[9, 0, 0, 0, 0]), &[9, 0, 0, 0]);
+        assert_eq!(machine.tick(true, true, &[9, 0, 0, 0, 0]), &[0; 4]);
+        assert_eq!(machine.tick(true, false, &[p[0] - 1, p[1], p[2], p[3], 0]), &[p[0] - 1, p[1], p[2], p[3]]);
+        assert!(FixedMachine::with_modulus([2, 0, 0, 1]).is_none());
+    }
+
+    #[cfg(feature = "gmp")]
+    #[test]
+    fn test_matches_modulo_machine() {
+        use crate::vectors::splitmix64;
+        use crate::ModuloMachine;
+        use rug::integer::Order;
+        use rug::Integer;
+
+        let p = Integer::from_str_radix("fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f", 16).unwrap();
+        let mut p_limbs = [0u64; P_LIMBS];
+        p.write_digits(&mut p_limbs, Order::Lsf);
+        let mut state = 5;
+        for (mut fixed, mut reference) in [
+            (FixedMachine::new(), ModuloMachine::new()),
+            (FixedMachine::with_modulus(p_limbs).unwrap(), ModuloMachine::with_modulus(p).unwrap()),
+        ] {
+            for i in 0..2000u32 {
+                let mut x: [u64; X_LIMBS] = core::array::from_fn(|_| splitmix64(&mut state));
+                if i.is_multiple_of(3) {
+                    x[4] = 0;
+                }
+                let (clk, reset) = (!i.is_multiple_of(2), i.is_multiple_of(97));
+                let expected = reference.tick(clk, reset, &Integer::from_digits(&x, Order::Lsf));
+                assert_eq!(Integer::from_digits(fixed.tick(clk, reset, &x), Order::Lsf), *expected, "tick {}", i);
+            }
+        }
+    }
+}
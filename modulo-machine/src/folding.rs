@@ -0,0 +1,164 @@
+//! Folding reduction for Mersenne and pseudo-Mersenne moduli.
+//!
+//! A modulus of the form `p = 2^k - c` makes `2^k ≡ c (mod p)`, so the bits
+//! of an operand above position `k` can be folded back in with one small
+//! multiply and an add instead of a division: `x = h·2^k + l ≡ h·c + l`.
+//! Each fold removes about `k - bits(c)` bits; a 300-bit operand needs one or
+//! two folds for moduli like `2^255 - 19` or secp256k1's field prime, then at
+//! most one subtraction. For Mersenne moduli (`c = 1`) the multiply drops
+//! out altogether.
+//!
+//! [`SpecialForm::detect`] recognises the moduli worth folding, and a machine
+//! built with one selects [`ReductionStrategy::Folding`] on its own.
+//!
+//! [`ReductionStrategy::Folding`]: crate::ReductionStrategy::Folding
+
+use rug::{Assign, Integer};
+
+/// Shape of a modulus that folding reduces faster than division
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecialForm {
+    /// `2^k - 1`
+    Mersenne { k: u32 },
+    /// `2^k - c` with `1 < c < 2^64` and `c` at most half as wide as `2^k`
+    PseudoMersenne { k: u32, c: u64 },
+}
+
+impl SpecialForm {
+    /// The form of `p`, if folding pays off for it
+    ///
+    /// `p` must span more than one 64-bit limb (GMP divides by a single
+    /// limb in one step already), and `c = 2^k - p` must fit one limb and be
+    /// at most `k / 2` bits wide, so that a fold is a single-limb multiply
+    /// and at least halves the excess width.
+    pub fn detect(p: &Integer) -> Option<Self> {
+        let k = p.significant_bits();
+        if k <= 64 {
+            return None;
+        }
+        let c = (Integer::from(1) << k) - p;
+        let c_bits = c.significant_bits();
+        let c = c.to_u64()?;
+        match c {
+            1 => Some(SpecialForm::Mersenne { k }),
+            _ if c_bits <= k / 2 => Some(SpecialForm::PseudoMersenne { k, c }),
+            _ => None,
+        }
+    }
+
+    /// Position of the fold, the bit width of the modulus
+    pub fn k(&self) -> u32 {
+        match *self {
+            SpecialForm::Mersenne { k } | SpecialForm::PseudoMersenne { k, .. } => k,
+        }
+    }
+
+    /// `2^k - p`
+    pub fn c(&self) -> u64 {
+        match *self {
+            SpecialForm::Mersenne { .. } => 1,
+            SpecialForm::PseudoMersenne { c, .. } => c,
+        }
+    }
+}
+
+/// Folding reducer for one special-form modulus
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoldingReducer {
+    p: Integer,
+    form: SpecialForm,
+}
+
+impl FoldingReducer {
+    /// Reducer for `p`, or `None` unless [`SpecialForm::detect`] accepts it
+    pub fn new(p: &Integer) -> Option<Self> {
+        Some(Self {
+            p: p.clone(),
+            form: SpecialForm::detect(p)?,
+        })
+    }
+
+    pub fn modulus(&self) -> &Integer {
+        &self.p
+    }
+
+    pub fn form(&self) -> SpecialForm {
+        self.form
+    }
+
+    /// Write `x mod p` into `out`
+    ///
+    /// A negative `x` reduces its magnitude and keeps the sign, matching
+    /// GMP's truncated `%`.
+    pub fn reduce_into(&self, x: &Integer, out: &mut Integer) {
+        let (k, c) = (self.form.k(), self.form.c());
+        out.assign(x.abs_ref());
+        let mut high = Integer::new();
+        while out.significant_bits() > k {
+            high.assign(&*out >> k);
+            out.keep_bits_mut(k);
+            if c != 1 {
+                high *= c;
+            }
+            *out += &high;
+        }
+        // Now below 2^k = p + c < 2p
+        if *out >= self.p {
+            *out -= &self.p;
+        }
+        if *x < 0 {
+            *out = Integer::from(-&*out);
+        }
+    }
+
+    pub fn reduce(&self, x: &Integer) -> Integer {
+        let mut out = Integer::new();
+        self.reduce_into(x, &mut out);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ModuloMachine, Preset, P_STR};
+
+    #[test]
+    fn test_detect() {
+        let pow = |k: u32| Integer::from(1) << k;
+        assert_eq!(SpecialForm::detect(&(pow(127) - 1u32)), Some(SpecialForm::Mersenne { k: 127 }));
+        assert_eq!(SpecialForm::detect(&(pow(255) - 19u32)), Some(SpecialForm::PseudoMersenne { k: 255, c: 19 }));
+        let secp256k1 = pow(256) - (pow(32) + 977u32);
+        assert_eq!(SpecialForm::detect(&secp256k1), Some(SpecialForm::PseudoMersenne { k: 256, c: (1 << 32) + 977 }));
+        // c wider than a limb, c wider than k / 2, and single-limb moduli
+        assert_eq!(SpecialForm::detect(&(pow(256) - pow(100) - 1u32)), None);
+        assert_eq!(SpecialForm::detect(&(pow(100) - pow(60) - 1u32)), None);
+        assert_eq!(SpecialForm::detect(&(pow(61) - 1u32)), None);
+        assert_eq!(SpecialForm::detect(&Integer::from_str_radix(P_STR, 10).unwrap()), None);
+        for preset in Preset::ALL {
+            assert_eq!(SpecialForm::detect(&preset.modulus()), None, "{}", preset.name());
+        }
+    }
+
+    #[test]
+    fn test_matches_gmp() {
+        let pow = |k: u32| Integer::from(1) << k;
+        for p in [pow(127) - 1u32, pow(255) - 19u32, pow(256) - (pow(32) + 977u32), pow(89) - 1u32] {
+            let reducer = FoldingReducer::new(&p).unwrap();
+            let cases = [
+                Integer::new(),
+                Integer::from(&p - 1u32),
+                p.clone(),
+                Integer::from(&p * 2u32) - 1u32,
+                pow(reducer.form().k()),
+                ModuloMachine::create_large_input(300, 0) - 1u32,
+                ModuloMachine::create_large_input(299, 12345),
+                Integer::from(-7),
+                -ModuloMachine::create_large_input(300, 99),
+            ];
+            for x in &cases {
+                assert_eq!(reducer.reduce(x), Integer::from(x % &p), "{} mod {}", x, p);
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Newline-delimited JSON batches, for driving the model from other
+//! languages and tools.
+//!
+//! Each input line is one tick, with the fields of the JSON-RPC `tick`
+//! method:
+//!
+//! ```text
+//! {"clk": true, "reset": false, "x": "0x1f"}
+//! ```
+//!
+//! `clk` and `reset` default to `false`; `x` is a number or a decimal or
+//! `0x`-prefixed hex string. Each output line echoes the tick with its index
+//! and the output after it, big integers as decimal strings:
+//!
+//! ```text
+//! {"clk":true,"output":"31","reset":false,"tick":0,"x":"31"}
+//! ```
+//!
+//! Blank lines are skipped and do not count as ticks.
+
+use crate::rpc::parse_tick;
+use crate::{ModuloMachine, TickInput};
+use rug::Integer;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+
+/// Read every tick from `reader`
+pub fn read_ticks<R: BufRead>(reader: R) -> io::Result<Vec<TickInput>> {
+    let mut ticks = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        if let Some(tick) = parse_line(i, &line?)? {
+            ticks.push(tick);
+        }
+    }
+    Ok(ticks)
+}
+
+/// One result line, without the newline
+pub fn result_line(tick: u64, input: &TickInput, output: &Integer) -> String {
+    json!({
+        "tick": tick,
+        "clk": input.clk,
+        "reset": input.reset,
+        "x": input.x.to_string(),
+        "output": output.to_string(),
+    })
+    .to_string()
+}
+
+/// Write one result line per tick, numbered from 0
+pub fn write_results<'a, W: Write>(
+    mut writer: W,
+    entries: impl IntoIterator<Item = (&'a TickInput, &'a Integer)>,
+) -> io::Result<()> {
+    for (tick, (input, output)) in entries.into_iter().enumerate() {
+        writeln!(writer, "{}", result_line(tick as u64, input, output))?;
+    }
+    Ok(())
+}
+
+/// Stream ticks from `reader` through `machine`, writing each result as soon
+/// as it is computed; returns the number of ticks
+pub fn run<R: BufRead, W: Write>(machine: &mut ModuloMachine, reader: R, mut writer: W) -> io::Result<u64> {
+    let mut tick = 0;
+    for (i, line) in reader.lines().enumerate() {
+        let Some(input) = parse_line(i, &line?)? else {
+            continue;
+        };
+        let output = machine.tick(input.clk, input.reset, &input.x);
+        writeln!(writer, "{}", result_line(tick, &input, output))?;
+        tick += 1;
+    }
+    writer.flush()?;
+    Ok(tick)
+}
+
+fn parse_line(index: usize, line: &str) -> io::Result<Option<TickInput>> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", index + 1, message));
+    let value: Value = serde_json::from_str(line).map_err(|e| invalid(e.to_string()))?;
+    if !value.is_object() {
+        return Err(invalid("expected a JSON object".to_string()));
+    }
+    let (clk, reset, x) = parse_tick(&value).map_err(|e| invalid(e.message))?;
+    Ok(Some(TickInput::new(clk, reset, x)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let p = ModuloMachine::new().get_prime().clone();
+        let input = format!(
+            "{{\"reset\": true}}\n\n{{\"x\": \"0x{0}\"}}\n{{\"clk\": true, \"x\": \"0x{0}\"}}\n",
+            Integer::from(&p + 31u32).to_string_radix(16)
+        );
+        let ticks = read_ticks(input.as_bytes()).unwrap();
+        assert_eq!(ticks.len(), 3);
+        assert!(ticks[0].reset && !ticks[0].clk);
+
+        let mut output = Vec::new();
+        assert_eq!(run(&mut ModuloMachine::new(), input.as_bytes(), &mut output).unwrap(), 3);
+        let output = String::from_utf8(output).unwrap();
+        let last: Value = serde_json::from_str(output.lines().last().unwrap()).unwrap();
+        assert_eq!(last["tick"], 2);
+        assert_eq!(last["output"], "31");
+        assert_eq!(last["x"], Integer::from(&p + 31u32).to_string());
+
+        // Results are valid input, so runs can be chained
+        assert_eq!(read_ticks(output.as_bytes()).unwrap(), ticks);
+
+        let mut written = Vec::new();
+        let outputs: Vec<Integer> = ticks.iter().map(|_| Integer::new()).collect();
+        write_results(&mut written, ticks.iter().zip(&outputs)).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap().lines().count(), 3);
+
+        for bad in ["{", "[1]", "{\"x\": \"zz\"}", "{\"clk\": 1}"] {
+            let err = read_ticks(format!("{{}}\n{}", bad).as_bytes()).unwrap_err();
+            assert!(err.to_string().starts_with("line 2:"), "{}: {}", bad, err);
+        }
+    }
+}
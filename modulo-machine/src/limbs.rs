@@ -0,0 +1,53 @@
+//! Fixed-size little-endian u64 limb arithmetic.
+//!
+//! Every routine runs a fixed number of iterations for a given array size and
+//! never branches on limb values, so callers can build constant-time reductions
+//! on top of them.
+
+/// Full schoolbook product `a * b`; `O` must equal `A + B`
+pub(crate) fn mul<const A: usize, const B: usize, const O: usize>(
+    a: &[u64; A],
+    b: &[u64; B],
+) -> [u64; O] {
+    debug_assert_eq!(O, A + B);
+    let mut out = [0u64; O];
+    for i in 0..A {
+        let mut carry = 0u64;
+        for j in 0..B {
+            let t = out[i + j] as u128 + (a[i] as u128) * (b[j] as u128) + carry as u128;
+            out[i + j] = t as u64;
+            carry = (t >> 64) as u64;
+        }
+        out[i + B] = carry;
+    }
+    out
+}
+
+/// `a - b` modulo 2^(64·N), returning the difference and the final borrow (0 or 1)
+pub(crate) fn sub<const N: usize>(a: &[u64; N], b: &[u64; N]) -> ([u64; N], u64) {
+    let mut out = [0u64; N];
+    let mut borrow = 0u64;
+    for i in 0..N {
+        let (d1, b1) = a[i].overflowing_sub(b[i]);
+        let (d2, b2) = d1.overflowing_sub(borrow);
+        out[i] = d2;
+        borrow = (b1 | b2) as u64;
+    }
+    (out, borrow)
+}
+
+/// Pick `a` where `mask` is all ones and `b` where it is zero
+pub(crate) fn select<const N: usize>(mask: u64, a: &[u64; N], b: &[u64; N]) -> [u64; N] {
+    let mut out = [0u64; N];
+    for i in 0..N {
+        out[i] = (a[i] & mask) | (b[i] & !mask);
+    }
+    out
+}
+
+/// Subtract `m` from `r` if `r >= m`, without branching on either value
+pub(crate) fn cond_sub<const N: usize>(r: &[u64; N], m: &[u64; N]) -> [u64; N] {
+    let (d, borrow) = sub(r, m);
+    // borrow == 0 means r >= m: mask becomes all ones and selects the difference
+    select(borrow.wrapping_sub(1), &d, r)
+}
@@ -0,0 +1,2255 @@
+//! The GMP-backed machine and its configuration.
+
+use rug::integer::Order;
+use rug::{Integer, Assign};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fmt;
+use crate::alu::ModExpUnit;
+use crate::fixed::{P_LIMBS, X_LIMBS};
+use rayon::prelude::*;
+use crate::{
+    arith, ct, validate_machine_modulus, validate_modulus, BarrettReducer, CtReducer, IntegerArena, MachineError, MachineStats, ModulusError, MontgomeryContext, Op, Preset,
+    FoldingReducer, PreinvReducer, ShiftSubtractReducer, SpecialForm, P_STR, X_BITS,
+};
+
+/// One clock cycle of stimulus with an owned input value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TickInput {
+    pub clk: bool,
+    pub reset: bool,
+    pub x: Integer,
+}
+
+impl TickInput {
+    pub fn new(clk: bool, reset: bool, x: Integer) -> Self {
+        Self { clk, reset, x }
+    }
+}
+
+/// Value the output register takes on reset
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum ResetValue {
+    #[default]
+    Zero,
+    /// Every bit of the 256-bit register set (`2^256 - 1`)
+    AllOnes,
+    /// Design-specific sentinel
+    Custom(#[cfg_attr(feature = "serde", serde(with = "crate::serde_int"))] Integer),
+}
+
+impl ResetValue {
+    /// The register contents this reset value produces
+    pub fn value(&self) -> Integer {
+        match self {
+            ResetValue::Zero => Integer::new(),
+            ResetValue::AllOnes => (Integer::from(1) << 256) - 1,
+            ResetValue::Custom(x) => x.clone(),
+        }
+    }
+}
+
+/// What a rising edge computes from the input and the current output
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum Datapath {
+    /// `output = x mod P`
+    #[default]
+    Reduce,
+    /// `output = (output + x) mod P`, a modular accumulator; reset loads the
+    /// reset value, which the next edge accumulates onto
+    Accumulate,
+    /// `output = (output · a + x) mod P` with a fixed `a`: streaming
+    /// coefficients highest first evaluates a polynomial at `a` (Horner)
+    Mac(#[cfg_attr(feature = "serde", serde(with = "crate::serde_int"))] Integer),
+}
+
+/// Which clock transitions latch a new output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum EdgeMode {
+    /// Low→high, like a posedge flop
+    #[default]
+    Rising,
+    /// High→low, for negative-edge-triggered designs
+    Falling,
+    /// Every transition, for DDR-style datapaths
+    Both,
+}
+
+/// When an asserted reset takes effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum ResetKind {
+    /// Immediately, whatever the clock does (the historical behaviour)
+    #[default]
+    Async,
+    /// Only on an active clock edge; the output holds otherwise
+    Sync,
+}
+
+/// Result convention for a negative X
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum SignedMode {
+    /// Truncated division, as GMP's `%`: the result takes X's sign, so
+    /// `-3 mod P = -3` (the historical behaviour)
+    #[default]
+    Truncated,
+    /// Euclidean: always in `[0, P)`, so `-3 mod P = P - 3`
+    Euclidean,
+}
+
+/// How the machine computes `X mod P` on a rising edge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum ReductionStrategy {
+    /// GMP division; fastest, but its running time depends on the operand
+    #[default]
+    Gmp,
+    /// Fixed-iteration Barrett reduction with running time independent of the operand
+    ConstantTime,
+    /// Barrett reduction on GMP integers: no division for operands up to the
+    /// bus width, but variable-time like [`ReductionStrategy::Gmp`]
+    Barrett,
+    /// Bit-serial shift-and-subtract, one compare/subtract per cycle, with
+    /// the cycle count reported by [`ModuloMachine::reduction_cycles`]
+    ShiftSubtract,
+    /// Fold the bits above `2^k` back in for a Mersenne or pseudo-Mersenne
+    /// modulus `2^k - c`; selected automatically when the modulus has that
+    /// form (see [`SpecialForm::detect`])
+    Folding,
+    /// Schoolbook division using the modulus's limb reciprocal, computed once
+    /// at construction instead of by GMP on every edge
+    Preinverted,
+}
+
+impl ReductionStrategy {
+    /// Short lowercase name, as the serde and JSON-RPC encodings use
+    pub fn name(self) -> &'static str {
+        match self {
+            ReductionStrategy::Gmp => "gmp",
+            ReductionStrategy::ConstantTime => "constant_time",
+            ReductionStrategy::Barrett => "barrett",
+            ReductionStrategy::ShiftSubtract => "shift_subtract",
+            ReductionStrategy::Folding => "folding",
+            ReductionStrategy::Preinverted => "preinverted",
+        }
+    }
+}
+
+/// What a rising edge does with an input wider than the 300-bit bus
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum InputWidthPolicy {
+    /// Reduce the full value, as if the bus were unbounded (no real hardware
+    /// behaves like this, but it is the historical default)
+    #[default]
+    Reduce,
+    /// Keep the low 300 bits, like a bus that drops the excess wires
+    Wrap,
+    /// Clamp the magnitude to `2^300 - 1`
+    Saturate,
+    /// Hold the output and report [`MachineError::InputTooWide`] through
+    /// [`ModuloMachine::tick_checked`]
+    Error,
+}
+
+/// Modulo Machine using GMP library for modular arithmetic
+///
+/// A clone is an independent machine in the same state, with an empty result
+/// arena. `Display` shows the output register in the radix chosen with
+/// [`ModuloMachine::set_display_radix`]; `Debug` adds the clock and modes.
+#[derive(Clone)]
+pub struct ModuloMachine {
+    /// The modulus: the specification's P unless built with
+    /// [`ModuloMachine::with_modulus`]
+    pub(crate) p: Integer,
+    /// Current output (256-bit) 
+    pub(crate) output: Integer,
+    /// Internal state for clock simulation
+    pub(crate) clk_prev: bool,
+    /// Clock transitions that latch
+    pub(crate) edge_mode: EdgeMode,
+    /// Whether reset waits for a clock edge
+    pub(crate) reset_kind: ResetKind,
+    /// Handling of inputs wider than the X bus
+    pub(crate) width_policy: InputWidthPolicy,
+    /// Strict mode: edges reject any input the bus cannot carry
+    pub(crate) strict: bool,
+    /// What a rising edge computes
+    pub(crate) datapath: Datapath,
+    /// Sign of the result for negative operands
+    pub(crate) signed_mode: SignedMode,
+    /// Multi-cycle exponentiation in flight, and the one-edge done pulse
+    pub(crate) modexp: Option<ModExpUnit>,
+    pub(crate) done: bool,
+    /// Results in flight, oldest first; one stage per cycle of latency
+    /// (`None` is a bubble)
+    pub(crate) pipeline: VecDeque<Option<Integer>>,
+    /// Whether the last active edge latched a result
+    pub(crate) output_valid: bool,
+    /// Lazy reduction: inputs below this bound are latched unreduced
+    pub(crate) lazy_threshold: Option<Integer>,
+    /// Selected reduction strategy
+    pub(crate) strategy: ReductionStrategy,
+    /// Barrett reducer, present when the constant-time strategy is selected
+    pub(crate) ct: Option<CtReducer>,
+    /// Barrett constant for `p`, computed at construction
+    pub(crate) barrett: BarrettReducer,
+    /// Iterative reducer, and the cycles it spent on the last edge
+    pub(crate) shift_sub: ShiftSubtractReducer,
+    pub(crate) reduction_cycles: Option<u32>,
+    /// Folding reducer, present when the modulus has a special form
+    pub(crate) folding: Option<FoldingReducer>,
+    /// Normalised modulus and its reciprocal, computed at construction
+    pub(crate) preinv: PreinvReducer,
+    /// Montgomery constants for `p` (`R = 2^256`)
+    pub(crate) montgomery: MontgomeryContext,
+    /// Configured reset value and the register contents it produces
+    pub(crate) reset_value: ResetValue,
+    pub(crate) reset_output: Integer,
+    /// Spare result Integers reused by batch processing
+    pub(crate) arena: IntegerArena,
+    /// Simulation time of the next tick and the time units each tick spans
+    pub(crate) time: u64,
+    pub(crate) time_step: u64,
+    /// Radix of the output in `Display` and `Debug`
+    pub(crate) display_radix: u32,
+    /// Counters since creation or the last `reset_stats`
+    pub(crate) stats: MachineStats,
+    /// Operand buffer reused by `tick_limbs`
+    pub(crate) limb_input: Integer,
+    /// Pre-computed values for fast modular arithmetic
+    /// Cached for repeated operations with same modulus
+    pub(crate) _p_bits: u32,
+}
+
+impl ModuloMachine {
+    /// Create a new modulo machine instance
+    pub fn new() -> Self {
+        Self::with_capacity_hint(256)
+    }
+
+    /// Create a machine whose output register and result arena reserve
+    /// `bits` up front, so long simulations don't grow them tick by tick
+    pub fn with_capacity_hint(bits: usize) -> Self {
+        let p = Integer::from_str_radix(P_STR, 10).expect("Failed to parse prime P");
+        Self::build(p, bits)
+    }
+
+    /// Create a machine reducing modulo `p` instead of the specification's P
+    ///
+    /// `p` may be composite or narrower than 256 bits, but must be odd and fit
+    /// the output register (see [`validate_machine_modulus`]). Everything
+    /// derived from the modulus, such as the constant-time reducer, is
+    /// computed from `p`; a strategy that cannot handle it is refused by
+    /// [`ModuloMachine::set_reduction_strategy`].
+    ///
+    /// The Miller–Rabin and top-bit checks of [`validate_modulus`] are
+    /// deliberately not applied here: the specification's own P is
+    /// composite, the Goldilocks preset is 64 bits wide, and reducing modulo
+    /// a composite is a legitimate thing to model. Setups that need a
+    /// 256-bit prime should construct through
+    /// [`ModuloMachine::with_validated_modulus`] to fail fast instead.
+    pub fn with_modulus(p: Integer) -> Result<Self, ModulusError> {
+        validate_machine_modulus(&p)?;
+        Ok(Self::build(p, 256))
+    }
+
+    /// [`ModuloMachine::with_modulus`], accepting only a 256-bit prime
+    /// according to [`validate_modulus`]
+    pub fn with_validated_modulus(p: Integer) -> Result<Self, ModulusError> {
+        validate_modulus(&p)?;
+        Ok(Self::build(p, 256))
+    }
+
+    /// Create a machine reducing modulo a well-known modulus
+    pub fn for_preset(preset: Preset) -> Self {
+        Self::with_modulus(preset.modulus()).expect("preset moduli are valid machine moduli")
+    }
+
+    pub(crate) fn build(p: Integer, bits: usize) -> Self {
+        let p_bits = p.significant_bits();
+        let barrett = BarrettReducer::new(&p);
+        let shift_sub = ShiftSubtractReducer::new(&p);
+        let montgomery = MontgomeryContext::new(&p).expect("machine moduli are odd and fit the register");
+        let folding = FoldingReducer::new(&p);
+        let preinv = PreinvReducer::new(&p);
+        let strategy = if folding.is_some() { ReductionStrategy::Folding } else { ReductionStrategy::Gmp };
+
+        Self {
+            output: Integer::with_capacity(bits),
+            p,
+            clk_prev: false,
+            edge_mode: EdgeMode::Rising,
+            reset_kind: ResetKind::Async,
+            width_policy: InputWidthPolicy::Reduce,
+            strict: false,
+            datapath: Datapath::Reduce,
+            signed_mode: SignedMode::Truncated,
+            modexp: None,
+            done: false,
+            pipeline: VecDeque::new(),
+            output_valid: false,
+            lazy_threshold: None,
+            strategy,
+            ct: None,
+            barrett,
+            shift_sub,
+            reduction_cycles: None,
+            folding,
+            preinv,
+            montgomery,
+            reset_value: ResetValue::Zero,
+            reset_output: Integer::new(),
+            arena: IntegerArena::new(bits),
+            time: 0,
+            time_step: 1,
+            display_radix: 10,
+            stats: MachineStats::default(),
+            limb_input: Integer::new(),
+            _p_bits: p_bits,
+        }
+    }
+
+    /// Reset the machine (output takes the configured reset value)
+    pub fn reset(&mut self) {
+        self.output.assign(&self.reset_output);
+        self.clk_prev = false;
+        self.modexp = None;
+        self.done = false;
+        self.pipeline.iter_mut().for_each(|stage| *stage = None);
+        self.output_valid = false;
+        self.reduction_cycles = None;
+    }
+
+    /// Process one clock cycle
+    /// - clk: clock input (1 bit)
+    /// - reset: reset input (1 bit), asynchronous unless configured with
+    ///   [`ResetKind::Sync`]
+    /// - x: input value (300 bits max); a negative value is reduced under
+    ///   the configured [`SignedMode`]
+    ///
+    /// Returns: current output (256 bits max)
+    pub fn tick(&mut self, clk: bool, reset: bool, x: &Integer) -> &Integer {
+        self.stats.record_tick(reset, x.significant_bits());
+        sim_event!(trace, time = self.time, clk, reset, x_bits = x.significant_bits(), "tick");
+        // Handle reset
+        if reset {
+            self.reset_tick(clk);
+            return &self.output;
+        }
+
+        // Process on the active clock edge
+        if self.is_active_edge(clk) {
+            self.done = false;
+            self.stats.rising_edges += 1;
+            match self.bus_value(x) {
+                Ok(bus) => {
+                    if x.significant_bits() > X_BITS {
+                        sim_event!(debug, time = self.time, x_bits = x.significant_bits(), policy = ?self.width_policy, "over-wide input");
+                    }
+                    let x = self.edge_operand(bus);
+                    self.reduction_cycles = self.edge_cycles(&x);
+                    // Comparing against P would put a data-dependent branch
+                    // back on the constant-time path, so it goes uncounted
+                    if self.strategy != ReductionStrategy::ConstantTime && *x >= self.p {
+                        self.stats.reductions += 1;
+                        sim_event!(debug, time = self.time, x_bits = x.significant_bits(), strategy = ?self.strategy, cycles = ?self.reduction_cycles, "reduction performed");
+                    }
+                    if self.pipeline.is_empty() {
+                        // Swap the register out so its allocation is reused
+                        let mut output = std::mem::take(&mut self.output);
+                        self.next_output_into(&x, &mut output);
+                        self.output = output;
+                        self.output_valid = true;
+                    } else {
+                        let mut next = Integer::new();
+                        self.next_output_into(&x, &mut next);
+                        self.advance_pipeline(Some(next));
+                    }
+                }
+                Err(_e) => {
+                    sim_event!(warn, time = self.time, error = %_e, "edge dropped");
+                    self.reduction_cycles = None;
+                    self.advance_pipeline(None);
+                }
+            }
+            sim_event!(debug, time = self.time, x_bits = x.significant_bits(), output = %self.output, output_bits = self.output.significant_bits(), valid = self.output_valid, "clock edge");
+        }
+
+        self.clk_prev = clk;
+        self.time += self.time_step;
+        &self.output
+    }
+
+    /// Like [`tick`], but report an input rejected by strict mode or
+    /// [`InputWidthPolicy::Error`]
+    ///
+    /// The tick is still applied (the clock advances and the output holds), so
+    /// a caller can log the error and keep simulating.
+    ///
+    /// [`tick`]: ModuloMachine::tick
+    pub fn tick_checked(&mut self, clk: bool, reset: bool, x: &Integer) -> Result<&Integer, MachineError> {
+        let edge = self.is_active_edge(clk) && !reset;
+        let checked = if edge { self.bus_value(x).map(drop) } else { Ok(()) };
+        self.tick(clk, reset, x);
+        checked.map(|()| &self.output)
+    }
+
+    /// [`tick`] on fixed limbs, least significant first, for co-simulation
+    /// loops that drive the bus as machine words
+    ///
+    /// The operand is loaded into a buffer the machine keeps and the result
+    /// read straight out of the output register, so once the buffer has grown
+    /// to the bus width a tick allocates nothing. Returns the output as
+    /// [`output_to_limbs`] does.
+    ///
+    /// [`tick`]: ModuloMachine::tick
+    /// [`output_to_limbs`]: ModuloMachine::output_to_limbs
+    pub fn tick_limbs(&mut self, clk: bool, reset: bool, x: &[u64; X_LIMBS]) -> [u64; P_LIMBS] {
+        let mut input = std::mem::take(&mut self.limb_input);
+        input.assign_digits(x, Order::Lsf);
+        self.tick(clk, reset, &input);
+        self.limb_input = input;
+        self.output_to_limbs()
+    }
+
+    /// Clock the machine as a modular ALU: a rising edge latches `op` applied
+    /// to `a` and `b`
+    ///
+    /// `Op::Mod` is exactly [`tick`] with `x = a`. The other operations
+    /// latch a result in `[0, P)` computed with GMP, independent of the
+    /// width policy and reduction strategy.
+    ///
+    /// `Op::ModExp` takes several cycles (see [`alu`]); while it is
+    /// [`busy`](ModuloMachine::busy), rising edges advance it whatever the
+    /// opcode and operands, and the output holds until it is done.
+    ///
+    /// [`tick`]: ModuloMachine::tick
+    pub fn tick_op(&mut self, clk: bool, reset: bool, op: Op, a: &Integer, b: &Integer) -> &Integer {
+        if op == Op::Mod && !self.busy() {
+            return self.tick(clk, reset, a);
+        }
+        self.clocked(clk, reset, |machine, output| {
+            sim_event!(debug, time = machine.time, op = ?op, a_bits = a.significant_bits(), b_bits = b.significant_bits(), busy = machine.busy(), "alu edge");
+            machine.done = false;
+            match machine.modexp.as_mut() {
+                Some(unit) => {
+                    if let Some(result) = unit.step(&machine.p) {
+                        output.assign(result);
+                        machine.modexp = None;
+                        machine.done = true;
+                    }
+                }
+                None if op == Op::ModExp => {
+                    // The issuing edge only loads the operands, unless the
+                    // exponent has no bits to process
+                    let mut unit = ModExpUnit::start(a, b, &machine.p);
+                    if unit.finished() {
+                        output.assign(unit.step(&machine.p).unwrap());
+                        machine.done = true;
+                    } else {
+                        machine.modexp = Some(unit);
+                    }
+                }
+                None => output.assign(op.apply(a, b, &machine.p)),
+            }
+        })
+    }
+
+    /// Shift `stage` into the pipeline and latch whatever leaves it
+    fn advance_pipeline(&mut self, stage: Option<Integer>) {
+        self.pipeline.push_back(stage);
+        match self.pipeline.pop_front().flatten() {
+            Some(result) => {
+                self.output = result;
+                self.output_valid = true;
+            }
+            None => self.output_valid = false,
+        }
+    }
+
+    /// Whether `clk` after the previous level is an edge the machine latches on
+    fn is_active_edge(&self, clk: bool) -> bool {
+        match self.edge_mode {
+            EdgeMode::Rising => clk && !self.clk_prev,
+            EdgeMode::Falling => !clk && self.clk_prev,
+            EdgeMode::Both => clk != self.clk_prev,
+        }
+    }
+
+    /// Whether the last active edge latched a result: low after reset, while
+    /// the pipeline fills, for a bubble, and for an input the bus rejected
+    pub fn output_valid(&self) -> bool {
+        self.output_valid
+    }
+
+    /// Whether a multi-cycle operation is in flight
+    pub fn busy(&self) -> bool {
+        self.modexp.is_some()
+    }
+
+    /// Whether the last rising edge latched the result of a multi-cycle
+    /// operation; cleared by the next edge
+    pub fn done(&self) -> bool {
+        self.done
+    }
+
+    /// Clock a Montgomery multiplier: a rising edge latches `a·b·R^-1 mod P`
+    ///
+    /// With `a` and `b` in Montgomery form (see
+    /// [`ModuloMachine::to_montgomery`]) the register holds the Montgomery
+    /// form of their product, so chained multiplications never leave the
+    /// domain. Reset and clock handling match [`tick`]; the input width policy
+    /// and reduction strategy do not apply.
+    ///
+    /// [`tick`]: ModuloMachine::tick
+    pub fn tick_montgomery_mul(&mut self, clk: bool, reset: bool, a: &Integer, b: &Integer) -> &Integer {
+        self.clocked(clk, reset, |machine, output| output.assign(machine.montgomery.mul(a, b)))
+    }
+
+    /// Montgomery form `x·R mod P` of `x`, with `R = 2^256`
+    pub fn to_montgomery(&self, x: &Integer) -> Integer {
+        self.montgomery.to_montgomery(x)
+    }
+
+    /// Ordinary value `a·R^-1 mod P` of the Montgomery-form `a`
+    pub fn from_montgomery(&self, a: &Integer) -> Integer {
+        self.montgomery.from_montgomery(a)
+    }
+
+    /// Montgomery constants for the machine's modulus
+    pub fn montgomery(&self) -> &MontgomeryContext {
+        &self.montgomery
+    }
+
+    /// Shared clocking for datapaths other than the reducer: on a rising edge
+    /// outside reset, `latch` writes the next register value
+    fn clocked(&mut self, clk: bool, reset: bool, latch: impl FnOnce(&mut Self, &mut Integer)) -> &Integer {
+        self.stats.record_tick(reset, 0);
+        if reset {
+            self.reset_tick(clk);
+            return &self.output;
+        }
+        if self.is_active_edge(clk) {
+            self.stats.rising_edges += 1;
+            let mut output = std::mem::take(&mut self.output);
+            latch(self, &mut output);
+            self.output = output;
+            self.output_valid = !self.busy();
+        }
+        self.clk_prev = clk;
+        self.time += self.time_step;
+        &self.output
+    }
+
+    /// A tick with reset asserted, under the configured [`ResetKind`]
+    fn reset_tick(&mut self, clk: bool) {
+        match self.reset_kind {
+            ResetKind::Async => {
+                self.reset();
+                sim_event!(debug, time = self.time, output = %self.output, "reset applied");
+            }
+            ResetKind::Sync => {
+                if self.is_active_edge(clk) {
+                    self.reset();
+                    sim_event!(debug, time = self.time, output = %self.output, "reset applied");
+                }
+                self.clk_prev = clk;
+            }
+        }
+        self.time += self.time_step;
+    }
+
+    /// `x` as the bus presents it under strict mode or the input width policy
+    fn bus_value<'a>(&self, x: &'a Integer) -> Result<Cow<'a, Integer>, MachineError> {
+        if self.strict {
+            Self::validate_input(x)?;
+            return Ok(Cow::Borrowed(x));
+        }
+        let bits = x.significant_bits();
+        if bits <= X_BITS {
+            return Ok(Cow::Borrowed(x));
+        }
+        match self.width_policy {
+            InputWidthPolicy::Reduce => Ok(Cow::Borrowed(x)),
+            InputWidthPolicy::Wrap => Ok(Cow::Owned(Integer::from(x.keep_bits_ref(X_BITS)))),
+            InputWidthPolicy::Saturate => {
+                let max = (Integer::from(1) << X_BITS) - 1u32;
+                Ok(Cow::Owned(if *x < 0 { -max } else { max }))
+            }
+            InputWidthPolicy::Error => Err(MachineError::InputTooWide { bits }),
+        }
+    }
+
+    /// Combinational view: the value the next rising edge would latch for `x`
+    ///
+    /// Applies the same lazy-reduction and strategy settings as [`tick`]
+    /// without touching the register or clock state, so checkers can compare
+    /// against the pre-register value. Reset is not modelled; a reset in the
+    /// same cycle loads the reset value instead. An input the width policy
+    /// rejects previews the held output.
+    ///
+    /// [`tick`]: ModuloMachine::tick
+    pub fn peek_next(&self, x: &Integer) -> Integer {
+        match self.bus_value(x) {
+            Ok(x) => {
+                let x = self.edge_operand(x);
+                let mut next = Integer::new();
+                self.next_output_into(&x, &mut next);
+                next
+            }
+            Err(_) => self.output.clone(),
+        }
+    }
+
+    /// The value the datapath reduces for bus value `x`
+    fn edge_operand<'a>(&self, x: Cow<'a, Integer>) -> Cow<'a, Integer> {
+        match &self.datapath {
+            Datapath::Reduce => x,
+            Datapath::Accumulate => Cow::Owned(Integer::from(&self.output + &*x)),
+            // Up to 556 bits before reduction: a 256-bit output times a
+            // 300-bit constant; strategies fall back to GMP past their width
+            Datapath::Mac(a) => Cow::Owned(Integer::from(&self.output * a) + &*x),
+        }
+    }
+
+    /// Whether lazy reduction latches edge operand `x` as-is
+    fn latches_unreduced(&self, x: &Integer) -> bool {
+        matches!(&self.lazy_threshold, Some(threshold) if *x >= 0 && x < threshold)
+    }
+
+    /// Write the value latched on a rising edge with input `x` into `out`
+    fn next_output_into(&self, x: &Integer, out: &mut Integer) {
+        if self.latches_unreduced(x) {
+            // Partially reduced residue, like a lazy-reduction datapath
+            out.assign(x);
+        } else {
+            self.reduce_into(x, out);
+            if self.signed_mode == SignedMode::Euclidean && *out < 0 {
+                *out += &self.p;
+            }
+        }
+    }
+
+    /// Cycles the shift-and-subtract reducer spends on edge operand `x`
+    fn edge_cycles(&self, x: &Integer) -> Option<u32> {
+        if self.strategy != ReductionStrategy::ShiftSubtract {
+            return None;
+        }
+        Some(if self.latches_unreduced(x) { 0 } else { self.shift_sub.cycles(x) })
+    }
+
+    /// Write `x mod P` into `out` using the configured strategy
+    fn reduce_into(&self, x: &Integer, out: &mut Integer) {
+        match self.strategy {
+            ReductionStrategy::Barrett => {
+                self.barrett.reduce_into(x, out);
+                return;
+            }
+            ReductionStrategy::ShiftSubtract => {
+                self.shift_sub.reduce_into(x, out);
+                return;
+            }
+            ReductionStrategy::Preinverted => {
+                self.preinv.reduce_into(x, out);
+                return;
+            }
+            ReductionStrategy::Folding => {
+                if let Some(reducer) = &self.folding {
+                    reducer.reduce_into(x, out);
+                    return;
+                }
+            }
+            ReductionStrategy::Gmp | ReductionStrategy::ConstantTime => {}
+        }
+        if let Some(reducer) = &self.ct {
+            // Operands without a fixed-limb form (negative or over 320 bits)
+            // are outside the spec and fall back to GMP
+            if let Some(limbs) = ct::to_limbs(x) {
+                out.assign_digits(&reducer.reduce(&limbs), Order::Lsf);
+                return;
+            }
+        }
+        // Compute X mod P using GMP's modular arithmetic
+        out.assign(x % &self.p);
+    }
+
+    /// Batch processing for multiple inputs
+    /// Processes multiple clock cycles in one call
+    ///
+    /// Result Integers come from the machine's arena; hand the vector back with
+    /// [`ModuloMachine::recycle`] to avoid allocating on the next batch.
+    pub fn process_batch(&mut self, inputs: &[(bool, bool, &Integer)]) -> Vec<Integer> {
+        #[cfg(feature = "tracing")]
+        let start = std::time::Instant::now();
+        let mut results = Vec::with_capacity(inputs.len());
+        
+        for &(clk, reset, x) in inputs {
+            self.tick(clk, reset, x);
+            let mut result = self.arena.take();
+            result.assign(&self.output);
+            results.push(result);
+        }
+        
+        sim_event!(info, ticks = inputs.len(), elapsed = ?start.elapsed(), "batch complete");
+        results
+    }
+
+    /// [`process_batch`] into a caller-owned vector, reusing the Integers
+    /// already in it
+    ///
+    /// `out` ends up with one output per input. Its existing elements are
+    /// overwritten in place, surplus ones go back to the arena and missing
+    /// ones come from it, so passing the same vector batch after batch stops
+    /// allocating once it has reached the batch size.
+    ///
+    /// [`process_batch`]: ModuloMachine::process_batch
+    pub fn process_batch_into(&mut self, inputs: &[(bool, bool, &Integer)], out: &mut Vec<Integer>) {
+        #[cfg(feature = "tracing")]
+        let start = std::time::Instant::now();
+        if out.len() > inputs.len() {
+            self.arena.give_all(out.drain(inputs.len()..));
+        }
+
+        for (i, &(clk, reset, x)) in inputs.iter().enumerate() {
+            self.tick(clk, reset, x);
+            match out.get_mut(i) {
+                Some(result) => result.assign(&self.output),
+                None => {
+                    let mut result = self.arena.take();
+                    result.assign(&self.output);
+                    out.push(result);
+                }
+            }
+        }
+
+        sim_event!(info, ticks = inputs.len(), elapsed = ?start.elapsed(), "batch complete");
+    }
+
+    /// [`process_batch`] as an iterator: each tick is applied only when its
+    /// output is requested
+    ///
+    /// Outputs are drawn from the arena like [`process_batch`]'s, so a
+    /// caller that hands them back with [`recycle`] keeps batch after batch
+    /// allocation-free without ever holding a whole batch of results.
+    ///
+    /// [`process_batch`]: ModuloMachine::process_batch
+    /// [`recycle`]: ModuloMachine::recycle
+    pub fn process_batch_iter<'m, 'a>(&'m mut self, inputs: &'a [(bool, bool, &'a Integer)]) -> BatchIter<'m, 'a> {
+        BatchIter {
+            machine: self,
+            inputs: inputs.iter(),
+        }
+    }
+
+    /// Latch each value on its own rising edge and collect the outputs
+    ///
+    /// Drives a full low→high clock cycle per value, so callers don't have to
+    /// interleave clock-low ticks themselves. Returns one output per value.
+    pub fn process_values(&mut self, values: &[Integer]) -> Vec<Integer> {
+        let mut results = Vec::with_capacity(values.len());
+
+        for x in values {
+            self.cycle(x);
+            let mut result = self.arena.take();
+            result.assign(&self.output);
+            results.push(result);
+        }
+
+        results
+    }
+
+    /// Drive the machine lazily from any source of ticks, yielding the output
+    /// after each one
+    ///
+    /// Nothing is buffered: a tick is applied only when its output is pulled,
+    /// so stimulus can be piped from a file reader or a
+    /// [`StimulusGenerator`](crate::StimulusGenerator) of any length. Each
+    /// output is written into its tick's own input Integer, so the stream
+    /// allocates nothing beyond what the source does.
+    ///
+    /// ```
+    /// use modulo_machine::{ModuloMachine, StimulusGenerator};
+    ///
+    /// let mut machine = ModuloMachine::new();
+    /// let p = machine.get_prime().clone();
+    /// let mut outputs = machine.process_stream(StimulusGenerator::new(7)).take(1000);
+    /// assert!(outputs.all(|o| o < p));
+    /// ```
+    pub fn process_stream<'m, I>(&'m mut self, ticks: I) -> impl Iterator<Item = Integer> + 'm
+    where
+        I: IntoIterator<Item = TickInput>,
+        I::IntoIter: 'm,
+    {
+        ticks.into_iter().map(move |tick| {
+            let TickInput { clk, reset, mut x } = tick;
+            self.tick(clk, reset, &x);
+            x.assign(&self.output);
+            x
+        })
+    }
+
+    /// Reduce independent operands across rayon's global pool, one output per
+    /// value in input order
+    ///
+    /// Unlike [`process_batch`] and [`process_values`], nothing is clocked:
+    /// each value gets exactly what [`peek_next`] returns for it, the result
+    /// the next rising edge would latch under the current configuration
+    /// (width policy, strategy, lazy reduction, signed mode) and register
+    /// contents. The register, clock, pipeline, counters and simulation time
+    /// are untouched, so values cannot influence each other and are reduced
+    /// in whatever order the workers pick them up. Use it to reduce large
+    /// vector files at full core count; use the clocked methods when the
+    /// sequence matters (reset, accumulate and MAC datapaths, pipelines).
+    ///
+    /// [`process_batch`]: ModuloMachine::process_batch
+    /// [`process_values`]: ModuloMachine::process_values
+    /// [`peek_next`]: ModuloMachine::peek_next
+    pub fn reduce_batch_parallel(&self, values: &[Integer]) -> Vec<Integer> {
+        // Small chunks per task: one reduction is far cheaper than a steal
+        values.par_iter().with_min_len(256).map(|x| self.peek_next(x)).collect()
+    }
+
+    /// Return spent batch results to the arena for reuse
+    pub fn recycle(&mut self, results: Vec<Integer>) {
+        self.arena.give_all(results);
+    }
+
+    /// Number of spare Integers held for the next batch
+    pub fn arena_len(&self) -> usize {
+        self.arena.len()
+    }
+
+    /// Configure the value the output register takes on reset
+    ///
+    /// Takes effect on the next reset; the current output is left untouched.
+    pub fn set_reset_value(&mut self, reset_value: ResetValue) {
+        self.reset_output = reset_value.value();
+        self.reset_value = reset_value;
+    }
+
+    /// Currently configured reset value
+    pub fn reset_value(&self) -> &ResetValue {
+        &self.reset_value
+    }
+
+    /// Choose which clock transitions latch (default [`EdgeMode::Rising`])
+    ///
+    /// Everything documented as happening on a rising edge happens on the
+    /// selected edges instead; [`ModuloMachine::process_values`] drives
+    /// whatever clock pattern produces one active edge per value.
+    pub fn set_edge_mode(&mut self, mode: EdgeMode) {
+        self.edge_mode = mode;
+    }
+
+    pub fn edge_mode(&self) -> EdgeMode {
+        self.edge_mode
+    }
+
+    /// Choose whether reset waits for an active clock edge (default
+    /// [`ResetKind::Async`])
+    pub fn set_reset_kind(&mut self, kind: ResetKind) {
+        self.reset_kind = kind;
+    }
+
+    pub fn reset_kind(&self) -> ResetKind {
+        self.reset_kind
+    }
+
+    /// Choose the sign convention for negative operands (default
+    /// [`SignedMode::Truncated`])
+    pub fn set_signed_mode(&mut self, mode: SignedMode) {
+        self.signed_mode = mode;
+    }
+
+    pub fn signed_mode(&self) -> SignedMode {
+        self.signed_mode
+    }
+
+    /// Choose what rising edges compute (default [`Datapath::Reduce`])
+    pub fn set_datapath(&mut self, datapath: Datapath) {
+        self.datapath = datapath;
+    }
+
+    pub fn datapath(&self) -> &Datapath {
+        &self.datapath
+    }
+
+    /// Delay results by `depth` active edges, like a reducer with that many
+    /// register stages (default 0: the sampling edge latches its own result)
+    ///
+    /// Results already in flight are discarded. The delay applies to
+    /// [`tick`] and everything built on it; [`tick_op`] and
+    /// [`tick_montgomery_mul`] latch directly. Accumulating datapaths feed
+    /// back the register, so they see the output from `depth` edges earlier.
+    ///
+    /// [`tick`]: ModuloMachine::tick
+    /// [`tick_op`]: ModuloMachine::tick_op
+    /// [`tick_montgomery_mul`]: ModuloMachine::tick_montgomery_mul
+    pub fn set_pipeline_depth(&mut self, depth: usize) {
+        self.pipeline = std::iter::repeat_n(None, depth).collect();
+    }
+
+    pub fn pipeline_depth(&self) -> usize {
+        self.pipeline.len()
+    }
+
+    /// Enable lazy reduction: on a rising edge, inputs below `threshold`
+    /// (typically `2P`) are latched as-is and only larger inputs are reduced.
+    /// `None` restores full reduction on every edge.
+    pub fn set_lazy_reduction(&mut self, threshold: Option<Integer>) {
+        self.lazy_threshold = threshold;
+    }
+
+    /// Current lazy-reduction threshold, if enabled
+    pub fn lazy_threshold(&self) -> Option<&Integer> {
+        self.lazy_threshold.as_ref()
+    }
+
+    /// Select how rising edges reduce their operand
+    ///
+    /// Returns `false`, leaving the current strategy in place, if the strategy
+    /// cannot handle the configured modulus. A new machine starts with
+    /// [`ReductionStrategy::Folding`] if its modulus has a special form and
+    /// [`ReductionStrategy::Gmp`] otherwise.
+    pub fn set_reduction_strategy(&mut self, strategy: ReductionStrategy) -> bool {
+        match strategy {
+            ReductionStrategy::Gmp | ReductionStrategy::Barrett | ReductionStrategy::ShiftSubtract | ReductionStrategy::Preinverted => self.ct = None,
+            ReductionStrategy::Folding if self.folding.is_some() => self.ct = None,
+            ReductionStrategy::Folding => return false,
+            ReductionStrategy::ConstantTime => match CtReducer::new(&self.p) {
+                Some(reducer) => self.ct = Some(reducer),
+                None => return false,
+            },
+        }
+        self.strategy = strategy;
+        true
+    }
+
+    /// Strategy currently used on rising edges
+    pub fn reduction_strategy(&self) -> ReductionStrategy {
+        self.strategy
+    }
+
+    /// The modulus's Mersenne or pseudo-Mersenne form, if
+    /// [`ReductionStrategy::Folding`] can reduce by it
+    pub fn special_form(&self) -> Option<SpecialForm> {
+        self.folding.as_ref().map(FoldingReducer::form)
+    }
+
+    /// Cycles the last edge's reduction took under
+    /// [`ReductionStrategy::ShiftSubtract`]: `bits(x) - bits(P) + 1`, or 0
+    /// for an operand latched without reduction
+    ///
+    /// `None` for the single-call strategies, after reset, and when the edge
+    /// dropped its input.
+    pub fn reduction_cycles(&self) -> Option<u32> {
+        self.reduction_cycles
+    }
+
+    /// Choose how rising edges treat inputs wider than 300 bits
+    pub fn set_input_width_policy(&mut self, policy: InputWidthPolicy) {
+        self.width_policy = policy;
+    }
+
+    pub fn input_width_policy(&self) -> InputWidthPolicy {
+        self.width_policy
+    }
+
+    /// Enforce the bus on every rising edge: inputs that are negative or
+    /// wider than 300 bits hold the output and are reported by
+    /// [`ModuloMachine::tick_checked`], whatever the width policy
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Advance simulation time by `units` per tick (default 1)
+    ///
+    /// Use the RTL simulator's timescale units: with a clock period of 10
+    /// units and one tick per clock phase, each tick spans 5.
+    pub fn set_time_step(&mut self, units: u64) {
+        self.time_step = units;
+    }
+
+    pub fn time_step(&self) -> u64 {
+        self.time_step
+    }
+
+    /// Timestamp the next tick will be applied at; reset does not rewind it
+    pub fn sim_time(&self) -> u64 {
+        self.time
+    }
+
+    /// Move the simulation clock, e.g. to match an RTL run's start offset
+    pub fn set_sim_time(&mut self, time: u64) {
+        self.time = time;
+    }
+
+    /// Show the output in `radix` when the machine is formatted (default 10)
+    ///
+    /// Radix 16, 8 and 2 get a `0x`, `0o` or `0b` prefix.
+    ///
+    /// # Panics
+    ///
+    /// If `radix` is outside `2..=36`.
+    pub fn set_display_radix(&mut self, radix: u32) {
+        assert!((2..=36).contains(&radix), "display radix must be in 2..=36");
+        self.display_radix = radix;
+    }
+
+    pub fn display_radix(&self) -> u32 {
+        self.display_radix
+    }
+
+    /// Whether the output register holds a fully reduced value in `[0, P)`
+    pub fn is_canonical(&self) -> bool {
+        self.output < self.p
+    }
+
+    /// Fully reduce the output register in place without a clock edge
+    pub fn canonicalize(&mut self) -> &Integer {
+        if !self.is_canonical() {
+            self.output %= &self.p;
+        }
+        &self.output
+    }
+
+    /// Drive the clock through one active edge with `x`, latching `x mod P`:
+    /// a full low→high or high→low cycle, or a single transition when both
+    /// edges latch
+    pub(crate) fn cycle(&mut self, x: &Integer) -> &Integer {
+        match self.edge_mode {
+            EdgeMode::Rising => {
+                self.tick(false, false, x);
+                self.tick(true, false, x)
+            }
+            EdgeMode::Falling => {
+                self.tick(true, false, x);
+                self.tick(false, false, x)
+            }
+            EdgeMode::Both => self.tick(!self.clk_prev, false, x),
+        }
+    }
+
+    /// Get current output without processing a clock tick
+    pub fn get_output(&self) -> &Integer {
+        &self.output
+    }
+
+    /// The 256-bit output register as big-endian bytes, zero-padded, and in
+    /// two's complement if a signed mode left it negative
+    pub fn output_to_bytes_be(&self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        Integer::from(self.output.keep_bits_ref(256)).write_digits(&mut bytes, Order::Msf);
+        bytes
+    }
+
+    /// The 256-bit output register as limbs, least significant first, in
+    /// two's complement if a signed mode left it negative
+    ///
+    /// Allocation-free unless the output is negative or, under lazy
+    /// reduction or a wide reset value, wider than 256 bits.
+    pub fn output_to_limbs(&self) -> [u64; P_LIMBS] {
+        let mut limbs = [0; P_LIMBS];
+        if self.output >= 0 && self.output.significant_bits() <= 256 {
+            self.output.write_digits(&mut limbs, Order::Lsf);
+        } else {
+            Integer::from(self.output.keep_bits_ref(256)).write_digits(&mut limbs, Order::Lsf);
+        }
+        limbs
+    }
+
+    /// Counters since creation or the last [`reset_stats`]
+    ///
+    /// [`reset_stats`]: ModuloMachine::reset_stats
+    pub fn stats(&self) -> MachineStats {
+        MachineStats {
+            strategy: self.strategy,
+            ..self.stats
+        }
+    }
+
+    /// Zero the counters; the datapath is untouched
+    pub fn reset_stats(&mut self) {
+        self.stats = MachineStats::default();
+    }
+
+    /// Get the modulus: P, or the one given to [`ModuloMachine::with_modulus`]
+    pub fn get_prime(&self) -> &Integer {
+        &self.p
+    }
+
+    /// Square root of `x` modulo P, or `None` if `x` is not a quadratic residue
+    ///
+    /// Requires a prime modulus: the specification's P is composite, so this
+    /// always returns `None` for it. See [`arith::mod_sqrt`] for which of the
+    /// two roots is returned.
+    pub fn mod_sqrt(&self, x: &Integer) -> Option<Integer> {
+        arith::mod_sqrt(x, &self.p)
+    }
+
+    /// Inverse of `x` modulo P, or `None` if `x` shares a factor with P
+    ///
+    /// The golden model for inversion units; see [`arith::mod_inverse`].
+    pub fn mod_inverse(&self, x: &Integer) -> Option<Integer> {
+        arith::mod_inverse(x, &self.p)
+    }
+
+    /// `(a * b) mod P` for every pair, with each double-width product
+    ///
+    /// Lets a multiplier-then-reducer pipeline check both stages against one
+    /// call; see [`arith::ModMul`].
+    pub fn mod_mul_batch(&self, pairs: &[(Integer, Integer)]) -> Vec<arith::ModMul> {
+        arith::mod_mul_batch(pairs, &self.p)
+    }
+
+    /// Check `x` is a value the unsigned 300-bit X bus can carry
+    pub fn validate_input(x: &Integer) -> Result<(), MachineError> {
+        if *x < 0 {
+            return Err(MachineError::NegativeInput);
+        }
+        match x.significant_bits() {
+            bits if bits > X_BITS => Err(MachineError::InputTooWide { bits }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Validate that input X is within 300-bit limit
+    ///
+    /// Advisory only; [`ModuloMachine::set_strict`] enforces the bus on
+    /// every edge instead.
+    pub fn validate_input_size(x: &Integer) -> bool {
+        // 300 bits can represent numbers up to 2^300 - 1
+        x.significant_bits() <= X_BITS
+    }
+
+    /// Validate that output is within 256-bit limit
+    pub fn validate_output_size(output: &Integer) -> bool {
+        // 256 bits can represent numbers up to 2^256 - 1  
+        output.significant_bits() <= 256
+    }
+
+    /// Create input from string
+    ///
+    /// Surrounding whitespace and a leading sign are allowed, a `0x`, `0o` or
+    /// `0b` prefix overrides `radix`, and single underscores may separate
+    /// digits (`0xdead_beef`). A prefix letter that is itself a digit in
+    /// `radix` is read as a digit, so `"0b1"` is still `0xb1` in radix 16.
+    /// Unlike `Integer::from_str_radix`, a radix outside `2..=36` is an error
+    /// rather than a panic.
+    pub fn create_input(s: &str, radix: i32) -> Result<Integer, MachineError> {
+        if !(2..=36).contains(&radix) {
+            return Err(MachineError::InvalidRadix { radix });
+        }
+        let s = s.trim();
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (radix, digits) = strip_radix_prefix(s, radix as u32);
+        if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+            return Err(MachineError::InvalidDigits);
+        }
+        let digits: String = digits.chars().filter(|&c| c != '_').collect();
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return Err(MachineError::InvalidDigits);
+        }
+        let x = Integer::from_str_radix(&digits, radix as i32).map_err(|_| MachineError::InvalidDigits)?;
+        Ok(if negative { -x } else { x })
+    }
+
+    /// Create input from big-endian bytes, the layout of
+    /// [`constants::P`]'s bytes and the C FFI
+    pub fn create_input_bytes(bytes: &[u8]) -> Integer {
+        Self::input_from_bytes_be(bytes)
+    }
+
+    /// Unsigned input from big-endian bytes, as a bus model or hash function
+    /// produces them; any length, leading zeros allowed
+    pub fn input_from_bytes_be(bytes: &[u8]) -> Integer {
+        Integer::from_digits(bytes, Order::Msf)
+    }
+
+    /// Unsigned input from little-endian bytes
+    pub fn input_from_bytes_le(bytes: &[u8]) -> Integer {
+        Integer::from_digits(bytes, Order::Lsf)
+    }
+
+    /// Create input from base64 (standard or URL-safe alphabet, padding
+    /// optional, whitespace ignored) holding big-endian bytes
+    pub fn create_input_base64(s: &str) -> Result<Integer, MachineError> {
+        decode_base64(s).map(|bytes| Self::create_input_bytes(&bytes)).ok_or(MachineError::InvalidDigits)
+    }
+
+    /// Create input from u64
+    pub fn create_input_u64(val: u64) -> Integer {
+        Integer::from(val)
+    }
+
+    /// Create large test inputs
+    pub fn create_large_input(base_power: u32, offset: u64) -> Integer {
+        // Creates 2^base_power + offset
+        (Integer::from(1) << base_power) + offset
+    }
+}
+
+/// Outputs of [`ModuloMachine::process_batch_iter`], one per input tick
+pub struct BatchIter<'m, 'a> {
+    machine: &'m mut ModuloMachine,
+    inputs: std::slice::Iter<'a, (bool, bool, &'a Integer)>,
+}
+
+impl Iterator for BatchIter<'_, '_> {
+    type Item = Integer;
+
+    fn next(&mut self) -> Option<Integer> {
+        let &(clk, reset, x) = self.inputs.next()?;
+        self.machine.tick(clk, reset, x);
+        let mut result = self.machine.arena.take();
+        result.assign(&self.machine.output);
+        Some(result)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inputs.size_hint()
+    }
+}
+
+impl ExactSizeIterator for BatchIter<'_, '_> {}
+
+impl Default for ModuloMachine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Split a `0x`/`0o`/`0b` prefix off `s`, unless its letter is a digit in
+/// `radix`
+fn strip_radix_prefix(s: &str, radix: u32) -> (u32, &str) {
+    let mut chars = s.chars();
+    if let (Some('0'), Some(letter)) = (chars.next(), chars.next()) {
+        let prefix_radix = match letter.to_ascii_lowercase() {
+            'x' => Some(16),
+            'o' => Some(8),
+            'b' => Some(2),
+            _ => None,
+        };
+        if let Some(prefix_radix) = prefix_radix.filter(|_| !letter.is_digit(radix)) {
+            return (prefix_radix, &s[2..]);
+        }
+    }
+    (radix, s)
+}
+
+fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(s.len() * 3 / 4);
+    let (mut buffer, mut bits) = (0u32, 0);
+    let mut padding = 0;
+    for c in s.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => {
+                padding += 1;
+                continue;
+            }
+            _ => return None,
+        };
+        // Nothing may follow padding
+        if padding > 0 {
+            return None;
+        }
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    // A lone trailing character carries fewer than 8 bits
+    if bits == 6 || padding > 2 {
+        return None;
+    }
+    Some(bytes)
+}
+
+/// `x` in `radix` as [`ModuloMachine`]'s `Display` shows its output: a
+/// `0x`, `0o` or `0b` prefix for radix 16, 8 or 2, after any sign
+pub fn format_radix(x: &Integer, radix: u32) -> String {
+    let prefix = match radix {
+        16 => "0x",
+        8 => "0o",
+        2 => "0b",
+        _ => "",
+    };
+    let sign = if *x < 0 { "-" } else { "" };
+    format!("{}{}{}", sign, prefix, Integer::from(x.abs_ref()).to_string_radix(radix as i32))
+}
+
+impl fmt::Display for ModuloMachine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_radix(&self.output, self.display_radix))
+    }
+}
+
+/// One line of the state that matters when reading logs; the modulus is
+/// shown by width only
+impl fmt::Debug for ModuloMachine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ModuloMachine")
+            .field("output", &format_args!("{}", self))
+            .field("clk", &self.clk_prev)
+            .field("p_bits", &self._p_bits)
+            .field("strategy", &self.strategy)
+            .field("datapath", &self.datapath)
+            .field("edge", &self.edge_mode)
+            .field("pipeline_depth", &self.pipeline.len())
+            .field("busy", &self.busy())
+            .field("time", &self.time)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants;
+
+    #[test]
+    fn test_machine_creation() {
+        let machine = ModuloMachine::new();
+        assert_eq!(*machine.get_output(), 0);
+        
+        // Verify P is loaded correctly
+        let expected_p = Integer::from_str_radix(P_STR, 10).unwrap();
+        assert_eq!(machine.get_prime(), &expected_p);
+    }
+
+    #[test]
+    fn test_with_modulus() {
+        // A small prime and a composite modulus
+        for p in [1_000_003u32, 15] {
+            let mut machine = ModuloMachine::with_modulus(Integer::from(p)).unwrap();
+            assert_eq!(machine.get_prime(), &p);
+            let values = [Integer::from(p + 2), Integer::from(5u32) << 200u32];
+            let expected: Vec<Integer> = values.iter().map(|x| Integer::from(x % p)).collect();
+            assert_eq!(machine.process_values(&values), expected);
+            // Too narrow for the constant-time reducer
+            assert!(!machine.set_reduction_strategy(ReductionStrategy::ConstantTime));
+        }
+
+        // A 256-bit prime gets a constant-time reducer derived from it
+        let secp256k1 = Integer::from_str_radix("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F", 16).unwrap();
+        let mut machine = ModuloMachine::with_modulus(secp256k1.clone()).unwrap();
+        assert!(machine.set_reduction_strategy(ReductionStrategy::ConstantTime));
+        let x = Integer::from(&secp256k1 * 3u32) + 4u32;
+        assert_eq!(*machine.cycle(&x), 4);
+
+        assert_eq!(ModuloMachine::with_modulus(Integer::from(1u32)).err(), Some(ModulusError::TooSmall));
+        assert_eq!(ModuloMachine::with_modulus(Integer::from(10u32)).err(), Some(ModulusError::Even));
+    }
+
+    #[test]
+    fn test_with_validated_modulus() {
+        // Primality and width are only enforced on the validated path
+        let spec = Integer::from_str_radix(P_STR, 10).unwrap();
+        assert!(ModuloMachine::with_modulus(spec.clone()).is_ok());
+        assert_eq!(ModuloMachine::with_validated_modulus(spec).err(), Some(ModulusError::Composite));
+        assert!(ModuloMachine::with_modulus(Integer::from(1_000_003u32)).is_ok());
+        assert_eq!(
+            ModuloMachine::with_validated_modulus(Integer::from(1_000_003u32)).err(),
+            Some(ModulusError::TopBitClear { bits: 20, expected: 256 })
+        );
+
+        let secp256k1 = Preset::Secp256k1N.modulus();
+        let mut machine = ModuloMachine::with_validated_modulus(secp256k1.clone()).unwrap();
+        assert_eq!(*machine.cycle(&(Integer::from(&secp256k1 * 2u32) + 9u32)), 9);
+    }
+
+    #[test]
+    fn test_for_preset() {
+        let mut machine = ModuloMachine::for_preset(Preset::Goldilocks);
+        assert_eq!(machine.get_prime(), &Preset::Goldilocks.modulus());
+        // 2^64 = 2^32 - 1 mod p
+        assert_eq!(*machine.cycle(&(Integer::from(1) << 64u32)), 0xffff_ffffu32);
+
+        let mut machine = ModuloMachine::for_preset(Preset::Bn254Fr);
+        let r = Preset::Bn254Fr.modulus();
+        assert_eq!(*machine.cycle(&Integer::from(&r + 9u32)), 9);
+    }
+
+    #[test]
+    fn test_reset_functionality() {
+        let mut machine = ModuloMachine::new();
+        
+        // Set some output first
+        let x = Integer::from(12345u32);
+        machine.tick(true, false, &x);
+        assert_ne!(*machine.get_output(), 0);
+        
+        // Reset should clear output
+        let zero = Integer::from(0);
+        machine.tick(false, true, &zero);
+        assert_eq!(*machine.get_output(), 0);
+    }
+
+    #[test]
+    fn test_reset_value() {
+        let mut machine = ModuloMachine::new();
+        let zero = Integer::new();
+
+        machine.set_reset_value(ResetValue::AllOnes);
+        assert_eq!(*machine.get_output(), 0);
+        machine.tick(false, true, &zero);
+        assert_eq!(machine.get_output().significant_bits(), 256);
+        assert_eq!(machine.get_output().count_ones(), Some(256));
+
+        machine.set_reset_value(ResetValue::Custom(Integer::from(0xDEADu32)));
+        machine.tick(false, true, &zero);
+        assert_eq!(*machine.get_output(), 0xDEAD);
+        assert_eq!(machine.reset_value(), &ResetValue::Custom(Integer::from(0xDEADu32)));
+
+        // Normal operation overwrites the sentinel
+        machine.cycle(&Integer::from(7u32));
+        assert_eq!(*machine.get_output(), 7);
+    }
+
+    #[test]
+    fn test_basic_modulo_operation() {
+        let mut machine = ModuloMachine::new();
+        let p = machine.get_prime().clone();
+        
+        // Test with a number smaller than P
+        let x_small = Integer::from(12345u32);
+        let result = machine.tick(true, false, &x_small);
+        assert_eq!(*result, x_small); // Should be unchanged since x < P
+        
+        // Test with P itself - need to cycle clock first
+        let zero = Integer::from(0);
+        machine.tick(false, false, &zero); // Clock low
+        let result = machine.tick(true, false, &p); // Clock high (rising edge)
+        assert_eq!(*result, 0); // P mod P = 0
+        
+        // Test with P + 1 - need to cycle clock first
+        machine.tick(false, false, &zero); // Clock low
+        let x_large = Integer::from(&p + 1);
+        let result = machine.tick(true, false, &x_large); // Clock high (rising edge)
+        assert_eq!(*result, 1); // (P + 1) mod P = 1
+    }
+
+    #[test]
+    fn test_input_size_validation() {
+        // Test valid 300-bit input
+        let max_300_bit = ModuloMachine::create_large_input(300, 0) - 1;
+        assert!(ModuloMachine::validate_input_size(&max_300_bit));
+        
+        // Test invalid 301-bit input  
+        let min_301_bit = ModuloMachine::create_large_input(300, 0);
+        assert!(!ModuloMachine::validate_input_size(&min_301_bit));
+    }
+
+    #[test]
+    fn test_output_size_validation() {
+        let machine = ModuloMachine::new();
+        let p = machine.get_prime();
+        
+        // Output should always be < P, so within 256 bits
+        assert!(ModuloMachine::validate_output_size(p));
+        let p_minus_one = Integer::from(p - 1);
+        assert!(ModuloMachine::validate_output_size(&p_minus_one));
+    }
+
+    #[test]
+    fn test_batch_processing() {
+        let mut machine = ModuloMachine::new();
+        
+        // Create test inputs
+        let input1 = Integer::from(12345u64);
+        let input2 = Integer::from(67890u64);
+        let input3 = Integer::from(0u64);
+        let input4 = Integer::from(99999u64);
+        
+        // Need to properly cycle clock for each operation
+        let inputs = vec![
+            (true, false, &input1),  // Rising edge - should process
+            (false, false, &input1), // Clock low
+            (true, false, &input2),  // Rising edge - should process input2
+            (false, true, &input3),  // Reset while clock low
+            (true, false, &input4),  // Rising edge after reset - should process input4
+        ];
+        
+        let results = machine.process_batch(&inputs);
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0], 12345); // First input processed
+        assert_eq!(results[1], 12345); // Clock low, output unchanged
+        assert_eq!(results[2], 67890); // Second input processed
+        assert_eq!(results[3], 0);     // After reset
+        assert_eq!(results[4], 99999); // Fourth input processed
+    }
+
+    #[test]
+    fn test_batch_results_are_recycled() {
+        let mut machine = ModuloMachine::with_capacity_hint(320);
+        let (a, b) = (Integer::from(11u32), Integer::from(22u32));
+        let inputs = [(true, false, &a), (false, false, &b), (true, false, &b)];
+
+        let results = machine.process_batch(&inputs);
+        assert_eq!(results, [11, 11, 22]);
+        assert_eq!(machine.arena_len(), 0);
+
+        machine.recycle(results);
+        assert_eq!(machine.arena_len(), 3);
+
+        // The next batch draws from the arena and still sees correct values
+        machine.reset();
+        let results = machine.process_batch(&inputs[..2]);
+        assert_eq!(results, [11, 11]);
+        assert_eq!(machine.arena_len(), 1);
+    }
+
+    #[test]
+    fn test_process_stream() {
+        let ticks = crate::StimulusGenerator::new(9).with_reset_probability(0.05).generate(400);
+        let refs: Vec<_> = ticks.iter().map(|t| (t.clk, t.reset, &t.x)).collect();
+        let expected = ModuloMachine::new().process_batch(&refs);
+
+        let mut machine = ModuloMachine::new();
+        assert_eq!(machine.process_stream(ticks.clone()).collect::<Vec<_>>(), expected);
+
+        // Lazy: only pulled ticks are applied, even from an endless source
+        let mut machine = ModuloMachine::new();
+        let first: Vec<Integer> = machine.process_stream(crate::StimulusGenerator::new(9).with_reset_probability(0.05)).take(10).collect();
+        assert_eq!(first, expected[..10]);
+        assert_eq!(machine.stats().ticks, 10);
+    }
+
+    #[test]
+    fn test_reduce_batch_parallel() {
+        let mut state = 3;
+        let values: Vec<Integer> = (0..5000)
+            .map(|_| {
+                let limbs: [u64; X_LIMBS] = std::array::from_fn(|_| crate::vectors::splitmix64(&mut state));
+                Integer::from_digits(&limbs, Order::Lsf).keep_bits(300)
+            })
+            .collect();
+        let mut machine = ModuloMachine::new();
+        machine.process_values(&[Integer::from(42)]);
+        let before = machine.stats();
+
+        let reduced = machine.reduce_batch_parallel(&values);
+        assert_eq!(reduced, ModuloMachine::new().process_values(&values));
+        // Stateless: the register and counters are as they were
+        assert_eq!(*machine.get_output(), 42);
+        assert_eq!(machine.stats(), before);
+
+        // Every value sees the same register snapshot
+        machine.set_datapath(Datapath::Accumulate);
+        let p = machine.get_prime().clone();
+        assert_eq!(machine.reduce_batch_parallel(&[Integer::from(1), Integer::from(&p - 42u32)]), [43, 0]);
+    }
+
+    #[test]
+    fn test_process_batch_into_and_iter() {
+        let values: Vec<Integer> = (0..6u32).map(|i| Integer::from(i * 1000 + 7)).collect();
+        let inputs: Vec<_> = values.iter().enumerate().map(|(i, x)| (i % 2 == 0, i == 4, x)).collect();
+        let expected = ModuloMachine::new().process_batch(&inputs);
+
+        let mut machine = ModuloMachine::new();
+        let mut out = Vec::new();
+        machine.process_batch_into(&inputs, &mut out);
+        assert_eq!(out, expected);
+
+        // A shorter batch hands the surplus to the arena and keeps the rest
+        machine.reset();
+        machine.process_batch_into(&inputs[..2], &mut out);
+        assert_eq!(out, expected[..2]);
+        assert_eq!(machine.arena_len(), 4);
+        machine.process_batch_into(&inputs, &mut out);
+        assert_eq!(machine.arena_len(), 0);
+
+        // The iterator applies ticks only as outputs are pulled
+        let mut machine = ModuloMachine::new();
+        let mut iter = machine.process_batch_iter(&inputs);
+        assert_eq!(iter.len(), 6);
+        let pulled: Vec<Integer> = iter.by_ref().take(3).collect();
+        assert_eq!(pulled, expected[..3]);
+        assert_eq!(iter.len(), 3);
+        assert_eq!(machine.stats().ticks, 3);
+        machine.recycle(pulled);
+        let rest: Vec<Integer> = machine.process_batch_iter(&inputs[3..]).collect();
+        assert_eq!(rest, expected[3..]);
+        assert_eq!(machine.arena_len(), 0);
+    }
+
+    #[test]
+    fn test_lazy_reduction() {
+        let mut machine = ModuloMachine::new();
+        let p = machine.get_prime().clone();
+        let two_p = Integer::from(&p * 2u32);
+        machine.set_lazy_reduction(Some(two_p.clone()));
+
+        // Below the threshold: latched partially reduced
+        let x = Integer::from(&p + 5u32);
+        machine.tick(true, false, &x);
+        assert_eq!(*machine.get_output(), x);
+        assert!(!machine.is_canonical());
+        assert_eq!(*machine.canonicalize(), 5);
+        assert!(machine.is_canonical());
+
+        // At or above the threshold: fully reduced
+        let x = Integer::from(&two_p + 7u32);
+        machine.tick(false, false, &x);
+        machine.tick(true, false, &x);
+        assert_eq!(*machine.get_output(), 7);
+        assert!(machine.is_canonical());
+
+        // Disabling restores full reduction
+        machine.set_lazy_reduction(None);
+        let x = Integer::from(&p + 9u32);
+        machine.tick(false, false, &x);
+        machine.tick(true, false, &x);
+        assert_eq!(*machine.get_output(), 9);
+    }
+
+    #[test]
+    fn test_constant_time_strategy() {
+        let mut machine = ModuloMachine::new();
+        assert_eq!(machine.reduction_strategy(), ReductionStrategy::Gmp);
+        assert!(machine.set_reduction_strategy(ReductionStrategy::ConstantTime));
+        assert_eq!(machine.reduction_strategy(), ReductionStrategy::ConstantTime);
+
+        let p = machine.get_prime().clone();
+        let values = [
+            Integer::from(12345u32),
+            Integer::from(&p + 1u32),
+            ModuloMachine::create_large_input(300, 0) - 1u32,
+        ];
+        let results = machine.process_values(&values);
+        for (x, result) in values.iter().zip(&results) {
+            assert_eq!(*result, Integer::from(x % &p));
+        }
+    }
+
+    #[test]
+    fn test_barrett_strategy() {
+        let mut machine = ModuloMachine::for_preset(Preset::Goldilocks);
+        assert!(machine.set_reduction_strategy(ReductionStrategy::Barrett));
+        assert_eq!(machine.reduction_strategy(), ReductionStrategy::Barrett);
+
+        let p = machine.get_prime().clone();
+        let values = [
+            Integer::from(&p + 1u32),
+            ModuloMachine::create_large_input(300, 0) - 1u32,
+            ModuloMachine::create_large_input(299, 987654321),
+        ];
+        let results = machine.process_values(&values);
+        for (x, result) in values.iter().zip(&results) {
+            assert_eq!(*result, Integer::from(x % &p));
+        }
+
+        // Switching back to GMP keeps the results
+        assert!(machine.set_reduction_strategy(ReductionStrategy::Gmp));
+        assert_eq!(machine.process_values(&values), results);
+    }
+
+    #[test]
+    fn test_preinverted_strategy() {
+        for mut machine in [ModuloMachine::new(), ModuloMachine::for_preset(Preset::Goldilocks)] {
+            let expected = machine.clone();
+            assert!(machine.set_reduction_strategy(ReductionStrategy::Preinverted));
+            assert_eq!(machine.stats().strategy, ReductionStrategy::Preinverted);
+
+            let p = machine.get_prime().clone();
+            let values = [
+                Integer::from(&p + 1u32),
+                ModuloMachine::create_large_input(300, 0) - 1u32,
+                ModuloMachine::create_large_input(299, 987654321),
+                Integer::from(-12345),
+            ];
+            assert_eq!(machine.process_values(&values), expected.clone().process_values(&values));
+        }
+    }
+
+    #[test]
+    fn test_folding_selected_for_special_form() {
+        let p = (Integer::from(1) << 255u32) - 19u32;
+        let mut machine = ModuloMachine::with_modulus(p.clone()).unwrap();
+        assert_eq!(machine.special_form(), Some(SpecialForm::PseudoMersenne { k: 255, c: 19 }));
+        assert_eq!(machine.reduction_strategy(), ReductionStrategy::Folding);
+        assert_eq!(machine.stats().strategy, ReductionStrategy::Folding);
+
+        let values = [Integer::from(&p + 4u32), ModuloMachine::create_large_input(300, 0) - 1u32, ModuloMachine::create_large_input(299, 42)];
+        let results = machine.process_values(&values);
+        for (x, result) in values.iter().zip(&results) {
+            assert_eq!(*result, Integer::from(x % &p));
+        }
+        assert!(machine.set_reduction_strategy(ReductionStrategy::Gmp));
+        assert_eq!(machine.stats().strategy, ReductionStrategy::Gmp);
+        assert_eq!(machine.process_values(&values), results);
+
+        let mut machine = ModuloMachine::new();
+        assert_eq!(machine.special_form(), None);
+        assert_eq!(machine.reduction_strategy(), ReductionStrategy::Gmp);
+        assert!(!machine.set_reduction_strategy(ReductionStrategy::Folding));
+    }
+
+    #[test]
+    fn test_accumulate_datapath() {
+        let mut machine = ModuloMachine::new();
+        let p = machine.get_prime().clone();
+        machine.set_datapath(Datapath::Accumulate);
+        assert_eq!(machine.datapath(), &Datapath::Accumulate);
+
+        // P - 1 added 1000 times wraps past P on every edge but the first
+        let x = Integer::from(&p - 1u32);
+        let outputs = machine.process_values(&vec![x.clone(); 1000]);
+        for (i, output) in outputs.iter().enumerate() {
+            assert_eq!(*output, Integer::from(&x * (i as u32 + 1)) % &p);
+        }
+        // 300-bit inputs fold in reduced
+        let wide = ModuloMachine::create_large_input(299, 42);
+        let expected = Integer::from(&outputs[999] + &wide) % &p;
+        assert_eq!(machine.peek_next(&wide), expected);
+        assert_eq!(machine.process_values(std::slice::from_ref(&wide)), [expected]);
+
+        // Reset restarts the sum from the reset value
+        machine.set_reset_value(ResetValue::Custom(Integer::from(10u32)));
+        machine.tick(false, true, &x);
+        assert_eq!(machine.process_values(&[Integer::from(5u32), Integer::from(7u32)]), [15, 22]);
+    }
+
+    #[test]
+    fn test_mac_datapath() {
+        let mut machine = ModuloMachine::new();
+        let p = machine.get_prime().clone();
+        let a = ModuloMachine::create_large_input(299, 3);
+        machine.set_datapath(Datapath::Mac(a.clone()));
+
+        // Horner evaluation of c0·a^3 + c1·a^2 + c2·a + c3
+        let coefficients = [
+            Integer::from(&p - 1u32),
+            ModuloMachine::create_large_input(300, 0) - 1u32,
+            Integer::from(7u32),
+            Integer::from(&p + 11u32),
+        ];
+        let mut expected = Integer::new();
+        for c in &coefficients {
+            expected = Integer::from(&expected * &a) + c;
+        }
+        expected %= &p;
+        for strategy in [ReductionStrategy::Gmp, ReductionStrategy::Barrett, ReductionStrategy::ShiftSubtract, ReductionStrategy::ConstantTime] {
+            assert!(machine.set_reduction_strategy(strategy));
+            machine.reset();
+            let outputs = machine.process_values(&coefficients);
+            assert_eq!(outputs[3], expected, "{:?}", strategy);
+        }
+    }
+
+    #[test]
+    fn test_edge_modes() {
+        let (a, b, c) = (Integer::from(11u32), Integer::from(22u32), Integer::from(33u32));
+        // clk: 0 1 1 0 1 with a new value each tick
+        let run = |mode: EdgeMode| {
+            let mut machine = ModuloMachine::new();
+            machine.set_edge_mode(mode);
+            let ticks = [(false, &a), (true, &b), (true, &c), (false, &a), (true, &c)];
+            ticks.iter().map(|&(clk, x)| machine.tick(clk, false, x).clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(run(EdgeMode::Rising), [0, 22, 22, 22, 33]);
+        assert_eq!(run(EdgeMode::Falling), [0, 0, 0, 11, 11]);
+        assert_eq!(run(EdgeMode::Both), [0, 22, 22, 11, 33]);
+
+        let values = [a.clone(), b.clone(), c.clone()];
+        for mode in [EdgeMode::Rising, EdgeMode::Falling, EdgeMode::Both] {
+            let mut machine = ModuloMachine::new();
+            machine.set_edge_mode(mode);
+            assert_eq!(machine.process_values(&values), values, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn test_reset_kinds() {
+        let x = Integer::from(42u32);
+        let mut machine = ModuloMachine::new();
+        machine.set_reset_kind(ResetKind::Sync);
+        machine.cycle(&x);
+
+        // Reset with the clock held high or low does nothing until an edge
+        assert_eq!(*machine.tick(true, true, &x), 42);
+        assert_eq!(*machine.tick(false, true, &x), 42);
+        assert_eq!(*machine.tick(true, true, &x), 0);
+        // The clock stayed high through the reset edge: no new edge here
+        assert_eq!(*machine.tick(true, false, &x), 0);
+        assert_eq!(*machine.cycle(&x), 42);
+
+        // Asynchronous reset clears immediately, and the next high clock is
+        // an edge again
+        machine.set_reset_kind(ResetKind::Async);
+        assert_eq!(*machine.tick(true, true, &x), 0);
+        assert_eq!(*machine.tick(true, false, &x), 42);
+    }
+
+    #[test]
+    fn test_signed_mode() {
+        let mut machine = ModuloMachine::new();
+        let p = machine.get_prime().clone();
+        let values = [Integer::from(-3), Integer::from(-&p) - 3u32, -ModuloMachine::create_large_input(299, 1), Integer::from(4u32)];
+        assert_eq!(machine.signed_mode(), SignedMode::Truncated);
+        let truncated = machine.process_values(&values);
+        assert_eq!(truncated[0], -3);
+        assert_eq!(truncated[1], -3);
+        assert!(truncated[2] < 0);
+
+        for strategy in [ReductionStrategy::Gmp, ReductionStrategy::Barrett, ReductionStrategy::ShiftSubtract, ReductionStrategy::ConstantTime] {
+            assert!(machine.set_reduction_strategy(strategy));
+            machine.set_signed_mode(SignedMode::Euclidean);
+            let euclidean = machine.process_values(&values);
+            assert_eq!(euclidean[0], Integer::from(&p - 3u32));
+            assert_eq!(euclidean[1], Integer::from(&p - 3u32));
+            assert_eq!(euclidean[2], Integer::from(&truncated[2] + &p));
+            assert_eq!(euclidean[3], 4);
+            assert_eq!(machine.peek_next(&values[0]), euclidean[0]);
+            machine.set_signed_mode(SignedMode::Truncated);
+        }
+    }
+
+    #[test]
+    fn test_tick_op() {
+        let mut machine = ModuloMachine::new();
+        let p = machine.get_prime().clone();
+        let (a, b) = (Integer::from(&p - 5u32), Integer::from(9u32));
+        let inverse = machine.mod_inverse(&a).unwrap();
+        let mut run = |op: Op| {
+            machine.tick_op(false, false, op, &a, &b);
+            machine.tick_op(true, false, op, &a, &b).clone()
+        };
+        assert_eq!(run(Op::AddMod), 4);
+        assert_eq!(run(Op::SubMod), Integer::from(&p - 14u32));
+        assert_eq!(run(Op::MulMod), Integer::from(&p - 45u32));
+        assert_eq!(run(Op::Inv), inverse);
+        assert_eq!(run(Op::Mod), a);
+
+        // The opcode is sampled on the edge only
+        let held = machine.tick_op(true, false, Op::AddMod, &a, &b).clone();
+        assert_eq!(held, a);
+        machine.tick_op(false, true, Op::MulMod, &a, &b);
+        assert_eq!(*machine.get_output(), 0);
+    }
+
+    #[test]
+    fn test_modexp_latency() {
+        let mut machine = ModuloMachine::new();
+        let p = machine.get_prime().clone();
+        let (base, exponent) = (Integer::from(&p - 3u32), Integer::from(0b1011_0110u32));
+        let expected = Op::ModExp.apply(&base, &exponent, &p);
+        machine.tick(false, false, &base);
+        machine.tick(true, false, &Integer::from(5u32));
+
+        // Issue, then one edge per exponent bit with garbage on the inputs
+        machine.tick_op(false, false, Op::ModExp, &base, &exponent);
+        machine.tick_op(true, false, Op::ModExp, &base, &exponent);
+        let mut edges = 0;
+        while !machine.done() {
+            assert!(machine.busy());
+            assert_eq!(*machine.get_output(), 5, "output holds while busy");
+            machine.tick_op(false, false, Op::AddMod, &p, &p);
+            machine.tick_op(true, false, Op::AddMod, &p, &p);
+            edges += 1;
+        }
+        assert_eq!(edges, 8);
+        assert!(!machine.busy());
+        assert_eq!(*machine.get_output(), expected);
+
+        // The done pulse lasts one edge; a zero exponent finishes on issue
+        machine.tick_op(false, false, Op::ModExp, &base, &Integer::new());
+        assert!(machine.done(), "no edge yet");
+        machine.tick_op(true, false, Op::ModExp, &base, &Integer::new());
+        assert!(machine.done() && *machine.get_output() == 1);
+        machine.tick_op(false, false, Op::Mod, &base, &base);
+        machine.tick_op(true, false, Op::Mod, &base, &base);
+        assert!(!machine.done());
+
+        // Reset aborts an exponentiation in flight
+        machine.tick_op(false, false, Op::ModExp, &base, &exponent);
+        machine.tick_op(true, false, Op::ModExp, &base, &exponent);
+        machine.tick_op(false, true, Op::ModExp, &base, &exponent);
+        assert!(!machine.busy() && *machine.get_output() == 0);
+    }
+
+    #[test]
+    fn test_shift_subtract_cycles() {
+        let mut machine = ModuloMachine::new();
+        assert!(machine.set_reduction_strategy(ReductionStrategy::ShiftSubtract));
+        let p = machine.get_prime().clone();
+        let x = ModuloMachine::create_large_input(300, 0) - 1u32;
+        machine.cycle(&x);
+        assert_eq!(*machine.get_output(), Integer::from(&x % &p));
+        assert_eq!(machine.reduction_cycles(), Some(300 - 256 + 1));
+
+        // Below P needs no subtraction; lazily latched operands skip the unit
+        machine.cycle(&Integer::from(5u32));
+        assert_eq!(machine.reduction_cycles(), Some(0));
+        machine.set_lazy_reduction(Some(Integer::from(&p * 2u32)));
+        machine.cycle(&Integer::from(&p + 1u32));
+        assert_eq!(machine.reduction_cycles(), Some(0));
+
+        machine.tick(false, true, &x);
+        assert_eq!(machine.reduction_cycles(), None);
+        assert!(machine.set_reduction_strategy(ReductionStrategy::Gmp));
+        machine.cycle(&x);
+        assert_eq!(machine.reduction_cycles(), None);
+    }
+
+    #[test]
+    fn test_clone_and_formatting() {
+        let mut machine = ModuloMachine::new();
+        machine.cycle(&Integer::from(255u32));
+        let mut copy = machine.clone();
+        copy.cycle(&Integer::from(1u32));
+        assert_eq!(*machine.get_output(), 255);
+        assert_eq!(*copy.get_output(), 1);
+
+        assert_eq!(machine.to_string(), "255");
+        for (radix, shown) in [(16, "0xff"), (2, "0b11111111"), (8, "0o377"), (36, "73")] {
+            machine.set_display_radix(radix);
+            assert_eq!(machine.to_string(), shown);
+        }
+        machine.set_signed_mode(SignedMode::Truncated);
+        machine.cycle(&Integer::from(-255));
+        assert_eq!(machine.to_string(), "-73");
+
+        machine.set_display_radix(16);
+        let debug = format!("{:?}", machine);
+        assert!(debug.starts_with("ModuloMachine { output: -0xff, clk: true, p_bits: 256, strategy: Gmp"), "{}", debug);
+    }
+
+    #[test]
+    fn test_pipeline_latency() {
+        let mut machine = ModuloMachine::new();
+        machine.set_strict(true);
+        machine.set_pipeline_depth(2);
+        let p = machine.get_prime().clone();
+        let inputs = [Integer::from(&p + 1u32), Integer::from(-1), Integer::from(&p + 3u32), Integer::new(), Integer::new()];
+
+        // Results appear two edges after sampling; the rejected input is a bubble
+        let mut seen = Vec::new();
+        for x in &inputs {
+            machine.cycle(x);
+            seen.push((machine.output_valid(), machine.get_output().to_u32()));
+        }
+        assert_eq!(seen, [(false, Some(0)), (false, Some(0)), (true, Some(1)), (false, Some(1)), (true, Some(3))]);
+
+        // Reset flushes the stages
+        machine.cycle(&Integer::from(7u32));
+        machine.tick(false, true, &Integer::new());
+        assert!(!machine.output_valid());
+        machine.cycle(&Integer::new());
+        machine.cycle(&Integer::new());
+        assert!(!machine.output_valid() && *machine.get_output() == 0);
+
+        // Depth 0 latches on the sampling edge
+        machine.set_pipeline_depth(0);
+        machine.cycle(&Integer::from(7u32));
+        assert!(machine.output_valid() && *machine.get_output() == 7);
+    }
+
+    #[test]
+    fn test_montgomery_mul() {
+        let mut machine = ModuloMachine::new();
+        let p = machine.get_prime().clone();
+        let (a, b) = (ModuloMachine::create_large_input(250, 17), Integer::from(&p - 2u32));
+        let (am, bm) = (machine.to_montgomery(&a), machine.to_montgomery(&b));
+
+        // Square-and-multiply chain entirely in the Montgomery domain
+        machine.tick_montgomery_mul(false, false, &am, &bm);
+        let product = machine.tick_montgomery_mul(true, false, &am, &bm).clone();
+        machine.tick_montgomery_mul(true, false, &product, &product);
+        assert_eq!(*machine.get_output(), product, "held clock must not re-trigger");
+        machine.tick_montgomery_mul(false, false, &product, &product);
+        let square = machine.tick_montgomery_mul(true, false, &product, &product).clone();
+
+        let expected = Integer::from(&a * &b) % &p;
+        assert_eq!(machine.from_montgomery(&product), expected);
+        assert_eq!(machine.from_montgomery(&square), Integer::from(&expected * &expected) % &p);
+
+        machine.tick_montgomery_mul(false, true, &am, &bm);
+        assert_eq!(*machine.get_output(), 0);
+    }
+
+    #[test]
+    fn test_process_values() {
+        let mut machine = ModuloMachine::new();
+        let p = machine.get_prime().clone();
+
+        // Leave the clock high first: the next value must still get an edge
+        machine.tick(true, false, &Integer::from(1u32));
+        let values = [Integer::from(5u32), Integer::from(5u32), Integer::from(&p + 3u32)];
+        assert_eq!(machine.process_values(&values), [5, 5, 3]);
+        assert!(machine.process_values(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_peek_next() {
+        let mut machine = ModuloMachine::new();
+        let p = machine.get_prime().clone();
+        let x = Integer::from(&p + 11u32);
+
+        // The preview doesn't change the register or consume the edge
+        machine.tick(false, false, &x);
+        assert_eq!(machine.peek_next(&x), 11);
+        assert_eq!(*machine.get_output(), 0);
+        let next = machine.peek_next(&x);
+        assert_eq!(*machine.tick(true, false, &x), next);
+
+        // Follows the lazy-reduction setting
+        machine.set_lazy_reduction(Some(Integer::from(&p * 2u32)));
+        assert_eq!(machine.peek_next(&x), x);
+    }
+
+    #[test]
+    fn test_sim_time() {
+        let mut machine = ModuloMachine::new();
+        machine.set_time_step(5);
+        machine.set_sim_time(100);
+        let x = Integer::from(3u32);
+
+        machine.tick(false, false, &x);
+        machine.tick(true, false, &x);
+        machine.tick(false, true, &x);
+        // Reset clears the register but not the simulation clock
+        assert_eq!(machine.sim_time(), 115);
+        assert_eq!(machine.time_step(), 5);
+    }
+
+    #[test]
+    fn test_input_width_policy() {
+        let mut machine = ModuloMachine::new();
+        let p = machine.get_prime().clone();
+        let bus_max = ModuloMachine::create_large_input(300, 0) - 1u32;
+        // 2^301 + 5: the low 300 bits are just 5
+        let wide = ModuloMachine::create_large_input(301, 5);
+
+        let latch = |machine: &mut ModuloMachine, policy| {
+            machine.set_input_width_policy(policy);
+            machine.tick(false, false, &wide);
+            machine.tick(true, false, &wide).clone()
+        };
+        assert_eq!(latch(&mut machine, InputWidthPolicy::Reduce), Integer::from(&wide % &p));
+        assert_eq!(latch(&mut machine, InputWidthPolicy::Wrap), 5);
+        assert_eq!(latch(&mut machine, InputWidthPolicy::Saturate), Integer::from(&bus_max % &p));
+
+        // Error holds the output; tick_checked reports why
+        assert_eq!(latch(&mut machine, InputWidthPolicy::Error), Integer::from(&bus_max % &p));
+        machine.tick(false, false, &wide);
+        assert_eq!(
+            machine.tick_checked(true, false, &wide),
+            Err(MachineError::InputTooWide { bits: 302 })
+        );
+        assert_eq!(machine.peek_next(&wide), Integer::from(&bus_max % &p));
+        machine.tick(false, false, &bus_max);
+        assert!(machine.tick_checked(true, false, &bus_max).is_ok());
+    }
+
+    #[test]
+    fn test_strict_mode() {
+        let mut machine = ModuloMachine::new();
+        machine.set_strict(true);
+        machine.set_input_width_policy(InputWidthPolicy::Wrap);
+        let wide = ModuloMachine::create_large_input(300, 5);
+        let edge = |machine: &mut ModuloMachine, x: &Integer| {
+            machine.tick(false, false, x);
+            machine.tick_checked(true, false, x).cloned()
+        };
+
+        assert_eq!(edge(&mut machine, &Integer::from(7u32)), Ok(Integer::from(7u32)));
+        // Strict overrides the width policy; the output holds
+        assert_eq!(edge(&mut machine, &wide), Err(MachineError::InputTooWide { bits: 301 }));
+        assert_eq!(edge(&mut machine, &Integer::from(-1)), Err(MachineError::NegativeInput));
+        assert_eq!(*machine.get_output(), 7);
+
+        machine.set_strict(false);
+        assert_eq!(edge(&mut machine, &wide), Ok(Integer::from(5u32)));
+
+        assert_eq!(ModuloMachine::validate_input(&wide), Err(MachineError::InputTooWide { bits: 301 }));
+        assert_eq!(ModuloMachine::create_input("ff", 16), Ok(Integer::from(255u32)));
+        assert_eq!(ModuloMachine::create_input("ff", 10), Err(MachineError::InvalidDigits));
+        assert_eq!(ModuloMachine::create_input("1", 37), Err(MachineError::InvalidRadix { radix: 37 }));
+    }
+
+    #[test]
+    fn test_create_input_formats() {
+        let parse = |s: &str, radix: i32| ModuloMachine::create_input(s, radix);
+        assert_eq!(parse("0x1F", 10), Ok(Integer::from(31)));
+        assert_eq!(parse(" 0b1010 ", 10), Ok(Integer::from(10)));
+        assert_eq!(parse("0o17", 10), Ok(Integer::from(15)));
+        assert_eq!(parse("-0xff", 10), Ok(Integer::from(-255)));
+        assert_eq!(parse("+1_000_000", 10), Ok(Integer::from(1_000_000)));
+        assert_eq!(parse("0xdead_beef", 10), Ok(Integer::from(0xdead_beef_u32)));
+        // `b` is a hex digit, so no binary prefix in radix 16
+        assert_eq!(parse("0b1", 16), Ok(Integer::from(0xb1)));
+        assert_eq!(parse("0x1f", 16), Ok(Integer::from(31)));
+        for bad in ["", "0x", "_1", "1_", "1__0", "0x_1", "0b102", "1 2", "--1"] {
+            assert_eq!(parse(bad, 10), Err(MachineError::InvalidDigits), "{:?}", bad);
+        }
+
+        let p = ModuloMachine::new().get_prime().clone();
+        assert_eq!(ModuloMachine::create_input_bytes(&constants::P.bytes_be), p);
+        assert_eq!(ModuloMachine::create_input_bytes(&[]), 0);
+        assert_eq!(ModuloMachine::create_input_base64("AQAB"), Ok(Integer::from(65537)));
+        assert_eq!(ModuloMachine::create_input_base64("3q2+7w=="), Ok(Integer::from(0xdead_beef_u32)));
+        assert_eq!(ModuloMachine::create_input_base64("3q2-7w"), Ok(Integer::from(0xdead_beef_u32)));
+        assert_eq!(ModuloMachine::create_input_base64("3q2+\n7w=="), Ok(Integer::from(0xdead_beef_u32)));
+        for bad in ["A", "AQ=B", "AQ===", "A*BC"] {
+            assert_eq!(ModuloMachine::create_input_base64(bad), Err(MachineError::InvalidDigits), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut machine = ModuloMachine::new();
+        let p = machine.get_prime().clone();
+        machine.process_values(&[Integer::from(5), Integer::from(&p + 1u32), p.clone()]);
+        // Holding the clock high is a tick but not an edge
+        machine.tick(true, false, &Integer::from(1));
+        machine.tick(true, true, &(Integer::from(1) << 299));
+        machine.tick_op(false, false, Op::AddMod, &Integer::from(1), &Integer::from(2));
+        machine.tick_op(true, false, Op::AddMod, &Integer::from(1), &Integer::from(2));
+
+        let stats = machine.stats();
+        assert_eq!(stats, MachineStats { ticks: 10, rising_edges: 4, resets: 1, reductions: 2, max_input_bits: 300, strategy: ReductionStrategy::Gmp });
+
+        // Counters survive a datapath reset and reset on their own
+        machine.reset();
+        assert_eq!(machine.stats(), stats);
+        machine.reset_stats();
+        assert_eq!(machine.stats(), MachineStats::default());
+        assert_eq!(*machine.get_output(), 0);
+    }
+
+    #[test]
+    fn test_stats_skip_reductions_when_constant_time() {
+        let mut machine = ModuloMachine::new();
+        assert!(machine.set_reduction_strategy(ReductionStrategy::ConstantTime));
+        let p = machine.get_prime().clone();
+        let outputs = machine.process_values(&[Integer::from(5), Integer::from(&p + 1u32)]);
+        assert_eq!(outputs, [5, 1]);
+
+        let stats = machine.stats();
+        assert_eq!((stats.rising_edges, stats.reductions), (2, 0));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing_events() {
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Clone, Default)]
+        struct Capture(Arc<Mutex<Vec<u8>>>);
+        impl std::io::Write for Capture {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let capture = Capture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            let mut machine = ModuloMachine::new();
+            let p = machine.get_prime().clone();
+            machine.process_values(&[Integer::from(5), Integer::from(&p + 1u32), Integer::from(1) << 301]);
+            machine.tick(false, true, &Integer::new());
+        });
+
+        let log = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(log.matches("clock edge").count(), 3, "{}", log);
+        // P + 1 and the over-wide input both reduce
+        assert_eq!(log.matches("reduction performed").count(), 2, "{}", log);
+        assert!(log.contains("over-wide input time=5 x_bits=302 policy=Reduce"), "{}", log);
+        assert!(log.contains("reset applied"));
+        // Plain ticks are trace level, below this subscriber's filter
+        assert!(!log.contains(" tick "));
+    }
+
+    #[test]
+    fn test_byte_io() {
+        let mut machine = ModuloMachine::new();
+        let p = machine.get_prime().clone();
+        assert_eq!(ModuloMachine::input_from_bytes_be(&[0x01, 0x02]), 0x0102);
+        assert_eq!(ModuloMachine::input_from_bytes_le(&[0x01, 0x02]), 0x0201);
+        assert_eq!(ModuloMachine::input_from_bytes_be(&[0, 0, 7]), 7);
+
+        // A 38-byte (300-bit) operand straight off a bus
+        let x = Integer::from(&p + 0xabcd_u32);
+        let mut le = x.to_digits::<u8>(Order::Lsf);
+        le.resize(38, 0);
+        machine.process_values(&[ModuloMachine::input_from_bytes_le(&le)]);
+        let out = machine.output_to_bytes_be();
+        assert_eq!(out[30..], [0xab, 0xcd]);
+        assert!(out[..30].iter().all(|&b| b == 0));
+        assert_eq!(ModuloMachine::input_from_bytes_be(&out), 0xabcd);
+
+        machine.process_values(&[Integer::from(&p - 1u32)]);
+        assert_eq!(ModuloMachine::input_from_bytes_be(&machine.output_to_bytes_be()), Integer::from(&p - 1u32));
+
+        // Negative outputs wrap to two's complement
+        machine.set_signed_mode(SignedMode::Truncated);
+        machine.process_values(&[Integer::from(-1)]);
+        assert_eq!(machine.output_to_bytes_be(), [0xff; 32]);
+    }
+
+    #[test]
+    fn test_tick_limbs() {
+        let mut state = 11;
+        for strategy in [ReductionStrategy::Gmp, ReductionStrategy::ConstantTime, ReductionStrategy::ShiftSubtract] {
+            let mut limbs = ModuloMachine::new();
+            let mut reference = ModuloMachine::new();
+            assert!(limbs.set_reduction_strategy(strategy) && reference.set_reduction_strategy(strategy));
+            for i in 0..500u32 {
+                let x: [u64; X_LIMBS] = std::array::from_fn(|_| crate::vectors::splitmix64(&mut state));
+                let (clk, reset) = (!i.is_multiple_of(2), i.is_multiple_of(61));
+                let out = limbs.tick_limbs(clk, reset, &x);
+                let expected = reference.tick(clk, reset, &Integer::from_digits(&x, Order::Lsf));
+                assert_eq!(Integer::from_digits(&out, Order::Lsf), *expected, "{:?} tick {}", strategy, i);
+            }
+        }
+
+        let mut machine = ModuloMachine::new();
+        machine.set_signed_mode(SignedMode::Truncated);
+        machine.process_values(&[Integer::from(-2)]);
+        assert_eq!(machine.output_to_limbs(), [u64::MAX - 1, u64::MAX, u64::MAX, u64::MAX]);
+    }
+
+    #[test]
+    fn test_performance_helpers() {
+        // Test optimized input creation methods
+        let from_u64 = ModuloMachine::create_input_u64(12345);
+        assert_eq!(from_u64, 12345);
+        
+        let from_string = ModuloMachine::create_input("12345", 10).unwrap();
+        assert_eq!(from_string, 12345);
+        
+        let large_input = ModuloMachine::create_large_input(10, 123);
+        assert_eq!(large_input, 1024 + 123); // 2^10 + 123
+    }
+} 
\ No newline at end of file
@@ -1,117 +1,363 @@
-use modulo_machine::ModuloMachine;
+//! Command-line front end for the modulo machine.
+//!
+//! Usage:
+//! - `modmachine [OPTIONS] reduce X...`: print each `X mod P`, `X` in hex
+//!   with an optional `0x` prefix
+//! - `modmachine [OPTIONS] batch --input FILE [--output FILE]`: ticks as
+//!   JSON lines from a file, results as JSON lines
+//! - `modmachine [OPTIONS] stimulus --file FILE [--vcd FILE] [--trace-json
+//!   FILE]`: replay a `clk reset x` stimulus file, or a CSV (`.csv`) checked
+//!   against its `expected` column, printing the output after each tick and
+//!   optionally saving the whole run as a JSON trace
+//! - `modmachine [OPTIONS] query --trace FILE (--above N | --expected FILE)`:
+//!   the first cycle of a JSON trace whose output exceeds `N`, or differs
+//!   from an expected-output file (one value per line, in `--radix`)
+//! - `modmachine [OPTIONS] jsonl`: ticks as JSON lines on stdin, results on
+//!   stdout
+//! - `modmachine [OPTIONS] repl`: drive the pins interactively
+//! - `modmachine [OPTIONS] --pipe`: one `clk reset x_hex` line in, one
+//!   output line (hex) out, flushed per line, for driving the model as a
+//!   subprocess
+//! - `modmachine serve [--host ADDR] [--port N]`
+//! - `modmachine serve-http [--host ADDR] [--port N]` (`service` feature):
+//!   HTTP/JSON sessions shared by every client
+//!
+//! Options: `--modulus N` (decimal or `0x` hex) or `--preset NAME` choose the
+//! modulus; `--radix R` sets the radix of printed outputs (default 16).
+//! Other operands (`--modulus`, `--above`) are decimal or `0x` hex.
+
+use modulo_machine::record::{Trace, TraceEntry};
+use modulo_machine::repl::Repl;
+use modulo_machine::trace_json::{self, TraceDocument};
+use modulo_machine::{csv, format_radix, jsonl, rpc, trace_file, ModuloMachine, Preset, TickInput, WaveformRecorder};
 use rug::Integer;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, IsTerminal, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::process;
+
+const DEFAULT_HOST: &str = "127.0.0.1";
+const DEFAULT_PORT: u16 = 7878;
+
+fn usage() -> ! {
+    eprintln!("usage: modmachine [OPTIONS] reduce X_HEX...");
+    eprintln!("       modmachine [OPTIONS] batch --input ticks.jsonl [--output results.jsonl]");
+    eprintln!("       modmachine [OPTIONS] stimulus --file vectors.{{txt,csv}} [--vcd out.vcd] [--trace-json run.json]");
+    eprintln!("       modmachine [OPTIONS] query --trace run.json (--above N | --expected outputs.hex)");
+    eprintln!("       modmachine [OPTIONS] jsonl < ticks.jsonl > results.jsonl");
+    eprintln!("       modmachine [OPTIONS] repl");
+    eprintln!("       modmachine [OPTIONS] --pipe");
+    eprintln!("       modmachine serve [--host ADDR] [--port N]");
+    #[cfg(feature = "service")]
+    eprintln!("       modmachine serve-http [--host ADDR] [--port N]");
+    eprintln!();
+    eprintln!("options: --modulus N | --preset NAME   modulus (default: the specification's P)");
+    eprintln!("         --radix R                     radix of printed outputs, 2..=36 (default 16)");
+    process::exit(2);
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("❌ {}", message);
+    process::exit(1);
+}
+
+/// Options shared by the subcommands that run a machine
+struct Options {
+    modulus: Option<Integer>,
+    radix: u32,
+}
+
+impl Options {
+    /// Take leading options off `args`, returning the rest
+    fn parse(args: &[String]) -> (Options, &[String]) {
+        let mut options = Options { modulus: None, radix: 16 };
+        let mut rest = args;
+        while let [flag, value, tail @ ..] = rest {
+            match flag.as_str() {
+                "--modulus" => options.modulus = Some(parse_value(value)),
+                "--preset" => {
+                    let preset = Preset::from_name(value).unwrap_or_else(|| {
+                        let names: Vec<&str> = Preset::ALL.iter().map(|p| p.name()).collect();
+                        fail(format!("unknown preset `{}` (expected one of {})", value, names.join(", ")))
+                    });
+                    options.modulus = Some(preset.modulus());
+                }
+                "--radix" => {
+                    options.radix = value.parse().ok().filter(|r| (2..=36).contains(r)).unwrap_or_else(|| usage());
+                }
+                _ => break,
+            }
+            rest = tail;
+        }
+        (options, rest)
+    }
+
+    fn machine(&self) -> ModuloMachine {
+        let mut machine = match &self.modulus {
+            Some(p) => ModuloMachine::with_modulus(p.clone()).unwrap_or_else(|e| fail(format!("bad modulus: {}", e))),
+            None => ModuloMachine::new(),
+        };
+        machine.set_display_radix(self.radix);
+        machine
+    }
+}
+
+/// Decimal or `0x` hex, as the JSON-RPC server accepts
+fn parse_value(s: &str) -> Integer {
+    rpc::parse_integer(&Value::String(s.to_string())).unwrap_or_else(|_| fail(format!("not an integer: {}", s)))
+}
+
+/// Hex, `0x` optional, as `reduce` takes its operands
+fn parse_hex(s: &str) -> Option<Integer> {
+    ModuloMachine::create_input(s, 16).ok()
+}
+
+/// Values of `--flag VALUE` pairs, in `names` order; anything else is a
+/// usage error
+fn flags<'a, const N: usize>(args: &'a [String], names: [&str; N]) -> [Option<&'a str>; N] {
+    let mut values = [None; N];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let i = names.iter().position(|name| name == arg).unwrap_or_else(|| usage());
+        values[i] = Some(args.next().unwrap_or_else(|| usage()).as_str());
+    }
+    values
+}
+
+fn reduce(options: &Options, args: &[String]) {
+    if args.is_empty() {
+        usage();
+    }
+    let mut machine = options.machine();
+    for x in args {
+        let x = parse_hex(x).unwrap_or_else(|| fail(format!("not a hex integer: {}", x)));
+        machine.tick(false, false, &x);
+        machine.tick(true, false, &x);
+        println!("{}", machine);
+    }
+}
+
+fn batch(options: &Options, args: &[String]) {
+    let [input, output] = flags(args, ["--input", "--output"]);
+    let input = input.unwrap_or_else(|| usage());
+    let reader = BufReader::new(File::open(input).unwrap_or_else(|e| fail(format!("{}: {}", input, e))));
+    let writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(File::create(path).unwrap_or_else(|e| fail(format!("{}: {}", path, e))))),
+        None => Box::new(io::stdout().lock()),
+    };
+    if let Err(e) = jsonl::run(&mut options.machine(), reader, writer) {
+        fail(format!("{}: {}", input, e));
+    }
+}
+
+fn stimulus(options: &Options, args: &[String]) {
+    let [file, vcd, trace_json] = flags(args, ["--file", "--vcd", "--trace-json"]);
+    let file = file.unwrap_or_else(|| usage());
+    let reader = BufReader::new(File::open(file).unwrap_or_else(|e| fail(format!("{}: {}", file, e))));
+    let is_csv = Path::new(file).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    let rows = if is_csv {
+        csv::read_csv(reader)
+    } else {
+        trace_file::read_stimulus(reader).map(|ticks| ticks.into_iter().enumerate().map(|(i, tick)| csv::CsvRow { cycle: i as u64, tick, expected: None }).collect())
+    }
+    .unwrap_or_else(|e| fail(format!("{}: {}", file, e)));
+    let ticks: Vec<TickInput> = rows.iter().map(|row| row.tick.clone()).collect();
+
+    let mut machine = options.machine();
+    let start = machine.snapshot();
+    let outputs = match vcd {
+        Some(path) => {
+            let mut recorder = WaveformRecorder::create(path).unwrap_or_else(|e| fail(format!("{}: {}", path, e)));
+            let outputs = recorder.run(&mut machine, &ticks).and_then(|outputs| recorder.finish().map(|_| outputs));
+            outputs.unwrap_or_else(|e| fail(format!("{}: {}", path, e)))
+        }
+        None => ticks.iter().map(|t| machine.tick(t.clk, t.reset, &t.x).clone()).collect(),
+    };
+    if let Some(path) = trace_json {
+        let step = machine.time_step();
+        let entries = ticks
+            .iter()
+            .zip(&outputs)
+            .enumerate()
+            .map(|(i, (t, output))| TraceEntry {
+                time: start.sim_time() + i as u64 * step,
+                clk: t.clk,
+                reset: t.reset,
+                x: t.x.clone(),
+                output: output.clone(),
+            })
+            .collect();
+        let doc = TraceDocument::from_trace(&Trace { start, entries });
+        let file = File::create(path).unwrap_or_else(|e| fail(format!("{}: {}", path, e)));
+        doc.write(BufWriter::new(file)).unwrap_or_else(|e| fail(format!("{}: {}", path, e)));
+    }
+
+    let mut failures = 0;
+    for (row, output) in rows.iter().zip(&outputs) {
+        match &row.expected {
+            Some(expected) if expected != output => {
+                failures += 1;
+                println!("{} {} MISMATCH expected {}", row.cycle, format_radix(output, options.radix), format_radix(expected, options.radix));
+            }
+            _ => println!("{} {}", row.cycle, format_radix(output, options.radix)),
+        }
+    }
+    let checked = rows.iter().filter(|row| row.expected.is_some()).count();
+    if checked > 0 {
+        eprintln!("{} of {} checked cycles matched", checked - failures, checked);
+    }
+    if failures > 0 {
+        process::exit(1);
+    }
+}
+
+fn query(options: &Options, args: &[String]) {
+    let [trace, above, expected] = flags(args, ["--trace", "--above", "--expected"]);
+    let trace = trace.unwrap_or_else(|| usage());
+    let open = |path: &str| BufReader::new(File::open(path).unwrap_or_else(|e| fail(format!("{}: {}", path, e))));
+    let doc = TraceDocument::read(open(trace)).unwrap_or_else(|e| fail(format!("{}: {}", trace, e)));
+    let show = |t: &trace_json::JsonTick| format!("cycle {} (time {}): output {}", t.cycle, t.time, format_radix(&t.output, options.radix));
+
+    match (above, expected) {
+        (Some(threshold), None) => {
+            let threshold = parse_value(threshold);
+            match doc.first_above(&threshold) {
+                Some(t) => println!("{} exceeds {}", show(t), format_radix(&threshold, options.radix)),
+                None => println!("no output exceeds {} in {} cycles", format_radix(&threshold, options.radix), doc.ticks.len()),
+            }
+        }
+        (None, Some(path)) => {
+            let values = trace_json::read_expected(open(path), options.radix as i32).unwrap_or_else(|e| fail(format!("{}: {}", path, e)));
+            match doc.first_mismatch(&values) {
+                Some(t) => {
+                    println!("{}, expected {}", show(t), format_radix(&values[t.cycle as usize], options.radix));
+                    process::exit(1);
+                }
+                None => println!("{} of {} cycles match", values.len().min(doc.ticks.len()), doc.ticks.len()),
+            }
+        }
+        _ => usage(),
+    }
+}
+
+fn serve(args: &[String]) {
+    let listener = listen(args);
+    println!("Serving JSON-RPC on {}", listener.local_addr().unwrap());
+
+    if let Err(e) = rpc::serve(listener) {
+        eprintln!("❌ Server stopped: {}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(feature = "service")]
+fn serve_http(args: &[String]) {
+    let listener = listen(args);
+    println!("Serving HTTP sessions on {}", listener.local_addr().unwrap());
+
+    if let Err(e) = modulo_machine::service::serve(listener) {
+        eprintln!("❌ Server stopped: {}", e);
+        process::exit(1);
+    }
+}
+
+/// Bind `--host`/`--port`
+fn listen(args: &[String]) -> TcpListener {
+    let mut host = DEFAULT_HOST.to_string();
+    let mut port = DEFAULT_PORT;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--host" => host = args.next().cloned().unwrap_or_else(|| usage()),
+            "--port" => {
+                port = args
+                    .next()
+                    .and_then(|p| p.parse().ok())
+                    .unwrap_or_else(|| usage())
+            }
+            _ => usage(),
+        }
+    }
+
+    TcpListener::bind((host.as_str(), port)).unwrap_or_else(|e| {
+        eprintln!("❌ Cannot listen on {}:{}: {}", host, port, e);
+        process::exit(1);
+    })
+}
+
+fn run_jsonl(options: &Options, args: &[String]) {
+    if !args.is_empty() {
+        usage();
+    }
+    let mut machine = options.machine();
+    if let Err(e) = jsonl::run(&mut machine, io::stdin().lock(), io::stdout().lock()) {
+        eprintln!("❌ {}", e);
+        process::exit(1);
+    }
+}
+
+fn repl(options: &Options, args: &[String]) {
+    if !args.is_empty() {
+        usage();
+    }
+    let interactive = io::stdin().is_terminal();
+    if interactive {
+        println!("Modulo machine REPL; type `help` for commands");
+    }
+    if let Err(e) = Repl::new(options.machine()).run(io::stdin().lock(), io::stdout().lock(), interactive) {
+        fail(e);
+    }
+}
+
+fn pipe(options: &Options, args: &[String]) {
+    if !args.is_empty() {
+        usage();
+    }
+    if let Err(e) = trace_file::pipe(&mut options.machine(), io::stdin().lock(), io::stdout().lock()) {
+        fail(e);
+    }
+}
 
 fn main() {
-    println!("Modulo Machine Demo");
-    println!("===================");
-    
-    // Create a new modulo machine
-    let mut machine = ModuloMachine::new();
-    
-    println!("Prime P: {}", machine.get_prime());
-    println!("P has {} bits", machine.get_prime().significant_bits());
-    println!();
-    
-    // Test 1: Simple small number
-    println!("Test 1: X = 12345");
-    let x1 = ModuloMachine::create_input_u64(12345);
-    let result1 = machine.tick(true, false, &x1);
-    println!("Input:  {} ({} bits)", x1, x1.significant_bits());
-    println!("Output: {} ({} bits)", result1, result1.significant_bits());
-    println!();
-    
-    // Test 2: Number equal to P
-    println!("Test 2: X = P");
-    let x2 = machine.get_prime().clone();
-    let zero = Integer::from(0);
-    machine.tick(false, false, &zero); // Clock low
-    let result2 = machine.tick(true, false, &x2); // Clock high (rising edge)
-    println!("Input:  {} ({} bits)", x2, x2.significant_bits());
-    println!("Output: {} ({} bits)", result2, result2.significant_bits());
-    println!();
-    
-    // Test 3: Number larger than P
-    println!("Test 3: X = P + 100000");
-    let x3 = Integer::from(machine.get_prime() + 100000u32);
-    machine.tick(false, false, &zero); // Clock low
-    let result3 = machine.tick(true, false, &x3); // Clock high (rising edge)
-    println!("Input:  {} ({} bits)", x3, x3.significant_bits());
-    println!("Output: {} ({} bits)", result3, result3.significant_bits());
-    println!();
-    
-    // Test 4: Large 300-bit number
-    println!("Test 4: Large 300-bit number");
-    let x4 = ModuloMachine::create_large_input(299, 123456789);
-    machine.tick(false, false, &zero); // Clock low
-    let result4 = machine.tick(true, false, &x4); // Clock high (rising edge)
-    println!("Input:  {} ({} bits)", x4, x4.significant_bits());
-    println!("Output: {} ({} bits)", result4, result4.significant_bits());
-    println!();
-    
-    // Test 5: Reset functionality
-    println!("Test 5: Reset functionality");
-    println!("Before reset - Output: {}", machine.get_output());
-    machine.tick(false, true, &zero); // Reset
-    println!("After reset  - Output: {}", machine.get_output());
-    println!();
-    
-    // Test 6: Clock edge behavior
-    println!("Test 6: Clock edge behavior");
-    let x6 = ModuloMachine::create_input_u64(555555);
-    
-    // Clock low -> high (should process)
-    println!("Clock low -> high:");
-    machine.tick(false, false, &x6); // Clock low
-    println!("  Output after low:  {}", machine.get_output());
-    let result6a = machine.tick(true, false, &x6); // Clock high (rising edge)
-    println!("  Output after high: {}", result6a);
-    
-    // Clock high -> high (should not process again)
-    println!("Clock high -> high:");
-    let different_x = ModuloMachine::create_input_u64(999999);
-    let result6b = machine.tick(true, false, &different_x); // Clock still high
-    println!("  Output (unchanged): {}", result6b);
-    println!();
-    
-    // Test 7: Batch Processing
-    println!("Test 7: Batch Processing");
-    let batch1 = ModuloMachine::create_input_u64(1111);
-    let batch2 = ModuloMachine::create_input_u64(2222);
-    let batch3 = ModuloMachine::create_input_u64(3333);
-    let batch_reset = Integer::from(0);
-    let batch4 = ModuloMachine::create_input_u64(4444);
-    
-    let batch_inputs = vec![
-        (true, false, &batch1),
-        (true, false, &batch2), 
-        (true, false, &batch3),
-        (false, true, &batch_reset), // Reset
-        (true, false, &batch4),
-    ];
-    
-    let batch_results = machine.process_batch(&batch_inputs);
-    println!("Batch processed {} inputs:", batch_results.len());
-    for (i, result) in batch_results.iter().enumerate() {
-        println!("  Result {}: {}", i + 1, result);
-    }
-    println!();
-    
-    // Validation tests
-    println!("📋 Validation Tests");
-    println!("==================");
-    
-    // Test input size validation
-    let max_300_bit = ModuloMachine::create_large_input(300, 0) - 1;
-    let too_large = ModuloMachine::create_large_input(300, 0);
-    
-    println!("300-bit max valid:   {}", ModuloMachine::validate_input_size(&max_300_bit));
-    println!("301-bit (too large): {}", ModuloMachine::validate_input_size(&too_large));
-    
-    // Test output size validation
-    let p = machine.get_prime().clone();
-    println!("Output size for P:     {}", ModuloMachine::validate_output_size(&p));
-    let p_minus_one = Integer::from(&p - 1);
-    println!("Output size for P-1:   {}", ModuloMachine::validate_output_size(&p_minus_one));
-    
-    println!("\n✅ Demo completed!");
-} 
\ No newline at end of file
+    #[cfg(feature = "tracing")]
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+        )
+        .init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (options, args) = Options::parse(&args);
+    match args.first().map(String::as_str) {
+        Some("reduce") => reduce(&options, &args[1..]),
+        Some("batch") => batch(&options, &args[1..]),
+        Some("stimulus") => stimulus(&options, &args[1..]),
+        Some("query") => query(&options, &args[1..]),
+        Some("jsonl") => run_jsonl(&options, &args[1..]),
+        Some("repl") => repl(&options, &args[1..]),
+        Some("--pipe") => pipe(&options, &args[1..]),
+        // Each connection gets its own default machine
+        Some("serve") if options.modulus.is_none() => serve(&args[1..]),
+        #[cfg(feature = "service")]
+        Some("serve-http") if options.modulus.is_none() => serve_http(&args[1..]),
+        _ => usage(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reduce_operands_are_hex() {
+        assert_eq!(parse_hex("ff"), Some(Integer::from(255)));
+        assert_eq!(parse_hex("0xff"), Some(Integer::from(255)));
+        assert_eq!(parse_hex("dead_beef"), Some(Integer::from(0xdead_beefu32)));
+        assert_eq!(parse_hex("10"), Some(Integer::from(16)));
+        assert_eq!(parse_hex("xyz"), None);
+    }
+}
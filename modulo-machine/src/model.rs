@@ -0,0 +1,41 @@
+use crate::ModuloMachine;
+use rug::Integer;
+
+/// Anything that behaves like the modulo machine at its pins
+///
+/// Implemented by [`ModuloMachine`] itself; implement it for alternative
+/// backends or for adapters around an RTL simulation so they can be compared
+/// against the golden model.
+pub trait ModuloModel {
+    /// Apply one clock cycle and return the output after it
+    fn tick(&mut self, clk: bool, reset: bool, x: &Integer) -> Integer;
+
+    /// Current output without applying a clock cycle
+    fn output(&self) -> Integer;
+
+    /// Return to the power-on state, clearing any clock history
+    fn reset(&mut self);
+
+    /// Simulation time units spanned by one tick, used to timestamp traces
+    fn time_step(&self) -> u64 {
+        1
+    }
+}
+
+impl ModuloModel for ModuloMachine {
+    fn tick(&mut self, clk: bool, reset: bool, x: &Integer) -> Integer {
+        ModuloMachine::tick(self, clk, reset, x).clone()
+    }
+
+    fn output(&self) -> Integer {
+        self.get_output().clone()
+    }
+
+    fn reset(&mut self) {
+        ModuloMachine::reset(self)
+    }
+
+    fn time_step(&self) -> u64 {
+        ModuloMachine::time_step(self)
+    }
+}
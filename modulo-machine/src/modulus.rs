@@ -0,0 +1,146 @@
+//! Sanity checks for modulus parameters.
+
+use crate::arith::PRIMALITY_REPS;
+use rug::integer::IsPrime;
+use rug::Integer;
+use std::fmt;
+
+/// Width of the machine's output register, and so of its modulus
+pub const MODULUS_BITS: u32 = 256;
+
+/// Reason a modulus was rejected by [`validate_modulus`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModulusError {
+    /// Below 3, so there is nothing to reduce into
+    TooSmall,
+    /// Even moduli are never prime (and break Barrett/Montgomery backends)
+    Even,
+    /// Wider than the output register
+    TooWide { bits: u32, max: u32 },
+    /// Top bit of the register unused, so outputs never exercise it
+    TopBitClear { bits: u32, expected: u32 },
+    /// Failed a Miller–Rabin round
+    Composite,
+}
+
+impl fmt::Display for ModulusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModulusError::TooSmall => write!(f, "modulus must be at least 3"),
+            ModulusError::Even => write!(f, "modulus must be odd"),
+            ModulusError::TooWide { bits, max } => {
+                write!(f, "modulus is {} bits, wider than the {}-bit register", bits, max)
+            }
+            ModulusError::TopBitClear { bits, expected } => {
+                write!(f, "modulus is {} bits, expected the top bit of {} to be set", bits, expected)
+            }
+            ModulusError::Composite => write!(f, "modulus is not prime"),
+        }
+    }
+}
+
+impl std::error::Error for ModulusError {}
+
+/// Check that `p` fits the machine's datapath: odd, at least 3 and no wider
+/// than the output register
+///
+/// Weaker than [`validate_modulus`]: composite and narrower moduli pass, as
+/// [`ModuloMachine::with_modulus`] accepts them.
+///
+/// [`ModuloMachine::with_modulus`]: crate::ModuloMachine::with_modulus
+pub fn validate_machine_modulus(p: &Integer) -> Result<(), ModulusError> {
+    if *p < 3 {
+        return Err(ModulusError::TooSmall);
+    }
+    if p.is_even() {
+        return Err(ModulusError::Even);
+    }
+    let bits = p.significant_bits();
+    if bits > MODULUS_BITS {
+        return Err(ModulusError::TooWide { bits, max: MODULUS_BITS });
+    }
+    Ok(())
+}
+
+/// Check that `p` is a usable modulus for the 256-bit machine
+///
+/// Equivalent to `validate_modulus_bits(p, MODULUS_BITS)`.
+pub fn validate_modulus(p: &Integer) -> Result<(), ModulusError> {
+    validate_modulus_bits(p, MODULUS_BITS)
+}
+
+/// Check that `p` is an odd prime of exactly `bits` bits
+///
+/// The cheap structural checks run first, so the reported error is the most
+/// basic thing wrong with `p`.
+pub fn validate_modulus_bits(p: &Integer, bits: u32) -> Result<(), ModulusError> {
+    if *p < 3 {
+        return Err(ModulusError::TooSmall);
+    }
+    if p.is_even() {
+        return Err(ModulusError::Even);
+    }
+
+    let actual = p.significant_bits();
+    if actual > bits {
+        return Err(ModulusError::TooWide { bits: actual, max: bits });
+    }
+    if actual < bits {
+        return Err(ModulusError::TopBitClear { bits: actual, expected: bits });
+    }
+
+    if p.is_probably_prime(PRIMALITY_REPS) == IsPrime::No {
+        return Err(ModulusError::Composite);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::P_STR;
+
+    #[test]
+    fn test_validate_modulus() {
+        // secp256k1 field prime: odd, prime, top bit set
+        let secp256k1 = Integer::from_str_radix(
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F",
+            16,
+        )
+        .unwrap();
+        assert_eq!(validate_modulus(&secp256k1), Ok(()));
+
+        assert_eq!(validate_modulus(&Integer::from(1u32)), Err(ModulusError::TooSmall));
+        assert_eq!(validate_modulus(&(secp256k1.clone() + 1u32)), Err(ModulusError::Even));
+        assert_eq!(
+            validate_modulus(&(Integer::from(1u32) << 256u32)),
+            Err(ModulusError::Even)
+        );
+        assert_eq!(
+            validate_modulus(&((Integer::from(1u32) << 256u32) + 1u32)),
+            Err(ModulusError::TooWide { bits: 257, max: 256 })
+        );
+        assert_eq!(
+            validate_modulus_bits(&Integer::from(1_000_003u32), 256),
+            Err(ModulusError::TopBitClear { bits: 20, expected: 256 })
+        );
+        assert_eq!(validate_modulus_bits(&Integer::from(1_000_003u32), 20), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_machine_modulus() {
+        assert_eq!(validate_machine_modulus(&Integer::from(15u32)), Ok(()));
+        assert_eq!(validate_machine_modulus(&Integer::from(0u32)), Err(ModulusError::TooSmall));
+        assert_eq!(validate_machine_modulus(&Integer::from(16u32)), Err(ModulusError::Even));
+        assert_eq!(
+            validate_machine_modulus(&((Integer::from(1u32) << 256u32) + 1u32)),
+            Err(ModulusError::TooWide { bits: 257, max: 256 })
+        );
+    }
+
+    #[test]
+    fn test_specification_modulus_is_composite() {
+        let p = Integer::from_str_radix(P_STR, 10).unwrap();
+        assert_eq!(validate_modulus(&p), Err(ModulusError::Composite));
+    }
+}
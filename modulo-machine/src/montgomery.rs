@@ -0,0 +1,121 @@
+//! Montgomery-form arithmetic with `R = 2^256`, the output register width.
+//!
+//! A value `x` is held as `x·R mod P`. Multiplying two such values and
+//! applying REDC (HAC 14.32) yields the Montgomery form of the product using
+//! only multiplications, masks and shifts, which is what hardware datapaths
+//! that stay in the Montgomery domain implement.
+
+use rug::Integer;
+use std::borrow::Cow;
+
+/// Bits of `R`
+pub const R_BITS: u32 = 256;
+
+/// Precomputed constants for Montgomery arithmetic modulo one odd `p`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MontgomeryContext {
+    p: Integer,
+    /// `-p^-1 mod R`
+    p_neg_inv: Integer,
+    /// `R^2 mod p`, to enter the domain with one REDC
+    r2: Integer,
+}
+
+impl MontgomeryContext {
+    /// Precompute the constants for `p`
+    ///
+    /// Returns `None` unless `p` is odd, above 1 and fits in `R_BITS` bits.
+    pub fn new(p: &Integer) -> Option<Self> {
+        if *p <= 1 || p.is_even() || p.significant_bits() > R_BITS {
+            return None;
+        }
+
+        // Newton iteration doubles the correct low bits of p^-1 each round,
+        // starting from p itself (p·p = 1 mod 8)
+        let mut inv = p.clone();
+        let mut bits = 3;
+        while bits < R_BITS {
+            let correction = Integer::from(2u32) - Integer::from(p * &inv);
+            inv = Integer::from(&inv * &correction).keep_bits(R_BITS);
+            bits *= 2;
+        }
+        let p_neg_inv = (Integer::from(1) << R_BITS) - inv;
+        let r2 = (Integer::from(1) << (2 * R_BITS)) % p;
+
+        Some(Self { p: p.clone(), p_neg_inv, r2 })
+    }
+
+    pub fn modulus(&self) -> &Integer {
+        &self.p
+    }
+
+    /// REDC: `t·R^-1 mod p` for `0 <= t < p·R`
+    pub fn redc(&self, t: &Integer) -> Integer {
+        let m = (Integer::from(t.keep_bits_ref(R_BITS)) * &self.p_neg_inv).keep_bits(R_BITS);
+        let mut u = (m * &self.p + t) >> R_BITS;
+        if u >= self.p {
+            u -= &self.p;
+        }
+        u
+    }
+
+    /// `x·R mod p` for any `x`
+    pub fn to_montgomery(&self, x: &Integer) -> Integer {
+        self.redc(&Integer::from(&*self.canonical(x) * &self.r2))
+    }
+
+    /// `a·R^-1 mod p`: the ordinary value of a Montgomery-form `a`
+    pub fn from_montgomery(&self, a: &Integer) -> Integer {
+        self.redc(&self.canonical(a))
+    }
+
+    /// Montgomery product `a·b·R^-1 mod p`
+    ///
+    /// Operands outside `[0, p)` are reduced first, so the product always
+    /// satisfies REDC's `t < p·R` bound.
+    pub fn mul(&self, a: &Integer, b: &Integer) -> Integer {
+        self.redc(&Integer::from(&*self.canonical(a) * &*self.canonical(b)))
+    }
+
+    fn canonical<'a>(&self, x: &'a Integer) -> Cow<'a, Integer> {
+        if *x >= 0 && *x < self.p {
+            Cow::Borrowed(x)
+        } else {
+            let mut r = Integer::from(x % &self.p);
+            if r < 0 {
+                r += &self.p;
+            }
+            Cow::Owned(r)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Preset, P_STR};
+
+    #[test]
+    fn test_round_trip_and_mul() {
+        let mut moduli = vec![Integer::from_str_radix(P_STR, 10).unwrap(), Integer::from(15u32)];
+        moduli.extend(Preset::ALL.iter().map(|preset| preset.modulus()));
+        for p in &moduli {
+            let ctx = MontgomeryContext::new(p).unwrap();
+            let r = Integer::from(1) << R_BITS;
+            for (a, b) in [(3u32, 5u32), (0, 7), (123456789, 987654321)] {
+                let (a, b) = (Integer::from(a), Integer::from(b));
+                let am = ctx.to_montgomery(&a);
+                assert_eq!(am, Integer::from(&a * &r) % p);
+                assert_eq!(ctx.from_montgomery(&am), Integer::from(&a % p));
+
+                let product = ctx.mul(&am, &ctx.to_montgomery(&b));
+                assert_eq!(ctx.from_montgomery(&product), Integer::from(&a * &b) % p);
+            }
+            let minus_one = ctx.to_montgomery(&Integer::from(-1));
+            assert_eq!(ctx.from_montgomery(&minus_one), Integer::from(p - 1u32));
+        }
+
+        assert!(MontgomeryContext::new(&Integer::from(10u32)).is_none());
+        assert!(MontgomeryContext::new(&((Integer::from(1) << 256u32) + 1u32)).is_none());
+    }
+}
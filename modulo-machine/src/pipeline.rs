@@ -0,0 +1,137 @@
+//! Async pipeline adapter on tokio channels (`async` feature).
+//!
+//! Co-simulation frameworks on an async runtime feed stimulus from one task
+//! and check outputs in another. [`spawn`] moves a machine onto its own task
+//! with a bounded channel on each side:
+//!
+//! ```
+//! use modulo_machine::{pipeline, ModuloMachine, TickInput};
+//! use rug::Integer;
+//!
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! let mut pipe = pipeline::spawn(ModuloMachine::new(), 64);
+//! pipe.inputs.send(TickInput::new(true, false, Integer::from(31))).await.unwrap();
+//! assert_eq!(pipe.outputs.recv().await.unwrap(), 31);
+//! let (machine, rest) = pipe.shutdown().await;
+//! assert!(rest.is_empty());
+//! assert_eq!(machine.stats().ticks, 1);
+//! # });
+//! ```
+//!
+//! Every input produces exactly one output, in order. The capacity bounds
+//! both channels, so a producer that runs ahead of its consumer waits on
+//! `send` instead of buffering without limit. Shutdown is graceful: once
+//! every input sender is dropped, the task applies whatever is still
+//! buffered, closes the output channel and returns the machine for
+//! inspection. If the output receiver is dropped instead, the task stops
+//! after the tick whose output could not be delivered.
+
+use crate::{ModuloMachine, TickInput};
+use rug::Integer;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::task::JoinHandle;
+
+/// A machine running on its own task; see the [module docs](self)
+///
+/// The fields are public so the two ends can be moved to different tasks;
+/// [`AsyncPipeline::shutdown`] is the convenience for keeping them together.
+pub struct AsyncPipeline {
+    /// Stimulus, one tick per message
+    pub inputs: Sender<TickInput>,
+    /// The output after each tick
+    pub outputs: Receiver<Integer>,
+    /// Resolves to the machine once the pipeline has drained
+    pub task: JoinHandle<ModuloMachine>,
+}
+
+impl AsyncPipeline {
+    /// Stop accepting stimulus, collect the outputs still in flight and
+    /// return the machine
+    ///
+    /// Panics if the pipeline task panicked.
+    pub async fn shutdown(self) -> (ModuloMachine, Vec<Integer>) {
+        let Self { inputs, mut outputs, task } = self;
+        drop(inputs);
+        let mut rest = Vec::new();
+        while let Some(output) = outputs.recv().await {
+            rest.push(output);
+        }
+        (task.await.expect("pipeline task panicked"), rest)
+    }
+}
+
+/// Spawn `machine` onto the current tokio runtime behind channels holding up
+/// to `capacity` messages each
+///
+/// Panics outside a runtime or if `capacity` is zero, like
+/// [`tokio::spawn`] and [`mpsc::channel`].
+pub fn spawn(machine: ModuloMachine, capacity: usize) -> AsyncPipeline {
+    let (inputs, input_rx) = mpsc::channel(capacity);
+    let (output_tx, outputs) = mpsc::channel(capacity);
+    AsyncPipeline {
+        inputs,
+        outputs,
+        task: tokio::spawn(run(machine, input_rx, output_tx)),
+    }
+}
+
+/// Drive `machine` from `inputs` until the stimulus runs out or nobody is
+/// listening on `outputs`, then return it
+///
+/// For callers that build their own channels, or want the loop on a task of
+/// their choosing.
+pub async fn run(mut machine: ModuloMachine, mut inputs: Receiver<TickInput>, outputs: Sender<Integer>) -> ModuloMachine {
+    while let Some(TickInput { clk, reset, x }) = inputs.recv().await {
+        let output = machine.tick(clk, reset, &x).clone();
+        if outputs.send(output).await.is_err() {
+            sim_event!(debug, "pipeline output receiver dropped");
+            break;
+        }
+    }
+    machine
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StimulusGenerator;
+
+    #[tokio::test]
+    async fn test_pipeline_matches_batch() {
+        let ticks = StimulusGenerator::new(4).with_reset_probability(0.05).generate(500);
+        let expected: Vec<Integer> = ModuloMachine::new().process_stream(ticks.clone()).collect();
+
+        // A capacity far below the stimulus length: the producer waits on the consumer
+        let AsyncPipeline { inputs, mut outputs, task } = spawn(ModuloMachine::new(), 4);
+        let producer = tokio::spawn(async move {
+            for tick in ticks {
+                inputs.send(tick).await.unwrap();
+            }
+        });
+        let mut received = Vec::new();
+        while let Some(output) = outputs.recv().await {
+            received.push(output);
+        }
+        producer.await.unwrap();
+        assert_eq!(received, expected);
+        assert_eq!(task.await.unwrap().stats().ticks, 500);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_and_dropped_receiver_stops() {
+        let pipe = spawn(ModuloMachine::new(), 8);
+        for x in [5, 6, 7] {
+            pipe.inputs.send(TickInput::new(x % 2 == 1, false, Integer::from(x))).await.unwrap();
+        }
+        let (machine, rest) = pipe.shutdown().await;
+        assert_eq!(rest, [5, 5, 7]);
+        assert_eq!(machine.stats().ticks, 3);
+
+        let AsyncPipeline { inputs, outputs, task } = spawn(ModuloMachine::new(), 1);
+        drop(outputs);
+        inputs.send(TickInput::new(true, false, Integer::from(1))).await.unwrap();
+        let machine = task.await.unwrap();
+        assert_eq!(machine.stats().ticks, 1);
+        assert!(inputs.is_closed());
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{ModuloMachine, TickInput};
+use rug::Integer;
+
+/// Expands a stream of operand values into cycle-accurate stimulus
+///
+/// Each operation occupies one clock period of `period` ticks (clock low for
+/// the first half, high for the rest) followed by `idle_cycles` further
+/// periods in which the clock keeps running but no new operand is presented.
+/// Reset pulses are overlaid at absolute tick indices, exactly as an
+/// asynchronous testbench reset would hit the DUT: an operation whose rising
+/// edge falls inside a pulse is lost.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Playback {
+    period: u32,
+    idle_cycles: u32,
+    idle_value: Option<Integer>,
+    reset_at: Vec<u64>,
+    reset_width: u32,
+}
+
+impl Playback {
+    /// Playback with a clock period of `period` ticks (at least 2)
+    pub fn new(period: u32) -> Self {
+        Self {
+            period: period.max(2),
+            idle_cycles: 0,
+            idle_value: None,
+            reset_at: Vec::new(),
+            reset_width: 1,
+        }
+    }
+
+    /// Run the clock for `cycles` extra periods after every operation
+    pub fn with_idle_cycles(mut self, cycles: u32) -> Self {
+        self.idle_cycles = cycles;
+        self
+    }
+
+    /// Drive `x` on the bus while idle instead of holding the last operand
+    ///
+    /// Since the machine latches on every rising edge, idle edges then load
+    /// `x mod P`, as they would on a DUT without an input-valid qualifier.
+    pub fn with_idle_value(mut self, x: Integer) -> Self {
+        self.idle_value = Some(x);
+        self
+    }
+
+    /// Assert reset starting at absolute tick `tick`
+    pub fn with_reset_at(mut self, tick: u64) -> Self {
+        self.reset_at.push(tick);
+        self
+    }
+
+    /// Hold each reset pulse for `ticks` ticks (default 1)
+    pub fn with_reset_width(mut self, ticks: u32) -> Self {
+        self.reset_width = ticks.max(1);
+        self
+    }
+
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+
+    /// Ticks spent per operation, including idle periods
+    pub fn ticks_per_operation(&self) -> u64 {
+        self.period as u64 * (1 + self.idle_cycles as u64)
+    }
+
+    /// Tick index, relative to the start of its period, of the rising edge
+    pub fn edge_offset(&self) -> u32 {
+        self.period / 2
+    }
+
+    /// Expand `values` into full per-tick stimulus
+    ///
+    /// The stream is extended with idle ticks if a reset pulse lies past the
+    /// last operation.
+    pub fn expand(&self, values: &[Integer]) -> Vec<TickInput> {
+        let ops_ticks = values.len() as u64 * self.ticks_per_operation();
+        let reset_end = self
+            .reset_at
+            .iter()
+            .map(|&t| t + self.reset_width as u64)
+            .max()
+            .unwrap_or(0);
+        let total = ops_ticks.max(reset_end);
+
+        let zero = Integer::new();
+        let mut stimulus = Vec::with_capacity(total as usize);
+        for tick in 0..total {
+            let op = (tick / self.ticks_per_operation()) as usize;
+            let in_op = tick % self.ticks_per_operation();
+            let clk = (tick % self.period as u64) >= self.edge_offset() as u64;
+
+            let active = op < values.len() && in_op < self.period as u64;
+            let x = match (values.get(op).or(values.last()), &self.idle_value) {
+                (Some(x), _) if active => x,
+                (_, Some(idle)) => idle,
+                // Idle without an idle value: hold the last operand
+                (Some(held), None) => held,
+                (None, None) => &zero,
+            };
+
+            stimulus.push(TickInput::new(clk, self.in_reset(tick), x.clone()));
+        }
+        stimulus
+    }
+
+    /// Play `values` through `machine`, returning the output after every tick
+    pub fn run(&self, machine: &mut ModuloMachine, values: &[Integer]) -> Vec<Integer> {
+        self.expand(values)
+            .iter()
+            .map(|t| machine.tick(t.clk, t.reset, &t.x).clone())
+            .collect()
+    }
+
+    fn in_reset(&self, tick: u64) -> bool {
+        self.reset_at
+            .iter()
+            .any(|&start| tick >= start && tick < start + self.reset_width as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(vs: &[u32]) -> Vec<Integer> {
+        vs.iter().map(|&v| Integer::from(v)).collect()
+    }
+
+    #[test]
+    fn test_clock_waveform_and_idle() {
+        let playback = Playback::new(4).with_idle_cycles(1);
+        let stimulus = playback.expand(&values(&[10, 20]));
+        assert_eq!(stimulus.len(), 16);
+
+        let clk: Vec<bool> = stimulus.iter().map(|t| t.clk).collect();
+        assert_eq!(&clk[..8], [false, false, true, true, false, false, true, true]);
+        // Idle period holds the operand
+        assert!(stimulus[..8].iter().all(|t| t.x == 10));
+        assert!(stimulus[8..].iter().all(|t| t.x == 20));
+
+        let mut machine = ModuloMachine::new();
+        let outputs = playback.run(&mut machine, &values(&[10, 20]));
+        assert_eq!(outputs[1], 0);
+        assert_eq!(outputs[2], 10);
+        assert_eq!(outputs[10], 20);
+        assert_eq!(outputs[15], 20);
+    }
+
+    #[test]
+    fn test_idle_value_is_latched() {
+        let playback = Playback::new(2).with_idle_cycles(1).with_idle_value(Integer::from(7));
+        let mut machine = ModuloMachine::new();
+        let outputs = playback.run(&mut machine, &values(&[3]));
+        assert_eq!(outputs, [0, 3, 3, 7]);
+    }
+
+    #[test]
+    fn test_reset_pulses() {
+        // The pulse covers the second operation's rising edge, so it is lost
+        let playback = Playback::new(2).with_reset_at(3).with_reset_width(1);
+        let mut machine = ModuloMachine::new();
+        let outputs = playback.run(&mut machine, &values(&[5, 6, 8]));
+        assert_eq!(outputs, [0, 5, 5, 0, 0, 8]);
+
+        // Pulses past the last operation extend the stream
+        let stimulus = Playback::new(2).with_reset_at(9).expand(&values(&[1]));
+        assert_eq!(stimulus.len(), 10);
+        assert!(stimulus[9].reset);
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{ModuloMachine, TickInput};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use rug::Integer;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Aggregated statistics over one pool run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Number of stimulus sequences executed
+    pub sequences: usize,
+    /// Total clock cycles across all sequences
+    pub ticks: u64,
+    /// Active clock edges outside reset, as counted by
+    /// [`MachineStats::rising_edges`](crate::MachineStats::rising_edges)
+    pub rising_edges: u64,
+    /// Cycles with reset asserted
+    pub resets: u64,
+    /// Wall-clock time for the whole run
+    pub elapsed: Duration,
+}
+
+/// Per-sequence results of a pool run, in input order
+#[derive(Debug, Clone)]
+pub struct PoolReport<R> {
+    pub results: Vec<R>,
+    pub stats: PoolStats,
+}
+
+/// A pool of N machines for running independent stimulus sequences in parallel
+///
+/// Sequences are scheduled on a rayon work-stealing pool; each worker thread
+/// owns one machine and resets it and its counters before every sequence, so
+/// no state leaks between sequences regardless of how the work is split. The
+/// run's statistics are the sum of the machines' own counters.
+pub struct MachinePool {
+    pool: ThreadPool,
+    machines: Vec<Mutex<ModuloMachine>>,
+}
+
+impl MachinePool {
+    /// Create a pool with `n` worker threads and machines
+    pub fn new(n: usize) -> Self {
+        let n = n.max(1);
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(n)
+            .build()
+            .expect("Failed to build machine pool");
+        let machines = (0..n).map(|_| Mutex::new(ModuloMachine::new())).collect();
+
+        Self { pool, machines }
+    }
+
+    /// Number of machines (and worker threads) in the pool
+    pub fn size(&self) -> usize {
+        self.machines.len()
+    }
+
+    /// Run every sequence on a fresh machine and collect the output per tick
+    pub fn run(&self, sequences: &[Vec<TickInput>]) -> PoolReport<Vec<Integer>> {
+        self.run_with(sequences, |_, outputs| outputs.to_vec())
+    }
+
+    /// Run every sequence and reduce its outputs with `f(sequence_index, outputs)`
+    ///
+    /// Use this instead of [`MachinePool::run`] for very large regressions so
+    /// only the per-sequence verdicts are kept in memory.
+    pub fn run_with<R, F>(&self, sequences: &[Vec<TickInput>], f: F) -> PoolReport<R>
+    where
+        R: Send,
+        F: Fn(usize, &[Integer]) -> R + Sync,
+    {
+        let start = Instant::now();
+
+        let per_sequence: Vec<(R, PoolStats)> = self.pool.install(|| {
+            sequences
+                .par_iter()
+                .enumerate()
+                .map(|(index, sequence)| {
+                    // The pool's own index, not rayon's global one, which
+                    // would also be set on some other pool's thread
+                    let worker = self.pool.current_thread_index().expect("pool sequences run on the pool's worker threads");
+                    let mut machine = self.machines[worker].lock().unwrap();
+                    machine.reset();
+                    machine.reset_stats();
+
+                    let outputs: Vec<Integer> = sequence.iter().map(|input| machine.tick(input.clk, input.reset, &input.x).clone()).collect();
+                    let counters = machine.stats();
+                    let stats = PoolStats {
+                        sequences: 1,
+                        ticks: counters.ticks,
+                        rising_edges: counters.rising_edges,
+                        resets: counters.resets,
+                        ..PoolStats::default()
+                    };
+
+                    (f(index, &outputs), stats)
+                })
+                .collect()
+        });
+
+        let mut stats = PoolStats::default();
+        let mut results = Vec::with_capacity(per_sequence.len());
+        for (result, sequence_stats) in per_sequence {
+            stats.sequences += sequence_stats.sequences;
+            stats.ticks += sequence_stats.ticks;
+            stats.rising_edges += sequence_stats.rising_edges;
+            stats.resets += sequence_stats.resets;
+            results.push(result);
+        }
+        stats.elapsed = start.elapsed();
+        sim_event!(
+            info,
+            sequences = stats.sequences,
+            ticks = stats.ticks,
+            rising_edges = stats.rising_edges,
+            resets = stats.resets,
+            elapsed = ?stats.elapsed,
+            "pool run complete"
+        );
+
+        PoolReport { results, stats }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(values: &[u64]) -> Vec<TickInput> {
+        values
+            .iter()
+            .flat_map(|&v| {
+                let x = Integer::from(v);
+                [TickInput::new(false, false, x.clone()), TickInput::new(true, false, x)]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pool_matches_sequential_runs() {
+        let sequences: Vec<Vec<TickInput>> = (0..64u64)
+            .map(|i| sequence(&[i, i * 1000 + 7, i * i]))
+            .collect();
+
+        let pool = MachinePool::new(4);
+        let report = pool.run(&sequences);
+        assert_eq!(report.results.len(), sequences.len());
+
+        for (sequence, outputs) in sequences.iter().zip(&report.results) {
+            let mut machine = ModuloMachine::new();
+            let expected: Vec<Integer> = sequence
+                .iter()
+                .map(|t| machine.tick(t.clk, t.reset, &t.x).clone())
+                .collect();
+            assert_eq!(outputs, &expected);
+        }
+
+        assert_eq!(report.stats.sequences, 64);
+        assert_eq!(report.stats.ticks, 64 * 6);
+        assert_eq!(report.stats.rising_edges, 64 * 3);
+        assert_eq!(report.stats.resets, 0);
+    }
+
+    #[test]
+    fn test_stats_follow_machine_counters() {
+        let mut sequences = vec![sequence(&[1, 2]); 8];
+        // A rising edge, a reset tick, then the clock low again
+        sequences.push(vec![
+            TickInput::new(true, false, Integer::from(5)),
+            TickInput::new(true, true, Integer::from(5)),
+            TickInput::new(false, false, Integer::from(5)),
+        ]);
+        let report = MachinePool::new(3).run(&sequences);
+
+        let mut expected = (0, 0, 0);
+        for sequence in &sequences {
+            let mut machine = ModuloMachine::new();
+            for t in sequence {
+                machine.tick(t.clk, t.reset, &t.x);
+            }
+            let stats = machine.stats();
+            expected = (expected.0 + stats.ticks, expected.1 + stats.rising_edges, expected.2 + stats.resets);
+        }
+        assert_eq!((report.stats.ticks, report.stats.rising_edges, report.stats.resets), expected);
+        assert_eq!(report.stats.resets, 1);
+    }
+
+    #[test]
+    fn test_runs_inside_another_pool() {
+        // Calling from a bigger pool must still index this pool's machines
+        let outer = ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        let pool = MachinePool::new(1);
+        let sequences = vec![sequence(&[7]); 8];
+        let report = outer.install(|| pool.run(&sequences));
+        assert_eq!(report.stats.sequences, 8);
+        assert!(report.results.iter().all(|outputs| outputs[1] == 7));
+    }
+
+    #[test]
+    fn test_sequences_are_isolated() {
+        // A sequence without any rising edge must see the reset output,
+        // not whatever the previous sequence on that worker left behind.
+        let mut sequences = vec![sequence(&[12345]); 16];
+        sequences.extend(vec![vec![TickInput::new(false, false, Integer::from(99u32))]; 16]);
+
+        let pool = MachinePool::new(2);
+        let report = pool.run_with(&sequences, |_, outputs| outputs.last().cloned().unwrap());
+        assert!(report.results[..16].iter().all(|o| *o == 12345));
+        assert!(report.results[16..].iter().all(|o| *o == 0));
+    }
+}
@@ -0,0 +1,243 @@
+//! Division by the modulus with its reciprocal cached at construction.
+//!
+//! GMP's `x % p` normalises the divisor and computes its limb reciprocal
+//! inside every call before running schoolbook division. For a machine the
+//! modulus never changes, so [`PreinvReducer`] does that setup once: it keeps
+//! the modulus shifted until its top bit is set, together with the
+//! Möller–Granlund reciprocal of its top two limbs (of its only limb, for
+//! moduli below 2^64). A reduction shifts the operand into a stack buffer
+//! and, per quotient limb, takes one 3/2 quotient estimate from the cached
+//! reciprocal, one multiply-subtract and at most one add-back. A 300-bit
+//! operand and a 256-bit modulus need two quotient limbs, and nothing is
+//! allocated beyond growing the output; [`PreinvReducer::reduce_limbs`]
+//! skips the Integer conversions altogether.
+//!
+//! Reference: N. Möller and T. Granlund, "Improved division by invariant
+//! integers", IEEE Transactions on Computers 60(2), 2011.
+
+use crate::ct;
+use crate::fixed::{P_LIMBS, X_LIMBS};
+use rug::integer::Order;
+use rug::{Assign, Integer};
+
+/// Normalised modulus and cached reciprocal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreinvReducer {
+    p: Integer,
+    /// `p << shift`, least significant limb first; limbs from `n` on are zero
+    d: [u64; P_LIMBS],
+    /// Limbs in `p`
+    n: usize,
+    /// Leading zeros of `p`'s top limb
+    shift: u32,
+    /// `floor((β^3 - 1) / (d[n-1]·β + d[n-2])) - β` with `β = 2^64`, or
+    /// `floor((β^2 - 1) / d[0]) - β` for a single-limb modulus
+    v: u64,
+}
+
+impl PreinvReducer {
+    /// Normalise `p` and compute its reciprocal; `p` must be positive and at
+    /// most 256 bits, like any machine modulus
+    pub fn new(p: &Integer) -> Self {
+        let bits = p.significant_bits();
+        assert!(*p > 0 && bits <= 64 * P_LIMBS as u32, "preinverted division needs a positive modulus of at most 256 bits");
+        let n = bits.div_ceil(64) as usize;
+        let shift = 64 * n as u32 - bits;
+        let mut d = [0u64; P_LIMBS];
+        Integer::from(p << shift).write_digits(&mut d[..n], Order::Lsf);
+        let v = if n == 1 {
+            (u128::MAX / d[0] as u128) as u64
+        } else {
+            let top = Integer::from(d[n - 1]) << 64u32 | Integer::from(d[n - 2]);
+            let v = ((Integer::from(1) << 192u32) - 1u32) / top - (Integer::from(1) << 64u32);
+            v.to_u64().expect("the reciprocal of a normalised divisor fits one limb")
+        };
+        Self { p: p.clone(), d, n, shift, v }
+    }
+
+    pub fn modulus(&self) -> &Integer {
+        &self.p
+    }
+
+    /// Write `x mod p` into `out`
+    ///
+    /// Operands that are negative or wider than 320 bits fall back to GMP's
+    /// division.
+    pub fn reduce_into(&self, x: &Integer, out: &mut Integer) {
+        match ct::to_limbs(x) {
+            Some(limbs) => out.assign_digits(&self.reduce_limbs(&limbs)[..self.n], Order::Lsf),
+            None => out.assign(x % &self.p),
+        }
+    }
+
+    /// `x mod p` for a 320-bit operand, least significant limb first
+    pub fn reduce_limbs(&self, x: &[u64; X_LIMBS]) -> [u64; P_LIMBS] {
+        let (n, shift) = (self.n, self.shift);
+        let m = x.iter().rposition(|&limb| limb != 0).map_or(0, |top| top + 1);
+        let mut r = [0u64; P_LIMBS];
+        if m < n {
+            // Fewer limbs than p, so already below it
+            r[..n].copy_from_slice(&x[..n]);
+            return r;
+        }
+
+        // u = x << shift, one limb longer; its top limb is below 2^shift,
+        // hence below the normalised modulus's top limb
+        let mut u = [0u64; X_LIMBS + 1];
+        for i in 0..m {
+            u[i] |= x[i] << shift;
+            if shift > 0 {
+                u[i + 1] = x[i] >> (64 - shift);
+            }
+        }
+
+        let d = &self.d[..n];
+        if n == 1 {
+            let mut rem = u[m];
+            for &limb in u[..m].iter().rev() {
+                rem = div2by1(rem, limb, d[0], self.v);
+            }
+            r[0] = rem >> shift;
+            return r;
+        }
+        let (d1, d0) = (d[n - 1], d[n - 2]);
+        for j in (0..=m - n).rev() {
+            let window = &mut u[j..=j + n];
+            let (u2, u1, u0) = (window[n], window[n - 1], window[n - 2]);
+            // The window is below d·β, so (u2, u1) <= (d1, d0); on equality
+            // the quotient limb is β - 1
+            let q = if (u2, u1) == (d1, d0) { u64::MAX } else { div3by2(u2, u1, u0, d1, d0, self.v) };
+            if submul(window, d, q) {
+                add_back(window, d);
+            }
+        }
+        for i in 0..n {
+            r[i] = u[i] >> shift;
+            if shift > 0 {
+                r[i] |= u[i + 1] << (64 - shift);
+            }
+        }
+        r
+    }
+
+    pub fn reduce(&self, x: &Integer) -> Integer {
+        let mut out = Integer::new();
+        self.reduce_into(x, &mut out);
+        out
+    }
+}
+
+/// Remainder of `(u1·β + u0) / d` for normalised `d` and `u1 < d`
+/// (Möller–Granlund algorithm 4)
+fn div2by1(u1: u64, u0: u64, d: u64, v: u64) -> u64 {
+    let q = (v as u128 * u1 as u128).wrapping_add(((u1 as u128) << 64) | u0 as u128);
+    let (q1, q0) = (((q >> 64) as u64).wrapping_add(1), q as u64);
+    let mut r = u0.wrapping_sub(q1.wrapping_mul(d));
+    if r > q0 {
+        r = r.wrapping_add(d);
+    }
+    if r >= d {
+        r -= d;
+    }
+    r
+}
+
+/// Quotient of `(u2·β² + u1·β + u0) / (d1·β + d0)` for normalised `d1` and
+/// `(u2, u1) < (d1, d0)` (Möller–Granlund algorithm 5)
+fn div3by2(u2: u64, u1: u64, u0: u64, d1: u64, d0: u64, v: u64) -> u64 {
+    let d = ((d1 as u128) << 64) | d0 as u128;
+    let q = (v as u128 * u2 as u128).wrapping_add(((u2 as u128) << 64) | u1 as u128);
+    let (mut q1, q0) = ((q >> 64) as u64, q as u64);
+    let r1 = u1.wrapping_sub(q1.wrapping_mul(d1));
+    let mut r = (((r1 as u128) << 64) | u0 as u128).wrapping_sub(d0 as u128 * q1 as u128).wrapping_sub(d);
+    q1 = q1.wrapping_add(1);
+    if (r >> 64) as u64 >= q0 {
+        q1 = q1.wrapping_sub(1);
+        r = r.wrapping_add(d);
+    }
+    if r >= d {
+        q1 += 1;
+    }
+    q1
+}
+
+/// `w -= q·d` over `d.len() + 1` limbs, returning whether it went negative
+fn submul(w: &mut [u64], d: &[u64], q: u64) -> bool {
+    let (mut carry, mut borrow) = (0u64, false);
+    for (wi, &di) in w.iter_mut().zip(d) {
+        let product = di as u128 * q as u128 + carry as u128;
+        carry = (product >> 64) as u64;
+        let (s, b1) = wi.overflowing_sub(product as u64);
+        let (s, b2) = s.overflowing_sub(borrow as u64);
+        *wi = s;
+        borrow = b1 | b2;
+    }
+    let top = &mut w[d.len()];
+    let (s, b1) = top.overflowing_sub(carry);
+    let (s, b2) = s.overflowing_sub(borrow as u64);
+    *top = s;
+    b1 | b2
+}
+
+/// `w += d`, undoing a quotient estimate one too large; the carry out of the
+/// top limb cancels the borrow
+fn add_back(w: &mut [u64], d: &[u64]) {
+    let mut carry = false;
+    for (wi, &di) in w.iter_mut().zip(d) {
+        let (s, c1) = wi.overflowing_add(di);
+        let (s, c2) = s.overflowing_add(carry as u64);
+        *wi = s;
+        carry = c1 | c2;
+    }
+    let top = &mut w[d.len()];
+    *top = top.wrapping_add(carry as u64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectors::splitmix64;
+    use crate::{ModuloMachine, Preset, P_STR};
+
+    #[test]
+    fn test_matches_gmp() {
+        let mut moduli = vec![
+            Integer::from_str_radix(P_STR, 10).unwrap(),
+            Integer::from(3u32),
+            Integer::from(u64::MAX),
+            (Integer::from(1) << 64u32) + 1u32,
+            (Integer::from(1) << 256u32) - 1u32,
+            (Integer::from(1) << 128u32) + 3u32,
+        ];
+        moduli.extend(Preset::ALL.iter().map(|preset| preset.modulus()));
+        let mut state = 17;
+        for p in &moduli {
+            let reducer = PreinvReducer::new(p);
+            let mut cases = vec![
+                Integer::new(),
+                Integer::from(p - 1u32),
+                p.clone(),
+                Integer::from(p * 2u32) - 1u32,
+                // Quotient limbs of β - 1
+                Integer::from(p << 64u32) - 1u32,
+                ModuloMachine::create_large_input(300, 0) - 1u32,
+                (Integer::from(1) << 320u32) - 1u32,
+                // GMP fallback
+                Integer::from(-7),
+                Integer::from(1) << 400u32,
+            ];
+            for _ in 0..200 {
+                let limbs: [u64; X_LIMBS] = core::array::from_fn(|_| splitmix64(&mut state));
+                let bits = (splitmix64(&mut state) % 321) as u32;
+                cases.push(Integer::from_digits(&limbs, Order::Lsf).keep_bits(bits));
+            }
+            for x in &cases {
+                let expected = Integer::from(x % p);
+                assert_eq!(reducer.reduce(x), expected, "{} mod {}", x, p);
+                if let Some(limbs) = ct::to_limbs(x) {
+                    assert_eq!(Integer::from_digits(&reducer.reduce_limbs(&limbs), Order::Lsf), expected);
+                }
+            }
+        }
+    }
+}
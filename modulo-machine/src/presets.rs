@@ -0,0 +1,102 @@
+//! Well-known moduli, so testbenches reusing the model for another field do
+//! not paste decimal strings by hand.
+//!
+//! Each value is checked below against its defining formula or the hex form
+//! published with the curve.
+
+use rug::Integer;
+
+/// A published modulus the machine can be built for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Preset {
+    /// BLS12-381 scalar field order r
+    Bls12_381Fr,
+    /// BN254 (alt_bn128) scalar field order r
+    Bn254Fr,
+    /// secp256k1 group order n
+    Secp256k1N,
+    /// NIST P-256 base field prime `2^256 - 2^224 + 2^192 + 2^96 - 1`
+    P256,
+    /// Goldilocks prime `2^64 - 2^32 + 1`
+    Goldilocks,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 5] = [
+        Preset::Bls12_381Fr,
+        Preset::Bn254Fr,
+        Preset::Secp256k1N,
+        Preset::P256,
+        Preset::Goldilocks,
+    ];
+
+    /// Short lowercase name, as accepted by [`Preset::from_name`]
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Bls12_381Fr => "bls12-381-fr",
+            Preset::Bn254Fr => "bn254-fr",
+            Preset::Secp256k1N => "secp256k1-n",
+            Preset::P256 => "p256",
+            Preset::Goldilocks => "goldilocks",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Preset> {
+        Preset::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+
+    /// The modulus as a decimal string
+    pub fn decimal(self) -> &'static str {
+        match self {
+            Preset::Bls12_381Fr => "52435875175126190479447740508185965837690552500527637822603658699938581184513",
+            Preset::Bn254Fr => "21888242871839275222246405745257275088548364400416034343698204186575808495617",
+            Preset::Secp256k1N => "115792089237316195423570985008687907852837564279074904382605163141518161494337",
+            Preset::P256 => "115792089210356248762697446949407573530086143415290314195533631308867097853951",
+            Preset::Goldilocks => "18446744069414584321",
+        }
+    }
+
+    pub fn modulus(self) -> Integer {
+        Integer::from_str_radix(self.decimal(), 10).expect("preset moduli are valid decimal")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate_machine_modulus;
+
+    fn pow2(bits: u32) -> Integer {
+        Integer::from(1) << bits
+    }
+
+    #[test]
+    fn test_presets_match_published_values() {
+        let hex = |s: &str| Integer::from_str_radix(s, 16).unwrap();
+        assert_eq!(
+            Preset::Bls12_381Fr.modulus(),
+            hex("73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001")
+        );
+        assert_eq!(
+            Preset::Bn254Fr.modulus(),
+            hex("30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001")
+        );
+        assert_eq!(
+            Preset::Secp256k1N.modulus(),
+            hex("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141")
+        );
+        assert_eq!(
+            Preset::P256.modulus(),
+            pow2(256) - pow2(224) + pow2(192) + pow2(96) - 1u32
+        );
+        assert_eq!(Preset::Goldilocks.modulus(), pow2(64) - pow2(32) + 1u32);
+
+        for preset in Preset::ALL {
+            let p = preset.modulus();
+            assert_eq!(validate_machine_modulus(&p), Ok(()), "{}", preset.name());
+            assert!(p.is_probably_prime(30) != rug::integer::IsPrime::No, "{}", preset.name());
+            assert_eq!(Preset::from_name(preset.name()), Some(preset));
+        }
+        assert_eq!(Preset::from_name("p-384"), None);
+    }
+}
@@ -0,0 +1,203 @@
+//! Python bindings (`python` feature).
+//!
+//! Build and install into the active virtualenv with
+//! `maturin develop --release` from this crate's directory; see
+//! `pyproject.toml`. Python ints map directly to operands and outputs.
+//!
+//! The batch methods release the GIL while they run, so cocotb coroutines
+//! and other Python threads keep going during long batches.
+
+use crate::{ModuloMachine, P_STR};
+use num_bigint::{BigInt, Sign};
+use pyo3::prelude::*;
+use rug::integer::Order;
+use rug::Integer;
+use std::collections::HashMap;
+
+fn to_integer(x: &BigInt) -> Integer {
+    let (sign, digits) = x.to_u64_digits();
+    let magnitude = Integer::from_digits(&digits, Order::Lsf);
+    if sign == Sign::Minus {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+fn to_bigint(x: &Integer) -> BigInt {
+    let sign = if *x < 0 { Sign::Minus } else { Sign::Plus };
+    BigInt::from_slice(sign, &x.to_digits::<u32>(Order::Lsf))
+}
+
+type TraceRow = (u64, bool, bool, BigInt, BigInt);
+
+/// The golden-model machine, with optional per-tick tracing
+#[pyclass(name = "ModuloMachine", module = "modulo_machine")]
+pub struct PyModuloMachine {
+    machine: ModuloMachine,
+    /// `(time, clk, reset, x, output)` per tick while tracing is enabled
+    trace: Option<Vec<TraceRow>>,
+}
+
+impl PyModuloMachine {
+    fn step(&mut self, clk: bool, reset: bool, x: &BigInt) -> BigInt {
+        let time = self.machine.sim_time();
+        let output = to_bigint(self.machine.tick(clk, reset, &to_integer(x)));
+        if let Some(trace) = &mut self.trace {
+            trace.push((time, clk, reset, x.clone(), output.clone()));
+        }
+        output
+    }
+}
+
+#[pymethods]
+impl PyModuloMachine {
+    #[new]
+    fn new() -> Self {
+        Self {
+            machine: ModuloMachine::new(),
+            trace: None,
+        }
+    }
+
+    /// Apply one clock cycle and return the output
+    fn tick(&mut self, clk: bool, reset: bool, x: BigInt) -> BigInt {
+        self.step(clk, reset, &x)
+    }
+
+    /// Apply a list of `(clk, reset, x)` ticks, returning the output after each
+    fn process_batch(&mut self, py: Python<'_>, inputs: Vec<(bool, bool, BigInt)>) -> Vec<BigInt> {
+        py.allow_threads(|| {
+            inputs
+                .iter()
+                .map(|(clk, reset, x)| self.step(*clk, *reset, x))
+                .collect()
+        })
+    }
+
+    /// Latch each value on its own rising edge, returning one output per value
+    fn process_values(&mut self, py: Python<'_>, values: Vec<BigInt>) -> Vec<BigInt> {
+        py.allow_threads(|| {
+            values
+                .iter()
+                .map(|x| {
+                    self.step(false, false, x);
+                    self.step(true, false, x)
+                })
+                .collect()
+        })
+    }
+
+    /// Reset the output register without counting a tick
+    fn reset(&mut self) {
+        self.machine.reset();
+    }
+
+    #[getter]
+    fn output(&self) -> BigInt {
+        to_bigint(self.machine.get_output())
+    }
+
+    #[getter]
+    fn prime(&self) -> BigInt {
+        to_bigint(self.machine.get_prime())
+    }
+
+    /// Timestamp the next tick will be applied at
+    #[getter]
+    fn time(&self) -> u64 {
+        self.machine.sim_time()
+    }
+
+    /// Simulation time units per tick, in the RTL simulator's timescale
+    #[getter]
+    fn time_step(&self) -> u64 {
+        self.machine.time_step()
+    }
+
+    #[setter]
+    fn set_time_step(&mut self, units: u64) {
+        self.machine.set_time_step(units);
+    }
+
+    /// Counters since creation or `reset_stats`: `ticks`, `rising_edges`,
+    /// `resets`, `reductions` (edges with `x >= P`, not counted under the
+    /// constant-time strategy) and `max_input_bits`
+    fn stats(&self) -> HashMap<&'static str, u64> {
+        let stats = self.machine.stats();
+        HashMap::from([
+            ("ticks", stats.ticks),
+            ("rising_edges", stats.rising_edges),
+            ("resets", stats.resets),
+            ("reductions", stats.reductions),
+            ("max_input_bits", stats.max_input_bits as u64),
+        ])
+    }
+
+    /// Zero the counters without touching the machine
+    fn reset_stats(&mut self) {
+        self.machine.reset_stats();
+    }
+
+    /// Start (or stop) recording every tick; starting clears the old trace
+    #[pyo3(signature = (enabled = true))]
+    fn enable_trace(&mut self, enabled: bool) {
+        self.trace = enabled.then(Vec::new);
+    }
+
+    /// Recorded `(time, clk, reset, x, output)` tuples
+    fn trace(&self) -> Vec<TraceRow> {
+        self.trace.clone().unwrap_or_default()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ModuloMachine(output={}, ticks={})", self.machine.get_output(), self.machine.stats().ticks)
+    }
+}
+
+#[pymodule]
+fn modulo_machine(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyModuloMachine>()?;
+    m.add("P", to_bigint(&Integer::from_str_radix(P_STR, 10).expect("Failed to parse prime P")))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bigint_round_trip() {
+        for x in [
+            Integer::new(),
+            Integer::from(-12345),
+            ModuloMachine::create_large_input(299, 987654321),
+        ] {
+            assert_eq!(to_integer(&to_bigint(&x)), x);
+        }
+    }
+
+    #[test]
+    fn test_stats_and_trace() {
+        let mut machine = PyModuloMachine::new();
+        machine.enable_trace(true);
+        machine.set_time_step(5);
+        let p = machine.prime();
+
+        pyo3::prepare_freethreaded_python();
+        let outputs = Python::with_gil(|py| machine.process_values(py, vec![BigInt::from(5), &p + 1]));
+        assert_eq!(outputs, [BigInt::from(5), BigInt::from(1)]);
+        machine.tick(false, true, BigInt::from(0));
+
+        let stats = machine.stats();
+        assert_eq!((stats["ticks"], stats["rising_edges"], stats["resets"], stats["reductions"]), (5, 2, 1, 1));
+        let trace = machine.trace();
+        assert_eq!(trace.len(), 5);
+        assert_eq!(trace[3], (15, true, false, &p + 1, BigInt::from(1)));
+        assert_eq!(machine.time(), 25);
+
+        let outputs = Python::with_gil(|py| machine.process_batch(py, vec![(false, false, BigInt::from(7)), (true, false, BigInt::from(7))]));
+        assert_eq!(outputs[1], BigInt::from(7));
+        assert_eq!(machine.trace().len(), 7);
+    }
+}
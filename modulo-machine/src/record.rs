@@ -0,0 +1,229 @@
+//! Record and replay of tick sequences.
+//!
+//! A [`Recorder`] wraps a machine and logs every tick applied through it
+//! (inputs, output and simulation time) into a [`Trace`], along with the
+//! machine's state when recording started. [`replay`] re-executes a trace
+//! from that state and diffs each output against the recorded one, so a
+//! failing randomized run can be reproduced exactly, and bisected with
+//! [`Trace::prefix`].
+//!
+//! With the `serde` feature a trace serializes like a [`MachineState`], big
+//! integers as decimal strings, so it can be saved alongside a failing test.
+
+use crate::{MachineState, ModuloMachine, TickInput};
+use rug::Integer;
+
+/// One recorded tick
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TraceEntry {
+    /// Simulation time at which the tick was applied
+    pub time: u64,
+    pub clk: bool,
+    pub reset: bool,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_int"))]
+    pub x: Integer,
+    /// Output after the tick
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_int"))]
+    pub output: Integer,
+}
+
+/// A recorded run: the starting state and every tick after it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Trace {
+    pub start: MachineState,
+    pub entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The inputs alone, e.g. for [`triage`](crate::triage())
+    pub fn stimulus(&self) -> Vec<TickInput> {
+        self.entries.iter().map(|e| TickInput::new(e.clk, e.reset, e.x.clone())).collect()
+    }
+
+    /// The first `len` ticks, from the same starting state
+    pub fn prefix(&self, len: usize) -> Trace {
+        Trace {
+            start: self.start.clone(),
+            entries: self.entries[..len.min(self.entries.len())].to_vec(),
+        }
+    }
+}
+
+/// Ticks a machine and logs each tick into a [`Trace`]
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    machine: ModuloMachine,
+    trace: Trace,
+}
+
+impl Recorder {
+    /// Start recording from the machine's current state
+    pub fn new(machine: ModuloMachine) -> Self {
+        let start = machine.snapshot();
+        Self {
+            machine,
+            trace: Trace { start, entries: Vec::new() },
+        }
+    }
+
+    /// Apply one tick and log it
+    pub fn tick(&mut self, clk: bool, reset: bool, x: &Integer) -> &Integer {
+        let time = self.machine.sim_time();
+        let output = self.machine.tick(clk, reset, x);
+        self.trace.entries.push(TraceEntry {
+            time,
+            clk,
+            reset,
+            x: x.clone(),
+            output: output.clone(),
+        });
+        output
+    }
+
+    /// Log a whole stimulus, returning the output after each tick
+    pub fn run(&mut self, stimulus: &[TickInput]) -> Vec<Integer> {
+        stimulus.iter().map(|t| self.tick(t.clk, t.reset, &t.x).clone()).collect()
+    }
+
+    pub fn machine(&self) -> &ModuloMachine {
+        &self.machine
+    }
+
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+
+    /// Stop recording, handing back the machine and the trace
+    pub fn finish(self) -> (ModuloMachine, Trace) {
+        (self.machine, self.trace)
+    }
+}
+
+/// A replayed tick whose output differed from the recording
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceMismatch {
+    /// Index of the tick in the trace
+    pub index: usize,
+    pub time: u64,
+    /// Recorded output
+    pub expected: Integer,
+    /// Output on replay
+    pub actual: Integer,
+}
+
+/// Outcome of [`replay`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceReplay {
+    /// Ticks replayed
+    pub ticks: usize,
+    pub mismatches: Vec<TraceMismatch>,
+}
+
+impl TraceReplay {
+    pub fn is_clean(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+
+    pub fn first_mismatch(&self) -> Option<&TraceMismatch> {
+        self.mismatches.first()
+    }
+}
+
+/// Re-execute `trace` on a fresh machine restored to its starting state
+pub fn replay(trace: &Trace) -> TraceReplay {
+    replay_on(&mut ModuloMachine::from_state(&trace.start), trace)
+}
+
+/// Re-execute `trace` on `machine` as it is, without restoring the starting
+/// state first, e.g. to check a changed configuration against a recording
+pub fn replay_on(machine: &mut ModuloMachine, trace: &Trace) -> TraceReplay {
+    let mut replay = TraceReplay::default();
+    for (index, entry) in trace.entries.iter().enumerate() {
+        let time = machine.sim_time();
+        let actual = machine.tick(entry.clk, entry.reset, &entry.x);
+        if *actual != entry.output {
+            replay.mismatches.push(TraceMismatch {
+                index,
+                time,
+                expected: entry.output.clone(),
+                actual: actual.clone(),
+            });
+        }
+        replay.ticks += 1;
+    }
+    replay
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Datapath;
+
+    fn recorded() -> Trace {
+        let mut machine = ModuloMachine::new();
+        machine.set_datapath(Datapath::Accumulate);
+        machine.set_time_step(10);
+        // Recording starts mid-run, so replay depends on the starting state
+        machine.cycle(&Integer::from(100u32));
+        let mut recorder = Recorder::new(machine);
+        let p = recorder.machine().get_prime().clone();
+        for x in [Integer::from(5u32), Integer::from(&p + 1u32), Integer::from(7u32)] {
+            recorder.tick(false, false, &x);
+            recorder.tick(true, false, &x);
+        }
+        let (machine, trace) = recorder.finish();
+        assert_eq!(*machine.get_output(), 113);
+        trace
+    }
+
+    #[test]
+    fn test_replay_reproduces_recording() {
+        let trace = recorded();
+        assert_eq!(trace.len(), 6);
+        assert_eq!(trace.entries[0].time, 20);
+        assert_eq!(trace.entries[5].time, 70);
+        assert_eq!(trace.entries[3].output, 106);
+        assert_eq!(trace.stimulus()[1], TickInput::new(true, false, Integer::from(5u32)));
+
+        let replayed = replay(&trace);
+        assert!(replayed.is_clean());
+        assert_eq!(replayed.ticks, 6);
+    }
+
+    #[test]
+    fn test_replay_reports_divergence() {
+        let mut trace = recorded();
+        trace.entries[3].output = Integer::from(1u32);
+        let replayed = replay(&trace);
+        assert_eq!(
+            replayed.first_mismatch(),
+            Some(&TraceMismatch { index: 3, time: 50, expected: Integer::from(1u32), actual: Integer::from(106u32) })
+        );
+        assert_eq!(replayed.mismatches.len(), 1);
+        // Bisecting: the prefix before the bad tick replays cleanly
+        assert!(replay(&trace.prefix(3)).is_clean());
+        assert!(!replay(&trace.prefix(4)).is_clean());
+
+        // Without the starting state, a fresh machine diverges straight away
+        let replayed = replay_on(&mut ModuloMachine::new(), &recorded());
+        assert_eq!(replayed.first_mismatch().unwrap().index, 0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        let trace = recorded();
+        let json = serde_json::to_string(&trace).unwrap();
+        assert_eq!(serde_json::from_str::<Trace>(&json).unwrap(), trace);
+    }
+}
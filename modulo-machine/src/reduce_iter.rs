@@ -0,0 +1,117 @@
+//! Modular reduction as an iterator combinator.
+//!
+//! [`ModReduceExt`] gives every `Iterator<Item = Integer>` a
+//! [`mod_reduce`](ModReduceExt::mod_reduce) adapter, so the machine bolts onto
+//! an existing data pipeline in one call:
+//!
+//! ```
+//! use modulo_machine::{ModReduceExt, ModuloMachine};
+//! use rug::Integer;
+//!
+//! let machine = ModuloMachine::new();
+//! let p = machine.get_prime().clone();
+//! let reduced: Vec<Integer> = [Integer::from(&p + 5u32), Integer::from(7)].into_iter().mod_reduce(&machine).collect();
+//! assert_eq!(reduced, [5, 7]);
+//!
+//! let small: Vec<Integer> = (10..13).map(Integer::from).mod_reduce(&Integer::from(11)).collect();
+//! assert_eq!(small, [10, 0, 1]);
+//! ```
+
+use crate::ModuloMachine;
+use rug::Integer;
+
+/// Anything that can reduce one operand in place
+pub trait ModReducer {
+    fn reduce_in_place(&self, x: &mut Integer);
+}
+
+/// What the next rising edge would latch, as [`ModuloMachine::peek_next`]:
+/// the machine's width policy, strategy and modes apply, and it is not
+/// clocked
+impl ModReducer for ModuloMachine {
+    fn reduce_in_place(&self, x: &mut Integer) {
+        *x = self.peek_next(x);
+    }
+}
+
+/// `x mod m` with GMP's truncated convention (a negative `x` gives a
+/// non-positive result, like [`SignedMode::Truncated`]); panics if `m` is
+/// zero
+///
+/// [`SignedMode::Truncated`]: crate::SignedMode::Truncated
+impl ModReducer for Integer {
+    fn reduce_in_place(&self, x: &mut Integer) {
+        *x %= self;
+    }
+}
+
+/// Adds [`mod_reduce`](ModReduceExt::mod_reduce) to every iterator of
+/// Integers
+pub trait ModReduceExt: Iterator<Item = Integer> + Sized {
+    /// Reduce each item with a machine or a bare modulus, reusing the item's
+    /// own allocation for the result where the reducer allows
+    fn mod_reduce<R: ModReducer + ?Sized>(self, reducer: &R) -> ModReduce<'_, Self, R> {
+        ModReduce { iter: self, reducer }
+    }
+}
+
+impl<I: Iterator<Item = Integer>> ModReduceExt for I {}
+
+/// Iterator returned by [`ModReduceExt::mod_reduce`]
+pub struct ModReduce<'r, I, R: ?Sized> {
+    iter: I,
+    reducer: &'r R,
+}
+
+impl<I: Iterator<Item = Integer>, R: ModReducer + ?Sized> Iterator for ModReduce<'_, I, R> {
+    type Item = Integer;
+
+    fn next(&mut self) -> Option<Integer> {
+        let mut x = self.iter.next()?;
+        self.reducer.reduce_in_place(&mut x);
+        Some(x)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I: ExactSizeIterator<Item = Integer>, R: ModReducer + ?Sized> ExactSizeIterator for ModReduce<'_, I, R> {}
+
+impl<I: DoubleEndedIterator<Item = Integer>, R: ModReducer + ?Sized> DoubleEndedIterator for ModReduce<'_, I, R> {
+    fn next_back(&mut self) -> Option<Integer> {
+        let mut x = self.iter.next_back()?;
+        self.reducer.reduce_in_place(&mut x);
+        Some(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InputWidthPolicy, SignedMode};
+
+    #[test]
+    fn test_mod_reduce() {
+        let mut machine = ModuloMachine::new();
+        let p = machine.get_prime().clone();
+        let values = vec![Integer::from(-3), Integer::from(&p * 3u32) + 4u32, Integer::from(12), (Integer::from(1) << 310) + 5u32];
+
+        let reduced: Vec<Integer> = values.clone().into_iter().mod_reduce(&machine).collect();
+        assert_eq!(reduced, values.iter().map(|x| machine.peek_next(x)).collect::<Vec<_>>());
+        assert_eq!(reduced[..3], [Integer::from(-3), Integer::from(4), Integer::from(12)]);
+        assert_eq!(values.clone().into_iter().mod_reduce(&p).collect::<Vec<_>>(), reduced);
+
+        // The machine's configuration applies; nothing is clocked
+        machine.set_signed_mode(SignedMode::Euclidean);
+        machine.set_input_width_policy(InputWidthPolicy::Wrap);
+        let iter = values.clone().into_iter().mod_reduce(&machine);
+        assert_eq!(iter.len(), 4);
+        let reduced: Vec<Integer> = iter.rev().collect();
+        assert_eq!(reduced[3], Integer::from(&p - 3u32));
+        assert_eq!(reduced[0], 5);
+        assert_eq!(*machine.get_output(), 0);
+        assert_eq!(machine.stats().ticks, 0);
+    }
+}
@@ -0,0 +1,167 @@
+//! Interactive exploration of edge and reset semantics (`modmachine repl`).
+//!
+//! The REPL holds the machine's input pins. Each command that drives a pin
+//! applies one tick with the new levels and prints the resulting state:
+//!
+//! ```text
+//! > x 0x1f
+//! t=0 clk=0 reset=0 x=0x1f o=0x0
+//! > clk 1
+//! t=1 clk=1 reset=0 x=0x1f o=0x1f  (rising edge)
+//! ```
+//!
+//! Commands: `clk 0|1`, `reset 0|1`, `x VALUE` (decimal, or `0x`, `0o` or
+//! `0b` prefixed, as [`ModuloMachine::create_input`] reads it),
+//! `tick` (apply the current levels again), `show`, `help` and `quit`.
+
+use crate::{format_radix, ModuloMachine};
+use rug::Integer;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "commands: clk 0|1, reset 0|1, x VALUE, tick, show, help, quit";
+
+/// Pin levels and the machine they drive
+pub struct Repl {
+    machine: ModuloMachine,
+    clk: bool,
+    reset: bool,
+    x: Integer,
+}
+
+impl Repl {
+    pub fn new(machine: ModuloMachine) -> Self {
+        Self {
+            machine,
+            clk: false,
+            reset: false,
+            x: Integer::new(),
+        }
+    }
+
+    pub fn machine(&self) -> &ModuloMachine {
+        &self.machine
+    }
+
+    /// Run one command line, returning the text to print, or `None` for
+    /// `quit`
+    pub fn execute(&mut self, line: &str) -> Option<String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let reply = match words.as_slice() {
+            [] => String::new(),
+            ["quit" | "exit"] => return None,
+            ["help"] => HELP.to_string(),
+            ["show"] => self.state(),
+            ["tick"] => self.apply(),
+            ["clk", level] => match bit(level) {
+                Some(level) => {
+                    let rising = level && !self.clk && !self.reset;
+                    self.clk = level;
+                    let state = self.apply();
+                    if rising {
+                        format!("{}  (rising edge)", state)
+                    } else {
+                        state
+                    }
+                }
+                None => format!("error: clk takes 0 or 1, not `{}`", level),
+            },
+            ["reset", level] => match bit(level) {
+                Some(level) => {
+                    self.reset = level;
+                    self.apply()
+                }
+                None => format!("error: reset takes 0 or 1, not `{}`", level),
+            },
+            ["x", value] => match ModuloMachine::create_input(value, 10) {
+                Ok(value) => {
+                    self.x = value;
+                    self.apply()
+                }
+                Err(_) => format!("error: `{}` is not a number", value),
+            },
+            _ => format!("error: unknown command `{}`; {}", line.trim(), HELP),
+        };
+        Some(reply)
+    }
+
+    /// Read commands until `quit` or end of input, writing a prompt before
+    /// each when `prompt` is set
+    pub fn run<R: BufRead, W: Write>(&mut self, reader: R, mut writer: W, prompt: bool) -> io::Result<()> {
+        let mut lines = reader.lines();
+        loop {
+            if prompt {
+                write!(writer, "> ")?;
+                writer.flush()?;
+            }
+            let Some(line) = lines.next() else {
+                return Ok(());
+            };
+            match self.execute(&line?) {
+                Some(reply) if reply.is_empty() => {}
+                Some(reply) => writeln!(writer, "{}", reply)?,
+                None => return Ok(()),
+            }
+        }
+    }
+
+    fn apply(&mut self) -> String {
+        let time = self.machine.sim_time();
+        self.machine.tick(self.clk, self.reset, &self.x);
+        self.state_at(time)
+    }
+
+    fn state(&self) -> String {
+        let time = self.machine.sim_time().saturating_sub(self.machine.time_step());
+        self.state_at(time)
+    }
+
+    fn state_at(&self, time: u64) -> String {
+        let radix = self.machine.display_radix();
+        format!(
+            "t={} clk={} reset={} x={} o={}",
+            time,
+            self.clk as u8,
+            self.reset as u8,
+            format_radix(&self.x, radix),
+            self.machine
+        )
+    }
+}
+
+fn bit(s: &str) -> Option<bool> {
+    match s {
+        "0" => Some(false),
+        "1" => Some(true),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session() {
+        let mut machine = ModuloMachine::new();
+        machine.set_display_radix(16);
+        let p = machine.get_prime().clone();
+        let mut repl = Repl::new(machine);
+
+        let script = format!("x 0x{}\nclk 1\n\nclk 0\nreset 1\nshow\nx zz\nfrob\nquit\nclk 1\n", Integer::from(&p + 0x1f).to_string_radix(16));
+        let mut out = Vec::new();
+        repl.run(script.as_bytes(), &mut out, false).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert!(lines[0].starts_with("t=0 clk=0 reset=0 x=0x") && lines[0].ends_with(" o=0x0"), "{}", lines[0]);
+        assert!(lines[1].starts_with("t=1 clk=1 ") && lines[1].ends_with(" o=0x1f  (rising edge)"), "{}", lines[1]);
+        assert!(lines[2].ends_with(" o=0x1f") && lines[2].contains("clk=0"));
+        assert!(lines[3].starts_with("t=3 clk=0 reset=1 ") && lines[3].ends_with(" o=0x0"));
+        assert_eq!(lines[4], lines[3]);
+        assert!(lines[5].starts_with("error: `zz`"));
+        assert!(lines[6].starts_with("error: unknown command `frob`"));
+        // Nothing runs after quit
+        assert_eq!(lines.len(), 7);
+        assert_eq!(repl.machine().sim_time(), 4);
+    }
+}
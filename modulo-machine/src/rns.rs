@@ -0,0 +1,309 @@
+//! Residue Number System decomposition.
+//!
+//! An RNS datapath represents a wide operand by its residues modulo a set of
+//! pairwise coprime word-sized moduli, so additions and multiplications run
+//! as independent narrow channels with no carries between them. The value is
+//! determined uniquely modulo the product of the moduli, the basis's dynamic
+//! range. [`RnsBasis`] is the software reference for the forward conversion:
+//!
+//! ```
+//! use modulo_machine::RnsBasis;
+//! use rug::Integer;
+//!
+//! let basis = RnsBasis::new([13, 17, 19]).unwrap();
+//! assert_eq!(*basis.dynamic_range(), 13 * 17 * 19);
+//! assert_eq!(basis.residues(&Integer::from(1000)), [12, 14, 12]);
+//!
+//! // One row of residues per channel, each channel reduced on its own thread
+//! let xs = [Integer::from(1000), Integer::from(-1)];
+//! assert_eq!(basis.channels(&xs), [[12, 12], [14, 16], [12, 18]]);
+//! ```
+//!
+//! Residues are always in `[0, m)`: a negative operand is represented by its
+//! Euclidean residue, as an RNS channel has no sign.
+//!
+//! The reverse conversion is the Chinese Remainder Theorem: [`crt_combine`]
+//! solves any system of congruences, checking that residues modulo moduli
+//! with a common factor agree on it, and [`RnsBasis::combine`] recombines one
+//! set of channel residues. An operand below the dynamic range round-trips
+//! exactly, so an RNS datapath can be checked against the monolithic
+//! reduction:
+//!
+//! ```
+//! use modulo_machine::{ModuloMachine, RnsBasis};
+//!
+//! // Five 62-bit primes cover a 300-bit operand
+//! let basis = RnsBasis::new([(1 << 62) - 57, (1 << 62) - 87, (1 << 62) - 117, (1 << 62) - 143, (1 << 62) - 153]).unwrap();
+//! let x = ModuloMachine::create_large_input(300, 7);
+//! let x = basis.combine(&basis.residues(&x)).unwrap();
+//! let machine = ModuloMachine::new();
+//! assert_eq!(machine.peek_next(&x), x % machine.get_prime());
+//! ```
+
+use rayon::prelude::*;
+use rug::integer::Order;
+use rug::Integer;
+use std::fmt;
+
+/// Reason [`RnsBasis::new`] rejected a set of moduli
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RnsError {
+    /// No moduli at all
+    Empty,
+    /// A channel modulus below 2, which has nothing to hold
+    TooSmall { index: usize, modulus: u64 },
+    /// Two channel moduli share the factor `gcd`
+    NotCoprime { first: u64, second: u64, gcd: u64 },
+}
+
+impl fmt::Display for RnsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RnsError::Empty => write!(f, "an RNS basis needs at least one modulus"),
+            RnsError::TooSmall { index, modulus } => write!(f, "channel {} modulus {} must be at least 2", index, modulus),
+            RnsError::NotCoprime { first, second, gcd } => {
+                write!(f, "channel moduli {} and {} share the factor {}", first, second, gcd)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RnsError {}
+
+/// Reason [`crt_combine`] or [`RnsBasis::combine`] found no solution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CrtError {
+    /// No congruences to combine
+    Empty,
+    /// Congruence `index` has a modulus below 1
+    InvalidModulus { index: usize, modulus: Integer },
+    /// Congruence `index` contradicts the ones before it: its residue and
+    /// theirs differ modulo `gcd`, the factor its modulus shares with them
+    Incompatible { index: usize, residue: Integer, modulus: Integer, gcd: Integer },
+    /// [`RnsBasis::combine`] got a different number of residues than the
+    /// basis has channels
+    LengthMismatch { expected: usize, found: usize },
+}
+
+impl fmt::Display for CrtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrtError::Empty => write!(f, "no congruences to combine"),
+            CrtError::InvalidModulus { index, modulus } => write!(f, "congruence {} has modulus {}, expected at least 1", index, modulus),
+            CrtError::Incompatible { index, residue, modulus, gcd } => write!(
+                f,
+                "congruence {} (x = {} mod {}) contradicts the earlier ones modulo their common factor {}",
+                index, residue, modulus, gcd
+            ),
+            CrtError::LengthMismatch { expected, found } => write!(f, "expected {} residues, one per channel, found {}", expected, found),
+        }
+    }
+}
+
+impl std::error::Error for CrtError {}
+
+/// The least non-negative `x` with `x ≡ residue (mod modulus)` for every
+/// pair, which is unique modulo the least common multiple of the moduli
+///
+/// The moduli need not be coprime; where two share a factor, their residues
+/// must agree modulo it, or the system has no solution and
+/// [`CrtError::Incompatible`] names the first congruence that breaks it.
+/// Residues may be negative or exceed their modulus.
+pub fn crt_combine(congruences: &[(Integer, Integer)]) -> Result<Integer, CrtError> {
+    if congruences.is_empty() {
+        return Err(CrtError::Empty);
+    }
+    // Invariant: x solves the congruences so far and lies in [0, lcm)
+    let mut x = Integer::new();
+    let mut lcm = Integer::from(1);
+    for (index, (residue, modulus)) in congruences.iter().enumerate() {
+        if *modulus < 1 {
+            return Err(CrtError::InvalidModulus { index, modulus: modulus.clone() });
+        }
+        let gcd = Integer::from(lcm.gcd_ref(modulus));
+        let diff = Integer::from(residue - &x);
+        if !diff.is_divisible(&gcd) {
+            return Err(CrtError::Incompatible { index, residue: residue.clone(), modulus: modulus.clone(), gcd });
+        }
+        // x + lcm·t ≡ residue (mod modulus), so t ≡ (diff / g)·(lcm / g)^-1 (mod modulus / g)
+        let step = Integer::from(modulus.div_exact_ref(&gcd));
+        if step == 1 {
+            // Implied by the earlier congruences
+            continue;
+        }
+        let inverse = Integer::from(lcm.div_exact_ref(&gcd)).invert(&step).expect("lcm / g and modulus / g are coprime");
+        let mut t = diff.div_exact(&gcd) * inverse % &step;
+        if t < 0 {
+            t += &step;
+        }
+        x += Integer::from(&lcm * &t);
+        lcm *= step;
+    }
+    Ok(x)
+}
+
+/// A set of pairwise coprime channel moduli; see the [module docs](self)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RnsBasis {
+    moduli: Vec<u64>,
+    /// Product of the moduli
+    range: Integer,
+}
+
+impl RnsBasis {
+    /// Basis with one channel per modulus, in the order given
+    pub fn new(moduli: impl Into<Vec<u64>>) -> Result<Self, RnsError> {
+        let moduli = moduli.into();
+        if moduli.is_empty() {
+            return Err(RnsError::Empty);
+        }
+        if let Some((index, &modulus)) = moduli.iter().enumerate().find(|(_, &m)| m < 2) {
+            return Err(RnsError::TooSmall { index, modulus });
+        }
+        for (i, &first) in moduli.iter().enumerate() {
+            for &second in &moduli[i + 1..] {
+                let gcd = gcd(first, second);
+                if gcd != 1 {
+                    return Err(RnsError::NotCoprime { first, second, gcd });
+                }
+            }
+        }
+        let range = moduli.iter().fold(Integer::from(1), |acc, &m| acc * m);
+        Ok(Self { moduli, range })
+    }
+
+    pub fn moduli(&self) -> &[u64] {
+        &self.moduli
+    }
+
+    /// Product of the moduli: operands in `[0, M)` round-trip exactly
+    pub fn dynamic_range(&self) -> &Integer {
+        &self.range
+    }
+
+    /// Residue of `x` in every channel, in basis order
+    pub fn residues(&self, x: &Integer) -> Vec<u64> {
+        let limbs = x.to_digits::<u64>(Order::Lsf);
+        self.moduli.iter().map(|&m| residue(&limbs, *x < 0, m)).collect()
+    }
+
+    /// The operand in `[0, M)` with these channel residues, in basis order
+    ///
+    /// A residue at or above its channel modulus is taken modulo it.
+    pub fn combine(&self, residues: &[u64]) -> Result<Integer, CrtError> {
+        if residues.len() != self.moduli.len() {
+            return Err(CrtError::LengthMismatch { expected: self.moduli.len(), found: residues.len() });
+        }
+        let congruences: Vec<(Integer, Integer)> = residues.iter().zip(&self.moduli).map(|(&r, &m)| (Integer::from(r), Integer::from(m))).collect();
+        crt_combine(&congruences)
+    }
+
+    /// Residues of a batch, one row per channel: `channels(xs)[c][i]` is
+    /// `xs[i]` modulo channel `c`
+    ///
+    /// Each channel reduces the whole batch as a separate parallel task, as
+    /// independent hardware lanes would.
+    pub fn channels(&self, xs: &[Integer]) -> Vec<Vec<u64>> {
+        let operands: Vec<(Vec<u64>, bool)> = xs.iter().map(|x| (x.to_digits::<u64>(Order::Lsf), *x < 0)).collect();
+        self.moduli
+            .par_iter()
+            .map(|&m| operands.iter().map(|(limbs, negative)| residue(limbs, *negative, m)).collect())
+            .collect()
+    }
+}
+
+/// `±limbs mod m` in `[0, m)`, Horner over the magnitude's limbs from the top
+fn residue(limbs: &[u64], negative: bool, m: u64) -> u64 {
+    let r = limbs.iter().rev().fold(0u64, |r, &limb| ((((r as u128) << 64) | limb as u128) % m as u128) as u64);
+    if negative && r != 0 {
+        m - r
+    } else {
+        r
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ModuloMachine;
+
+    #[test]
+    fn test_residues_match_gmp() {
+        // Four 62-bit primes and a power of two
+        let basis = RnsBasis::new([(1 << 62) - 57, (1 << 62) - 87, (1 << 62) - 117, (1 << 62) - 143, 1 << 40]).unwrap();
+        let xs = [
+            Integer::new(),
+            Integer::from(-1),
+            ModuloMachine::create_large_input(300, 0) - 1u32,
+            -ModuloMachine::create_large_input(299, 12345),
+            Integer::from(basis.dynamic_range() - 1u32),
+            Integer::from(u64::MAX),
+        ];
+        let channels = basis.channels(&xs);
+        assert_eq!(channels.len(), 5);
+        for (i, x) in xs.iter().enumerate() {
+            let residues = basis.residues(x);
+            for (c, &m) in basis.moduli().iter().enumerate() {
+                let mut expected = Integer::from(x % m);
+                if expected < 0 {
+                    expected += m;
+                }
+                assert_eq!(residues[c], expected, "{} mod {}", x, m);
+                assert_eq!(channels[c][i], residues[c]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_matches_machine() {
+        let basis = RnsBasis::new([(1 << 62) - 57, (1 << 62) - 87, (1 << 62) - 117, (1 << 62) - 143, (1 << 62) - 153, 1 << 40]).unwrap();
+        assert!(*basis.dynamic_range() > ModuloMachine::create_large_input(300, 0));
+        let xs: Vec<Integer> = (0..50).map(|seed| ModuloMachine::create_large_input(300, seed)).collect();
+        let mut machine = ModuloMachine::new();
+        let expected = machine.process_values(&xs);
+        let channels = basis.channels(&xs);
+        for (i, x) in xs.iter().enumerate() {
+            let residues: Vec<u64> = channels.iter().map(|channel| channel[i]).collect();
+            let combined = basis.combine(&residues).unwrap();
+            assert_eq!(combined, *x);
+            assert_eq!(combined % machine.get_prime(), expected[i]);
+        }
+        // Negative operands come back as their representative in [0, M)
+        assert_eq!(basis.combine(&basis.residues(&Integer::from(-5))).unwrap(), Integer::from(basis.dynamic_range() - 5u32));
+        assert_eq!(basis.combine(&[1, 2]), Err(CrtError::LengthMismatch { expected: 6, found: 2 }));
+    }
+
+    #[test]
+    fn test_crt_combine() {
+        let pairs = |v: &[(i64, u64)]| v.iter().map(|&(r, m)| (Integer::from(r), Integer::from(m))).collect::<Vec<_>>();
+        assert_eq!(crt_combine(&pairs(&[(2, 3), (3, 5), (2, 7)])), Ok(Integer::from(23)));
+        // Non-coprime moduli with consistent residues, out-of-range and negative residues
+        assert_eq!(crt_combine(&pairs(&[(3, 4), (5, 6)])), Ok(Integer::from(11)));
+        assert_eq!(crt_combine(&pairs(&[(11, 4), (-1, 6), (1, 1)])), Ok(Integer::from(11)));
+        assert_eq!(crt_combine(&pairs(&[(5, 12), (1, 4)])), Ok(Integer::from(5)));
+
+        assert_eq!(crt_combine(&[]), Err(CrtError::Empty));
+        assert_eq!(
+            crt_combine(&pairs(&[(1, 3), (0, 0)])),
+            Err(CrtError::InvalidModulus { index: 1, modulus: Integer::new() })
+        );
+        let err = crt_combine(&pairs(&[(1, 4), (2, 5), (2, 6)])).unwrap_err();
+        assert_eq!(err, CrtError::Incompatible { index: 2, residue: Integer::from(2), modulus: Integer::from(6), gcd: Integer::from(2) });
+        assert_eq!(err.to_string(), "congruence 2 (x = 2 mod 6) contradicts the earlier ones modulo their common factor 2");
+    }
+
+    #[test]
+    fn test_rejects_bad_moduli() {
+        assert_eq!(RnsBasis::new([]), Err(RnsError::Empty));
+        assert_eq!(RnsBasis::new([3, 1, 5]), Err(RnsError::TooSmall { index: 1, modulus: 1 }));
+        assert_eq!(RnsBasis::new([15, 7, 21]), Err(RnsError::NotCoprime { first: 15, second: 21, gcd: 3 }));
+        assert_eq!(RnsBasis::new(vec![7, 7]).unwrap_err().to_string(), "channel moduli 7 and 7 share the factor 7");
+    }
+}
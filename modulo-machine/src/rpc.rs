@@ -0,0 +1,420 @@
+//! Line-delimited JSON-RPC 2.0 server driving a golden-model machine.
+//!
+//! Each TCP connection gets its own [`RpcSession`]; every request is a single
+//! JSON object on its own line and is answered by a single line. Big integers
+//! travel as strings, decimal or `0x`-prefixed hex, and are returned as
+//! decimal strings.
+//!
+//! | Method           | Params                                  | Result                       |
+//! |------------------|-----------------------------------------|------------------------------|
+//! | `tick`           | `{clk, reset, x}`                       | `{output}`                   |
+//! | `batch`          | `{ticks: [{clk, reset, x}, ...]}`       | `{outputs}`                  |
+//! | `process_values` | `{values: [x, ...]}`                    | `{outputs}`                  |
+//! | `reset`          | none                                    | `{output}`                   |
+//! | `snapshot`       | none                                    | machine configuration/state  |
+//! | `checkpoint`     | none                                    | `{id}`                       |
+//! | `restore`        | `{id}`                                  | `{output}`                   |
+//! | `stats`          | none                                    | [`MachineStats`] fields      |
+//! | `reset_stats`    | none                                    | `null`                       |
+//!
+//! `restore` returns the machine to the state saved by `checkpoint` with that
+//! id, so a client can fork several stimuli from one point. Checkpoints live
+//! as long as the session.
+//!
+//! Servers with extra methods reuse the envelope handling through
+//! [`respond`] and [`serve_stream`], delegating the methods above to
+//! [`RpcSession::dispatch`].
+
+use crate::state::MachineState;
+use crate::{Datapath, EdgeMode, MachineStats, ModuloMachine, ResetKind, ResetValue, SignedMode};
+use rug::Integer;
+use serde_json::{json, Map, Value};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+
+/// JSON-RPC error object
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(INVALID_PARAMS, message)
+    }
+}
+
+/// One client's machine plus its request handling
+pub struct RpcSession {
+    machine: ModuloMachine,
+    clk_prev: bool,
+    checkpoints: Vec<MachineState>,
+}
+
+impl RpcSession {
+    pub fn new() -> Self {
+        Self::with_machine(ModuloMachine::new())
+    }
+
+    /// Serve requests against a pre-configured machine
+    pub fn with_machine(machine: ModuloMachine) -> Self {
+        Self {
+            machine,
+            clk_prev: false,
+            checkpoints: Vec::new(),
+        }
+    }
+
+    pub fn machine(&self) -> &ModuloMachine {
+        &self.machine
+    }
+
+    /// The session machine's counters
+    pub fn stats(&self) -> MachineStats {
+        self.machine.stats()
+    }
+
+    /// Handle one request line and produce the response line
+    pub fn handle_line(&mut self, line: &str) -> String {
+        respond(line, |method, params| self.dispatch(method, params))
+    }
+
+    /// Run one method call, without the JSON-RPC envelope
+    pub fn dispatch(&mut self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        match method {
+            "tick" => {
+                let (clk, reset, x) = parse_tick(params)?;
+                Ok(json!({ "output": self.tick(clk, reset, &x) }))
+            }
+            "batch" => {
+                let ticks = field(params, "ticks")?
+                    .as_array()
+                    .ok_or_else(|| RpcError::invalid_params("ticks must be an array"))?;
+                let ticks = ticks.iter().map(parse_tick).collect::<Result<Vec<_>, _>>()?;
+                let outputs: Vec<String> = ticks
+                    .iter()
+                    .map(|(clk, reset, x)| self.tick(*clk, *reset, x))
+                    .collect();
+                Ok(json!({ "outputs": outputs }))
+            }
+            "process_values" => {
+                let values = field(params, "values")?
+                    .as_array()
+                    .ok_or_else(|| RpcError::invalid_params("values must be an array"))?;
+                let values = values.iter().map(parse_integer).collect::<Result<Vec<_>, _>>()?;
+                let mut outputs = Vec::with_capacity(values.len());
+                for x in &values {
+                    self.tick(false, false, x);
+                    outputs.push(self.tick(true, false, x));
+                }
+                Ok(json!({ "outputs": outputs }))
+            }
+            "reset" => {
+                let zero = Integer::new();
+                Ok(json!({ "output": self.tick(false, true, &zero) }))
+            }
+            "snapshot" => Ok(self.snapshot()),
+            "checkpoint" => {
+                self.checkpoints.push(self.machine.snapshot());
+                Ok(json!({ "id": self.checkpoints.len() - 1 }))
+            }
+            "restore" => {
+                let id = field(params, "id")?
+                    .as_u64()
+                    .ok_or_else(|| RpcError::invalid_params("id must be a non-negative integer"))?;
+                let state = self
+                    .checkpoints
+                    .get(id as usize)
+                    .ok_or_else(|| RpcError::invalid_params(format!("no checkpoint {}", id)))?;
+                self.machine.restore(state);
+                self.clk_prev = state.clk();
+                Ok(json!({ "output": self.machine.get_output().to_string() }))
+            }
+            "stats" => {
+                let stats = self.machine.stats();
+                Ok(json!({
+                    "ticks": stats.ticks,
+                    "rising_edges": stats.rising_edges,
+                    "resets": stats.resets,
+                    "reductions": stats.reductions,
+                    "max_input_bits": stats.max_input_bits,
+                    "strategy": stats.strategy.name(),
+                }))
+            }
+            "reset_stats" => {
+                self.machine.reset_stats();
+                Ok(Value::Null)
+            }
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("unknown method '{}'", method))),
+        }
+    }
+
+    fn tick(&mut self, clk: bool, reset: bool, x: &Integer) -> String {
+        self.clk_prev = clk && !reset;
+        self.machine.tick(clk, reset, x).to_string()
+    }
+
+    fn snapshot(&self) -> Value {
+        let reset_value = match self.machine.reset_value() {
+            ResetValue::Zero => json!("zero"),
+            ResetValue::AllOnes => json!("all_ones"),
+            ResetValue::Custom(x) => json!(x.to_string()),
+        };
+        let strategy = self.machine.reduction_strategy().name();
+
+        let datapath = match self.machine.datapath() {
+            Datapath::Reduce => json!("reduce"),
+            Datapath::Accumulate => json!("accumulate"),
+            Datapath::Mac(a) => json!({ "mac": a.to_string() }),
+        };
+
+        json!({
+            "output": self.machine.get_output().to_string(),
+            "clk": self.clk_prev,
+            "modulus": self.machine.get_prime().to_string(),
+            "datapath": datapath,
+            "signed_mode": match self.machine.signed_mode() {
+                SignedMode::Truncated => "truncated",
+                SignedMode::Euclidean => "euclidean",
+            },
+            "reset_value": reset_value,
+            "strategy": strategy,
+            "strict": self.machine.is_strict(),
+            "reduction_cycles": self.machine.reduction_cycles(),
+            "pipeline_depth": self.machine.pipeline_depth(),
+            "output_valid": self.machine.output_valid(),
+            "reset_kind": match self.machine.reset_kind() {
+                ResetKind::Async => "async",
+                ResetKind::Sync => "sync",
+            },
+            "edge": match self.machine.edge_mode() {
+                EdgeMode::Rising => "rising",
+                EdgeMode::Falling => "falling",
+                EdgeMode::Both => "both",
+            },
+            "lazy_threshold": self.machine.lazy_threshold().map(Integer::to_string),
+            "canonical": self.machine.is_canonical(),
+            "time": self.machine.sim_time(),
+            "time_step": self.machine.time_step(),
+        })
+    }
+}
+
+impl Default for RpcSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parse a request line, run it through `dispatch` and produce the response
+/// line
+pub fn respond(line: &str, dispatch: impl FnOnce(&str, &Value) -> Result<Value, RpcError>) -> String {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return error_response(Value::Null, RpcError::new(PARSE_ERROR, e.to_string())),
+    };
+
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = match request.get("method").and_then(Value::as_str) {
+        Some(method) if request.get("jsonrpc") == Some(&json!("2.0")) => method,
+        _ => return error_response(id, RpcError::new(INVALID_REQUEST, "expected a JSON-RPC 2.0 request")),
+    };
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+    match dispatch(method, &params) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }).to_string(),
+        Err(e) => error_response(id, e),
+    }
+}
+
+fn error_response(id: Value, error: RpcError) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+    .to_string()
+}
+
+fn field<'a>(params: &'a Value, name: &str) -> Result<&'a Value, RpcError> {
+    params
+        .get(name)
+        .ok_or_else(|| RpcError::invalid_params(format!("missing '{}'", name)))
+}
+
+pub(crate) fn parse_tick(params: &Value) -> Result<(bool, bool, Integer), RpcError> {
+    let flag = |name: &str| -> Result<bool, RpcError> {
+        match params.get(name) {
+            None => Ok(false),
+            Some(v) => v
+                .as_bool()
+                .ok_or_else(|| RpcError::invalid_params(format!("'{}' must be a boolean", name))),
+        }
+    };
+    let x = match params.get("x") {
+        Some(x) => parse_integer(x)?,
+        None => Integer::new(),
+    };
+    Ok((flag("clk")?, flag("reset")?, x))
+}
+
+/// Accept strings as [`ModuloMachine::create_input`] reads them (decimal, or
+/// `0x`, `0o` or `0b` prefixed, with optional `_` separators), or plain JSON
+/// integers
+pub fn parse_integer(value: &Value) -> Result<Integer, RpcError> {
+    let parsed = match value {
+        Value::String(s) => ModuloMachine::create_input(s, 10).ok(),
+        Value::Number(n) => n.as_u64().map(Integer::from).or_else(|| n.as_i64().map(Integer::from)),
+        _ => None,
+    };
+    parsed.ok_or_else(|| RpcError::invalid_params(format!("not an integer: {}", value)))
+}
+
+/// Accept connections forever, serving each on its own thread and machine
+pub fn serve(listener: TcpListener) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        thread::spawn(move || {
+            if let Err(_e) = serve_connection(stream) {
+                sim_event!(warn, error = %_e, "rpc connection closed with error");
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Serve one client until it disconnects
+pub fn serve_connection(stream: TcpStream) -> io::Result<()> {
+    let mut session = RpcSession::new();
+    serve_stream(stream, |line| session.handle_line(line))
+}
+
+/// Answer each request line on `stream` with `handle` until the client
+/// disconnects
+pub fn serve_stream(stream: TcpStream, mut handle: impl FnMut(&str) -> String) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = handle(&line);
+        writer.write_all(response.as_bytes())?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Parse a response line, for clients and tests
+pub fn parse_response(line: &str) -> Result<Value, RpcError> {
+    let mut response: Map<String, Value> = serde_json::from_str(line)
+        .map_err(|e| RpcError::new(PARSE_ERROR, e.to_string()))?;
+    if let Some(error) = response.remove("error") {
+        return Err(RpcError::new(
+            error["code"].as_i64().unwrap_or_default(),
+            error["message"].as_str().unwrap_or_default(),
+        ));
+    }
+    Ok(response.remove("result").unwrap_or(Value::Null))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(session: &mut RpcSession, method: &str, params: Value) -> Result<Value, RpcError> {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        parse_response(&session.handle_line(&request.to_string()))
+    }
+
+    #[test]
+    fn test_tick_and_batch() {
+        let mut session = RpcSession::new();
+        let p = session.machine().get_prime().clone();
+
+        let result = call(&mut session, "tick", json!({ "clk": true, "x": "12345" })).unwrap();
+        assert_eq!(result["output"], "12345");
+
+        let p_plus_2 = format!("0x{}", Integer::from(&p + 2u32).to_string_radix(16));
+        let result = call(
+            &mut session,
+            "batch",
+            json!({ "ticks": [{ "clk": false, "x": p_plus_2 }, { "clk": true, "x": p_plus_2 }] }),
+        )
+        .unwrap();
+        assert_eq!(result["outputs"], json!(["12345", "2"]));
+
+        let result = call(&mut session, "process_values", json!({ "values": [7, "8"] })).unwrap();
+        assert_eq!(result["outputs"], json!(["7", "8"]));
+
+        let stats = call(&mut session, "stats", Value::Null).unwrap();
+        assert_eq!(stats, json!({ "ticks": 7, "rising_edges": 4, "resets": 0, "reductions": 1, "max_input_bits": 256, "strategy": "gmp" }));
+        assert_eq!(call(&mut session, "reset_stats", Value::Null).unwrap(), Value::Null);
+        assert_eq!(session.stats(), MachineStats::default());
+
+        let snapshot = call(&mut session, "snapshot", Value::Null).unwrap();
+        assert_eq!(snapshot["output"], "8");
+        assert_eq!(snapshot["modulus"], p.to_string());
+    }
+
+    #[test]
+    fn test_checkpoint_and_restore() {
+        let mut session = RpcSession::new();
+        call(&mut session, "process_values", json!({ "values": [7] })).unwrap();
+        let id = call(&mut session, "checkpoint", Value::Null).unwrap()["id"].clone();
+
+        let first = call(&mut session, "process_values", json!({ "values": [100] })).unwrap();
+        assert_eq!(call(&mut session, "restore", json!({ "id": id })).unwrap()["output"], "7");
+        let second = call(&mut session, "process_values", json!({ "values": [100] })).unwrap();
+        assert_eq!(first, second);
+
+        assert_eq!(call(&mut session, "restore", json!({ "id": 5 })).unwrap_err().code, INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut session = RpcSession::new();
+        assert_eq!(parse_response(&session.handle_line("{not json")).unwrap_err().code, PARSE_ERROR);
+        assert_eq!(
+            parse_response(&session.handle_line(r#"{"id": 1, "method": "tick"}"#)).unwrap_err().code,
+            INVALID_REQUEST
+        );
+        assert_eq!(call(&mut session, "frobnicate", Value::Null).unwrap_err().code, METHOD_NOT_FOUND);
+        assert_eq!(
+            call(&mut session, "tick", json!({ "clk": true, "x": "zz" })).unwrap_err().code,
+            INVALID_PARAMS
+        );
+    }
+
+    #[test]
+    fn test_tcp_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener));
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        writeln!(writer, r#"{{"jsonrpc":"2.0","id":"a","method":"tick","params":{{"clk":true,"x":"42"}}}}"#).unwrap();
+
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let response: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(response["id"], "a");
+        assert_eq!(response["result"]["output"], "42");
+    }
+}
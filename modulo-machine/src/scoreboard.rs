@@ -0,0 +1,241 @@
+//! Lockstep comparison of two model implementations.
+//!
+//! A [`Scoreboard`] applies every tick to a reference and a device under
+//! test, both [`ModuloModel`]s (two backends, or the golden model and an
+//! adapter around an RTL simulation), and compares their outputs. The first
+//! divergence is kept with its full context: the input, both outputs before
+//! and after, and the ticks leading up to it.
+
+use crate::model::ModuloModel;
+use crate::TickInput;
+use rug::Integer;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Ticks of history kept by default
+pub const DEFAULT_CONTEXT: usize = 8;
+
+/// One compared tick
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoreboardEntry {
+    pub tick: u64,
+    pub input: TickInput,
+    /// Reference output
+    pub expected: Integer,
+    /// DUT output
+    pub actual: Integer,
+}
+
+/// The first tick on which the models disagreed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoreboardMismatch {
+    /// The failing tick
+    pub entry: ScoreboardEntry,
+    /// Simulation time of the failing tick, in the reference's time steps
+    pub time: u64,
+    /// Reference and DUT outputs before the failing tick
+    pub expected_before: Integer,
+    pub actual_before: Integer,
+    /// Preceding ticks, oldest first, all of which agreed
+    pub history: Vec<ScoreboardEntry>,
+}
+
+impl fmt::Display for ScoreboardMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let e = &self.entry;
+        writeln!(f, "divergence at tick {} (time {})", e.tick, self.time)?;
+        writeln!(f, "  input:     clk={} reset={} x=0x{}", e.input.clk as u8, e.input.reset as u8, e.input.x.to_string_radix(16))?;
+        writeln!(f, "  before:    reference=0x{} dut=0x{}", self.expected_before.to_string_radix(16), self.actual_before.to_string_radix(16))?;
+        writeln!(f, "  expected:  0x{}", e.expected.to_string_radix(16))?;
+        write!(f, "  actual:    0x{}", e.actual.to_string_radix(16))?;
+        for h in &self.history {
+            write!(f, "\n  tick {:>6}: clk={} reset={} x=0x{} o=0x{}", h.tick, h.input.clk as u8, h.input.reset as u8, h.input.x.to_string_radix(16), h.expected.to_string_radix(16))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ScoreboardMismatch {}
+
+/// Drives a reference and a DUT in lockstep
+pub struct Scoreboard<R: ModuloModel, D: ModuloModel> {
+    reference: R,
+    dut: D,
+    context: usize,
+    history: VecDeque<ScoreboardEntry>,
+    ticks: u64,
+    mismatch: Option<ScoreboardMismatch>,
+}
+
+impl<R: ModuloModel, D: ModuloModel> Scoreboard<R, D> {
+    /// Compare `dut` against `reference` from their current states
+    pub fn new(reference: R, dut: D) -> Self {
+        Self {
+            reference,
+            dut,
+            context: DEFAULT_CONTEXT,
+            history: VecDeque::new(),
+            ticks: 0,
+            mismatch: None,
+        }
+    }
+
+    /// Keep `ticks` ticks of history for the mismatch report
+    pub fn with_context(mut self, ticks: usize) -> Self {
+        self.context = ticks;
+        self
+    }
+
+    /// Apply one tick to both models; returns whether their outputs agree
+    ///
+    /// Ticks after the first divergence are still applied, but only the
+    /// first divergence is reported.
+    pub fn tick(&mut self, clk: bool, reset: bool, x: &Integer) -> bool {
+        let expected_before = self.reference.output();
+        let actual_before = self.dut.output();
+        let entry = ScoreboardEntry {
+            tick: self.ticks,
+            input: TickInput::new(clk, reset, x.clone()),
+            expected: self.reference.tick(clk, reset, x),
+            actual: self.dut.tick(clk, reset, x),
+        };
+        self.ticks += 1;
+        let agrees = entry.expected == entry.actual;
+        if !agrees && self.mismatch.is_none() {
+            self.mismatch = Some(ScoreboardMismatch {
+                time: entry.tick * self.reference.time_step(),
+                expected_before,
+                actual_before,
+                history: self.history.iter().cloned().collect(),
+                entry: entry.clone(),
+            });
+        }
+        if self.context > 0 {
+            if self.history.len() == self.context {
+                self.history.pop_front();
+            }
+            self.history.push_back(entry);
+        }
+        agrees
+    }
+
+    /// Apply `stimulus` until the models first disagree
+    pub fn run(&mut self, stimulus: &[TickInput]) -> Result<(), &ScoreboardMismatch> {
+        for t in stimulus {
+            if !self.tick(t.clk, t.reset, &t.x) {
+                break;
+            }
+        }
+        match &self.mismatch {
+            Some(mismatch) => Err(mismatch),
+            None => Ok(()),
+        }
+    }
+
+    /// Reset both models and forget the history and any divergence
+    pub fn reset(&mut self) {
+        self.reference.reset();
+        self.dut.reset();
+        self.history.clear();
+        self.ticks = 0;
+        self.mismatch = None;
+    }
+
+    /// Ticks compared so far
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn first_mismatch(&self) -> Option<&ScoreboardMismatch> {
+        self.mismatch.as_ref()
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.mismatch.is_none()
+    }
+
+    pub fn reference(&self) -> &R {
+        &self.reference
+    }
+
+    pub fn dut(&self) -> &D {
+        &self.dut
+    }
+
+    /// Hand back both models
+    pub fn into_models(self) -> (R, D) {
+        (self.reference, self.dut)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ModuloMachine, ReductionStrategy};
+
+    /// DUT that drops the top input bit, as a mis-sized port would
+    struct Truncating(ModuloMachine);
+
+    impl ModuloModel for Truncating {
+        fn tick(&mut self, clk: bool, reset: bool, x: &Integer) -> Integer {
+            ModuloModel::tick(&mut self.0, clk, reset, &Integer::from(x.keep_bits_ref(299)))
+        }
+
+        fn output(&self) -> Integer {
+            self.0.get_output().clone()
+        }
+
+        fn reset(&mut self) {
+            self.0.reset()
+        }
+    }
+
+    fn stimulus() -> Vec<TickInput> {
+        let mut stimulus = Vec::new();
+        for i in 1..=20u32 {
+            let x = if i == 15 { Integer::from(1) << 299 } else { Integer::from(i * 1000) };
+            stimulus.push(TickInput::new(false, false, x.clone()));
+            stimulus.push(TickInput::new(true, false, x));
+        }
+        stimulus
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        let mut barrett = ModuloMachine::new();
+        assert!(barrett.set_reduction_strategy(ReductionStrategy::Barrett));
+        let mut scoreboard = Scoreboard::new(ModuloMachine::new(), barrett);
+        assert!(scoreboard.run(&stimulus()).is_ok());
+        assert_eq!(scoreboard.ticks(), 40);
+        assert!(scoreboard.is_clean());
+    }
+
+    #[test]
+    fn test_first_divergence_with_context() {
+        let mut reference = ModuloMachine::new();
+        reference.set_time_step(10);
+        let mut scoreboard = Scoreboard::new(reference, Truncating(ModuloMachine::new())).with_context(3);
+        let mismatch = scoreboard.run(&stimulus()).unwrap_err().clone();
+        // Stops at the rising edge of the 15th value
+        assert_eq!(scoreboard.ticks(), 30);
+        assert_eq!(mismatch.entry.tick, 29);
+        assert_eq!(mismatch.time, 290);
+        assert_eq!(mismatch.entry.actual, 0);
+        assert_eq!(mismatch.expected_before, 14000);
+        assert_eq!(mismatch.actual_before, 14000);
+        let ticks: Vec<u64> = mismatch.history.iter().map(|h| h.tick).collect();
+        assert_eq!(ticks, [26, 27, 28]);
+        let report = mismatch.to_string();
+        assert!(report.starts_with("divergence at tick 29 (time 290)\n"));
+        assert_eq!(report.lines().count(), 8);
+
+        // Later divergences do not replace the first
+        let x = (Integer::from(1) << 299) + 5u32;
+        scoreboard.tick(false, false, &x);
+        assert!(!scoreboard.tick(true, false, &x));
+        assert_eq!(scoreboard.first_mismatch(), Some(&mismatch));
+        scoreboard.reset();
+        assert!(scoreboard.is_clean());
+        assert_eq!(scoreboard.ticks(), 0);
+    }
+}
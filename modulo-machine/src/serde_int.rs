@@ -0,0 +1,108 @@
+//! Big integers as strings for the `serde` feature.
+//!
+//! Values are written as decimal strings and read back from decimal or
+//! `0x`-prefixed hex, with an optional leading `-`, the same encodings the
+//! JSON-RPC server accepts. Strings keep full precision in formats whose
+//! numbers are 64-bit (JSON, TOML).
+//!
+//! Use with `#[serde(with = "crate::serde_int")]`, or the submodules for
+//! optional values and sequences of them.
+
+use rug::Integer;
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+/// Serializes the borrowed value as a decimal string
+struct Str<'a>(&'a Integer);
+
+impl Serialize for Str<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self.0)
+    }
+}
+
+/// Deserializes a decimal or hex string
+struct Parsed(Integer);
+
+impl<'de> Deserialize<'de> for Parsed {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        parse(&s).map(Parsed).ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(&s), &"a decimal or 0x-prefixed hex integer"))
+    }
+}
+
+fn parse(s: &str) -> Option<Integer> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let (radix, body) = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => (16, hex),
+        None => (10, digits),
+    };
+    // Only digits: from_str_radix would also take a sign or underscores
+    if body.is_empty() || !body.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    let magnitude = Integer::from_str_radix(body, radix as i32).ok()?;
+    Some(if negative { -magnitude } else { magnitude })
+}
+
+pub fn serialize<S: Serializer>(value: &Integer, serializer: S) -> Result<S::Ok, S::Error> {
+    Str(value).serialize(serializer)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Integer, D::Error> {
+    Parsed::deserialize(deserializer).map(|parsed| parsed.0)
+}
+
+/// `Option<Integer>`, with `None` as null
+pub mod option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(value: &Option<Integer>, serializer: S) -> Result<S::Ok, S::Error> {
+        value.as_ref().map(Str).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Integer>, D::Error> {
+        Option::<Parsed>::deserialize(deserializer).map(|value| value.map(|parsed| parsed.0))
+    }
+}
+
+/// A sequence of `Option<Integer>`, such as the pipeline stages
+pub mod option_seq {
+    use super::*;
+
+    pub fn serialize<'a, S, I>(values: I, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        I: IntoIterator<Item = &'a Option<Integer>>,
+    {
+        serializer.collect_seq(values.into_iter().map(|value| value.as_ref().map(Str)))
+    }
+
+    pub fn deserialize<'de, D, C>(deserializer: D) -> Result<C, D::Error>
+    where
+        D: Deserializer<'de>,
+        C: FromIterator<Option<Integer>>,
+    {
+        let values = Vec::<Option<Parsed>>::deserialize(deserializer)?;
+        Ok(values.into_iter().map(|value| value.map(|parsed| parsed.0)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("12345"), Some(Integer::from(12345)));
+        assert_eq!(parse("0xff"), Some(Integer::from(255)));
+        assert_eq!(parse("-0x10"), Some(Integer::from(-16)));
+        assert_eq!(parse("-7"), Some(Integer::from(-7)));
+        for bad in ["", "0x", "--1", "-+1", "+1", "0x+5", "1_000", "12a", "0xg"] {
+            assert_eq!(parse(bad), None, "{:?}", bad);
+        }
+    }
+}
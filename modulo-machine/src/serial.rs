@@ -0,0 +1,603 @@
+//! Narrow-bus front ends for the machine.
+//!
+//! [`SerialInputMachine`] takes the 300-bit operand as 32- or 64-bit words,
+//! one per rising edge, in either word order; `last_word` on the final edge
+//! reduces the assembled operand and a short or overlong transfer is handled
+//! per [`ShortTransfer`]. [`OutputShifter`] serializes the 256-bit result the
+//! same way, flagging its final word with `last`. [`BitSerialMachine`] takes
+//! one bit per edge and reduces on `load_done`.
+
+use crate::{ModuloMachine, X_BITS};
+use rug::{Assign, Integer};
+use std::fmt;
+
+/// Width of the O bus the output shifter serializes
+const O_BITS: u32 = 256;
+
+/// Width of each word on the serial input bus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordWidth {
+    W32,
+    W64,
+}
+
+impl WordWidth {
+    pub fn bits(self) -> u32 {
+        match self {
+            WordWidth::W32 => 32,
+            WordWidth::W64 => 64,
+        }
+    }
+
+    /// Number of words needed to carry the full 300-bit operand
+    pub fn words_per_operand(self) -> u32 {
+        X_BITS.div_ceil(self.bits())
+    }
+
+    /// Number of words needed to carry the full 256-bit result
+    pub fn words_per_result(self) -> u32 {
+        O_BITS.div_ceil(self.bits())
+    }
+
+    fn mask(self, word: u64) -> u64 {
+        match self {
+            WordWidth::W32 => word & 0xFFFF_FFFF,
+            WordWidth::W64 => word,
+        }
+    }
+}
+
+/// Order in which the words of X arrive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordOrder {
+    /// Least significant word first
+    LswFirst,
+    /// Most significant word first
+    MswFirst,
+}
+
+/// What a transfer ended by `last_word` before a full operand does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShortTransfer {
+    /// Reduce the words received; the missing words are the most
+    /// significant and read as zero
+    #[default]
+    ZeroExtend,
+    /// Drop the partial operand and hold the output
+    Discard,
+}
+
+/// Transfer-length error reported by [`SerialInputMachine::tick_checked`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SerialError {
+    /// `last_word` arrived with fewer words than an operand needs
+    Underflow { words: u32, expected: u32 },
+    /// More words than an operand needs; the excess falls off the register
+    Overflow { words: u32, expected: u32 },
+}
+
+impl fmt::Display for SerialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerialError::Underflow { words, expected } => {
+                write!(f, "transfer ended after {} of {} words", words, expected)
+            }
+            SerialError::Overflow { words, expected } => {
+                write!(f, "transfer reached {} words, more than the {} an operand takes", words, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SerialError {}
+
+/// One beat on the serial output bus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputWord {
+    pub word: u64,
+    /// Set on the final word of the result (`out_last`)
+    pub last: bool,
+}
+
+/// Shift register that serializes a 256-bit result onto a narrow output bus
+///
+/// `load` presents the first word immediately; each `advance` presents the
+/// next one. After the word flagged `last` the bus goes idle until the next
+/// load. Loading while a transfer is in flight restarts with the new result.
+#[derive(Debug, Clone)]
+pub struct OutputShifter {
+    width: WordWidth,
+    order: WordOrder,
+    words: Vec<u64>,
+    next: usize,
+    current: Option<OutputWord>,
+}
+
+impl OutputShifter {
+    pub fn new(width: WordWidth, order: WordOrder) -> Self {
+        Self {
+            width,
+            order,
+            words: Vec::new(),
+            next: 0,
+            current: None,
+        }
+    }
+
+    /// Capture a new result and drive its first word
+    pub fn load(&mut self, value: &Integer) {
+        self.words = split_words(value, O_BITS, self.width, self.order);
+        self.next = 0;
+        self.advance();
+    }
+
+    /// Shift the next word onto the bus, or go idle after the last one
+    pub fn advance(&mut self) {
+        self.current = self.words.get(self.next).map(|&word| OutputWord {
+            word,
+            last: self.next + 1 == self.words.len(),
+        });
+        if self.current.is_some() {
+            self.next += 1;
+        }
+    }
+
+    /// Word currently driven on the output bus, `None` when idle
+    pub fn current(&self) -> Option<OutputWord> {
+        self.current
+    }
+
+    pub fn reset(&mut self) {
+        self.words.clear();
+        self.next = 0;
+        self.current = None;
+    }
+}
+
+/// Split the low `total_bits` of `x` into bus words in transfer order
+fn split_words(x: &Integer, total_bits: u32, width: WordWidth, order: WordOrder) -> Vec<u64> {
+    let bits = width.bits();
+    let mut words: Vec<u64> = (0..total_bits.div_ceil(bits))
+        .map(|i| Integer::from(x >> (i * bits)).keep_bits(bits).to_u64_wrapping())
+        .collect();
+    if order == WordOrder::MswFirst {
+        words.reverse();
+    }
+    words
+}
+
+/// Modulo machine fed over a narrow bus, one word per rising edge
+///
+/// Each rising edge shifts `word` into the operand register. When `last_word`
+/// is asserted on the same edge the assembled operand (truncated to 300 bits)
+/// is reduced and latched into the output, and the operand register is cleared
+/// for the next transfer.
+///
+/// With [`SerialInputMachine::with_serial_output`] the result is also shifted
+/// out one word per rising edge, starting on the edge that latched it.
+///
+/// A transfer shorter than [`WordWidth::words_per_operand`] is handled per
+/// [`ShortTransfer`]; [`SerialInputMachine::tick_checked`] reports short and
+/// overlong transfers.
+pub struct SerialInputMachine {
+    machine: ModuloMachine,
+    width: WordWidth,
+    order: WordOrder,
+    /// Operand assembled so far
+    operand: Integer,
+    /// Words shifted in since the last reduction or reset
+    words_loaded: u32,
+    clk_prev: bool,
+    short_transfer: ShortTransfer,
+    /// Optional serializer for the result
+    shifter: Option<OutputShifter>,
+}
+
+impl SerialInputMachine {
+    pub fn new(width: WordWidth, order: WordOrder) -> Self {
+        Self::with_machine(ModuloMachine::new(), width, order)
+    }
+
+    pub fn with_machine(machine: ModuloMachine, width: WordWidth, order: WordOrder) -> Self {
+        Self {
+            machine,
+            width,
+            order,
+            operand: Integer::new(),
+            words_loaded: 0,
+            clk_prev: false,
+            short_transfer: ShortTransfer::ZeroExtend,
+            shifter: None,
+        }
+    }
+
+    /// Choose what a transfer shorter than a full operand does
+    pub fn with_short_transfer(mut self, policy: ShortTransfer) -> Self {
+        self.short_transfer = policy;
+        self
+    }
+
+    /// Also shift each result out over a narrow output bus
+    pub fn with_serial_output(mut self, width: WordWidth, order: WordOrder) -> Self {
+        self.shifter = Some(OutputShifter::new(width, order));
+        self
+    }
+
+    /// Process one clock cycle
+    /// - clk: clock input (1 bit)
+    /// - reset: reset input (1 bit), clears the output and any partial operand
+    /// - word: input word, bits above the configured width are ignored
+    /// - last_word: strobe marking the final word of the operand
+    ///
+    /// Returns: current output (256 bits max)
+    pub fn tick(&mut self, clk: bool, reset: bool, word: u64, last_word: bool) -> &Integer {
+        if reset {
+            self.reset();
+            return self.machine.get_output();
+        }
+
+        if clk && !self.clk_prev {
+            self.shift_in(word);
+            if last_word {
+                let short = self.words_loaded < self.width.words_per_operand();
+                if !(short && self.short_transfer == ShortTransfer::Discard) {
+                    self.operand.keep_bits_mut(X_BITS);
+                    let result = self.machine.cycle(&self.operand);
+                    if let Some(shifter) = &mut self.shifter {
+                        shifter.load(result);
+                    }
+                }
+                self.operand.assign(0);
+                self.words_loaded = 0;
+            } else if let Some(shifter) = &mut self.shifter {
+                shifter.advance();
+            }
+        }
+
+        self.clk_prev = clk;
+        self.machine.get_output()
+    }
+
+    /// Like [`tick`], but report a transfer of the wrong length
+    ///
+    /// The tick is still applied, so an overlong transfer keeps shifting and
+    /// a short one is handled per [`ShortTransfer`].
+    ///
+    /// [`tick`]: SerialInputMachine::tick
+    pub fn tick_checked(&mut self, clk: bool, reset: bool, word: u64, last_word: bool) -> Result<&Integer, SerialError> {
+        let expected = self.width.words_per_operand();
+        let words = self.words_loaded + 1;
+        let edge = clk && !self.clk_prev && !reset;
+        let checked = if edge && last_word && words < expected {
+            Err(SerialError::Underflow { words, expected })
+        } else if edge && words > expected {
+            Err(SerialError::Overflow { words, expected })
+        } else {
+            Ok(())
+        };
+        self.tick(clk, reset, word, last_word);
+        checked.map(|()| self.machine.get_output())
+    }
+
+    /// Reset the output register and discard any partially loaded operand
+    pub fn reset(&mut self) {
+        self.machine.reset();
+        self.operand.assign(0);
+        self.words_loaded = 0;
+        self.clk_prev = false;
+        if let Some(shifter) = &mut self.shifter {
+            shifter.reset();
+        }
+    }
+
+    /// Words shifted in since the last reduction or reset
+    pub fn words_loaded(&self) -> u32 {
+        self.words_loaded
+    }
+
+    pub fn get_output(&self) -> &Integer {
+        self.machine.get_output()
+    }
+
+    /// Word currently on the serial output bus (`None` when idle or disabled)
+    pub fn out_word(&self) -> Option<OutputWord> {
+        self.shifter.as_ref().and_then(OutputShifter::current)
+    }
+
+    /// Split `x` into bus words in this machine's transfer order
+    pub fn split_operand(&self, x: &Integer) -> Vec<u64> {
+        split_words(x, X_BITS, self.width, self.order)
+    }
+
+    fn shift_in(&mut self, word: u64) {
+        let bits = self.width.bits();
+        let word = self.width.mask(word);
+        match self.order {
+            WordOrder::LswFirst => {
+                self.operand += Integer::from(word) << (self.words_loaded * bits);
+            }
+            WordOrder::MswFirst => {
+                self.operand <<= bits;
+                self.operand += word;
+            }
+        }
+        self.words_loaded += 1;
+    }
+}
+
+/// Modulo machine fed one operand bit per rising edge
+///
+/// Each rising edge shifts `bit` into a 300-bit operand register, in the
+/// configured order (`MswFirst` for most significant bit first). A rising
+/// edge with `load_done` asserted shifts nothing: it reduces the operand
+/// assembled so far, latches the result and clears the register, modelling
+/// an area-optimized design with a one-bit input port.
+pub struct BitSerialMachine {
+    machine: ModuloMachine,
+    order: WordOrder,
+    operand: Integer,
+    bits_loaded: u32,
+    clk_prev: bool,
+}
+
+impl BitSerialMachine {
+    pub fn new(order: WordOrder) -> Self {
+        Self::with_machine(ModuloMachine::new(), order)
+    }
+
+    pub fn with_machine(machine: ModuloMachine, order: WordOrder) -> Self {
+        Self {
+            machine,
+            order,
+            operand: Integer::new(),
+            bits_loaded: 0,
+            clk_prev: false,
+        }
+    }
+
+    /// Process one clock cycle
+    /// - clk: clock input (1 bit)
+    /// - reset: reset input (1 bit), clears the output and any partial operand
+    /// - bit: serial input bit, shifted in on a rising edge without `load_done`
+    /// - load_done: reduce the assembled operand on this rising edge
+    ///
+    /// Returns: current output (256 bits max)
+    pub fn tick(&mut self, clk: bool, reset: bool, bit: bool, load_done: bool) -> &Integer {
+        if reset {
+            self.reset();
+            return self.machine.get_output();
+        }
+
+        if clk && !self.clk_prev {
+            if load_done {
+                self.machine.cycle(&self.operand);
+                self.operand.assign(0);
+                self.bits_loaded = 0;
+            } else {
+                self.shift_in(bit);
+            }
+        }
+
+        self.clk_prev = clk;
+        self.machine.get_output()
+    }
+
+    /// Reset the output register and discard any partially loaded operand
+    pub fn reset(&mut self) {
+        self.machine.reset();
+        self.operand.assign(0);
+        self.bits_loaded = 0;
+        self.clk_prev = false;
+    }
+
+    /// Bits shifted in since the last reduction or reset
+    pub fn bits_loaded(&self) -> u32 {
+        self.bits_loaded
+    }
+
+    pub fn get_output(&self) -> &Integer {
+        self.machine.get_output()
+    }
+
+    /// The 300 bits of `x` in this machine's shift order
+    pub fn split_operand(&self, x: &Integer) -> Vec<bool> {
+        let mut bits: Vec<bool> = (0..X_BITS).map(|i| x.get_bit(i)).collect();
+        if self.order == WordOrder::MswFirst {
+            bits.reverse();
+        }
+        bits
+    }
+
+    fn shift_in(&mut self, bit: bool) {
+        match self.order {
+            // Bits past the top of the register fall off
+            WordOrder::LswFirst => {
+                if bit && self.bits_loaded < X_BITS {
+                    self.operand.set_bit(self.bits_loaded, true);
+                }
+            }
+            WordOrder::MswFirst => {
+                self.operand <<= 1;
+                if bit {
+                    self.operand += 1u32;
+                }
+                self.operand.keep_bits_mut(X_BITS);
+            }
+        }
+        self.bits_loaded += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(machine: &mut SerialInputMachine, x: &Integer) -> Integer {
+        let words = machine.split_operand(x);
+        let last = words.len() - 1;
+        for (i, word) in words.into_iter().enumerate() {
+            machine.tick(false, false, 0, false);
+            machine.tick(true, false, word, i == last);
+        }
+        machine.get_output().clone()
+    }
+
+    #[test]
+    fn test_all_widths_and_orders() {
+        let x = ModuloMachine::create_large_input(299, 123456789);
+        let p = ModuloMachine::new().get_prime().clone();
+        let expected = Integer::from(&x % &p);
+
+        for width in [WordWidth::W32, WordWidth::W64] {
+            for order in [WordOrder::LswFirst, WordOrder::MswFirst] {
+                let mut machine = SerialInputMachine::new(width, order);
+                assert_eq!(load(&mut machine, &x), expected, "{:?} {:?}", width, order);
+                assert_eq!(machine.words_loaded(), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_output_holds_until_last_word() {
+        let mut machine = SerialInputMachine::new(WordWidth::W64, WordOrder::LswFirst);
+        load(&mut machine, &Integer::from(42u32));
+
+        machine.tick(false, false, 0, false);
+        machine.tick(true, false, 7, false);
+        assert_eq!(machine.words_loaded(), 1);
+        assert_eq!(*machine.get_output(), 42);
+
+        // Holding the clock high doesn't shift in another word
+        machine.tick(true, false, 9, true);
+        assert_eq!(machine.words_loaded(), 1);
+
+        machine.tick(false, false, 0, false);
+        machine.tick(true, false, 1, true);
+        assert_eq!(*machine.get_output(), (1u128 << 64) + 7);
+    }
+
+    #[test]
+    fn test_serial_output_round_trip() {
+        let x = ModuloMachine::create_large_input(299, 987654321);
+        let p = ModuloMachine::new().get_prime().clone();
+        let expected = Integer::from(&x % &p);
+
+        for width in [WordWidth::W32, WordWidth::W64] {
+            let mut machine = SerialInputMachine::new(WordWidth::W64, WordOrder::LswFirst)
+                .with_serial_output(width, WordOrder::LswFirst);
+            load(&mut machine, &x);
+
+            let mut words = Vec::new();
+            loop {
+                let beat = machine.out_word().expect("bus went idle before out_last");
+                words.push(beat.word);
+                if beat.last {
+                    break;
+                }
+                machine.tick(false, false, 0, false);
+                machine.tick(true, false, 0, false);
+            }
+            assert_eq!(words.len() as u32, width.words_per_result());
+
+            let mut result = Integer::new();
+            for (i, word) in words.iter().enumerate() {
+                result += Integer::from(*word) << (i as u32 * width.bits());
+            }
+            assert_eq!(result, expected);
+
+            // Bus idles after the last word
+            machine.tick(false, false, 0, false);
+            machine.tick(true, false, 0, false);
+            assert_eq!(machine.out_word(), None);
+        }
+    }
+
+    #[test]
+    fn test_output_shifter_msw_first() {
+        let mut shifter = OutputShifter::new(WordWidth::W64, WordOrder::MswFirst);
+        shifter.load(&(Integer::from(3u32) << 192u32));
+        assert_eq!(shifter.current(), Some(OutputWord { word: 3, last: false }));
+        for _ in 0..3 {
+            shifter.advance();
+        }
+        assert_eq!(shifter.current(), Some(OutputWord { word: 0, last: true }));
+    }
+
+    #[test]
+    fn test_bit_serial_load() {
+        let x = ModuloMachine::create_large_input(299, 123456789);
+        let p = ModuloMachine::new().get_prime().clone();
+        for order in [WordOrder::LswFirst, WordOrder::MswFirst] {
+            let mut machine = BitSerialMachine::new(order);
+            for bit in machine.split_operand(&x) {
+                machine.tick(false, false, false, false);
+                machine.tick(true, false, bit, false);
+            }
+            assert_eq!(machine.bits_loaded(), 300);
+            assert_eq!(*machine.get_output(), 0, "{:?}: no reduction before load_done", order);
+
+            machine.tick(false, false, false, false);
+            machine.tick(true, false, true, true);
+            assert_eq!(*machine.get_output(), Integer::from(&x % &p), "{:?}", order);
+            assert_eq!(machine.bits_loaded(), 0);
+        }
+
+        // Short MSB-first operand, then a reset mid-load
+        let mut machine = BitSerialMachine::new(WordOrder::MswFirst);
+        for bit in [true, false, true, true] {
+            machine.tick(false, false, false, false);
+            machine.tick(true, false, bit, false);
+        }
+        machine.tick(false, false, false, false);
+        assert_eq!(*machine.tick(true, false, false, true), 0b1011);
+        machine.tick(false, false, false, false);
+        machine.tick(true, false, true, false);
+        machine.tick(false, true, false, false);
+        assert_eq!(machine.bits_loaded(), 0);
+        assert_eq!(*machine.get_output(), 0);
+    }
+
+    #[test]
+    fn test_transfer_length_checks() {
+        let expected = WordWidth::W64.words_per_operand();
+        let mut machine = SerialInputMachine::new(WordWidth::W64, WordOrder::MswFirst);
+        let edge = |machine: &mut SerialInputMachine, word, last| {
+            machine.tick(false, false, 0, false);
+            machine.tick_checked(true, false, word, last).cloned()
+        };
+
+        // Two-word transfer: reported, but reduced zero-extended by default
+        edge(&mut machine, 1, false).unwrap();
+        assert_eq!(edge(&mut machine, 2, true), Err(SerialError::Underflow { words: 2, expected }));
+        assert_eq!(*machine.get_output(), (1u128 << 64) + 2);
+
+        // A full transfer is fine; one word more overflows
+        for _ in 1..expected {
+            edge(&mut machine, 0, false).unwrap();
+        }
+        assert_eq!(edge(&mut machine, 7, true), Ok(Integer::from(7u32)));
+        for _ in 0..expected {
+            edge(&mut machine, 0, false).unwrap();
+        }
+        assert_eq!(edge(&mut machine, 9, true), Err(SerialError::Overflow { words: expected + 1, expected }));
+
+        // Discard drops short transfers and holds the output
+        let mut machine = SerialInputMachine::new(WordWidth::W32, WordOrder::LswFirst).with_short_transfer(ShortTransfer::Discard);
+        edge(&mut machine, 5, true).unwrap_err();
+        assert_eq!(*machine.get_output(), 0);
+        assert_eq!(machine.words_loaded(), 0);
+    }
+
+    #[test]
+    fn test_reset_discards_partial_operand() {
+        let mut machine = SerialInputMachine::new(WordWidth::W32, WordOrder::MswFirst);
+        machine.tick(true, false, 0xAAAA, false);
+        machine.tick(false, true, 0, false);
+        assert_eq!(machine.words_loaded(), 0);
+        assert_eq!(*machine.get_output(), 0);
+
+        // 32-bit mode ignores the upper half of the word
+        machine.tick(true, false, 0xFFFF_FFFF_0000_0005, true);
+        assert_eq!(*machine.get_output(), 5);
+    }
+}
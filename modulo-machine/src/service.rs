@@ -0,0 +1,286 @@
+//! HTTP/JSON service giving remote clients session-scoped machines (`service`
+//! feature).
+//!
+//! Where [`rpc`](crate::rpc) ties a machine to one TCP connection, the
+//! service keeps machines in server-side sessions that outlive connections,
+//! so a regression farm can share one authoritative golden model:
+//!
+//! | Request                          | Body         | Response                             |
+//! |----------------------------------|--------------|--------------------------------------|
+//! | `POST /sessions`                 | none         | `201 {session}`                      |
+//! | `GET /sessions/{id}`             | none         | the session's `snapshot`             |
+//! | `POST /sessions/{id}/{method}`   | `params`     | the result of the JSON-RPC `method`  |
+//! | `DELETE /sessions/{id}`          | none         | `204`                                |
+//!
+//! `{method}` is any [`RpcSession::dispatch`] method (`tick`, `batch`,
+//! `process_values`, `reset`, `checkpoint`, `restore`, `stats`, ...) with the
+//! same params and results. Errors are `{"error": {code, message}}` with
+//! status 400, or 404 for unknown sessions, methods and paths.
+//!
+//! Each session has its own lock, so clients driving different sessions run
+//! in parallel; requests on one session are applied in arrival order.
+
+use crate::rpc::{RpcError, RpcSession, INVALID_REQUEST, METHOD_NOT_FOUND};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Largest request body accepted, to bound memory per request
+pub const MAX_BODY: usize = 64 << 20;
+
+/// Status and JSON body of a response; `Value::Null` sends no body
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, error: RpcError) -> Self {
+        Self {
+            status,
+            body: json!({ "error": { "code": error.code, "message": error.message } }),
+        }
+    }
+
+    fn not_found(message: impl Into<String>) -> Self {
+        Self::error(404, RpcError::new(METHOD_NOT_FOUND, message))
+    }
+}
+
+/// Session table shared by every connection
+#[derive(Default)]
+pub struct Service {
+    sessions: Mutex<HashMap<u64, Arc<Mutex<RpcSession>>>>,
+    next_id: AtomicU64,
+}
+
+impl Service {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of live sessions
+    pub fn sessions(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    /// Route one request; `body` is the raw request body
+    pub fn handle(&self, method: &str, path: &str, body: &str) -> Response {
+        let path = path.split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        match (method, segments.as_slice()) {
+            ("POST", ["sessions"]) => {
+                let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                self.sessions.lock().unwrap().insert(id, Arc::new(Mutex::new(RpcSession::new())));
+                Response {
+                    status: 201,
+                    body: json!({ "session": id.to_string() }),
+                }
+            }
+            ("GET", ["sessions", id]) => self.call(id, "snapshot", Value::Null),
+            ("DELETE", ["sessions", id]) => match id.parse().ok().and_then(|id: u64| self.sessions.lock().unwrap().remove(&id)) {
+                Some(_) => Response { status: 204, body: Value::Null },
+                None => Response::not_found(format!("no session {}", id)),
+            },
+            ("POST", ["sessions", id, rpc_method]) => {
+                let params = if body.trim().is_empty() {
+                    Value::Null
+                } else {
+                    match serde_json::from_str(body) {
+                        Ok(params) => params,
+                        Err(e) => return Response::error(400, RpcError::invalid_params(e.to_string())),
+                    }
+                };
+                self.call(id, rpc_method, params)
+            }
+            _ => Response::not_found(format!("no route for {} {}", method, path)),
+        }
+    }
+
+    fn call(&self, id: &str, method: &str, params: Value) -> Response {
+        let session = id.parse().ok().and_then(|id: u64| self.sessions.lock().unwrap().get(&id).cloned());
+        let Some(session) = session else {
+            return Response::not_found(format!("no session {}", id));
+        };
+        let result = session.lock().unwrap().dispatch(method, &params);
+        match result {
+            Ok(result) => Response::ok(result),
+            Err(e) if e.code == METHOD_NOT_FOUND => Response::error(404, e),
+            Err(e) => Response::error(400, e),
+        }
+    }
+}
+
+/// Accept connections forever, serving each on its own thread against one
+/// shared [`Service`]
+pub fn serve(listener: TcpListener) -> io::Result<()> {
+    let service = Arc::new(Service::new());
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let service = Arc::clone(&service);
+        thread::spawn(move || {
+            if let Err(_e) = serve_connection(&service, stream) {
+                sim_event!(warn, error = %_e, "service connection closed with error");
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Serve HTTP/1.1 requests on one connection until the client closes it or
+/// asks to with `Connection: close`
+pub fn serve_connection(service: &Service, stream: TcpStream) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line)? == 0 {
+            return Ok(());
+        }
+        let mut parts = request_line.split_whitespace();
+        let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+            return write_response(&mut writer, &Response::error(400, RpcError::new(INVALID_REQUEST, "malformed request line")), true);
+        };
+
+        let mut content_length = 0;
+        let mut close = false;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 {
+                return Ok(());
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                let value = value.trim();
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.parse().map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "bad Content-Length"))?;
+                } else if name.eq_ignore_ascii_case("connection") {
+                    close = value.eq_ignore_ascii_case("close");
+                }
+            }
+        }
+        if content_length > MAX_BODY {
+            let error = RpcError::new(INVALID_REQUEST, format!("body larger than {} bytes", MAX_BODY));
+            return write_response(&mut writer, &Response::error(413, error), true);
+        }
+
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+        let response = match String::from_utf8(body) {
+            Ok(body) => service.handle(method, path, &body),
+            Err(_) => Response::error(400, RpcError::invalid_params("body is not UTF-8")),
+        };
+        write_response(&mut writer, &response, close)?;
+        if close {
+            return Ok(());
+        }
+    }
+}
+
+fn write_response(writer: &mut impl Write, response: &Response, close: bool) -> io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        413 => "Payload Too Large",
+        _ => "",
+    };
+    let body = if response.body.is_null() { String::new() } else { response.body.to_string() };
+    write!(writer, "HTTP/1.1 {} {}\r\n", response.status, reason)?;
+    if !body.is_empty() {
+        write!(writer, "Content-Type: application/json\r\n")?;
+    }
+    if close {
+        write!(writer, "Connection: close\r\n")?;
+    }
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::INVALID_PARAMS;
+
+    #[test]
+    fn test_session_lifecycle() {
+        let service = Service::new();
+        let created = service.handle("POST", "/sessions", "");
+        assert_eq!(created.status, 201);
+        let id = created.body["session"].as_str().unwrap().to_string();
+        let other = service.handle("POST", "/sessions", "").body["session"].as_str().unwrap().to_string();
+        assert_ne!(id, other);
+
+        let tick = service.handle("POST", &format!("/sessions/{}/tick", id), r#"{"clk": true, "x": "0x1f"}"#);
+        assert_eq!(tick, Response::ok(json!({ "output": "31" })));
+        let batch = service.handle("POST", &format!("/sessions/{}/process_values", id), r#"{"values": [5, 6]}"#);
+        assert_eq!(batch.body["outputs"], json!(["5", "6"]));
+
+        // Sessions are independent
+        assert_eq!(service.handle("GET", &format!("/sessions/{}", id), "").body["output"], "6");
+        assert_eq!(service.handle("GET", &format!("/sessions/{}", other), "").body["output"], "0");
+
+        let bad = service.handle("POST", &format!("/sessions/{}/tick", id), r#"{"x": "zz"}"#);
+        assert_eq!((bad.status, bad.body["error"]["code"].as_i64()), (400, Some(INVALID_PARAMS)));
+        assert_eq!(service.handle("POST", &format!("/sessions/{}/tick", id), "{").status, 400);
+        assert_eq!(service.handle("POST", &format!("/sessions/{}/frobnicate", id), "").status, 404);
+        assert_eq!(service.handle("GET", "/nowhere", "").status, 404);
+
+        assert_eq!(service.handle("DELETE", &format!("/sessions/{}", id), "").status, 204);
+        assert_eq!(service.handle("GET", &format!("/sessions/{}", id), "").status, 404);
+        assert_eq!(service.handle("DELETE", &format!("/sessions/{}", id), "").status, 404);
+        assert_eq!(service.sessions(), 1);
+    }
+
+    #[test]
+    fn test_http_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener));
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request = |method: &str, path: &str, body: &str, close: bool| -> (String, String) {
+            let connection = if close { "Connection: close\r\n" } else { "" };
+            write!(stream, "{} {} HTTP/1.1\r\nHost: test\r\n{}Content-Length: {}\r\n\r\n{}", method, path, connection, body.len(), body).unwrap();
+            let mut status = String::new();
+            reader.read_line(&mut status).unwrap();
+            let mut length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some(value) = header.strip_prefix("Content-Length: ") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            (status.trim_end().to_string(), String::from_utf8(body).unwrap())
+        };
+
+        let (status, body) = request("POST", "/sessions", "", false);
+        assert_eq!(status, "HTTP/1.1 201 Created");
+        let id: Value = serde_json::from_str(&body).unwrap();
+        let path = format!("/sessions/{}/tick", id["session"].as_str().unwrap());
+        let (status, body) = request("POST", &path, r#"{"clk": true, "x": 12345}"#, true);
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(serde_json::from_str::<Value>(&body).unwrap(), json!({ "output": "12345" }));
+    }
+}
@@ -0,0 +1,237 @@
+//! Thread-safe machine handle with deterministic tick ordering.
+//!
+//! A multi-threaded testbench usually has several driver threads feeding one
+//! model, and the order their ticks land in decides every output. A
+//! [`SharedModuloMachine`] is a cloneable handle to one machine behind a lock,
+//! and every tick it applies carries a sequence number. Threads that agree on
+//! a schedule up front call [`tick_at`](SharedModuloMachine::tick_at) with
+//! their slot in it; each call waits until all earlier slots have been
+//! applied, so the run is the same however the threads are scheduled:
+//!
+//! ```
+//! use modulo_machine::{ModuloMachine, SharedModuloMachine};
+//! use rug::Integer;
+//! use std::thread;
+//!
+//! let shared = SharedModuloMachine::new(ModuloMachine::new());
+//! // Two drivers interleaving rising and falling edges: 0, 2, 4 and 1, 3, 5
+//! let drivers: Vec<_> = (0..2u64)
+//!     .map(|lane| {
+//!         let shared = shared.clone();
+//!         thread::spawn(move || {
+//!             (0..3u64).map(|i| shared.tick_at(2 * i + lane, lane == 0, false, &Integer::from(i + 1)).unwrap()).collect::<Vec<_>>()
+//!         })
+//!     })
+//!     .collect();
+//! let outputs: Vec<_> = drivers.into_iter().map(|d| d.join().unwrap()).collect();
+//! assert_eq!(outputs, [[1, 2, 3], [1, 2, 3]]);
+//! assert_eq!(shared.next_sequence(), 6);
+//! ```
+//!
+//! [`tick`](SharedModuloMachine::tick) is the first-come variant for drivers
+//! that only need to know the order afterwards: it takes the next sequence
+//! number without waiting. A slot that is never filled stalls everything
+//! after it, so drivers that might give up should use
+//! [`tick_at_timeout`](SharedModuloMachine::tick_at_timeout).
+
+use crate::ModuloMachine;
+use rug::Integer;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Reason [`SharedModuloMachine::tick_at`] did not apply a tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceError {
+    /// The slot was already taken; `next` is the first free one
+    Stale { seq: u64, next: u64 },
+    /// Earlier slots were still unfilled when the timeout expired
+    TimedOut { seq: u64, next: u64 },
+}
+
+impl fmt::Display for SequenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SequenceError::Stale { seq, next } => write!(f, "tick {} was already applied; the next is {}", seq, next),
+            SequenceError::TimedOut { seq, next } => write!(f, "timed out waiting for tick {}; still at {}", seq, next),
+        }
+    }
+}
+
+impl std::error::Error for SequenceError {}
+
+struct State {
+    machine: ModuloMachine,
+    /// Sequence number of the next tick to apply
+    next: u64,
+}
+
+struct Inner {
+    state: Mutex<State>,
+    /// Signalled after every tick so waiters can check whether it is their turn
+    turn: Condvar,
+}
+
+/// A machine shared between threads; see the [module docs](self)
+///
+/// Cloning the handle shares the same machine.
+#[derive(Clone)]
+pub struct SharedModuloMachine {
+    inner: Arc<Inner>,
+}
+
+impl SharedModuloMachine {
+    /// Share `machine`; its first tick gets sequence number 0
+    pub fn new(machine: ModuloMachine) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                state: Mutex::new(State { machine, next: 0 }),
+                turn: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Sequence number the next tick will carry, i.e. how many have been
+    /// applied
+    pub fn next_sequence(&self) -> u64 {
+        self.lock().next
+    }
+
+    /// Apply a tick as soon as the lock is free and return its sequence
+    /// number with the output after it
+    pub fn tick(&self, clk: bool, reset: bool, x: &Integer) -> (u64, Integer) {
+        let mut state = self.lock();
+        let seq = state.next;
+        (seq, self.apply(&mut state, clk, reset, x))
+    }
+
+    /// Wait until ticks `0..seq` have been applied, then apply this one as
+    /// tick `seq` and return the output after it
+    ///
+    /// Blocks indefinitely if some earlier slot is never filled.
+    pub fn tick_at(&self, seq: u64, clk: bool, reset: bool, x: &Integer) -> Result<Integer, SequenceError> {
+        let mut state = self.lock();
+        while state.next < seq {
+            state = self.inner.turn.wait(state).unwrap();
+        }
+        if state.next > seq {
+            return Err(SequenceError::Stale { seq, next: state.next });
+        }
+        Ok(self.apply(&mut state, clk, reset, x))
+    }
+
+    /// [`tick_at`](Self::tick_at), giving up after `timeout` without
+    /// applying the tick
+    pub fn tick_at_timeout(&self, seq: u64, timeout: Duration, clk: bool, reset: bool, x: &Integer) -> Result<Integer, SequenceError> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.lock();
+        while state.next < seq {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(SequenceError::TimedOut { seq, next: state.next });
+            }
+            state = self.inner.turn.wait_timeout(state, remaining).unwrap().0;
+        }
+        if state.next > seq {
+            return Err(SequenceError::Stale { seq, next: state.next });
+        }
+        Ok(self.apply(&mut state, clk, reset, x))
+    }
+
+    /// Run `f` with the machine locked, e.g. to read statistics or take a
+    /// snapshot between ticks
+    ///
+    /// Ticks `f` applies directly do not take sequence numbers.
+    pub fn with<R>(&self, f: impl FnOnce(&mut ModuloMachine) -> R) -> R {
+        f(&mut self.lock().machine)
+    }
+
+    /// The output register, as of the last applied tick
+    pub fn output(&self) -> Integer {
+        self.lock().machine.get_output().clone()
+    }
+
+    /// Take the machine back once this is the only handle left; otherwise
+    /// return the handle unchanged
+    pub fn into_inner(self) -> Result<ModuloMachine, Self> {
+        match Arc::try_unwrap(self.inner) {
+            Ok(inner) => Ok(inner.state.into_inner().unwrap().machine),
+            Err(inner) => Err(Self { inner }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.inner.state.lock().unwrap()
+    }
+
+    fn apply(&self, state: &mut State, clk: bool, reset: bool, x: &Integer) -> Integer {
+        let output = state.machine.tick(clk, reset, x).clone();
+        sim_event!(trace, seq = state.next, "shared tick");
+        state.next += 1;
+        self.inner.turn.notify_all();
+        output
+    }
+}
+
+impl From<ModuloMachine> for SharedModuloMachine {
+    fn from(machine: ModuloMachine) -> Self {
+        Self::new(machine)
+    }
+}
+
+impl fmt::Debug for SharedModuloMachine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("SharedModuloMachine").field("next", &state.next).field("machine", &state.machine).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StimulusGenerator;
+    use std::thread;
+
+    #[test]
+    fn test_threads_follow_sequence() {
+        let ticks = StimulusGenerator::new(11).with_reset_probability(0.05).generate(400);
+        let expected: Vec<Integer> = ModuloMachine::new().process_stream(ticks.clone()).collect();
+
+        // Four threads own every fourth slot and submit in reverse thread order
+        let shared = SharedModuloMachine::new(ModuloMachine::new());
+        let threads = 4;
+        let handles: Vec<_> = (0..threads)
+            .rev()
+            .map(|lane| {
+                let shared = shared.clone();
+                let mine: Vec<_> = ticks.iter().enumerate().skip(lane).step_by(threads).map(|(i, t)| (i as u64, t.clone())).collect();
+                thread::spawn(move || mine.into_iter().map(|(seq, t)| (seq, shared.tick_at(seq, t.clk, t.reset, &t.x).unwrap())).collect::<Vec<_>>())
+            })
+            .collect();
+        let mut outputs: Vec<(u64, Integer)> = handles.into_iter().flat_map(|h| h.join().unwrap()).collect();
+        outputs.sort_by_key(|(seq, _)| *seq);
+        assert_eq!(outputs.into_iter().map(|(_, o)| o).collect::<Vec<_>>(), expected);
+        assert_eq!(shared.next_sequence(), 400);
+        assert_eq!(shared.output(), expected[399]);
+        assert_eq!(shared.with(|m| m.stats().ticks), 400);
+        assert_eq!(shared.into_inner().unwrap().stats().ticks, 400);
+    }
+
+    #[test]
+    fn test_stale_timeout_and_first_come() {
+        let shared = SharedModuloMachine::from(ModuloMachine::new());
+        assert_eq!(shared.tick(true, false, &Integer::from(5)), (0, Integer::from(5)));
+        assert_eq!(shared.tick_at(0, true, false, &Integer::from(6)), Err(SequenceError::Stale { seq: 0, next: 1 }));
+        assert_eq!(
+            shared.tick_at_timeout(3, Duration::from_millis(10), false, false, &Integer::from(6)),
+            Err(SequenceError::TimedOut { seq: 3, next: 1 })
+        );
+        assert_eq!(shared.tick_at_timeout(1, Duration::from_millis(10), false, false, &Integer::from(6)), Ok(Integer::from(5)));
+        assert_eq!(shared.with(|m| m.stats().ticks), 2);
+
+        let other = shared.clone();
+        let shared = shared.into_inner().unwrap_err();
+        drop(other);
+        assert!(shared.into_inner().is_ok());
+    }
+}
@@ -0,0 +1,97 @@
+//! Iterative shift-and-subtract reduction, one compare/subtract per cycle.
+//!
+//! The modulus starts aligned under the operand's most significant bit and
+//! moves right one place per cycle; each cycle subtracts it when the partial
+//! remainder is at least as large. This is the restoring reducer a small
+//! iterative datapath implements, so its cycle count is what an RTL
+//! co-simulation should wait for: an `n`-bit operand and a `k`-bit modulus
+//! take `n - k + 1` cycles, and none when `n < k`.
+
+use rug::{Assign, Integer};
+
+/// Bit-serial reducer for one modulus
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShiftSubtractReducer {
+    p: Integer,
+    p_bits: u32,
+}
+
+impl ShiftSubtractReducer {
+    /// Reducer for `p`, which must be positive
+    pub fn new(p: &Integer) -> Self {
+        assert!(*p > 0, "shift-and-subtract reduction needs a positive modulus");
+        Self {
+            p: p.clone(),
+            p_bits: p.significant_bits(),
+        }
+    }
+
+    pub fn modulus(&self) -> &Integer {
+        &self.p
+    }
+
+    /// Cycles the reduction of `x` takes, which depends only on its width
+    pub fn cycles(&self, x: &Integer) -> u32 {
+        (x.significant_bits() + 1).saturating_sub(self.p_bits)
+    }
+
+    /// Write `x mod p` into `out` and return the cycles it took
+    ///
+    /// A negative `x` reduces its magnitude and keeps the sign, matching
+    /// GMP's truncated `%`.
+    pub fn reduce_into(&self, x: &Integer, out: &mut Integer) -> u32 {
+        let cycles = self.cycles(x);
+        out.assign(x.abs_ref());
+        let mut shifted = Integer::new();
+        for shift in (0..cycles).rev() {
+            shifted.assign(&self.p << shift);
+            if *out >= shifted {
+                *out -= &shifted;
+            }
+        }
+        if *x < 0 {
+            *out = Integer::from(-&*out);
+        }
+        cycles
+    }
+
+    /// `x mod p` and the cycles it took
+    pub fn reduce(&self, x: &Integer) -> (Integer, u32) {
+        let mut out = Integer::new();
+        let cycles = self.reduce_into(x, &mut out);
+        (out, cycles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ModuloMachine, Preset, P_STR};
+
+    #[test]
+    fn test_matches_gmp_and_counts_cycles() {
+        let mut moduli = vec![Integer::from_str_radix(P_STR, 10).unwrap(), Integer::from(97u32)];
+        moduli.extend(Preset::ALL.iter().map(|preset| preset.modulus()));
+        for p in &moduli {
+            let reducer = ShiftSubtractReducer::new(p);
+            let cases = [
+                Integer::new(),
+                Integer::from(p - 1u32),
+                p.clone(),
+                ModuloMachine::create_large_input(300, 0) - 1u32,
+                Integer::from(-7),
+                -ModuloMachine::create_large_input(299, 5),
+            ];
+            for x in &cases {
+                let (r, cycles) = reducer.reduce(x);
+                assert_eq!(r, Integer::from(x % p), "{} mod {}", x, p);
+                assert_eq!(cycles, (x.significant_bits() + 1).saturating_sub(p.significant_bits()));
+            }
+        }
+
+        let reducer = ShiftSubtractReducer::new(&Integer::from(97u32));
+        assert_eq!(reducer.reduce(&Integer::from(96u32)), (Integer::from(96u32), 1));
+        assert_eq!(reducer.reduce(&Integer::from(50u32)), (Integer::from(50u32), 0));
+        assert_eq!(reducer.reduce(&Integer::from(1000u32)), (Integer::from(1000 % 97), 4));
+    }
+}
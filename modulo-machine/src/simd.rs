@@ -0,0 +1,400 @@
+//! Lane-parallel batch reduction on fixed limbs, for AVX2 and AVX-512.
+//!
+//! [`FixedReducer::reduce_batch`] reduces a slice of operands several at a
+//! time, one operand per 64-bit lane: eight per AVX-512 register, or four
+//! per AVX2 register. The kernel is the same Barrett reduction as
+//! [`FixedReducer::reduce`], regrouped into radix-2^28 limbs so that every
+//! limb product is a single `vpmuludq` and a whole column of products fits
+//! in a lane. Products are accumulated carry-save, with one carry pass per
+//! multiplication, and the two final subtractions are masked as in the
+//! scalar path, so the batch is constant-time too.
+//!
+//! The instruction set is picked at run time ([`SimdLevel::detect`]; in a
+//! `no_std` build, from the compile-time target features). Only AVX-512 is
+//! used automatically: four 32-bit multiplies per AVX2 instruction do no
+//! more work than one scalar 64-bit multiply, and on the CPUs measured the
+//! AVX2 kernel ran at about two thirds of the scalar reducer's throughput,
+//! so it is there for [`reduce_batch_at`](FixedReducer::reduce_batch_at)
+//! and the benchmarks. The scalar reducer handles everything else: other
+//! architectures, moduli below `2^252`, and the operands left over after the
+//! last full register.
+//!
+//! ```
+//! use modulo_machine::fixed::{FixedReducer, P_LIMBS};
+//!
+//! let p = FixedReducer::P;
+//! let xs: Vec<[u64; 5]> = (0..100).map(|i| [i, 0, 0, 0, i]).collect();
+//! let mut out = vec![[0; P_LIMBS]; xs.len()];
+//! p.reduce_batch(&xs, &mut out);
+//! assert!(xs.iter().zip(&out).all(|(x, o)| p.reduce(x) == *o));
+//! ```
+
+use crate::fixed::{FixedReducer, P_LIMBS, X_LIMBS};
+
+/// Instruction set used by [`FixedReducer::reduce_batch`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SimdLevel {
+    /// One operand at a time with [`FixedReducer::reduce`]
+    Scalar,
+    /// Four operands per 256-bit register
+    Avx2,
+    /// Eight operands per 512-bit register (AVX-512F)
+    Avx512,
+}
+
+impl SimdLevel {
+    /// The widest level the running CPU supports
+    pub fn detect() -> Self {
+        #[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
+        {
+            if std::is_x86_feature_detected!("avx512f") {
+                return SimdLevel::Avx512;
+            }
+            if std::is_x86_feature_detected!("avx2") {
+                return SimdLevel::Avx2;
+            }
+        }
+        if cfg!(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "avx512f")) {
+            SimdLevel::Avx512
+        } else if cfg!(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "avx2")) {
+            SimdLevel::Avx2
+        } else {
+            SimdLevel::Scalar
+        }
+    }
+}
+
+impl FixedReducer {
+    /// Reduce every operand in `xs` into the matching slot of `out`, with
+    /// the AVX-512 kernel when the CPU has it and the scalar reducer
+    /// otherwise; see the [module docs](self)
+    ///
+    /// Panics if the slices differ in length.
+    pub fn reduce_batch(&self, xs: &[[u64; X_LIMBS]], out: &mut [[u64; P_LIMBS]]) {
+        let level = match SimdLevel::detect() {
+            SimdLevel::Avx512 => SimdLevel::Avx512,
+            _ => SimdLevel::Scalar,
+        };
+        self.reduce_batch_at(level, xs, out)
+    }
+
+    /// [`reduce_batch`](Self::reduce_batch) at no more than `level`, for
+    /// comparing the paths; a level the CPU lacks falls back to the best it
+    /// has
+    pub fn reduce_batch_at(&self, level: SimdLevel, xs: &[[u64; X_LIMBS]], out: &mut [[u64; P_LIMBS]]) {
+        assert_eq!(xs.len(), out.len(), "reduce_batch needs one output slot per operand");
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        if let Some(kernel) = lanes::Kernel::new(self) {
+            match level.min(SimdLevel::detect()) {
+                // SAFETY: detect() reported the target feature
+                SimdLevel::Avx512 => return unsafe { lanes::avx512::batch(self, &kernel, xs, out) },
+                SimdLevel::Avx2 => return unsafe { lanes::avx2::batch(self, &kernel, xs, out) },
+                SimdLevel::Scalar => {}
+            }
+        }
+        #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+        let _ = level;
+        for (x, o) in xs.iter().zip(out) {
+            *o = self.reduce(x);
+        }
+    }
+}
+
+/// Bits per limb in the vector kernels: a limb product is below 2^56, so a
+/// 64-bit lane can sum a column of them without carrying
+const RADIX: usize = 28;
+const MASK: u64 = (1 << RADIX) - 1;
+/// Modulus limbs, `k` in HAC 14.42
+const K: usize = 10;
+
+/// The vector kernels' Barrett constant, `floor(b^2k / P)` with `b = 2^28`
+pub(crate) type LaneMu = [u64; K + 1];
+
+/// Compute [`LaneMu`] by binary long division of `2^560`; zero for moduli
+/// below `2^252`, which the kernels leave to the scalar reducer
+pub(crate) const fn lane_mu(p: &[u64; P_LIMBS]) -> LaneMu {
+    let mut mu = [0u64; K + 1];
+    if p[P_LIMBS - 1] >> 60 == 0 {
+        return mu;
+    }
+    // r < p < 2^256 before the shift, so it fits in five limbs after
+    let mut r = [0u64; P_LIMBS + 1];
+    let mut bit = 2 * K * RADIX;
+    loop {
+        let mut carry = (bit == 2 * K * RADIX) as u64;
+        let mut i = 0;
+        while i <= P_LIMBS {
+            let next = r[i] >> 63;
+            r[i] = (r[i] << 1) | carry;
+            carry = next;
+            i += 1;
+        }
+        let mut d = [0u64; P_LIMBS + 1];
+        let mut borrow = 0;
+        i = 0;
+        while i <= P_LIMBS {
+            let (d1, b1) = r[i].overflowing_sub(if i < P_LIMBS { p[i] } else { 0 });
+            let (d2, b2) = d1.overflowing_sub(borrow);
+            d[i] = d2;
+            borrow = (b1 | b2) as u64;
+            i += 1;
+        }
+        if borrow == 0 {
+            // The quotient is below 2^308 = b^(k+1), so only low bits are set
+            r = d;
+            mu[bit / RADIX] |= 1 << (bit % RADIX);
+        }
+        if bit == 0 {
+            return mu;
+        }
+        bit -= 1;
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod lanes {
+    //! Barrett (HAC 14.42) with `b = 2^28` and `k = 10`, one operand per
+    //! 64-bit lane. Every value is an array of limb vectors; the kernel is
+    //! written once and stamped out per instruction set so that each copy
+    //! is compiled, and fully inlined, under its own target feature.
+
+    use super::*;
+
+    /// Operand limbs: 336 bits, room for 320
+    const XL: usize = 12;
+
+    pub(super) struct Kernel {
+        /// P in `k + 1` limbs, top limb zero
+        p: [u64; K + 1],
+        mu: LaneMu,
+    }
+
+    impl Kernel {
+        /// `None` for moduli below `2^252`, whose top limb is empty
+        pub(super) fn new(reducer: &FixedReducer) -> Option<Self> {
+            if reducer.p[P_LIMBS - 1] >> 60 == 0 {
+                return None;
+            }
+            let mut p = [0; K + 1];
+            for (i, limb) in p.iter_mut().enumerate() {
+                *limb = split(&reducer.p, i);
+            }
+            Some(Self { p, mu: reducer.lane_mu })
+        }
+    }
+
+    /// Limb `i` of a u64-limb value
+    #[inline(always)]
+    fn split<const N: usize>(x: &[u64; N], i: usize) -> u64 {
+        let (word, shift) = (i * RADIX / 64, i * RADIX % 64);
+        let mut limb = if word < N { x[word] >> shift } else { 0 };
+        if shift > 64 - RADIX && word + 1 < N {
+            limb |= x[word + 1] << (64 - shift);
+        }
+        limb & MASK
+    }
+
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    /// Gathers, which take their arguments in a different order per set
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn gather256(base: *const i64, index: __m256i) -> __m256i {
+        unsafe { _mm256_i64gather_epi64::<8>(base, index) }
+    }
+
+    #[target_feature(enable = "avx512f")]
+    #[inline]
+    unsafe fn gather512(base: *const i64, index: __m512i) -> __m512i {
+        unsafe { _mm512_i64gather_epi64::<8>(index, base) }
+    }
+
+    macro_rules! kernel {
+        ($module:ident, $feature:literal, $lanes:literal, $vec:ident, $splat:ident, $load:ident, $store:ident, $add:ident, $sub:ident, $mul:ident, $and:ident, $or:ident, $andnot:ident, $srli:ident, $sllv:ident, $srlv:ident, $gather:ident) => {
+            pub(super) mod $module {
+                use super::*;
+
+                const W: usize = $lanes;
+                type V = $vec;
+
+                /// Reduce `xs` into `out`, full registers in the kernel and
+                /// the rest with the scalar reducer
+                #[target_feature(enable = $feature)]
+                pub(in super::super) fn batch(reducer: &FixedReducer, kernel: &Kernel, xs: &[[u64; X_LIMBS]], out: &mut [[u64; P_LIMBS]]) {
+                    let p = kernel.p.map(|limb| $splat(limb as i64));
+                    let mu = kernel.mu.map(|limb| $splat(limb as i64));
+                    let mut xs = xs.chunks_exact(W);
+                    let mut outs = out.chunks_exact_mut(W);
+                    for (x, o) in (&mut xs).zip(&mut outs) {
+                        reduce(&p, &mu, x, o);
+                    }
+                    for (x, o) in xs.remainder().iter().zip(outs.into_remainder()) {
+                        *o = reducer.reduce(x);
+                    }
+                }
+
+                #[target_feature(enable = $feature)]
+                #[inline]
+                fn reduce(p: &[V; K + 1], mu: &[V; K + 1], xs: &[[u64; X_LIMBS]], out: &mut [[u64; P_LIMBS]]) {
+                    // Transpose: word j of every lane's operand into one vector
+                    let mut words = [$splat(0); X_LIMBS];
+                    for (j, word) in words.iter_mut().enumerate() {
+                        let mut index = [0i64; W];
+                        for (l, i) in index.iter_mut().enumerate() {
+                            *i = (l * X_LIMBS + j) as i64;
+                        }
+                        // SAFETY: `xs` holds W operands, so every index is in bounds
+                        *word = unsafe { $gather(xs.as_ptr().cast(), $load(index.as_ptr().cast())) };
+                    }
+                    let mask = $splat(MASK as i64);
+                    let mut x = [$splat(0); XL];
+                    for (i, limb) in x.iter_mut().enumerate() {
+                        let (word, shift) = (i * RADIX / 64, i * RADIX % 64);
+                        *limb = $srlv(words[word], $splat(shift as i64));
+                        if shift > 64 - RADIX && word + 1 < X_LIMBS {
+                            *limb = $or(*limb, $sllv(words[word + 1], $splat((64 - shift) as i64)));
+                        }
+                        *limb = $and(*limb, mask);
+                    }
+                    // q1 = floor(x / b^(k-1)), below 2^68 for a 320-bit operand
+                    let q1 = [x[K - 1], x[K], x[K + 1]];
+                    let q2: [V; K + 4] = mul(&q1, mu);
+                    // q3 = floor(q2 / b^(k+1))
+                    let q3 = [q2[K + 1], q2[K + 2], q2[K + 3]];
+                    // r = (x - q3·P) mod b^(k+1), known to be below 3P
+                    let q3p: [V; K + 1] = mul(&q3, p);
+                    let mut x_low = [$splat(0); K + 1];
+                    x_low.copy_from_slice(&x[..=K]);
+                    let (mut r, _) = sub(&x_low, &q3p);
+                    r = cond_sub(&r, p);
+                    r = cond_sub(&r, p);
+
+                    // Back to u64 words, then out of the lanes
+                    for w in 0..P_LIMBS {
+                        let mut word = $splat(0);
+                        for (i, limb) in r.iter().take(K).enumerate() {
+                            let offset = (i * RADIX) as i64 - (w * 64) as i64;
+                            if (0..64).contains(&offset) {
+                                word = $or(word, $sllv(*limb, $splat(offset)));
+                            } else if offset < 0 && offset > -(RADIX as i64) {
+                                word = $or(word, $srlv(*limb, $splat(-offset)));
+                            }
+                        }
+                        let mut lanes = [0u64; W];
+                        // SAFETY: `lanes` has room for exactly one vector
+                        unsafe { $store(lanes.as_mut_ptr().cast(), word) };
+                        for (o, lane) in out.iter_mut().zip(lanes) {
+                            o[w] = lane;
+                        }
+                    }
+                }
+
+                /// `a * b mod b^O`, carry-save: whole limb products (below
+                /// 2^56, at most three per column) are summed per column and
+                /// one pass propagates the carries
+                #[target_feature(enable = $feature)]
+                #[inline]
+                fn mul<const A: usize, const B: usize, const O: usize>(a: &[V; A], b: &[V; B]) -> [V; O] {
+                    let mut cols = [$splat(0); O];
+                    for (i, ai) in a.iter().enumerate() {
+                        for (j, bj) in b.iter().enumerate() {
+                            if i + j < O {
+                                cols[i + j] = $add(cols[i + j], $mul(*ai, *bj));
+                            }
+                        }
+                    }
+                    let mask = $splat(MASK as i64);
+                    let mut carry = $splat(0);
+                    for col in cols.iter_mut() {
+                        let t = $add(*col, carry);
+                        *col = $and(t, mask);
+                        // RADIX; the immediate's type differs between the sets
+                        carry = $srli::<28>(t);
+                    }
+                    cols
+                }
+
+                /// `a - b mod b^N` and the final borrow (0 or 1 per lane); a
+                /// negative limb wraps, leaving the borrow in bit 63
+                #[target_feature(enable = $feature)]
+                #[inline]
+                fn sub<const N: usize>(a: &[V; N], b: &[V; N]) -> ([V; N], V) {
+                    let mask = $splat(MASK as i64);
+                    let mut out = [$splat(0); N];
+                    let mut borrow = $splat(0);
+                    for i in 0..N {
+                        let t = $sub($sub(a[i], b[i]), borrow);
+                        out[i] = $and(t, mask);
+                        borrow = $srli::<63>(t);
+                    }
+                    (out, borrow)
+                }
+
+                /// Subtract P from each lane that is at least P, without
+                /// branching
+                #[target_feature(enable = $feature)]
+                #[inline]
+                fn cond_sub(r: &[V; K + 1], p: &[V; K + 1]) -> [V; K + 1] {
+                    let (mut d, borrow) = sub(r, p);
+                    // No borrow means r >= P: keep the difference
+                    let keep = $sub(borrow, $splat(1));
+                    for (d, r) in d.iter_mut().zip(r) {
+                        *d = $or($and(*d, keep), $andnot(keep, *r));
+                    }
+                    d
+                }
+            }
+        };
+    }
+
+    kernel!(avx2, "avx2", 4, __m256i, _mm256_set1_epi64x, _mm256_loadu_si256, _mm256_storeu_si256, _mm256_add_epi64, _mm256_sub_epi64, _mm256_mul_epu32, _mm256_and_si256, _mm256_or_si256, _mm256_andnot_si256, _mm256_srli_epi64, _mm256_sllv_epi64, _mm256_srlv_epi64, gather256);
+    kernel!(avx512, "avx512f", 8, __m512i, _mm512_set1_epi64, _mm512_loadu_si512, _mm512_storeu_si512, _mm512_add_epi64, _mm512_sub_epi64, _mm512_mul_epu32, _mm512_and_si512, _mm512_or_si512, _mm512_andnot_si512, _mm512_srli_epi64, _mm512_sllv_epi64, _mm512_srlv_epi64, gather512);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// xorshift64*, enough to cover the operand space without a dependency
+    fn operands(count: usize, mut state: u64) -> Vec<[u64; X_LIMBS]> {
+        let mut next = || {
+            state ^= state >> 12;
+            state ^= state << 25;
+            state ^= state >> 27;
+            state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        };
+        (0..count).map(|_| core::array::from_fn(|_| next())).collect()
+    }
+
+    #[test]
+    fn test_levels_match_scalar() {
+        let p = FixedReducer::P;
+        let m = *p.modulus();
+        let mut xs = operands(203, 9);
+        // Edges: 0, P - 1, P, 2P - 1 and the all-ones operand
+        xs.extend([[0; X_LIMBS], [m[0] - 1, m[1], m[2], m[3], 0], [m[0], m[1], m[2], m[3], 0], [u64::MAX; X_LIMBS]]);
+        let mut two_p = [0u64; X_LIMBS];
+        let mut carry = 0;
+        for i in 0..P_LIMBS {
+            two_p[i] = (m[i] << 1) | carry;
+            carry = m[i] >> 63;
+        }
+        two_p[P_LIMBS] = carry;
+        two_p[0] -= 1;
+        xs.push(two_p);
+
+        let secp = FixedReducer::new([0xfffffffefffffc2f, u64::MAX, u64::MAX, u64::MAX]).unwrap();
+        let small = FixedReducer::new([0xfffffffefffffc2f, u64::MAX, u64::MAX, 0xffff_ffff]).unwrap();
+        for reducer in [p, secp, small] {
+            let expected: Vec<_> = xs.iter().map(|x| reducer.reduce(x)).collect();
+            for level in [SimdLevel::Scalar, SimdLevel::Avx2, SimdLevel::Avx512] {
+                let mut out = vec![[0; P_LIMBS]; xs.len()];
+                reducer.reduce_batch_at(level, &xs, &mut out);
+                assert_eq!(out, expected, "{:?}", level);
+            }
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! Checkpoints of a machine's complete state.
+//!
+//! [`ModuloMachine::snapshot`] captures everything a later tick depends on:
+//! the output register, the clock history, every configured mode, results in
+//! flight in the pipeline or the exponentiation unit, and simulation time.
+//! Restoring it into any machine, or building a fresh one with
+//! [`ModuloMachine::from_state`], resumes exactly where the snapshot was
+//! taken, so a long simulation can be checkpointed, or forked to try several
+//! stimuli from one point.
+//!
+//! With the `serde` feature a `MachineState`, and a `ModuloMachine` through
+//! it, serializes with big integers as decimal strings, so checkpoints can be
+//! written to JSON or TOML and shared between runs.
+
+use crate::alu::ModExpUnit;
+use crate::{Datapath, EdgeMode, InputWidthPolicy, ModuloMachine, ReductionStrategy, ResetKind, ResetValue, SignedMode};
+use rug::{Assign, Integer};
+use std::collections::VecDeque;
+
+/// Saved state of a [`ModuloMachine`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MachineState {
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "modulus", serialize_with = "crate::serde_int::serialize", deserialize_with = "deserialize_modulus")
+    )]
+    p: Integer,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_int"))]
+    output: Integer,
+    clk_prev: bool,
+    edge_mode: EdgeMode,
+    reset_kind: ResetKind,
+    width_policy: InputWidthPolicy,
+    strict: bool,
+    datapath: Datapath,
+    signed_mode: SignedMode,
+    modexp: Option<ModExpUnit>,
+    done: bool,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_int::option_seq"))]
+    pipeline: VecDeque<Option<Integer>>,
+    output_valid: bool,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_int::option"))]
+    lazy_threshold: Option<Integer>,
+    strategy: ReductionStrategy,
+    reduction_cycles: Option<u32>,
+    reset_value: ResetValue,
+    time: u64,
+    time_step: u64,
+}
+
+impl MachineState {
+    pub fn modulus(&self) -> &Integer {
+        &self.p
+    }
+
+    /// Output register at the checkpoint
+    pub fn output(&self) -> &Integer {
+        &self.output
+    }
+
+    /// Clock level the machine last saw
+    pub fn clk(&self) -> bool {
+        self.clk_prev
+    }
+
+    /// Simulation time of the next tick
+    pub fn sim_time(&self) -> u64 {
+        self.time
+    }
+}
+
+impl ModuloMachine {
+    /// Capture the machine's complete state
+    pub fn snapshot(&self) -> MachineState {
+        MachineState {
+            p: self.p.clone(),
+            output: self.output.clone(),
+            clk_prev: self.clk_prev,
+            edge_mode: self.edge_mode,
+            reset_kind: self.reset_kind,
+            width_policy: self.width_policy,
+            strict: self.strict,
+            datapath: self.datapath.clone(),
+            signed_mode: self.signed_mode,
+            modexp: self.modexp.clone(),
+            done: self.done,
+            pipeline: self.pipeline.clone(),
+            output_valid: self.output_valid,
+            lazy_threshold: self.lazy_threshold.clone(),
+            strategy: self.strategy,
+            reduction_cycles: self.reduction_cycles,
+            reset_value: self.reset_value.clone(),
+            time: self.time,
+            time_step: self.time_step,
+        }
+    }
+
+    /// Return to a state captured by [`ModuloMachine::snapshot`]
+    ///
+    /// The snapshot may come from a machine with another modulus; everything
+    /// derived from it is recomputed. A strategy the modulus does not
+    /// support, which only a hand-edited state can hold, falls back to
+    /// [`ReductionStrategy::Gmp`].
+    pub fn restore(&mut self, state: &MachineState) {
+        if self.p != state.p {
+            *self = Self::build(state.p.clone(), 256);
+        }
+        if !self.set_reduction_strategy(state.strategy) {
+            self.set_reduction_strategy(ReductionStrategy::Gmp);
+        }
+        self.output.assign(&state.output);
+        self.clk_prev = state.clk_prev;
+        self.edge_mode = state.edge_mode;
+        self.reset_kind = state.reset_kind;
+        self.width_policy = state.width_policy;
+        self.strict = state.strict;
+        self.datapath = state.datapath.clone();
+        self.signed_mode = state.signed_mode;
+        self.modexp = state.modexp.clone();
+        self.done = state.done;
+        self.pipeline = state.pipeline.clone();
+        self.output_valid = state.output_valid;
+        self.lazy_threshold = state.lazy_threshold.clone();
+        self.reduction_cycles = state.reduction_cycles;
+        self.set_reset_value(state.reset_value.clone());
+        self.time = state.time;
+        self.time_step = state.time_step;
+    }
+
+    /// Create a machine resuming from `state`
+    pub fn from_state(state: &MachineState) -> Self {
+        let mut machine = Self::build(state.p.clone(), 256);
+        machine.restore(state);
+        machine
+    }
+}
+
+/// A serialized modulus, checked so that restoring it cannot panic
+#[cfg(feature = "serde")]
+fn deserialize_modulus<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Integer, D::Error> {
+    let p = crate::serde_int::deserialize(deserializer)?;
+    crate::validate_machine_modulus(&p).map_err(serde::de::Error::custom)?;
+    Ok(p)
+}
+
+/// Serialized as its [`MachineState`]
+#[cfg(feature = "serde")]
+impl serde::Serialize for ModuloMachine {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.snapshot().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ModuloMachine {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        MachineState::deserialize(deserializer).map(|state| Self::from_state(&state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Op, Preset};
+
+    #[test]
+    fn test_fork_and_resume() {
+        let mut machine = ModuloMachine::new();
+        machine.set_datapath(Datapath::Accumulate);
+        machine.set_pipeline_depth(1);
+        machine.set_time_step(5);
+        for x in [3u32, 4, 5] {
+            machine.cycle(&Integer::from(x));
+        }
+        // Mid-cycle, with a result in the pipeline
+        machine.tick(false, false, &Integer::new());
+        let state = machine.snapshot();
+
+        let stimulus = [Integer::from(10u32), Integer::from(20u32), Integer::new()];
+        let run = |machine: &mut ModuloMachine| stimulus.iter().map(|x| machine.tick(true, false, x).clone()).collect::<Vec<_>>();
+        let original = run(&mut machine);
+
+        // A fork from the snapshot replays identically, even from another modulus
+        let mut fork = ModuloMachine::for_preset(Preset::Goldilocks);
+        fork.restore(&state);
+        assert_eq!(fork.snapshot(), state);
+        assert_eq!(run(&mut fork), original);
+        assert_eq!(fork.sim_time(), machine.sim_time());
+
+        let mut fresh = ModuloMachine::from_state(&state);
+        assert_eq!(*fresh.get_output(), *state.output());
+        assert_eq!(run(&mut fresh), original);
+    }
+
+    #[test]
+    fn test_restore_mid_exponentiation() {
+        let mut machine = ModuloMachine::for_preset(Preset::Goldilocks);
+        assert!(machine.set_reduction_strategy(ReductionStrategy::Barrett));
+        let (base, exponent) = (Integer::from(7u32), Integer::from(0b1101u32));
+        machine.tick_op(false, false, Op::ModExp, &base, &exponent);
+        machine.tick_op(true, false, Op::ModExp, &base, &exponent);
+        let state = machine.snapshot();
+
+        let mut fork = ModuloMachine::from_state(&state);
+        assert!(fork.busy());
+        assert_eq!(fork.reduction_strategy(), ReductionStrategy::Barrett);
+        while !fork.done() {
+            fork.tick_op(false, false, Op::Mod, &base, &base);
+            fork.tick_op(true, false, Op::Mod, &base, &base);
+        }
+        assert_eq!(*fork.get_output(), Op::ModExp.apply(&base, &exponent, state.modulus()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        let mut machine = ModuloMachine::new();
+        machine.set_datapath(Datapath::Mac(Integer::from(5u32)));
+        machine.set_reset_value(ResetValue::Custom(Integer::from(-1)));
+        machine.set_pipeline_depth(2);
+        machine.set_lazy_reduction(Some(Integer::from(1000u32)));
+        machine.cycle(&Integer::from(12u32));
+        let (base, exponent) = (Integer::from(3u32), Integer::from(0b101u32));
+        machine.tick_op(false, false, Op::ModExp, &base, &exponent);
+        machine.tick_op(true, false, Op::ModExp, &base, &exponent);
+
+        let json = serde_json::to_string(&machine).unwrap();
+        assert!(json.contains(&format!(r#""modulus":"{}""#, crate::P_STR)));
+        let restored: ModuloMachine = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.snapshot(), machine.snapshot());
+
+        // Moduli that would break the derived constants are refused
+        let even = json.replace(crate::P_STR, "1000");
+        assert!(serde_json::from_str::<MachineState>(&even).is_err());
+    }
+}
@@ -0,0 +1,39 @@
+//! Runtime counters kept by every machine.
+
+use crate::ReductionStrategy;
+
+/// What a machine has been driven with since creation or the last
+/// [`reset_stats`](crate::ModuloMachine::reset_stats)
+///
+/// Counters are bookkeeping, not machine state: a reset tick, `reset()` or
+/// restoring a [`MachineState`](crate::MachineState) leaves them alone, so a
+/// run can check afterwards that its stimulus actually exercised the design.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MachineStats {
+    /// Every tick applied, whatever the pins did
+    pub ticks: u64,
+    /// Active clock edges outside reset: rising edges by default, or the
+    /// transitions the configured [`EdgeMode`](crate::EdgeMode) selects
+    pub rising_edges: u64,
+    /// Ticks with reset asserted
+    pub resets: u64,
+    /// Edges whose operand was at least P, so reduction changed the value;
+    /// always zero under [`ReductionStrategy::ConstantTime`], where the
+    /// comparison would make the edge's timing depend on the operand
+    pub reductions: u64,
+    /// Widest input seen on any tick, in bits
+    pub max_input_bits: u32,
+    /// Strategy reducing rising-edge operands, so a run can confirm which
+    /// path it exercised, e.g. the folding chosen automatically for a
+    /// special-form modulus; not a counter, so resetting leaves it as is
+    pub strategy: ReductionStrategy,
+}
+
+impl MachineStats {
+    pub(crate) fn record_tick(&mut self, reset: bool, input_bits: u32) {
+        self.ticks += 1;
+        self.resets += reset as u64;
+        self.max_input_bits = self.max_input_bits.max(input_bits);
+    }
+}
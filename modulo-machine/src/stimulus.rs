@@ -0,0 +1,239 @@
+//! Reproducible pseudo-random stimulus.
+//!
+//! A [`StimulusGenerator`] turns a `u64` seed into an endless sequence of
+//! [`TickInput`]s. The clock follows a [`ClockPattern`], reset is asserted on
+//! each tick with a fixed probability, and every operand's bit width is drawn
+//! from a [`WidthDistribution`]. The same seed and settings always produce
+//! the same ticks, on every platform, so a failing random run can be replayed
+//! from the seed alone:
+//!
+//! ```
+//! use modulo_machine::{ClockPattern, ModuloMachine, Scoreboard, StimulusGenerator, WidthDistribution};
+//!
+//! let ticks = StimulusGenerator::new(42)
+//!     .with_clock(ClockPattern::Toggle)
+//!     .with_reset_probability(0.01)
+//!     .with_widths(WidthDistribution::Uniform { min: 250, max: 300 })
+//!     .generate(1000);
+//! let mut scoreboard = Scoreboard::new(ModuloMachine::new(), ModuloMachine::new());
+//! assert!(scoreboard.run(&ticks).is_ok());
+//! ```
+
+use crate::vectors::splitmix64;
+use crate::{TickInput, X_BITS};
+use rug::integer::Order;
+use rug::Integer;
+
+/// How the clock moves from tick to tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClockPattern {
+    /// Low, high, low, high: one rising edge every two ticks
+    #[default]
+    Toggle,
+    /// `high` ticks high then `low` ticks low, repeating
+    DutyCycle { high: u32, low: u32 },
+    /// An independent coin flip per tick, for irregular edge spacing
+    Random,
+}
+
+/// How wide each operand is
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum WidthDistribution {
+    /// Uniform over the whole 300-bit bus
+    #[default]
+    Full,
+    /// Exactly this many bits, top bit set; 0 gives zero
+    Fixed(u32),
+    /// A width drawn uniformly from `min..=max`, then an operand of exactly
+    /// that width; widths above 300 exercise the width policy
+    Uniform { min: u32, max: u32 },
+    /// `(width, weight)` pairs, a width drawn in proportion to its weight
+    Weighted(Vec<(u32, u32)>),
+}
+
+/// Seeded tick sequence; see the [module docs](self)
+#[derive(Debug, Clone)]
+pub struct StimulusGenerator {
+    seed: u64,
+    state: u64,
+    clock: ClockPattern,
+    reset_probability: f64,
+    widths: WidthDistribution,
+    tick: u64,
+}
+
+impl StimulusGenerator {
+    /// Toggling clock, no resets, full-width operands
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            state: seed,
+            clock: ClockPattern::default(),
+            reset_probability: 0.0,
+            widths: WidthDistribution::default(),
+            tick: 0,
+        }
+    }
+
+    pub fn with_clock(mut self, clock: ClockPattern) -> Self {
+        if let ClockPattern::DutyCycle { high, low } = clock {
+            assert!(high > 0 && low > 0, "a duty cycle needs at least one high and one low tick");
+        }
+        self.clock = clock;
+        self
+    }
+
+    /// Assert reset on each tick with probability `p` (`0.0..=1.0`)
+    pub fn with_reset_probability(mut self, p: f64) -> Self {
+        assert!((0.0..=1.0).contains(&p), "reset probability {} is outside 0..=1", p);
+        self.reset_probability = p;
+        self
+    }
+
+    pub fn with_widths(mut self, widths: WidthDistribution) -> Self {
+        match &widths {
+            WidthDistribution::Uniform { min, max } => assert!(min <= max, "empty width range {}..={}", min, max),
+            WidthDistribution::Weighted(weights) => {
+                assert!(weights.iter().any(|&(_, w)| w > 0), "width weights are all zero")
+            }
+            _ => {}
+        }
+        self.widths = widths;
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Ticks generated so far
+    pub fn ticks(&self) -> u64 {
+        self.tick
+    }
+
+    /// Start the sequence over from the seed
+    pub fn rewind(&mut self) {
+        self.state = self.seed;
+        self.tick = 0;
+    }
+
+    pub fn next_tick(&mut self) -> TickInput {
+        let clk = match self.clock {
+            ClockPattern::Toggle => !self.tick.is_multiple_of(2),
+            ClockPattern::DutyCycle { high, low } => {
+                let period = high as u64 + low as u64;
+                // Start low so the first high tick is a rising edge
+                self.tick % period >= low as u64
+            }
+            ClockPattern::Random => self.next_u64() & 1 == 1,
+        };
+        let reset = self.reset_probability > 0.0 && self.next_f64() < self.reset_probability;
+        let width = self.next_width();
+        let x = self.operand(width);
+        self.tick += 1;
+        TickInput::new(clk, reset, x)
+    }
+
+    /// The next `n` ticks
+    pub fn generate(&mut self, n: usize) -> Vec<TickInput> {
+        (0..n).map(|_| self.next_tick()).collect()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        splitmix64(&mut self.state)
+    }
+
+    /// Uniform in `[0, 1)`
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn next_width(&mut self) -> u32 {
+        match &self.widths {
+            WidthDistribution::Full => X_BITS,
+            &WidthDistribution::Fixed(bits) => bits,
+            &WidthDistribution::Uniform { min, max } => min + below(&mut self.state, (max - min) as u64 + 1) as u32,
+            WidthDistribution::Weighted(weights) => {
+                let total: u64 = weights.iter().map(|&(_, w)| w as u64).sum();
+                let mut pick = below(&mut self.state, total);
+                for &(bits, weight) in weights {
+                    if pick < weight as u64 {
+                        return bits;
+                    }
+                    pick -= weight as u64;
+                }
+                unreachable!("pick is below the total weight")
+            }
+        }
+    }
+
+    /// A random operand of `bits` bits; for `Full` the top bit is random too
+    fn operand(&mut self, bits: u32) -> Integer {
+        if bits == 0 {
+            return Integer::new();
+        }
+        let limbs: Vec<u64> = (0..bits.div_ceil(64)).map(|_| self.next_u64()).collect();
+        let mut x = Integer::from_digits(&limbs, Order::Lsf).keep_bits(bits);
+        if self.widths != WidthDistribution::Full {
+            x.set_bit(bits - 1, true);
+        }
+        x
+    }
+}
+
+/// Uniform in `0..n`, without modulo bias worth caring about
+fn below(state: &mut u64, n: u64) -> u64 {
+    ((splitmix64(state) as u128 * n as u128) >> 64) as u64
+}
+
+impl Iterator for StimulusGenerator {
+    type Item = TickInput;
+
+    fn next(&mut self) -> Option<TickInput> {
+        Some(self.next_tick())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reproducible() {
+        let generator = StimulusGenerator::new(7).with_reset_probability(0.25).with_clock(ClockPattern::Random);
+        let a = generator.clone().generate(200);
+        let mut b = generator.clone();
+        assert_eq!(a, b.generate(200));
+        b.rewind();
+        assert_eq!(a, b.take(200).collect::<Vec<_>>());
+        assert_ne!(a, StimulusGenerator::new(8).with_reset_probability(0.25).with_clock(ClockPattern::Random).generate(200));
+
+        let resets = a.iter().filter(|t| t.reset).count();
+        assert!((25..=75).contains(&resets), "{} resets", resets);
+        assert!(a.iter().all(|t| t.x.significant_bits() <= X_BITS));
+    }
+
+    #[test]
+    fn test_clock_patterns() {
+        let clocks = |pattern| StimulusGenerator::new(1).with_clock(pattern).generate(10).iter().map(|t| t.clk as u8).collect::<Vec<_>>();
+        assert_eq!(clocks(ClockPattern::Toggle), [0, 1, 0, 1, 0, 1, 0, 1, 0, 1]);
+        assert_eq!(clocks(ClockPattern::DutyCycle { high: 3, low: 2 }), [0, 0, 1, 1, 1, 0, 0, 1, 1, 1]);
+        assert!(StimulusGenerator::new(1).generate(100).iter().all(|t| !t.reset));
+    }
+
+    #[test]
+    fn test_width_distributions() {
+        let widths = |widths| StimulusGenerator::new(3).with_widths(widths).generate(300).iter().map(|t| t.x.significant_bits()).collect::<Vec<_>>();
+        assert!(widths(WidthDistribution::Fixed(17)).iter().all(|&w| w == 17));
+        assert!(widths(WidthDistribution::Fixed(0)).iter().all(|&w| w == 0));
+
+        let uniform = widths(WidthDistribution::Uniform { min: 250, max: 310 });
+        assert!(uniform.iter().all(|w| (250..=310).contains(w)));
+        assert!(uniform.contains(&250) && uniform.contains(&310));
+
+        let weighted = widths(WidthDistribution::Weighted(vec![(8, 1), (256, 0), (300, 3)]));
+        let narrow = weighted.iter().filter(|&&w| w == 8).count();
+        assert!(weighted.iter().all(|&w| w == 8 || w == 300));
+        assert!((40..=110).contains(&narrow), "{} narrow operands", narrow);
+    }
+}
@@ -0,0 +1,173 @@
+//! Valid/ready streaming wrapper, modelling the machine as a reduction block
+//! between two AXI-Stream-style interfaces.
+//!
+//! A transfer happens on a rising edge where both `valid` and `ready` are
+//! high. The block holds one result: `in_ready` is high while the output
+//! register is empty or being drained on the same edge
+//! (`!out_valid || out_ready`), so a consumer that is always ready sees one
+//! result per edge, and a stalled consumer stalls the producer.
+
+use crate::ModuloMachine;
+use rug::Integer;
+
+/// Interface signals after a tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamPorts<'a> {
+    /// The block would accept an input on the next edge (given the same
+    /// `out_ready`)
+    pub in_ready: bool,
+    /// `out_data` holds a result not yet taken
+    pub out_valid: bool,
+    pub out_data: &'a Integer,
+}
+
+/// [`ModuloMachine`] behind valid/ready handshakes on input and output
+pub struct StreamInterface {
+    machine: ModuloMachine,
+    out_valid: bool,
+    clk_prev: bool,
+    accepted: u64,
+    delivered: u64,
+}
+
+impl StreamInterface {
+    pub fn new(machine: ModuloMachine) -> Self {
+        Self {
+            machine,
+            out_valid: false,
+            clk_prev: false,
+            accepted: 0,
+            delivered: 0,
+        }
+    }
+
+    /// Process one clock cycle
+    /// - clk: clock input
+    /// - reset: clears the output register and drops any result
+    /// - in_valid/in_data: the producer's side of the input handshake
+    /// - out_ready: the consumer's side of the output handshake
+    ///
+    /// Returns the interface signals after the tick
+    pub fn tick(&mut self, clk: bool, reset: bool, in_valid: bool, in_data: &Integer, out_ready: bool) -> StreamPorts<'_> {
+        if reset {
+            self.reset();
+        } else {
+            if clk && !self.clk_prev {
+                let take_in = in_valid && self.in_ready(out_ready);
+                if self.out_valid && out_ready {
+                    self.out_valid = false;
+                    self.delivered += 1;
+                }
+                if take_in {
+                    self.machine.cycle(in_data);
+                    self.out_valid = true;
+                    self.accepted += 1;
+                }
+            }
+            self.clk_prev = clk;
+        }
+        self.ports(out_ready)
+    }
+
+    /// Whether an input would be taken on the next edge, given `out_ready`
+    pub fn in_ready(&self, out_ready: bool) -> bool {
+        !self.out_valid || out_ready
+    }
+
+    /// Current interface signals for a consumer driving `out_ready`
+    pub fn ports(&self, out_ready: bool) -> StreamPorts<'_> {
+        StreamPorts {
+            in_ready: self.in_ready(out_ready),
+            out_valid: self.out_valid,
+            out_data: self.machine.get_output(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.machine.reset();
+        self.out_valid = false;
+        self.clk_prev = false;
+    }
+
+    /// Input transfers since creation
+    pub fn accepted(&self) -> u64 {
+        self.accepted
+    }
+
+    /// Output transfers since creation
+    pub fn delivered(&self) -> u64 {
+        self.delivered
+    }
+
+    pub fn machine(&self) -> &ModuloMachine {
+        &self.machine
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Drive `inputs` through the block with the consumer ready on the
+    /// cycles `ready` says, collecting delivered results
+    fn run(inputs: &[Integer], ready: impl Fn(usize) -> bool, max_cycles: usize) -> (Vec<Integer>, usize) {
+        let mut stream = StreamInterface::new(ModuloMachine::new());
+        let mut next = 0;
+        let mut results = Vec::new();
+        for cycle in 0..max_cycles {
+            let out_ready = ready(cycle);
+            let in_valid = next < inputs.len();
+            let data = inputs.get(next).cloned().unwrap_or_default();
+            stream.tick(false, false, in_valid, &data, out_ready);
+            // Sample both handshakes just before the edge
+            let before = stream.ports(out_ready);
+            let (took_in, took_out) = (in_valid && before.in_ready, before.out_valid && out_ready);
+            if took_out {
+                results.push(before.out_data.clone());
+            }
+            stream.tick(true, false, in_valid, &data, out_ready);
+            next += took_in as usize;
+            if results.len() == inputs.len() {
+                return (results, cycle + 1);
+            }
+        }
+        (results, max_cycles)
+    }
+
+    #[test]
+    fn test_full_throughput_and_backpressure() {
+        let p = ModuloMachine::new().get_prime().clone();
+        let inputs: Vec<Integer> = (1..=6u32).map(|i| Integer::from(&p + i)).collect();
+        let expected: Vec<Integer> = (1..=6u32).map(Integer::from).collect();
+
+        // Always ready: one result per edge after the first
+        let (results, cycles) = run(&inputs, |_| true, 20);
+        assert_eq!(results, expected);
+        assert_eq!(cycles, 7);
+
+        // Ready every third cycle: nothing is lost or duplicated
+        let (results, _) = run(&inputs, |cycle| cycle % 3 == 2, 40);
+        assert_eq!(results, expected);
+    }
+
+    #[test]
+    fn test_stalled_consumer_holds_data() {
+        let mut stream = StreamInterface::new(ModuloMachine::new());
+        let (a, b) = (Integer::from(5u32), Integer::from(6u32));
+        stream.tick(false, false, true, &a, false);
+        let ports = stream.tick(true, false, true, &a, false);
+        assert!(ports.out_valid && !ports.in_ready);
+        assert_eq!(*ports.out_data, 5);
+
+        // b is offered but refused while the consumer stalls
+        stream.tick(false, false, true, &b, false);
+        let ports = stream.tick(true, false, true, &b, false);
+        assert_eq!(*ports.out_data, 5);
+        assert_eq!(stream.accepted(), 1);
+
+        // Reset drops the pending result
+        let ports = stream.tick(false, true, true, &b, false);
+        assert!(!ports.out_valid && ports.in_ready);
+        assert_eq!(stream.delivered(), 0);
+    }
+}
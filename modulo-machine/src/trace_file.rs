@@ -0,0 +1,200 @@
+//! Plain-text machine traces: the stimulus plus the output after each tick.
+//!
+//! One tick per line, `clk reset x o` with `x` and `o` in hex, extending the
+//! triage `stimulus.txt` format with the output column. Blank lines and lines
+//! starting with `#` are ignored on reading.
+//!
+//! Stimulus files without the output column, as RTL flows write them, are
+//! read with [`read_stimulus`] and replayed with [`run_stimulus_file`]. Their
+//! fields may also be separated by commas, and `x` may carry a `0x` prefix.
+//!
+//! [`pipe`] speaks the same stimulus format line by line, for external tools
+//! driving the model as a subprocess (`modmachine --pipe`).
+
+use crate::{ModuloMachine, TickInput};
+use rug::Integer;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+/// One recorded tick: the stimulus and the output after it
+pub type TraceEntry = (TickInput, Integer);
+
+/// Run `stimulus` through `machine`, pairing each tick with its output
+pub fn record(machine: &mut ModuloMachine, stimulus: &[TickInput]) -> Vec<TraceEntry> {
+    stimulus
+        .iter()
+        .map(|t| (t.clone(), machine.tick(t.clk, t.reset, &t.x).clone()))
+        .collect()
+}
+
+pub fn write_trace<'a, W: Write>(
+    mut writer: W,
+    entries: impl IntoIterator<Item = (&'a TickInput, &'a Integer)>,
+) -> io::Result<()> {
+    for (t, output) in entries {
+        writeln!(
+            writer,
+            "{} {} {} {}",
+            t.clk as u8,
+            t.reset as u8,
+            t.x.to_string_radix(16),
+            output.to_string_radix(16)
+        )?;
+    }
+    Ok(())
+}
+
+/// SHA-256 of the trace in this format: two runs share a digest exactly when
+/// every tick and output matches
+pub fn digest<'a>(entries: impl IntoIterator<Item = (&'a TickInput, &'a Integer)>) -> [u8; 32] {
+    let mut text = Vec::new();
+    write_trace(&mut text, entries).expect("writing to memory cannot fail");
+    Sha256::digest(text).into()
+}
+
+pub fn read_trace<R: BufRead>(reader: R) -> io::Result<Vec<TraceEntry>> {
+    read_lines(reader, "clk reset x o", |fields| match fields {
+        [clk, reset, x, o] => Some((TickInput::new(bit(clk)?, bit(reset)?, hex(x)?), hex(o)?)),
+        _ => None,
+    })
+}
+
+/// Read a stimulus file: one tick per line, `clk reset x` with `x` in hex
+pub fn read_stimulus<R: BufRead>(reader: R) -> io::Result<Vec<TickInput>> {
+    read_lines(reader, "clk reset x", |fields| match fields {
+        [clk, reset, x] => Some(TickInput::new(bit(clk)?, bit(reset)?, hex(x)?)),
+        _ => None,
+    })
+}
+
+/// Replay the stimulus file at `path` through a fresh machine and return the
+/// output after each tick
+pub fn run_stimulus_file(path: impl AsRef<Path>) -> io::Result<Vec<Integer>> {
+    let stimulus = read_stimulus(BufReader::new(File::open(path)?))?;
+    let mut machine = ModuloMachine::new();
+    Ok(stimulus.iter().map(|t| machine.tick(t.clk, t.reset, &t.x).clone()).collect())
+}
+
+/// Answer each stimulus line from `reader` with one line on `writer`,
+/// flushed immediately: the output after the tick in hex, or
+/// `error: line N: ...` for a malformed line, which applies no tick
+///
+/// Blank and `#` lines get no reply. Returns the number of ticks applied.
+pub fn pipe<R: BufRead, W: Write>(machine: &mut ModuloMachine, reader: R, mut writer: W) -> io::Result<u64> {
+    let mut ticks = 0;
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|f| !f.is_empty()).collect();
+        let tick = match fields.as_slice() {
+            [clk, reset, x] => bit(clk).zip(bit(reset)).zip(hex(x)),
+            _ => None,
+        };
+        match tick {
+            Some(((clk, reset), x)) => {
+                writeln!(writer, "{}", machine.tick(clk, reset, &x).to_string_radix(16))?;
+                ticks += 1;
+            }
+            None => writeln!(writer, "error: line {}: expected `clk reset x`", i + 1)?,
+        }
+        writer.flush()?;
+    }
+    Ok(ticks)
+}
+
+/// Parse every non-blank, non-comment line with `parse`, which gets the
+/// line's whitespace- or comma-separated fields
+fn read_lines<R: BufRead, T>(reader: R, format: &str, parse: impl Fn(&[&str]) -> Option<T>) -> io::Result<Vec<T>> {
+    let mut items = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|f| !f.is_empty()).collect();
+        let item = parse(&fields)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: expected `{}`", i + 1, format)))?;
+        items.push(item);
+    }
+    Ok(items)
+}
+
+fn bit(s: &str) -> Option<bool> {
+    match s {
+        "0" => Some(false),
+        "1" => Some(true),
+        _ => None,
+    }
+}
+
+/// Unsigned hex, optionally `0x`-prefixed
+fn hex(s: &str) -> Option<Integer> {
+    let digits = s.strip_prefix("0x").unwrap_or(s);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Integer::from_str_radix(digits, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_round_trip() {
+        let p = ModuloMachine::new().get_prime().clone();
+        let stimulus = [
+            TickInput::new(false, true, Integer::new()),
+            TickInput::new(false, false, Integer::from(&p + 5u32)),
+            TickInput::new(true, false, Integer::from(&p + 5u32)),
+        ];
+        let entries = record(&mut ModuloMachine::new(), &stimulus);
+        assert_eq!(entries[2].1, 5);
+
+        let mut text = Vec::new();
+        write_trace(&mut text, entries.iter().map(|(t, o)| (t, o))).unwrap();
+        assert!(String::from_utf8_lossy(&text).ends_with(" 5\n"));
+        assert_eq!(read_trace(&text[..]).unwrap(), entries);
+        let digest = digest(entries.iter().map(|(t, o)| (t, o)));
+        assert_eq!(digest, <[u8; 32]>::from(Sha256::digest(&text)));
+        assert_ne!(digest, super::digest(entries[..2].iter().map(|(t, o)| (t, o))));
+
+        assert!(read_trace(&b"# comment\n\n1 0 ff 0\n"[..]).is_ok());
+
+        // One reply per tick line, errors included, none for blank lines
+        let input = format!("0 1 0\n\n0 0 {0}\n1 0 0x{0}\nbogus\n# done\n", Integer::from(&p + 5u32).to_string_radix(16));
+        let mut out = Vec::new();
+        assert_eq!(pipe(&mut ModuloMachine::new(), input.as_bytes(), &mut out).unwrap(), 3);
+        assert_eq!(String::from_utf8(out).unwrap(), "0\n0\n5\nerror: line 5: expected `clk reset x`\n");
+        for bad in ["1 0 ff", "2 0 ff 0", "1 0 -ff 0", "1 0 zz 0"] {
+            assert!(read_trace(bad.as_bytes()).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_stimulus_file() {
+        let p = ModuloMachine::new().get_prime().clone();
+        let p_plus_5 = Integer::from(&p + 5u32).to_string_radix(16);
+        let text = format!("# clk reset x\n0 1 0\n0, 0, 0x{0}\n1,0,{0}\n\n1 0 {0}\n", p_plus_5);
+        let stimulus = read_stimulus(text.as_bytes()).unwrap();
+        assert_eq!(stimulus.len(), 4);
+        assert_eq!(stimulus[1], TickInput::new(false, false, Integer::from(&p + 5u32)));
+
+        let path = std::env::temp_dir().join(format!("stimulus-{}.txt", std::process::id()));
+        std::fs::write(&path, &text).unwrap();
+        let outputs = run_stimulus_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(outputs, [0u32, 0, 5, 5].map(Integer::from));
+
+        for bad in ["1 0", "1 0 ff 0", "1 0 0x", "1 0 0x-1", "1 0 +f"] {
+            assert!(read_stimulus(bad.as_bytes()).is_err(), "{}", bad);
+        }
+        assert!(run_stimulus_file("/nonexistent/stimulus.txt").is_err());
+    }
+}
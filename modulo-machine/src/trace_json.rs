@@ -0,0 +1,226 @@
+//! JSON export of a recorded run, and queries over it for post-mortem
+//! analysis.
+//!
+//! A [`TraceDocument`] is a whole [`Trace`] as one JSON object: the modulus
+//! and, per tick, its cycle index, simulation time, `clk`/`reset` pins, an
+//! `edge` flag for ticks that latched, and the input and output as
+//! `0x`-prefixed hex strings:
+//!
+//! ```json
+//! {"format": "modulo-machine-trace", "version": 1, "modulus": "0xe7eb...",
+//!  "ticks": [{"cycle": 0, "time": 0, "clk": false, "reset": false,
+//!             "edge": false, "x": "0x5", "output": "0x0"}, ...]}
+//! ```
+//!
+//! Read back with [`TraceDocument::read`], a document answers the usual
+//! first questions about a failure: [`first_above`] finds the first cycle
+//! whose output exceeded a threshold and [`first_mismatch`] the first whose
+//! output differs from an expected-output file read with [`read_expected`].
+//!
+//! [`first_above`]: TraceDocument::first_above
+//! [`first_mismatch`]: TraceDocument::first_mismatch
+
+use crate::record::Trace;
+use crate::{format_radix, ModuloMachine};
+use rug::Integer;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Read, Write};
+
+/// Value of the document's `format` field
+pub const FORMAT: &str = "modulo-machine-trace";
+/// Bumped whenever the document layout changes
+pub const VERSION: u64 = 1;
+
+/// One exported tick
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonTick {
+    /// Index of the tick in the run
+    pub cycle: u64,
+    pub time: u64,
+    pub clk: bool,
+    pub reset: bool,
+    /// Whether the clock rose outside reset on this tick
+    pub edge: bool,
+    pub x: Integer,
+    /// Output after the tick
+    pub output: Integer,
+}
+
+/// A run as exported to JSON
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceDocument {
+    pub modulus: Integer,
+    pub ticks: Vec<JsonTick>,
+}
+
+impl TraceDocument {
+    pub fn from_trace(trace: &Trace) -> Self {
+        let mut clk_prev = trace.start.clk();
+        let ticks = trace
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(cycle, e)| {
+                let edge = e.clk && !clk_prev && !e.reset;
+                clk_prev = e.clk && !e.reset;
+                JsonTick {
+                    cycle: cycle as u64,
+                    time: e.time,
+                    clk: e.clk,
+                    reset: e.reset,
+                    edge,
+                    x: e.x.clone(),
+                    output: e.output.clone(),
+                }
+            })
+            .collect();
+        Self {
+            modulus: trace.start.modulus().clone(),
+            ticks,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let ticks: Vec<Value> = self
+            .ticks
+            .iter()
+            .map(|t| {
+                json!({
+                    "cycle": t.cycle,
+                    "time": t.time,
+                    "clk": t.clk,
+                    "reset": t.reset,
+                    "edge": t.edge,
+                    "x": format_radix(&t.x, 16),
+                    "output": format_radix(&t.output, 16),
+                })
+            })
+            .collect();
+        json!({
+            "format": FORMAT,
+            "version": VERSION,
+            "modulus": format_radix(&self.modulus, 16),
+            "ticks": ticks,
+        })
+    }
+
+    /// Write the document as one line of JSON
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        serde_json::to_writer(&mut writer, &self.to_json())?;
+        writeln!(writer)
+    }
+
+    pub fn read<R: Read>(reader: R) -> io::Result<Self> {
+        let doc: Value = serde_json::from_reader(reader).map_err(|e| invalid(e.to_string()))?;
+        if doc["format"] != FORMAT {
+            return Err(invalid(format!("not a {} document", FORMAT)));
+        }
+        if doc["version"] != VERSION {
+            return Err(invalid(format!("unsupported version {}", doc["version"])));
+        }
+        let modulus = integer(&doc["modulus"]).ok_or_else(|| invalid("bad modulus"))?;
+        let ticks = doc["ticks"]
+            .as_array()
+            .ok_or_else(|| invalid("missing ticks"))?
+            .iter()
+            .enumerate()
+            .map(|(i, t)| {
+                let flag = |name: &str| t[name].as_bool();
+                let parsed = (|| {
+                    Some(JsonTick {
+                        cycle: t["cycle"].as_u64()?,
+                        time: t["time"].as_u64()?,
+                        clk: flag("clk")?,
+                        reset: flag("reset")?,
+                        edge: flag("edge")?,
+                        x: integer(&t["x"])?,
+                        output: integer(&t["output"])?,
+                    })
+                })();
+                parsed.ok_or_else(|| invalid(format!("tick {}: expected cycle, time, clk, reset, edge, x and output", i)))
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self { modulus, ticks })
+    }
+
+    /// The first tick whose output is greater than `threshold`, e.g. the
+    /// modulus to catch an unreduced result
+    pub fn first_above(&self, threshold: &Integer) -> Option<&JsonTick> {
+        self.ticks.iter().find(|t| t.output > *threshold)
+    }
+
+    /// The first tick whose output differs from `expected`, one value per
+    /// tick; ticks beyond the end of `expected` are not compared
+    pub fn first_mismatch(&self, expected: &[Integer]) -> Option<&JsonTick> {
+        self.ticks.iter().zip(expected).find(|(t, e)| t.output != **e).map(|(t, _)| t)
+    }
+}
+
+/// Expected outputs, one per line in `radix` (or `0x`/`0o`/`0b` prefixed);
+/// blank lines and lines starting with `#` are skipped
+pub fn read_expected<R: BufRead>(reader: R, radix: i32) -> io::Result<Vec<Integer>> {
+    let mut values = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let text = line.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        let value = ModuloMachine::create_input(text, radix).map_err(|e| invalid(format!("line {}: {}", i + 1, e)))?;
+        values.push(value);
+    }
+    Ok(values)
+}
+
+fn integer(value: &Value) -> Option<Integer> {
+    ModuloMachine::create_input(value.as_str()?, 10).ok()
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::Recorder;
+
+    #[test]
+    fn test_export_and_queries() {
+        let mut recorder = Recorder::new(ModuloMachine::new());
+        let p = recorder.machine().get_prime().clone();
+        let x = Integer::from(&p + 7u32);
+        recorder.tick(false, false, &x);
+        recorder.tick(true, false, &x);
+        recorder.tick(true, false, &Integer::from(9));
+        recorder.tick(true, true, &Integer::from(9));
+        recorder.tick(true, false, &Integer::from(9));
+        let doc = TraceDocument::from_trace(recorder.trace());
+        assert_eq!(doc.ticks.iter().map(|t| t.edge).collect::<Vec<_>>(), [false, true, false, false, true]);
+
+        let mut buffer = Vec::new();
+        doc.write(&mut buffer).unwrap();
+        let json: Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json["ticks"][1]["output"], "0x7");
+        assert_eq!(json["ticks"][4]["cycle"], 4);
+        let read = TraceDocument::read(buffer.as_slice()).unwrap();
+        assert_eq!(read, doc);
+
+        assert_eq!(doc.first_above(&Integer::from(8)).map(|t| t.cycle), Some(4));
+        assert_eq!(doc.first_above(&p), None);
+
+        let expected = read_expected("# outputs\n0\n7\n\n7\n0x1\n".as_bytes(), 16).unwrap();
+        assert_eq!(doc.first_mismatch(&expected).map(|t| (t.cycle, t.time)), Some((3, 3)));
+        assert_eq!(doc.first_mismatch(&expected[..3]), None);
+        assert!(read_expected("7\nzz\n".as_bytes(), 16).unwrap_err().to_string().starts_with("line 2: "));
+    }
+
+    #[test]
+    fn test_read_rejects_other_documents() {
+        for bad in [r#"{"format": "other", "version": 1}"#, r#"{"format": "modulo-machine-trace", "version": 2}"#, "[1, 2"] {
+            assert_eq!(TraceDocument::read(bad.as_bytes()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+        let missing = r#"{"format": "modulo-machine-trace", "version": 1, "modulus": "0x61", "ticks": [{"cycle": 0}]}"#;
+        assert!(TraceDocument::read(missing.as_bytes()).unwrap_err().to_string().starts_with("tick 0: "));
+    }
+}
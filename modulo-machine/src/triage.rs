@@ -0,0 +1,306 @@
+//! Mismatch triage: shrink a failing trace and bundle everything needed to
+//! debug it.
+//!
+//! Given a stimulus on which a device under test diverges from the reference
+//! model, [`triage`] produces a [`TriageBundle`] holding
+//!
+//! - the minimal stimulus that still reproduces a divergence, found by
+//!   truncating after the first mismatch and then greedily deleting chunks of
+//!   ticks (delta debugging),
+//! - the reference state just before the failing tick, and
+//! - a VCD of the ticks around the failure with both models' outputs.
+
+use crate::model::ModuloModel;
+use crate::vcd::VcdWriter;
+use crate::{TickInput, X_BITS};
+use rug::Integer;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Ticks on each side of the failure included in the VCD by default
+pub const DEFAULT_WINDOW: usize = 16;
+
+/// First tick at which two models disagree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub tick: usize,
+    pub expected: Integer,
+    pub actual: Integer,
+}
+
+/// Reference-model state immediately before the failing tick
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateBeforeFailure {
+    pub output: Integer,
+    /// Clock level the model last saw (low after a reset)
+    pub clk: bool,
+}
+
+/// Self-contained reproduction of a mismatch
+#[derive(Debug, Clone)]
+pub struct TriageBundle {
+    /// Tick of the first divergence in the original stimulus
+    pub original_tick: usize,
+    /// Length of the original stimulus
+    pub original_len: usize,
+    /// Minimal reproducing stimulus; it diverges on its last tick
+    pub stimulus: Vec<TickInput>,
+    /// Divergence within the minimal stimulus
+    pub divergence: Divergence,
+    pub state_before: StateBeforeFailure,
+    /// Waveform around the failure (clk, reset, x, reference and DUT outputs),
+    /// timestamped with the reference model's [`ModuloModel::time_step`]
+    pub vcd: String,
+}
+
+/// Reset both models and run `stimulus` until their outputs first differ
+pub fn first_divergence<R, D>(reference: &mut R, dut: &mut D, stimulus: &[TickInput]) -> Option<Divergence>
+where
+    R: ModuloModel,
+    D: ModuloModel,
+{
+    reference.reset();
+    dut.reset();
+    stimulus.iter().enumerate().find_map(|(tick, t)| {
+        let expected = reference.tick(t.clk, t.reset, &t.x);
+        let actual = dut.tick(t.clk, t.reset, &t.x);
+        (expected != actual).then_some(Divergence {
+            tick,
+            expected,
+            actual,
+        })
+    })
+}
+
+/// Smallest stimulus found that still makes the models diverge
+///
+/// Returns `stimulus` unchanged if it does not diverge at all.
+pub fn shrink<R, D>(reference: &mut R, dut: &mut D, stimulus: &[TickInput]) -> Vec<TickInput>
+where
+    R: ModuloModel,
+    D: ModuloModel,
+{
+    let mut current = match first_divergence(reference, dut, stimulus) {
+        Some(d) => stimulus[..=d.tick].to_vec(),
+        None => return stimulus.to_vec(),
+    };
+
+    let mut chunk = current.len() / 2;
+    while chunk > 0 {
+        let mut start = 0;
+        while start < current.len() {
+            let end = (start + chunk).min(current.len());
+            let candidate = [&current[..start], &current[end..]].concat();
+            match first_divergence(reference, dut, &candidate) {
+                // Keep the deletion and retry at the same position
+                Some(d) => current = candidate[..=d.tick].to_vec(),
+                None => start += chunk,
+            }
+        }
+        chunk /= 2;
+    }
+    current
+}
+
+/// Shrink a failing stimulus and collect a triage bundle, or `None` if the
+/// models agree on it
+pub fn triage<R, D>(reference: &mut R, dut: &mut D, stimulus: &[TickInput], window: usize) -> Option<TriageBundle>
+where
+    R: ModuloModel,
+    D: ModuloModel,
+{
+    let original = first_divergence(reference, dut, stimulus)?;
+    let minimal = shrink(reference, dut, stimulus);
+    let divergence = first_divergence(reference, dut, &minimal).expect("shrunk stimulus still diverges");
+
+    // Replay both models over the minimal stimulus, capturing the state before
+    // the failing tick and the outputs for the waveform
+    reference.reset();
+    dut.reset();
+    let mut state_before = None;
+    let mut clk = false;
+    let mut rows = Vec::with_capacity(minimal.len());
+    for (tick, t) in minimal.iter().enumerate() {
+        if tick == divergence.tick {
+            state_before = Some(StateBeforeFailure {
+                output: reference.output(),
+                clk,
+            });
+        }
+        let expected = reference.tick(t.clk, t.reset, &t.x);
+        rows.push((expected, dut.tick(t.clk, t.reset, &t.x)));
+        clk = t.clk && !t.reset;
+    }
+
+    let start = divergence.tick.saturating_sub(window);
+    let vcd = window_vcd(&minimal, &rows, start, reference.time_step()).expect("writing to memory cannot fail");
+
+    Some(TriageBundle {
+        original_tick: original.tick,
+        original_len: stimulus.len(),
+        state_before: state_before.expect("divergence lies within the stimulus"),
+        stimulus: minimal,
+        divergence,
+        vcd,
+    })
+}
+
+/// Waveform of `stimulus[start..]`, tick `n` stamped at `n * time_step`
+fn window_vcd(
+    stimulus: &[TickInput],
+    outputs: &[(Integer, Integer)],
+    start: usize,
+    time_step: u64,
+) -> io::Result<String> {
+    let signals = [("clk", 1), ("reset", 1), ("x", X_BITS), ("o_ref", 256), ("o_dut", 256)];
+    let mut vcd = VcdWriter::new(Vec::new(), "1ns", "modulo_machine", &signals)?;
+    for (tick, (t, (expected, actual))) in stimulus.iter().zip(outputs).enumerate().skip(start) {
+        let clk = Integer::from(t.clk as u32);
+        let reset = Integer::from(t.reset as u32);
+        vcd.sample(tick as u64 * time_step, &[&clk, &reset, &t.x, expected, actual])?;
+    }
+    Ok(String::from_utf8(vcd.finish()?).expect("VCD is ASCII"))
+}
+
+impl TriageBundle {
+    /// Human-readable summary of the mismatch
+    pub fn summary(&self) -> String {
+        format!(
+            "Divergence at tick {} of {} (shrunk to {} ticks)\n\
+             expected: 0x{}\n\
+             actual:   0x{}\n\
+             reference before failure: output=0x{} clk={}\n",
+            self.original_tick,
+            self.original_len,
+            self.stimulus.len(),
+            self.divergence.expected.to_string_radix(16),
+            self.divergence.actual.to_string_radix(16),
+            self.state_before.output.to_string_radix(16),
+            self.state_before.clk as u8,
+        )
+    }
+
+    /// Stimulus as text, one tick per line: `clk reset x` with `x` in hex
+    pub fn stimulus_text(&self) -> String {
+        self.stimulus
+            .iter()
+            .map(|t| format!("{} {} {}\n", t.clk as u8, t.reset as u8, t.x.to_string_radix(16)))
+            .collect()
+    }
+
+    /// Write `summary.txt`, `stimulus.txt` and `window.vcd` into `dir`
+    pub fn write_to_dir(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join("summary.txt"), self.summary())?;
+        fs::write(dir.join("stimulus.txt"), self.stimulus_text())?;
+        fs::write(dir.join("window.vcd"), &self.vcd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ModuloMachine;
+
+    /// DUT with an injected bug: the first edge after a reset skips the
+    /// reduction for inputs of P or more
+    struct SkipsReductionAfterReset {
+        machine: ModuloMachine,
+        output: Integer,
+        just_reset: bool,
+        clk_prev: bool,
+    }
+
+    impl SkipsReductionAfterReset {
+        fn new() -> Self {
+            Self {
+                machine: ModuloMachine::new(),
+                output: Integer::new(),
+                just_reset: false,
+                clk_prev: false,
+            }
+        }
+    }
+
+    impl ModuloModel for SkipsReductionAfterReset {
+        fn tick(&mut self, clk: bool, reset: bool, x: &Integer) -> Integer {
+            let edge = clk && !self.clk_prev && !reset;
+            self.output = ModuloModel::tick(&mut self.machine, clk, reset, x);
+            self.clk_prev = clk && !reset;
+            if reset {
+                self.just_reset = true;
+            } else if edge {
+                if self.just_reset && *x >= *self.machine.get_prime() {
+                    self.output = Integer::from(x.keep_bits_ref(256));
+                }
+                self.just_reset = false;
+            }
+            self.output.clone()
+        }
+
+        fn output(&self) -> Integer {
+            self.output.clone()
+        }
+
+        fn reset(&mut self) {
+            self.machine.reset();
+            self.output = Integer::new();
+            self.just_reset = false;
+            self.clk_prev = false;
+        }
+    }
+
+    fn stimulus() -> Vec<TickInput> {
+        let p = ModuloMachine::new().get_prime().clone();
+        let mut stimulus = Vec::new();
+        for i in 0..40u32 {
+            // Large values without a preceding reset are handled correctly
+            let x = if i % 3 == 0 { Integer::from(&p + i) } else { Integer::from(i) };
+            stimulus.push(TickInput::new(false, false, x.clone()));
+            stimulus.push(TickInput::new(true, false, x));
+        }
+        stimulus.push(TickInput::new(false, true, Integer::new()));
+        for i in 0..10u32 {
+            let x = Integer::from(&p + i);
+            stimulus.push(TickInput::new(false, false, x.clone()));
+            stimulus.push(TickInput::new(true, false, x));
+        }
+        stimulus
+    }
+
+    #[test]
+    fn test_triage_shrinks_to_minimal_reproduction() {
+        let stimulus = stimulus();
+        let mut reference = ModuloMachine::new();
+        let mut dut = SkipsReductionAfterReset::new();
+
+        let bundle = triage(&mut reference, &mut dut, &stimulus, DEFAULT_WINDOW).unwrap();
+        assert_eq!(bundle.original_tick, 82);
+        assert_eq!(bundle.original_len, stimulus.len());
+
+        // A reset followed by one rising edge with x >= P is all it takes
+        assert_eq!(bundle.stimulus.len(), 2);
+        assert!(bundle.stimulus[0].reset);
+        assert!(bundle.stimulus[1].clk && !bundle.stimulus[1].reset);
+        assert_eq!(bundle.divergence.tick, 1);
+        assert_eq!(bundle.state_before, StateBeforeFailure { output: Integer::new(), clk: false });
+
+        assert!(bundle.vcd.contains("o_dut [255:0]"));
+
+        // Timestamps follow the reference's time step
+        let mut reference = ModuloMachine::new();
+        reference.set_time_step(5);
+        let bundle = triage(&mut reference, &mut dut, &stimulus, DEFAULT_WINDOW).unwrap();
+        assert!(bundle.vcd.contains("\n#5\n"));
+        assert!(bundle.summary().contains("tick 82"));
+        assert_eq!(bundle.stimulus_text().lines().count(), 2);
+    }
+
+    #[test]
+    fn test_agreeing_models_produce_no_bundle() {
+        let mut reference = ModuloMachine::new();
+        let mut other = ModuloMachine::new();
+        assert!(triage(&mut reference, &mut other, &stimulus(), DEFAULT_WINDOW).is_none());
+    }
+}
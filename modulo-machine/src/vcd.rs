@@ -0,0 +1,316 @@
+//! Minimal Value Change Dump writer for viewing runs in GTKWave, and a
+//! reader for dumps recorded by RTL simulators.
+
+use rug::Integer;
+use std::fmt;
+use std::io::{self, Write};
+
+/// Streams a VCD file, emitting only the signals that changed at each time
+pub struct VcdWriter<W: Write> {
+    writer: W,
+    signals: Vec<(String, u32)>,
+    last: Vec<Option<Integer>>,
+}
+
+impl<W: Write> VcdWriter<W> {
+    /// Write the header declaring `signals` as `(name, width)` in `scope`
+    pub fn new(mut writer: W, timescale: &str, scope: &str, signals: &[(&str, u32)]) -> io::Result<Self> {
+        writeln!(writer, "$version {} {} $end", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))?;
+        writeln!(writer, "$timescale {} $end", timescale)?;
+        writeln!(writer, "$scope module {} $end", scope)?;
+        for (i, (name, width)) in signals.iter().enumerate() {
+            let reference = if *width == 1 {
+                name.to_string()
+            } else {
+                format!("{} [{}:0]", name, width - 1)
+            };
+            writeln!(writer, "$var wire {} {} {} $end", width, identifier(i), reference)?;
+        }
+        writeln!(writer, "$upscope $end")?;
+        writeln!(writer, "$enddefinitions $end")?;
+
+        Ok(Self {
+            writer,
+            signals: signals.iter().map(|(n, w)| (n.to_string(), *w)).collect(),
+            last: vec![None; signals.len()],
+        })
+    }
+
+    /// Record the values of every signal, in declaration order, at `time`
+    ///
+    /// Negative values are written in two's complement of the signal width.
+    pub fn sample(&mut self, time: u64, values: &[&Integer]) -> io::Result<()> {
+        assert_eq!(values.len(), self.signals.len(), "one value per declared signal");
+
+        let mut stamped = false;
+        for (i, value) in values.iter().enumerate() {
+            if self.last[i].as_ref() == Some(*value) {
+                continue;
+            }
+            if !stamped {
+                writeln!(self.writer, "#{}", time)?;
+                stamped = true;
+            }
+
+            let width = self.signals[i].1;
+            if width == 1 {
+                writeln!(self.writer, "{}{}", if **value == 0 { 0 } else { 1 }, identifier(i))?;
+            } else if **value < 0 {
+                // Two's complement of the declared width
+                let bits = Integer::from(value.keep_bits_ref(width));
+                writeln!(self.writer, "b{} {}", bits.to_string_radix(2), identifier(i))?;
+            } else {
+                writeln!(self.writer, "b{} {}", value.to_string_radix(2), identifier(i))?;
+            }
+            self.last[i] = Some((*value).clone());
+        }
+        Ok(())
+    }
+
+    /// Flush and hand back the underlying writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Short printable identifier codes: `!`, `"`, ... then two characters
+fn identifier(mut index: usize) -> String {
+    const FIRST: u8 = b'!';
+    const COUNT: usize = (b'~' - b'!' + 1) as usize;
+    let mut id = String::new();
+    loop {
+        id.push((FIRST + (index % COUNT) as u8) as char);
+        index /= COUNT;
+        if index == 0 {
+            return id;
+        }
+        index -= 1;
+    }
+}
+
+/// Reason a VCD could not be read or replayed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VcdError {
+    /// Malformed token (with the offending text)
+    Parse { token: String, message: &'static str },
+    /// No variable with this name
+    MissingSignal(String),
+    /// Several variables in different scopes share this name
+    AmbiguousSignal(String),
+}
+
+impl fmt::Display for VcdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VcdError::Parse { token, message } => write!(f, "{} at '{}'", message, token),
+            VcdError::MissingSignal(name) => write!(f, "no signal named '{}'", name),
+            VcdError::AmbiguousSignal(name) => {
+                write!(f, "signal name '{}' is ambiguous; use the full scope path", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VcdError {}
+
+/// A declared variable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VcdSignal {
+    /// Dotted scope path and name, e.g. `tb.dut.clk`
+    pub path: String,
+    pub width: u32,
+    /// Identifier code used in value changes; aliased wires share one
+    pub code: String,
+}
+
+/// One value change, in file order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VcdChange {
+    pub time: u64,
+    pub code: String,
+    pub value: Integer,
+}
+
+/// Parsed contents of a VCD file
+///
+/// Four-state values are read as two-state: `x` and `z` bits become 0. Real
+/// variables are skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VcdDump {
+    pub timescale: Option<String>,
+    pub signals: Vec<VcdSignal>,
+    pub changes: Vec<VcdChange>,
+}
+
+impl VcdDump {
+    pub fn parse(text: &str) -> Result<Self, VcdError> {
+        let mut dump = VcdDump::default();
+        let mut scopes: Vec<&str> = Vec::new();
+        let mut tokens = text.split_whitespace();
+        let mut time = 0;
+
+        while let Some(token) = tokens.next() {
+            match token {
+                "$scope" => {
+                    let _kind = next(&mut tokens, token)?;
+                    scopes.push(next(&mut tokens, token)?);
+                    skip_to_end(&mut tokens)?;
+                }
+                "$upscope" => {
+                    scopes.pop();
+                    skip_to_end(&mut tokens)?;
+                }
+                "$var" => {
+                    let _kind = next(&mut tokens, token)?;
+                    let width = next(&mut tokens, token)?;
+                    let width = width.parse().map_err(|_| parse_error(width, "bad variable width"))?;
+                    let code = next(&mut tokens, token)?.to_string();
+                    let name = next(&mut tokens, token)?;
+                    // An optional bit range like `[255:0]` follows
+                    skip_to_end(&mut tokens)?;
+                    let path = scopes.iter().copied().chain([name]).collect::<Vec<_>>().join(".");
+                    dump.signals.push(VcdSignal { path, width, code });
+                }
+                "$timescale" => {
+                    let mut parts = Vec::new();
+                    for part in tokens.by_ref() {
+                        if part == "$end" {
+                            break;
+                        }
+                        parts.push(part);
+                    }
+                    dump.timescale = Some(parts.concat());
+                }
+                // Value changes inside these sections are ordinary changes
+                "$dumpvars" | "$dumpall" | "$dumpon" | "$dumpoff" | "$end" => {}
+                _ if token.starts_with('$') => skip_to_end(&mut tokens)?,
+                _ if token.starts_with('#') => {
+                    time = token[1..].parse().map_err(|_| parse_error(token, "bad timestamp"))?;
+                }
+                _ => {
+                    let (kind, rest) = token.split_at(1);
+                    let (value, code) = match kind {
+                        "b" | "B" => (parse_bits(rest)?, next(&mut tokens, token)?),
+                        "r" | "R" => {
+                            next(&mut tokens, token)?;
+                            continue;
+                        }
+                        _ => (parse_bits(kind)?, rest),
+                    };
+                    if code.is_empty() {
+                        return Err(parse_error(token, "value change without identifier"));
+                    }
+                    dump.changes.push(VcdChange {
+                        time,
+                        code: code.to_string(),
+                        value,
+                    });
+                }
+            }
+        }
+        Ok(dump)
+    }
+
+    /// Look up a signal by full path or, if unique, by its bare name
+    pub fn signal(&self, name: &str) -> Result<&VcdSignal, VcdError> {
+        if let Some(signal) = self.signals.iter().find(|s| s.path == name) {
+            return Ok(signal);
+        }
+        let mut matches = self
+            .signals
+            .iter()
+            .filter(|s| s.path.rsplit('.').next() == Some(name));
+        match (matches.next(), matches.next()) {
+            (Some(signal), None) => Ok(signal),
+            (Some(_), Some(_)) => Err(VcdError::AmbiguousSignal(name.to_string())),
+            (None, _) => Err(VcdError::MissingSignal(name.to_string())),
+        }
+    }
+
+    /// Value changes grouped by timestamp, each group in file order
+    pub fn timesteps(&self) -> impl Iterator<Item = (u64, &[VcdChange])> {
+        self.changes
+            .chunk_by(|a, b| a.time == b.time)
+            .map(|group| (group[0].time, group))
+    }
+}
+
+fn next<'a>(tokens: &mut impl Iterator<Item = &'a str>, after: &str) -> Result<&'a str, VcdError> {
+    tokens.next().ok_or_else(|| parse_error(after, "unexpected end of file"))
+}
+
+fn skip_to_end<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<(), VcdError> {
+    tokens
+        .find(|&t| t == "$end")
+        .map(drop)
+        .ok_or_else(|| parse_error("$end", "unterminated section"))
+}
+
+fn parse_bits(bits: &str) -> Result<Integer, VcdError> {
+    let two_state: String = bits
+        .chars()
+        .map(|c| match c {
+            '0' | '1' => Ok(c),
+            'x' | 'X' | 'z' | 'Z' => Ok('0'),
+            _ => Err(parse_error(bits, "bad value")),
+        })
+        .collect::<Result<_, _>>()?;
+    Integer::from_str_radix(&two_state, 2).map_err(|_| parse_error(bits, "bad value"))
+}
+
+fn parse_error(token: &str, message: &'static str) -> VcdError {
+    VcdError::Parse {
+        token: token.to_string(),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_changes_are_emitted() {
+        let mut vcd = VcdWriter::new(Vec::new(), "1ns", "top", &[("clk", 1), ("o", 4)]).unwrap();
+        let (zero, one, five) = (Integer::new(), Integer::from(1), Integer::from(5));
+        vcd.sample(0, &[&zero, &zero]).unwrap();
+        vcd.sample(1, &[&one, &five]).unwrap();
+        vcd.sample(2, &[&one, &five]).unwrap();
+        vcd.sample(3, &[&zero, &five]).unwrap();
+        let text = String::from_utf8(vcd.finish().unwrap()).unwrap();
+
+        assert!(text.contains("$var wire 4 \" o [3:0] $end"));
+        let body = text.split("$enddefinitions $end\n").nth(1).unwrap();
+        assert_eq!(body, "#0\n0!\nb0 \"\n#1\n1!\nb101 \"\n#3\n0!\n");
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        let mut vcd = VcdWriter::new(Vec::new(), "10 ps", "top", &[("clk", 1), ("o", 4)]).unwrap();
+        vcd.sample(0, &[&Integer::new(), &Integer::from(3)]).unwrap();
+        vcd.sample(5, &[&Integer::from(1), &Integer::from(9)]).unwrap();
+        let text = String::from_utf8(vcd.finish().unwrap()).unwrap();
+
+        let dump = VcdDump::parse(&text).unwrap();
+        assert_eq!(dump.timescale.as_deref(), Some("10ps"));
+        assert_eq!(dump.signal("o").unwrap().width, 4);
+        assert_eq!(dump.signal("top.clk").unwrap().code, "!");
+        let steps: Vec<_> = dump.timesteps().map(|(t, c)| (t, c.len())).collect();
+        assert_eq!(steps, [(0, 2), (5, 2)]);
+        assert_eq!(dump.changes[3].value, 9);
+        assert_eq!(dump.signal("nope"), Err(VcdError::MissingSignal("nope".into())));
+    }
+
+    #[test]
+    fn test_parse_four_state_and_sections() {
+        let text = "$date today $end\n$scope module a $end $var reg 4 % x [3:0] $end $upscope $end\n\
+                    $scope module b $end $var wire 1 & x $end $var real 64 ' t $end $upscope $end\n\
+                    $enddefinitions $end\n#0 $dumpvars bx1z1 % z& r0.5 ' $end #7 1&";
+        let dump = VcdDump::parse(text).unwrap();
+        assert_eq!(dump.signal("x"), Err(VcdError::AmbiguousSignal("x".into())));
+        assert_eq!(dump.signal("b.x").unwrap().code, "&");
+        let values: Vec<_> = dump.changes.iter().map(|c| (c.time, c.value.to_u32().unwrap())).collect();
+        assert_eq!(values, [(0, 0b0101), (0, 0), (7, 1)]);
+    }
+}
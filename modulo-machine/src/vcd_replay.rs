@@ -0,0 +1,157 @@
+//! Post-hoc checking of waveforms recorded by an RTL simulator.
+//!
+//! [`replay_vcd`] drives the clk/reset/x transitions from a [`VcdDump`]
+//! through a model and compares the model's output with the DUT's recorded
+//! output at every timestamp where an input changed.
+
+use crate::model::ModuloModel;
+use crate::vcd::{VcdDump, VcdError};
+use rug::Integer;
+
+/// Names (bare or full scope path) of the signals to replay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplaySignals {
+    pub clk: String,
+    pub reset: String,
+    pub x: String,
+    pub output: String,
+}
+
+impl Default for ReplaySignals {
+    fn default() -> Self {
+        Self {
+            clk: "clk".into(),
+            reset: "reset".into(),
+            x: "x".into(),
+            output: "o".into(),
+        }
+    }
+}
+
+/// Model and DUT outputs after one input change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayCycle {
+    pub time: u64,
+    pub clk: bool,
+    pub reset: bool,
+    pub x: Integer,
+    /// Model output
+    pub expected: Integer,
+    /// Recorded DUT output at the same timestamp
+    pub actual: Integer,
+}
+
+impl ReplayCycle {
+    pub fn matches(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+/// Cycle-by-cycle comparison of a recorded run against the model
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VcdReplay {
+    pub cycles: Vec<ReplayCycle>,
+}
+
+impl VcdReplay {
+    pub fn mismatches(&self) -> impl Iterator<Item = &ReplayCycle> {
+        self.cycles.iter().filter(|c| !c.matches())
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.mismatches().next().is_none()
+    }
+}
+
+/// Replay the inputs recorded in `dump` through `model`
+///
+/// The model is reset first. Signals start at 0 until the dump sets them;
+/// timestamps at which only the output changes produce no cycle.
+pub fn replay_vcd<M: ModuloModel>(
+    model: &mut M,
+    dump: &VcdDump,
+    signals: &ReplaySignals,
+) -> Result<VcdReplay, VcdError> {
+    let code = |name: &str| dump.signal(name).map(|s| s.code.as_str());
+    let codes = [code(&signals.clk)?, code(&signals.reset)?, code(&signals.x)?, code(&signals.output)?];
+
+    model.reset();
+    let mut values = [Integer::new(), Integer::new(), Integer::new(), Integer::new()];
+    let mut replay = VcdReplay::default();
+    for (time, changes) in dump.timesteps() {
+        let mut input_changed = false;
+        for change in changes {
+            for (i, code) in codes.iter().enumerate() {
+                if change.code == *code {
+                    values[i].clone_from(&change.value);
+                    input_changed |= i < 3;
+                }
+            }
+        }
+        if !input_changed {
+            continue;
+        }
+
+        let [clk, reset, x, actual] = &values;
+        let (clk, reset) = (*clk != 0, *reset != 0);
+        let expected = model.tick(clk, reset, x);
+        replay.cycles.push(ReplayCycle {
+            time,
+            clk,
+            reset,
+            x: x.clone(),
+            expected,
+            actual: actual.clone(),
+        });
+    }
+    Ok(replay)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcd::VcdWriter;
+    use crate::{ModuloMachine, X_BITS};
+
+    /// A recorded run whose output goes wrong at time 30
+    fn recording(p: &Integer) -> String {
+        let signals = [("clk", 1), ("reset", 1), ("x", X_BITS), ("o", 256)];
+        let mut vcd = VcdWriter::new(Vec::new(), "1ns", "tb", &signals).unwrap();
+        let x = Integer::from(p + 7u32);
+        let (zero, one) = (Integer::new(), Integer::from(1));
+        vcd.sample(0, &[&zero, &one, &zero, &zero]).unwrap();
+        vcd.sample(5, &[&zero, &zero, &x, &zero]).unwrap();
+        vcd.sample(10, &[&one, &zero, &x, &Integer::from(7)]).unwrap();
+        vcd.sample(20, &[&zero, &zero, &x, &Integer::from(7)]).unwrap();
+        vcd.sample(30, &[&one, &zero, &x, &Integer::from(8)]).unwrap();
+        String::from_utf8(vcd.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_replay_flags_mismatch() {
+        let mut machine = ModuloMachine::new();
+        let p = machine.get_prime().clone();
+        let dump = VcdDump::parse(&recording(&p)).unwrap();
+
+        let replay = replay_vcd(&mut machine, &dump, &ReplaySignals::default()).unwrap();
+        assert_eq!(replay.cycles.len(), 5);
+        assert_eq!(replay.cycles[2].expected, 7);
+        assert!(!replay.is_clean());
+        let mismatches: Vec<_> = replay.mismatches().map(|c| c.time).collect();
+        assert_eq!(mismatches, [30]);
+    }
+
+    #[test]
+    fn test_missing_signal() {
+        let mut machine = ModuloMachine::new();
+        let dump = VcdDump::parse(&recording(&Integer::from(11))).unwrap();
+        let signals = ReplaySignals {
+            output: "dout".into(),
+            ..ReplaySignals::default()
+        };
+        assert_eq!(
+            replay_vcd(&mut machine, &dump, &signals),
+            Err(VcdError::MissingSignal("dout".into()))
+        );
+    }
+}
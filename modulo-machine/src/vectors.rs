@@ -0,0 +1,412 @@
+//! Canonical golden test vectors and their versioned archive format.
+//!
+//! An archive is a tar with a single top-level directory holding:
+//!
+//! - `x.hex`: one 300-bit input per line, 75 hex digits (`$readmemh` friendly)
+//! - `o.hex`: the matching 256-bit expected outputs, 64 hex digits
+//! - `cases.txt`: the corner-case name of each line
+//! - `manifest.json`: format version, tool version, seed, modulus, reset value,
+//!   widths and SHA-256 of every other file
+//!
+//! Archives are byte-for-byte reproducible from the seed and tool version, so
+//! hardware CI can pin one by checksum.
+//!
+//! [`VectorSet::sv_package`] renders the same vectors as a SystemVerilog
+//! package of constant arrays, for testbenches that would rather `import`
+//! them than read files at run time.
+
+use crate::ModuloMachine;
+use rug::integer::Order;
+use rug::Integer;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::io::{self, Read, Write};
+
+/// Bumped whenever the archive layout or the corner-case list changes
+pub const FORMAT_VERSION: u32 = 1;
+pub use crate::X_BITS;
+/// Output bus width
+pub const O_BITS: u32 = 256;
+
+/// One input/expected-output pair
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vector {
+    /// Corner case this vector covers, or `random`
+    pub case: String,
+    pub x: Integer,
+    pub o: Integer,
+}
+
+/// A generated vector set plus the parameters that produced it
+#[derive(Debug, Clone)]
+pub struct VectorSet {
+    pub seed: u64,
+    pub modulus: Integer,
+    /// Output register contents after reset on the generating machine
+    pub reset_value: Integer,
+    pub vectors: Vec<Vector>,
+}
+
+/// Documented corner cases for reduction modulo `p` on a 300-bit input bus
+pub fn corner_cases(p: &Integer) -> Vec<(&'static str, Integer)> {
+    let pow2 = |n: u32| Integer::from(1) << n;
+    let x_max = pow2(X_BITS) - 1u32;
+    let top_multiple = Integer::from(&x_max / p) * p;
+
+    let mut cases = vec![
+        ("zero", Integer::new()),
+        ("one", Integer::from(1)),
+        ("p_minus_1", Integer::from(p - 1u32)),
+        ("p", p.clone()),
+        ("p_plus_1", Integer::from(p + 1u32)),
+        ("two_p_minus_1", Integer::from(p * 2u32) - 1u32),
+        ("two_p", Integer::from(p * 2u32)),
+        ("two_p_plus_1", Integer::from(p * 2u32) + 1u32),
+        ("o_max", pow2(O_BITS) - 1u32),
+        ("o_overflow", pow2(O_BITS)),
+        ("x_top_bit", pow2(X_BITS - 1)),
+        ("top_multiple_minus_1", Integer::from(&top_multiple - 1u32)),
+        ("top_multiple", top_multiple.clone()),
+        ("top_multiple_plus_1", top_multiple + 1u32),
+        ("x_max", x_max.clone()),
+        ("alternating_a", Integer::from(&x_max / 3u32) << 1u32),
+        ("alternating_5", Integer::from(&x_max / 3u32)),
+    ];
+    // Carries across every 64-bit limb boundary
+    for (name, bit) in [
+        ("limb_boundary_64", 64),
+        ("limb_boundary_128", 128),
+        ("limb_boundary_192", 192),
+        ("limb_boundary_256", 256),
+    ] {
+        cases.push((name, pow2(bit) - 1u32));
+    }
+    cases
+}
+
+/// Corner cases followed by `random_count` seeded 300-bit inputs, with
+/// expected outputs from the golden model
+pub fn generate(seed: u64, random_count: usize) -> VectorSet {
+    let mut machine = ModuloMachine::new();
+    let modulus = machine.get_prime().clone();
+    let reset_value = machine.reset_value().value();
+
+    let mut inputs: Vec<(String, Integer)> = corner_cases(&modulus)
+        .into_iter()
+        .map(|(case, x)| (case.to_string(), x))
+        .collect();
+    let mut state = seed;
+    for _ in 0..random_count {
+        let limbs: Vec<u64> = (0..5).map(|_| splitmix64(&mut state)).collect();
+        let x = Integer::from_digits(&limbs, Order::Lsf).keep_bits(X_BITS);
+        inputs.push(("random".to_string(), x));
+    }
+
+    let xs: Vec<Integer> = inputs.iter().map(|(_, x)| x.clone()).collect();
+    let outputs = machine.process_values(&xs);
+    let vectors = inputs
+        .into_iter()
+        .zip(outputs)
+        .map(|((case, x), o)| Vector { case, x, o })
+        .collect();
+
+    VectorSet {
+        seed,
+        modulus,
+        reset_value,
+        vectors,
+    }
+}
+
+/// SplitMix64: tiny, seedable and stable across platforms and versions
+pub(crate) fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn hex_lines<'a>(values: impl Iterator<Item = &'a Integer>, bits: u32) -> String {
+    let digits = bits.div_ceil(4) as usize;
+    values
+        .map(|v| format!("{:0>width$}\n", v.to_string_radix(16), width = digits))
+        .collect()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl VectorSet {
+    /// Name of the archive's top-level directory
+    pub fn archive_name(&self) -> String {
+        format!("modulo-vectors-v{}-seed{}", FORMAT_VERSION, self.seed)
+    }
+
+    pub fn x_hex(&self) -> String {
+        hex_lines(self.vectors.iter().map(|v| &v.x), X_BITS)
+    }
+
+    pub fn o_hex(&self) -> String {
+        hex_lines(self.vectors.iter().map(|v| &v.o), O_BITS)
+    }
+
+    pub fn cases_txt(&self) -> String {
+        self.vectors.iter().map(|v| format!("{}\n", v.case)).collect()
+    }
+
+    /// The set as a SystemVerilog package named `name`
+    ///
+    /// Declares `N_VECTORS`, the modulus `P`, and the arrays `X`, `O` and
+    /// `CASES` indexed by vector:
+    ///
+    /// ```systemverilog
+    /// import modulo_vectors_pkg::*;
+    /// for (int i = 0; i < N_VECTORS; i++) begin
+    ///   x = X[i];
+    ///   @(posedge clk); #1;
+    ///   assert (o == O[i]) else $error("%s: got %h", CASES[i], o);
+    /// end
+    /// ```
+    pub fn sv_package(&self, name: &str) -> String {
+        let literals = |values: &mut dyn Iterator<Item = &Integer>, bits: u32| -> String {
+            let lines: Vec<String> = values.map(|v| format!("    {}'h{}", bits, v.to_string_radix(16))).collect();
+            lines.join(",\n")
+        };
+        let cases: Vec<String> = self.vectors.iter().map(|v| format!("    \"{}\"", v.case)).collect();
+        format!(
+            "// Generated by {} {} (vector format v{}, seed {})\n\
+             package {};\n\
+             \x20 localparam int N_VECTORS = {};\n\
+             \x20 localparam logic [{}:0] P = {}'h{};\n\
+             \x20 localparam logic [{}:0] X [N_VECTORS] = '{{\n{}\n  }};\n\
+             \x20 localparam logic [{}:0] O [N_VECTORS] = '{{\n{}\n  }};\n\
+             \x20 localparam string CASES [N_VECTORS] = '{{\n{}\n  }};\n\
+             endpackage\n",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            FORMAT_VERSION,
+            self.seed,
+            name,
+            self.vectors.len(),
+            O_BITS - 1,
+            O_BITS,
+            self.modulus.to_string_radix(16),
+            X_BITS - 1,
+            literals(&mut self.vectors.iter().map(|v| &v.x), X_BITS),
+            O_BITS - 1,
+            literals(&mut self.vectors.iter().map(|v| &v.o), O_BITS),
+            cases.join(",\n"),
+        )
+    }
+
+    /// Manifest describing the set; `files` maps each data file to its SHA-256
+    pub fn manifest(&self) -> Value {
+        json!({
+            "format_version": FORMAT_VERSION,
+            "tool": env!("CARGO_PKG_NAME"),
+            "tool_version": env!("CARGO_PKG_VERSION"),
+            "seed": self.seed,
+            "modulus": self.modulus.to_string(),
+            "reset_value": self.reset_value.to_string(),
+            "x_bits": X_BITS,
+            "o_bits": O_BITS,
+            "count": self.vectors.len(),
+            "files": {
+                "x.hex": sha256_hex(self.x_hex().as_bytes()),
+                "o.hex": sha256_hex(self.o_hex().as_bytes()),
+                "cases.txt": sha256_hex(self.cases_txt().as_bytes()),
+            },
+        })
+    }
+
+    /// Write the set as a reproducible tar archive
+    pub fn write_archive<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.write_archive_with(writer, &[])
+    }
+
+    /// Write the set as a reproducible tar archive with `extra` files after
+    /// the standard ones; the manifest does not cover them
+    pub fn write_archive_with<W: Write>(&self, writer: W, extra: &[(&str, String)]) -> io::Result<()> {
+        let manifest = serde_json::to_string_pretty(&self.manifest())? + "\n";
+        let files = [
+            ("manifest.json", manifest),
+            ("x.hex", self.x_hex()),
+            ("o.hex", self.o_hex()),
+            ("cases.txt", self.cases_txt()),
+        ];
+
+        let mut builder = tar::Builder::new(writer);
+        for (name, contents) in files.iter().chain(extra) {
+            let mut header = tar::Header::new_ustar();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            // Fixed metadata keeps the archive reproducible
+            header.set_mtime(0);
+            builder.append_data(
+                &mut header,
+                format!("{}/{}", self.archive_name(), name),
+                contents.as_bytes(),
+            )?;
+        }
+        builder.into_inner()?.flush()
+    }
+}
+
+/// Read an archive written by [`VectorSet::write_archive_with`], checking
+/// every data file against the manifest. Returns the set and any extra files
+/// by name.
+pub fn read_archive<R: Read>(reader: R) -> io::Result<(VectorSet, Vec<(String, String)>)> {
+    let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, what.to_string());
+    let mut files = Vec::new();
+    for entry in tar::Archive::new(reader).entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let name = path.split_once('/').map_or(path.as_str(), |(_, name)| name).to_string();
+        let mut contents = String::new();
+        entry.read_to_string(&mut contents)?;
+        files.push((name, contents));
+    }
+    let mut take = |name: &str| {
+        let at = files.iter().position(|(n, _)| n == name).ok_or_else(|| invalid(&format!("missing {}", name)))?;
+        Ok::<_, io::Error>(files.remove(at).1)
+    };
+    let manifest: Value = serde_json::from_str(&take("manifest.json")?)?;
+    let (x_hex, o_hex, cases) = (take("x.hex")?, take("o.hex")?, take("cases.txt")?);
+
+    if manifest["format_version"] != FORMAT_VERSION {
+        return Err(invalid("unsupported format version"));
+    }
+    for (name, contents) in [("x.hex", &x_hex), ("o.hex", &o_hex), ("cases.txt", &cases)] {
+        if manifest["files"][name] != sha256_hex(contents.as_bytes()) {
+            return Err(invalid(&format!("{} does not match the manifest", name)));
+        }
+    }
+    let number = |key: &str| {
+        let text = manifest[key].as_str().ok_or_else(|| invalid(key))?;
+        Integer::from_str_radix(text, 10).map_err(|_| invalid(key))
+    };
+    let hex = |line: &str| Integer::from_str_radix(line, 16).map_err(|_| invalid("bad hex line"));
+
+    let (xs, os, cases): (Vec<&str>, Vec<&str>, Vec<&str>) = (x_hex.lines().collect(), o_hex.lines().collect(), cases.lines().collect());
+    if xs.len() != os.len() || xs.len() != cases.len() {
+        return Err(invalid("vector files differ in length"));
+    }
+    let vectors = (0..xs.len())
+        .map(|i| {
+            Ok(Vector {
+                case: cases[i].to_string(),
+                x: hex(xs[i])?,
+                o: hex(os[i])?,
+            })
+        })
+        .collect::<io::Result<_>>()?;
+
+    let set = VectorSet {
+        seed: manifest["seed"].as_u64().ok_or_else(|| invalid("seed"))?,
+        modulus: number("modulus")?,
+        reset_value: number("reset_value")?,
+        vectors,
+    };
+    Ok((set, files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_vectors_match_model() {
+        let set = generate(7, 16);
+        let p = &set.modulus;
+        assert_eq!(set.vectors.len(), corner_cases(p).len() + 16);
+        for v in &set.vectors {
+            assert!(v.x.significant_bits() <= X_BITS, "{}", v.case);
+            assert_eq!(v.o, Integer::from(&v.x % p), "{}", v.case);
+        }
+
+        let x_hex = set.x_hex();
+        let x_lines: Vec<&str> = x_hex.lines().collect();
+        assert_eq!(x_lines.len(), set.vectors.len());
+        assert!(x_lines.iter().all(|l| l.len() == 75));
+        assert!(set.o_hex().lines().all(|l| l.len() == 64));
+    }
+
+    #[test]
+    fn test_sv_package() {
+        let set = generate(3, 2);
+        let sv = set.sv_package("modulo_vectors_pkg");
+        assert!(sv.contains("package modulo_vectors_pkg;\n"));
+        assert!(sv.contains(&format!("  localparam int N_VECTORS = {};\n", set.vectors.len())));
+        assert!(sv.contains(&format!("  localparam logic [255:0] P = 256'h{};\n", set.modulus.to_string_radix(16))));
+        assert!(sv.contains("  localparam logic [299:0] X [N_VECTORS] = '{\n    300'h0,\n    300'h1,\n"));
+        assert!(sv.contains(&format!("    256'h{}\n  }};\n", set.vectors.last().unwrap().o.to_string_radix(16))));
+        assert!(sv.contains("    \"zero\",\n"));
+        assert!(sv.ends_with("    \"random\"\n  };\nendpackage\n"));
+    }
+
+    #[test]
+    fn test_archive_is_reproducible() {
+        let mut a = Vec::new();
+        let mut b = Vec::new();
+        generate(42, 8).write_archive(&mut a).unwrap();
+        generate(42, 8).write_archive(&mut b).unwrap();
+        assert_eq!(a, b);
+
+        let mut c = Vec::new();
+        generate(43, 8).write_archive(&mut c).unwrap();
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_archive_contents() {
+        let set = generate(1, 4);
+        let mut bytes = Vec::new();
+        set.write_archive(&mut bytes).unwrap();
+
+        let mut archive = tar::Archive::new(bytes.as_slice());
+        let mut manifest = None;
+        let mut names = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().to_string_lossy().into_owned();
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents).unwrap();
+            if path.ends_with("manifest.json") {
+                manifest = Some(serde_json::from_str::<Value>(&contents).unwrap());
+            } else if path.ends_with("x.hex") {
+                assert_eq!(contents, set.x_hex());
+            }
+            names.push(path);
+        }
+
+        let prefix = set.archive_name();
+        assert_eq!(
+            names,
+            ["manifest.json", "x.hex", "o.hex", "cases.txt"].map(|n| format!("{}/{}", prefix, n))
+        );
+        let manifest = manifest.unwrap();
+        assert_eq!(manifest["seed"], 1);
+        assert_eq!(manifest["count"], set.vectors.len());
+        assert_eq!(manifest["files"]["x.hex"], sha256_hex(set.x_hex().as_bytes()));
+    }
+
+    #[test]
+    fn test_read_archive() {
+        let set = generate(5, 4);
+        let mut bytes = Vec::new();
+        set.write_archive_with(&mut bytes, &[("notes.txt", "hello\n".to_string())]).unwrap();
+        let (read, extra) = read_archive(bytes.as_slice()).unwrap();
+        assert_eq!(read.vectors, set.vectors);
+        assert_eq!((read.seed, &read.modulus), (5, &set.modulus));
+        assert_eq!(extra, [("notes.txt".to_string(), "hello\n".to_string())]);
+
+        // An edited data file no longer matches the manifest
+        let header = format!("{}/o.hex", set.archive_name());
+        let at = bytes.windows(header.len()).position(|w| w == header.as_bytes()).unwrap();
+        bytes[at + 512] ^= 1;
+        assert!(read_archive(bytes.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,23 @@
+//! Feeds arbitrary bytes through `sasquatch::fuzz::check_pipeline`.
+//!
+//! Run with `cargo fuzz run pipeline` from `sasquatch/`.
+
+#![no_main]
+
+use bls12_381_prover::{Config, Prover, Setup, Verifier};
+use libfuzzer_sys::fuzz_target;
+use std::sync::OnceLock;
+
+/// Setup is expensive, so every input shares one small domain
+fn keys() -> &'static (Prover, Verifier) {
+    static KEYS: OnceLock<(Prover, Verifier)> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        let setup = Setup::new(Config { log_n: 3 });
+        (Prover::new(setup.clone()), Verifier::new(setup))
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    let (prover, verifier) = keys();
+    sasquatch::fuzz::check_pipeline(prover, verifier, data);
+});
@@ -0,0 +1,273 @@
+//! Versioned container for a simulation run and its proofs.
+//!
+//! Layout: the magic `SQATRACE`, a little-endian `u32` format version, then
+//! sections of `tag (4 bytes) | length (u64 LE) | payload`:
+//!
+//! - `META`: JSON metadata (seed, tool version, anything the writer likes)
+//! - `TRCE`: the machine trace in `modulo_machine::trace_file` text form
+//! - `COMM`: trace commitment: rows, domain size, one compressed point per
+//!   column
+//! - `OPEN`: row openings: count, then per opening the row and one
+//!   `OpeningProof` per column
+//! - `DGST`: SHA-256 of every byte before this section; always last
+//!
+//! Readers skip sections they do not know, so new sections do not need a
+//! version bump; changing an existing section does.
+
+use crate::error::{verified, Result};
+use crate::trace::{RowOpening, TraceCommitment, TraceRow, COLUMNS};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use bls12_381_prover::{G1Affine, OpeningProof, Verifier};
+use modulo_machine::trace_file;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io::{Read, Write};
+
+pub const MAGIC: &[u8; 8] = b"SQATRACE";
+/// Bumped whenever an existing section's encoding changes
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Reason a container could not be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArtifactError {
+    BadMagic,
+    UnsupportedVersion(u32),
+    /// Input ended inside a section or before the digest
+    Truncated,
+    /// The digest does not match the contents
+    DigestMismatch,
+    /// A known section that does not decode
+    Malformed(&'static str),
+}
+
+impl fmt::Display for ArtifactError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArtifactError::BadMagic => write!(f, "not an artifact container"),
+            ArtifactError::UnsupportedVersion(v) => {
+                write!(f, "format version {} is not supported (this build reads {})", v, FORMAT_VERSION)
+            }
+            ArtifactError::Truncated => write!(f, "container is truncated"),
+            ArtifactError::DigestMismatch => write!(f, "container digest does not match its contents"),
+            ArtifactError::Malformed(section) => write!(f, "malformed {} section", section),
+        }
+    }
+}
+
+impl std::error::Error for ArtifactError {}
+
+/// A simulation run plus everything needed to re-verify it
+#[derive(Debug, Clone, Default)]
+pub struct Artifact {
+    pub metadata: Value,
+    pub trace: Vec<TraceRow>,
+    pub commitment: Option<TraceCommitment>,
+    pub openings: Vec<RowOpening>,
+}
+
+impl Artifact {
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        let mut out = MAGIC.to_vec();
+        out.extend(FORMAT_VERSION.to_le_bytes());
+
+        section(&mut out, b"META", &serde_json::to_vec(&self.metadata).expect("JSON values serialize"));
+        let mut trace = Vec::new();
+        let entries: Vec<_> = self.trace.iter().map(|r| (r.input(), r)).collect();
+        trace_file::write_trace(&mut trace, entries.iter().map(|(t, r)| (t, &r.output)))?;
+        section(&mut out, b"TRCE", &trace);
+
+        if let Some(commitment) = &self.commitment {
+            let mut bytes = Vec::new();
+            bytes.extend((commitment.rows as u64).to_le_bytes());
+            bytes.extend((commitment.domain_size as u64).to_le_bytes());
+            for column in &commitment.columns {
+                column.serialize_compressed(&mut bytes).expect("serializing to memory cannot fail");
+            }
+            section(&mut out, b"COMM", &bytes);
+        }
+        if !self.openings.is_empty() {
+            let mut bytes = Vec::new();
+            bytes.extend((self.openings.len() as u64).to_le_bytes());
+            for opening in &self.openings {
+                bytes.extend((opening.row as u64).to_le_bytes());
+                bytes.extend((opening.proofs.len() as u64).to_le_bytes());
+                for proof in &opening.proofs {
+                    proof.write(&mut bytes).expect("serializing to memory cannot fail");
+                }
+            }
+            section(&mut out, b"OPEN", &bytes);
+        }
+
+        let digest = Sha256::digest(&out);
+        section(&mut out, b"DGST", &digest);
+        writer.write_all(&out)?;
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        if data.len() < 12 || &data[..8] != MAGIC {
+            return Err(ArtifactError::BadMagic.into());
+        }
+        let version = u32::from_le_bytes(data[8..12].try_into().unwrap());
+        if version != FORMAT_VERSION {
+            return Err(ArtifactError::UnsupportedVersion(version).into());
+        }
+
+        let mut artifact = Artifact::default();
+        let mut at = 12;
+        loop {
+            let start = at;
+            let (tag, payload) = next_section(&data, &mut at)?;
+            match &tag {
+                b"META" => {
+                    artifact.metadata = serde_json::from_slice(payload).map_err(|_| ArtifactError::Malformed("META"))?;
+                }
+                b"TRCE" => {
+                    let entries = trace_file::read_trace(payload).map_err(|_| ArtifactError::Malformed("TRCE"))?;
+                    artifact.trace = entries
+                        .into_iter()
+                        .map(|(t, output)| TraceRow {
+                            clk: t.clk,
+                            reset: t.reset,
+                            x: t.x,
+                            output,
+                        })
+                        .collect();
+                }
+                b"COMM" => artifact.commitment = Some(read_commitment(payload).ok_or(ArtifactError::Malformed("COMM"))?),
+                b"OPEN" => artifact.openings = read_openings(payload).ok_or(ArtifactError::Malformed("OPEN"))?,
+                b"DGST" => {
+                    if payload != Sha256::digest(&data[..start]).as_slice() || at != data.len() {
+                        return Err(ArtifactError::DigestMismatch.into());
+                    }
+                    return Ok(artifact);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Check every opening against the commitment and the recorded trace
+    pub fn verify(&self, verifier: &Verifier) -> Result<()> {
+        let commitment = self.commitment.as_ref().ok_or(ArtifactError::Malformed("COMM"))?;
+        verified(commitment.rows == self.trace.len(), "trace commitment")?;
+        for opening in &self.openings {
+            let matches = opening.decode().as_ref() == self.trace.get(opening.row);
+            verified(matches && commitment.verify_row(verifier, opening), "trace row opening")?;
+        }
+        Ok(())
+    }
+}
+
+fn section(out: &mut Vec<u8>, tag: &[u8; 4], payload: &[u8]) {
+    out.extend(tag);
+    out.extend((payload.len() as u64).to_le_bytes());
+    out.extend(payload);
+}
+
+fn next_section<'a>(data: &'a [u8], at: &mut usize) -> Result<([u8; 4], &'a [u8]), ArtifactError> {
+    let header = data.get(*at..*at + 12).ok_or(ArtifactError::Truncated)?;
+    let tag: [u8; 4] = header[..4].try_into().unwrap();
+    let len = u64::from_le_bytes(header[4..].try_into().unwrap());
+    let end = usize::try_from(len)
+        .ok()
+        .and_then(|len| (*at + 12).checked_add(len))
+        .filter(|&end| end <= data.len())
+        .ok_or(ArtifactError::Truncated)?;
+    let payload = &data[*at + 12..end];
+    *at = end;
+    Ok((tag, payload))
+}
+
+fn read_u64(bytes: &mut &[u8]) -> Option<u64> {
+    let (head, rest) = bytes.split_first_chunk::<8>()?;
+    *bytes = rest;
+    Some(u64::from_le_bytes(*head))
+}
+
+fn read_commitment(mut bytes: &[u8]) -> Option<TraceCommitment> {
+    let rows = read_u64(&mut bytes)? as usize;
+    let domain_size = read_u64(&mut bytes)? as usize;
+    let mut columns = [G1Affine::default(); COLUMNS];
+    for column in &mut columns {
+        *column = G1Affine::deserialize_compressed(&mut bytes).ok()?;
+    }
+    bytes.is_empty().then_some(TraceCommitment {
+        rows,
+        domain_size,
+        columns,
+    })
+}
+
+fn read_openings(mut bytes: &[u8]) -> Option<Vec<RowOpening>> {
+    let count = read_u64(&mut bytes)?;
+    let mut openings = Vec::new();
+    for _ in 0..count {
+        let row = read_u64(&mut bytes)? as usize;
+        let proofs = (0..read_u64(&mut bytes)?)
+            .map(|_| OpeningProof::read(&mut bytes).ok())
+            .collect::<Option<_>>()?;
+        openings.push(RowOpening { row, proofs });
+    }
+    bytes.is_empty().then_some(openings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::CommittedTrace;
+    use crate::Error;
+    use bls12_381_prover::{Config, Prover, Setup};
+    use modulo_machine::{ModuloMachine, TickInput};
+    use rug::Integer;
+    use serde_json::json;
+
+    #[test]
+    fn test_artifact_round_trip_and_reverify() {
+        let setup = Setup::new(Config { log_n: 2 });
+        let prover = Prover::new(setup.clone());
+        let verifier = Verifier::new(setup);
+
+        let stimulus = [
+            TickInput::new(false, false, Integer::from(7)),
+            TickInput::new(true, false, Integer::from(7)),
+        ];
+        let trace = TraceRow::record(&mut ModuloMachine::new(), &stimulus);
+        let committed = CommittedTrace::commit(&prover, &trace).unwrap();
+        let artifact = Artifact {
+            metadata: json!({ "seed": 42 }),
+            trace: trace.clone(),
+            commitment: Some(committed.commitment().clone()),
+            openings: vec![committed.open_trace_row(1).unwrap()],
+        };
+
+        let mut bytes = Vec::new();
+        artifact.write(&mut bytes).unwrap();
+        let read = Artifact::read(&bytes[..]).unwrap();
+        assert_eq!(read.metadata["seed"], 42);
+        assert_eq!(read.trace, trace);
+        assert_eq!(read.commitment, artifact.commitment);
+        read.verify(&verifier).unwrap();
+
+        // Editing the trace breaks the digest; forging it consistently
+        // breaks verification
+        let mut tampered = bytes.clone();
+        let at = tampered.windows(4).position(|w| w == b"1 0 ").unwrap() + 4;
+        tampered[at] = b'8';
+        assert!(matches!(
+            Artifact::read(&tampered[..]),
+            Err(Error::Artifact(ArtifactError::DigestMismatch))
+        ));
+        let mut forged = read;
+        forged.trace[1].x = Integer::from(8);
+        assert!(matches!(forged.verify(&verifier), Err(Error::Verification(_))));
+
+        assert!(matches!(
+            Artifact::read(&bytes[..bytes.len() - 1]),
+            Err(Error::Artifact(ArtifactError::Truncated))
+        ));
+    }
+}
@@ -0,0 +1,185 @@
+//! Benchmarks both subsystems and tracks the results over time.
+//!
+//! Runs the modulo-machine throughput benches (every reduction backend) and
+//! the prover phase benches, appends one JSON line per run to a history file
+//! (and optionally long-format rows to a CSV), and prints each metric's
+//! change against the previous run in the history. Changes for the worse
+//! beyond `--threshold` percent are flagged.
+//!
+//! Usage: `bench-report [--history PATH] [--csv PATH] [--cycles N] [--log-n N] [--threshold PCT]`
+
+use bls12_381_prover::{Config, Fr, Prover, Setup, Verifier};
+use modulo_machine::{vectors, ModuloMachine, ReductionStrategy};
+use rug::Integer;
+use serde_json::{json, Map, Value};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::process;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_HISTORY: &str = "bench-history.jsonl";
+const DEFAULT_CYCLES: usize = 100_000;
+const DEFAULT_LOG_N: usize = 10;
+const DEFAULT_THRESHOLD: f64 = 10.0;
+
+fn usage() -> ! {
+    eprintln!("usage: bench-report [--history PATH] [--csv PATH] [--cycles N] [--log-n N] [--threshold PCT]");
+    process::exit(2);
+}
+
+struct Metric {
+    name: String,
+    value: f64,
+    unit: &'static str,
+    higher_is_better: bool,
+}
+
+fn machine_benches(cycles: usize) -> Vec<Metric> {
+    let set = vectors::generate(0, cycles);
+    let inputs: Vec<Integer> = set.vectors.into_iter().map(|v| v.x).collect();
+    let mut metrics = Vec::new();
+    for (name, strategy) in [
+        ("gmp", ReductionStrategy::Gmp),
+        ("barrett", ReductionStrategy::Barrett),
+        ("shift_subtract", ReductionStrategy::ShiftSubtract),
+        ("constant_time", ReductionStrategy::ConstantTime),
+        ("preinverted", ReductionStrategy::Preinverted),
+    ] {
+        let mut machine = ModuloMachine::new();
+        if !machine.set_reduction_strategy(strategy) {
+            continue;
+        }
+        let start = Instant::now();
+        let outputs = machine.process_values(&inputs);
+        let elapsed = start.elapsed().as_secs_f64();
+        machine.recycle(outputs);
+        metrics.push(Metric {
+            name: format!("machine.{}.ops_per_sec", name),
+            value: inputs.len() as f64 / elapsed,
+            unit: "ops/s",
+            higher_is_better: true,
+        });
+    }
+    metrics
+}
+
+fn prover_benches(log_n: usize) -> Vec<Metric> {
+    let mut timed = Vec::new();
+    let mut time = |name: &str, start: Instant| timed.push((format!("prover.{}.ms", name), start.elapsed()));
+
+    let start = Instant::now();
+    let setup = Setup::new(Config { log_n });
+    time("setup", start);
+    let prover = Prover::new(setup.clone());
+    let verifier = Verifier::new(setup);
+
+    let start = Instant::now();
+    let (commitment, evals) = prover.prove();
+    time("prove", start);
+    let start = Instant::now();
+    let proof = prover.create_opening_proof(&evals, Fr::from(7u64));
+    time("open", start);
+    let start = Instant::now();
+    assert!(verifier.verify_opening(&commitment, &proof), "benchmark opening rejected");
+    time("verify", start);
+
+    timed
+        .into_iter()
+        .map(|(name, elapsed)| Metric {
+            name,
+            value: elapsed.as_secs_f64() * 1e3,
+            unit: "ms",
+            higher_is_better: false,
+        })
+        .collect()
+}
+
+/// Results of the last run recorded in `history`, if any
+fn previous_run(history: &str) -> Option<Map<String, Value>> {
+    let text = fs::read_to_string(history).ok()?;
+    let last: Value = serde_json::from_str(text.lines().rev().find(|l| !l.trim().is_empty())?).ok()?;
+    last.get("results")?.as_object().cloned()
+}
+
+fn append(path: &str, text: &str) {
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut f| f.write_all(text.as_bytes()));
+    if let Err(e) = result {
+        eprintln!("❌ Cannot write {}: {}", path, e);
+        process::exit(1);
+    }
+}
+
+fn main() {
+    let mut history = DEFAULT_HISTORY.to_string();
+    let mut csv = None;
+    let mut cycles = DEFAULT_CYCLES;
+    let mut log_n = DEFAULT_LOG_N;
+    let mut threshold = DEFAULT_THRESHOLD;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--history" => history = value,
+            "--csv" => csv = Some(value),
+            "--cycles" => cycles = value.parse().unwrap_or_else(|_| usage()),
+            "--log-n" => log_n = value.parse().unwrap_or_else(|_| usage()),
+            "--threshold" => threshold = value.parse().unwrap_or_else(|_| usage()),
+            _ => usage(),
+        }
+    }
+
+    let mut metrics = machine_benches(cycles);
+    metrics.extend(prover_benches(log_n));
+    let previous = previous_run(&history);
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+    println!("{:<34} {:>16} {:>8}", "metric", "value", "change");
+    let mut regressions = 0;
+    for m in &metrics {
+        let change = previous
+            .as_ref()
+            .and_then(|p| p.get(&m.name)?.as_f64())
+            .filter(|&old| old > 0.0)
+            .map(|old| (m.value - old) / old * 100.0);
+        let (change, flag) = match change {
+            Some(pct) => {
+                let worse = if m.higher_is_better { -pct } else { pct };
+                regressions += (worse > threshold) as usize;
+                (format!("{:+.1}%", pct), if worse > threshold { " ⚠️" } else { "" })
+            }
+            None => ("new".to_string(), ""),
+        };
+        println!("{:<34} {:>10.2} {:<5} {:>8}{}", m.name, m.value, m.unit, change, flag);
+    }
+
+    let results: Map<String, Value> = metrics.iter().map(|m| (m.name.clone(), json!(m.value))).collect();
+    let run = json!({
+        "timestamp": timestamp,
+        "version": env!("CARGO_PKG_VERSION"),
+        "cycles": cycles,
+        "log_n": log_n,
+        "results": results,
+    });
+    append(&history, &format!("{}\n", run));
+    if let Some(csv) = csv {
+        let mut rows = String::new();
+        if fs::metadata(&csv).is_err() {
+            rows.push_str("timestamp,metric,value,unit\n");
+        }
+        for m in &metrics {
+            rows.push_str(&format!("{},{},{},{}\n", timestamp, m.name, m.value, m.unit));
+        }
+        append(&csv, &rows);
+    }
+
+    if previous.is_none() {
+        println!("\nFirst run recorded in {}", history);
+    } else if regressions > 0 {
+        println!("\n{} metric(s) regressed by more than {}%", regressions, threshold);
+    }
+}
@@ -0,0 +1,215 @@
+//! Lossless conversions among `rug::Integer`, `Fr`, fixed-width byte arrays,
+//! hex strings and limbs.
+//!
+//! Every conversion into a narrower representation checks the value fits
+//! and fails instead of truncating or reducing. Byte arrays are big-endian,
+//! as in the machine's hex vectors; limbs are least significant first.
+
+use ark_ff::{BigInteger, PrimeField};
+use bls12_381_prover::Fr;
+use rug::integer::Order;
+use rug::Integer;
+use std::fmt;
+
+/// Reason a value could not be converted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConvertError {
+    /// Negative values have no unsigned encoding
+    Negative,
+    /// Value needs more than `max` bits
+    TooWide { bits: u32, max: u32 },
+    /// Integer not below the scalar field modulus
+    NotInField,
+    /// Not a hex string, with or without a `0x` prefix
+    InvalidHex,
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertError::Negative => write!(f, "value is negative"),
+            ConvertError::TooWide { bits, max } => write!(f, "{}-bit value exceeds {} bits", bits, max),
+            ConvertError::NotInField => write!(f, "value is not below the scalar field modulus"),
+            ConvertError::InvalidHex => write!(f, "invalid hex string"),
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+/// Check `x` is non-negative and at most `max` bits wide
+fn check_width(x: &Integer, max: u32) -> Result<(), ConvertError> {
+    if *x < 0 {
+        return Err(ConvertError::Negative);
+    }
+    let bits = x.significant_bits();
+    if bits > max {
+        return Err(ConvertError::TooWide { bits, max });
+    }
+    Ok(())
+}
+
+/// The scalar field modulus
+pub fn fr_modulus() -> Integer {
+    Integer::from_digits(&Fr::MODULUS.to_bytes_le(), Order::Lsf)
+}
+
+/// `x` as a field element, if `0 <= x < r`
+pub fn integer_to_fr(x: &Integer) -> Result<Fr, ConvertError> {
+    check_width(x, Fr::MODULUS_BIT_SIZE)?;
+    if *x >= fr_modulus() {
+        return Err(ConvertError::NotInField);
+    }
+    Ok(Fr::from_le_bytes_mod_order(&x.to_digits::<u8>(Order::Lsf)))
+}
+
+/// The canonical representative of `f` in `[0, r)`
+pub fn fr_to_integer(f: &Fr) -> Integer {
+    Integer::from_digits(&f.into_bigint().to_bytes_le(), Order::Lsf)
+}
+
+/// `x` as `N` big-endian bytes, if it fits
+pub fn integer_to_bytes<const N: usize>(x: &Integer) -> Result<[u8; N], ConvertError> {
+    check_width(x, N as u32 * 8)?;
+    let mut bytes = [0u8; N];
+    x.write_digits(&mut bytes, Order::Msf);
+    Ok(bytes)
+}
+
+/// The unsigned integer with big-endian encoding `bytes`
+pub fn integer_from_bytes(bytes: &[u8]) -> Integer {
+    Integer::from_digits(bytes, Order::Msf)
+}
+
+/// `f` as 32 big-endian bytes
+pub fn fr_to_bytes(f: &Fr) -> [u8; 32] {
+    integer_to_bytes(&fr_to_integer(f)).expect("field elements fit 256 bits")
+}
+
+/// The field element with big-endian encoding `bytes`, rejecting
+/// non-canonical encodings
+pub fn fr_from_bytes(bytes: &[u8; 32]) -> Result<Fr, ConvertError> {
+    integer_to_fr(&integer_from_bytes(bytes))
+}
+
+/// Lowercase hex of `x`, zero-padded to `bits / 4` digits, if it fits
+pub fn integer_to_hex(x: &Integer, bits: u32) -> Result<String, ConvertError> {
+    check_width(x, bits)?;
+    let digits = bits.div_ceil(4) as usize;
+    Ok(format!("{:0>width$}", x.to_string_radix(16), width = digits))
+}
+
+/// Parse unsigned hex, with or without a `0x` prefix
+pub fn integer_from_hex(s: &str) -> Result<Integer, ConvertError> {
+    let digits = s.strip_prefix("0x").unwrap_or(s);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ConvertError::InvalidHex);
+    }
+    Integer::from_str_radix(digits, 16).map_err(|_| ConvertError::InvalidHex)
+}
+
+/// `f` as 64 hex digits
+pub fn fr_to_hex(f: &Fr) -> String {
+    integer_to_hex(&fr_to_integer(f), 256).expect("field elements fit 256 bits")
+}
+
+/// Parse a canonical field element from hex
+pub fn fr_from_hex(s: &str) -> Result<Fr, ConvertError> {
+    integer_to_fr(&integer_from_hex(s)?)
+}
+
+/// Bytes as lowercase hex, two digits each
+pub fn bytes_to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Parse bytes written by [`bytes_to_hex`]
+pub fn bytes_from_hex(s: &str) -> Result<Vec<u8>, ConvertError> {
+    if !s.len().is_multiple_of(2) || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ConvertError::InvalidHex);
+    }
+    Ok((0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect())
+}
+
+/// `x` as `n` little-endian words of `limb_bits` (at most 128) bits, if it
+/// fits
+pub fn integer_to_words(x: &Integer, limb_bits: u32, n: usize) -> Result<Vec<u128>, ConvertError> {
+    assert!((1..=128).contains(&limb_bits), "limbs are at most 128 bits");
+    check_width(x, limb_bits * n as u32)?;
+    let mut rest = x.clone();
+    let mut words = Vec::with_capacity(n);
+    for _ in 0..n {
+        words.push(Integer::from(rest.keep_bits_ref(limb_bits)).to_u128().unwrap());
+        rest >>= limb_bits;
+    }
+    Ok(words)
+}
+
+/// `x` as `N` little-endian field limbs of `limb_bits` bits each
+pub fn integer_to_limbs<const N: usize>(x: &Integer, limb_bits: u32) -> Result<[Fr; N], ConvertError> {
+    let words = integer_to_words(x, limb_bits, N)?;
+    Ok(std::array::from_fn(|i| Fr::from(words[i])))
+}
+
+/// Recombine little-endian field limbs, if each is below `2^limb_bits`
+pub fn integer_from_limbs(limbs: &[Fr], limb_bits: u32) -> Result<Integer, ConvertError> {
+    let mut x = Integer::new();
+    for limb in limbs.iter().rev() {
+        let limb = fr_to_integer(limb);
+        check_width(&limb, limb_bits)?;
+        x <<= limb_bits;
+        x += limb;
+    }
+    Ok(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use modulo_machine::{ModuloMachine, Preset};
+
+    #[test]
+    fn test_round_trips() {
+        let r = fr_modulus();
+        assert_eq!(r, Preset::Bls12_381Fr.modulus());
+        for x in [Integer::new(), Integer::from(12345), Integer::from(&r - 1u32)] {
+            let f = integer_to_fr(&x).unwrap();
+            assert_eq!(fr_to_integer(&f), x);
+            assert_eq!(fr_from_bytes(&fr_to_bytes(&f)), Ok(f));
+            assert_eq!(fr_from_hex(&fr_to_hex(&f)), Ok(f));
+        }
+        assert_eq!(fr_to_integer(&-Fr::from(1u64)), Integer::from(&r - 1u32));
+
+        let x = ModuloMachine::create_large_input(299, 987654321);
+        let bytes: [u8; 38] = integer_to_bytes(&x).unwrap();
+        assert_eq!(integer_from_bytes(&bytes), x);
+        let hex = integer_to_hex(&x, 300).unwrap();
+        assert_eq!(hex.len(), 75);
+        assert_eq!(integer_from_hex(&format!("0x{}", hex)), Ok(x.clone()));
+        let limbs: [Fr; 3] = integer_to_limbs(&x, 128).unwrap();
+        assert_eq!(integer_from_limbs(&limbs, 128), Ok(x.clone()));
+        assert_eq!(integer_to_words(&x, 64, 5).unwrap()[0], x.to_u64_wrapping() as u128);
+    }
+
+    #[test]
+    fn test_conversions_never_truncate() {
+        let r = fr_modulus();
+        assert_eq!(integer_to_fr(&r), Err(ConvertError::NotInField));
+        assert_eq!(integer_to_fr(&Integer::from(-1)), Err(ConvertError::Negative));
+        assert_eq!(
+            integer_to_bytes::<2>(&Integer::from(0x10000)),
+            Err(ConvertError::TooWide { bits: 17, max: 16 })
+        );
+        assert_eq!(fr_from_bytes(&integer_to_bytes(&r).unwrap()), Err(ConvertError::NotInField));
+        for bad in ["", "0x", "-1", "12g"] {
+            assert_eq!(integer_from_hex(bad), Err(ConvertError::InvalidHex));
+        }
+        assert_eq!(bytes_from_hex(&bytes_to_hex(&[0, 0xab])), Ok(vec![0, 0xab]));
+        assert_eq!(bytes_from_hex("abc"), Err(ConvertError::InvalidHex));
+        assert!(integer_to_limbs::<2>(&(Integer::from(1) << 128u32), 64).is_err());
+        assert_eq!(
+            integer_from_limbs(&[Fr::from(1u64 << 32)], 32),
+            Err(ConvertError::TooWide { bits: 33, max: 32 })
+        );
+    }
+}
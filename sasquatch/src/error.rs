@@ -0,0 +1,81 @@
+//! One error type for everything composed from both subsystems.
+//!
+//! Each module keeps its own specific error; [`Error`] wraps them all with
+//! `From` conversions, so code spanning the machine and the prover can use
+//! `?` throughout and callers can still match on the source. Verifiers
+//! return `bool`; [`verified`] turns a rejection into an error.
+
+use crate::artifact::ArtifactError;
+use crate::convert::ConvertError;
+use crate::range::RangeError;
+use crate::reduction::ReductionError;
+use crate::service::ServiceError;
+use crate::signoff::SignoffError;
+use crate::trace::TraceError;
+use modulo_machine::axi::AxiError;
+use modulo_machine::{MachineError, ModulusError, VcdError};
+use std::io;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("machine: {0}")]
+    Machine(#[from] MachineError),
+    #[error("modulus: {0}")]
+    Modulus(#[from] ModulusError),
+    #[error("AXI: {0}")]
+    Axi(#[from] AxiError),
+    #[error("VCD: {0}")]
+    Vcd(#[from] VcdError),
+    #[error("conversion: {0}")]
+    Convert(#[from] ConvertError),
+    #[error("trace: {0}")]
+    Trace(#[from] TraceError),
+    #[error("range proof: {0}")]
+    Range(#[from] RangeError),
+    #[error("reduction proof: {0}")]
+    Reduction(#[from] ReductionError),
+    #[error("artifact: {0}")]
+    Artifact(#[from] ArtifactError),
+    #[error("sign-off: {0}")]
+    Signoff(#[from] SignoffError),
+    #[error("service: {0}")]
+    Service(#[from] ServiceError),
+    /// A proof the verifier rejected, named by what it proves
+    #[error("{0} failed to verify")]
+    Verification(&'static str),
+    #[error("I/O: {0}")]
+    Io(#[from] io::Error),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// `Ok` if the verifier accepted, otherwise [`Error::Verification`]
+pub fn verified(accepted: bool, what: &'static str) -> Result<()> {
+    if accepted {
+        Ok(())
+    } else {
+        Err(Error::Verification(what))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::range::prove_range;
+    use bls12_381_prover::{Config, Fr, Prover, Setup};
+
+    #[test]
+    fn test_errors_convert_and_stay_matchable() {
+        fn prove_too_wide() -> Result<()> {
+            let prover = Prover::new(Setup::new(Config { log_n: 1 }));
+            let _ = prove_range(&prover, &[Fr::from(4u64)], 2)?;
+            Ok(())
+        }
+        let err = prove_too_wide().unwrap_err();
+        assert!(matches!(err, Error::Range(RangeError::OutOfRange { column: 0, index: 0 })));
+        assert_eq!(err.to_string(), "range proof: value 0 of column 0 is outside its range");
+
+        assert!(verified(true, "trace row").is_ok());
+        assert_eq!(verified(false, "trace row").unwrap_err().to_string(), "trace row failed to verify");
+    }
+}
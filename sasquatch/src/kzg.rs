@@ -0,0 +1,57 @@
+//! Helpers on top of the prover's KZG commitments: linear relations between
+//! commitments and batched openings at a single point.
+
+use bls12_381_prover::{
+    CurveGroup, EvaluationDomain, Fr, G1Affine, G1Projective, OpeningProof, Prover, Radix2EvaluationDomain,
+    VariableBaseMSM, Verifier, Zero,
+};
+
+/// Evaluation domain the prover commits over
+pub(crate) fn domain(size: usize) -> Radix2EvaluationDomain<Fr> {
+    Radix2EvaluationDomain::new(size).expect("domain size is a power of two")
+}
+
+/// Whether `Σ scalar·commitment + constant·G` is the identity
+///
+/// `G` commits to the all-ones vector, so this checks the linear relation
+/// `Σ scalar·column + constant = 0` on every row at once.
+pub(crate) fn combination_vanishes(verifier: &Verifier, terms: &[(G1Affine, Fr)], constant: Fr) -> bool {
+    let (mut bases, mut scalars): (Vec<_>, Vec<_>) = terms.iter().copied().unzip();
+    bases.push(verifier.setup().srs_monomial_g1[0]);
+    scalars.push(constant);
+    G1Projective::msm(&bases, &scalars).unwrap().is_zero()
+}
+
+/// Open every polynomial (given by its evaluations over the commitment
+/// domain) at `point` with one proof, combining them with powers of `gamma`
+pub(crate) fn batch_open(prover: &Prover, polynomials: &[&[Fr]], point: Fr, gamma: Fr) -> OpeningProof {
+    let mut combined = vec![Fr::zero(); prover.config().two_n()];
+    let mut scale = Fr::from(1u64);
+    for evals in polynomials {
+        for (c, e) in combined.iter_mut().zip(evals.iter()) {
+            *c += scale * e;
+        }
+        scale *= gamma;
+    }
+    prover.create_opening_proof(&combined, point)
+}
+
+/// Check a [`batch_open`] proof against the claimed `evaluations`
+pub(crate) fn batch_verify(
+    verifier: &Verifier,
+    commitments: &[G1Affine],
+    evaluations: &[Fr],
+    point: Fr,
+    gamma: Fr,
+    proof: &OpeningProof,
+) -> bool {
+    if commitments.len() != evaluations.len() || proof.point != point {
+        return false;
+    }
+    let powers: Vec<Fr> = std::iter::successors(Some(Fr::from(1u64)), |s| Some(*s * gamma))
+        .take(commitments.len())
+        .collect();
+    let combined = G1Projective::msm(commitments, &powers).unwrap().into_affine();
+    let evaluation: Fr = powers.iter().zip(evaluations).map(|(s, e)| *s * e).sum();
+    proof.evaluation == evaluation && verifier.verify_opening(&combined, proof)
+}
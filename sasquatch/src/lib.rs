@@ -0,0 +1,27 @@
+//! Glue between the modulo machine and the BLS12-381 prover: provable
+//! simulation runs.
+
+pub mod artifact;
+pub mod convert;
+pub mod error;
+pub mod fuzz;
+mod kzg;
+pub mod range;
+pub mod reduction;
+pub mod seed;
+pub mod service;
+pub mod signoff;
+pub mod trace;
+pub mod witness;
+
+pub use artifact::{Artifact, ArtifactError};
+pub use convert::ConvertError;
+pub use error::{Error, Result};
+pub use range::{prove_range, verify_range, RangeProof};
+pub use reduction::{prove_machine_run, prove_reduction, verify_reduction, ReductionCommitment, ReductionProof};
+pub use seed::Seed;
+pub use service::{AttestedSession, RollingCommitter, ServiceError, WindowCommitment, WindowOpening};
+pub use signoff::{SignedVectors, SignoffError};
+pub use trace::{CommittedTrace, RowOpening, TraceCommitment, TraceRow};
+pub use ::transcript;
+pub use witness::{TraceWitness, WitnessEncoding};
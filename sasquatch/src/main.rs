@@ -0,0 +1,253 @@
+//! Command-line front end spanning the modulo machine and the prover.
+//!
+//! Usage:
+//!
+//! - `sasquatch modsim [--seed N] [--count N] [VALUE...]`
+//! - `sasquatch kzg [--log-n N] [--seed N] [--openings N]`
+//! - `sasquatch pipeline [--log-n N] [--seed N] [--count N] [--open N]`
+//! - `sasquatch sign-vectors [--seed N] [--random N] [--log-n N] [OUT]`
+//! - `sasquatch verify-archive PATH`
+//! - `sasquatch serve [--port N] [--window N] [--log-n N] [--seed N]`
+//!
+//! Without `--seed` a random root seed is chosen and printed, so any run can
+//! be repeated exactly.
+
+use bls12_381_prover::{Config, Fr, Prover, Setup, UniformRand, Verifier};
+use modulo_machine::{vectors, ModuloMachine, TickInput};
+use rug::Integer;
+use sasquatch::error::verified;
+use sasquatch::{convert, service, AttestedSession, CommittedTrace, Result, RollingCommitter, Seed, SignedVectors, TraceRow};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::net::TcpListener;
+use std::process;
+use std::time::Instant;
+
+const DEFAULT_LOG_N: u64 = 6;
+const DEFAULT_PORT: u64 = 7879;
+
+fn usage() -> ! {
+    eprintln!("usage: sasquatch modsim [--seed N] [--count N] [VALUE...]");
+    eprintln!("       sasquatch kzg [--log-n N] [--seed N] [--openings N]");
+    eprintln!("       sasquatch pipeline [--log-n N] [--seed N] [--count N] [--open N]");
+    eprintln!("       sasquatch sign-vectors [--seed N] [--random N] [--log-n N] [OUT]");
+    eprintln!("       sasquatch verify-archive PATH");
+    eprintln!("       sasquatch serve [--port N] [--window N] [--log-n N] [--seed N]");
+    process::exit(2);
+}
+
+/// Parse `--flag value` pairs into `options`, returning the positional
+/// arguments
+fn parse_flags<'a>(args: &'a [String], options: &mut [(&str, &mut Option<u64>)]) -> Vec<&'a str> {
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            positional.push(arg.as_str());
+            continue;
+        }
+        let Some((_, slot)) = options.iter_mut().find(|(flag, _)| arg[2..] == **flag) else {
+            usage();
+        };
+        **slot = Some(args.next().and_then(|v| v.parse().ok()).unwrap_or_else(|| usage()));
+    }
+    positional
+}
+
+/// Decimal, or hex with a `0x` prefix
+fn parse_value(s: &str) -> Integer {
+    let parsed = if s.starts_with("0x") {
+        convert::integer_from_hex(s).map_err(|e| e.to_string())
+    } else {
+        ModuloMachine::create_input(s, 10).map_err(|e| e.to_string())
+    };
+    parsed.unwrap_or_else(|e| {
+        eprintln!("❌ Invalid value {}: {}", s, e);
+        process::exit(2);
+    })
+}
+
+/// Root seed from `--seed`, or a fresh one; printed either way
+fn root_seed(seed: Option<u64>) -> Seed {
+    let root = seed.unwrap_or_else(Seed::random_root);
+    eprintln!("Seed: {}", root);
+    Seed::new(root)
+}
+
+/// `count` seeded 300-bit inputs
+fn random_inputs(seed: &Seed, count: usize) -> Vec<Integer> {
+    let set = vectors::generate(seed.derive("stimulus").to_u64(), count);
+    let skip = set.vectors.len() - count;
+    set.vectors.into_iter().skip(skip).map(|v| v.x).collect()
+}
+
+fn setup(log_n: u64, seed: &Seed) -> Setup {
+    if !(1..=20).contains(&log_n) {
+        eprintln!("❌ --log-n must lie in [1, 20]");
+        process::exit(2);
+    }
+    Setup::from_rng(Config { log_n: log_n as usize }, &mut seed.derive("setup").rng())
+}
+
+fn modsim(args: &[String]) {
+    let (mut seed, mut count) = (None, None);
+    let values = parse_flags(args, &mut [("seed", &mut seed), ("count", &mut count)]);
+    let inputs = if values.is_empty() {
+        random_inputs(&root_seed(seed), count.unwrap_or(4) as usize)
+    } else {
+        values.into_iter().map(parse_value).collect()
+    };
+
+    let mut machine = ModuloMachine::new();
+    for (x, o) in inputs.iter().zip(machine.process_values(&inputs)) {
+        println!("{:#x} mod P = {:#x}", x, o);
+    }
+}
+
+fn kzg(args: &[String]) {
+    let (mut log_n, mut seed, mut openings) = (None, None, None);
+    let flags = &mut [("log-n", &mut log_n), ("seed", &mut seed), ("openings", &mut openings)];
+    if !parse_flags(args, flags).is_empty() {
+        usage();
+    }
+
+    let seed = root_seed(seed);
+    let setup = setup(log_n.unwrap_or(DEFAULT_LOG_N), &seed);
+    let prover = Prover::new(setup.clone());
+    let verifier = Verifier::new(setup);
+    let (commitment, evals) = prover.prove_with_rng(&mut seed.derive("witness").rng());
+    println!("Committed to a degree-{} polynomial", evals.len() - 1);
+
+    let mut rng = seed.derive("openings").rng();
+    let mut failed = false;
+    for i in 1..=openings.unwrap_or(3) {
+        let proof = prover.create_opening_proof(&evals, Fr::rand(&mut rng));
+        let valid = verifier.verify_opening(&commitment, &proof);
+        println!("Opening #{}: {}", i, if valid { "✓ PASSED" } else { "✗ FAILED" });
+        failed |= !valid;
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn pipeline(args: &[String]) -> Result<()> {
+    let (mut log_n, mut seed, mut count, mut open) = (None, None, None, None);
+    let flags = &mut [
+        ("log-n", &mut log_n),
+        ("seed", &mut seed),
+        ("count", &mut count),
+        ("open", &mut open),
+    ];
+    if !parse_flags(args, flags).is_empty() {
+        usage();
+    }
+
+    let seed = root_seed(seed);
+    let setup = setup(log_n.unwrap_or(DEFAULT_LOG_N), &seed);
+    let prover = Prover::new(setup.clone());
+    let verifier = Verifier::new(setup);
+    // Two ticks per value; by default fill the domain
+    let count = count.map_or(prover.config().two_n() / 2, |c| c as usize);
+    let open = open.unwrap_or(3) as usize;
+
+    let stimulus: Vec<TickInput> = random_inputs(&seed, count)
+        .into_iter()
+        .flat_map(|x| [TickInput::new(false, false, x.clone()), TickInput::new(true, false, x)])
+        .collect();
+    let rows = TraceRow::record(&mut ModuloMachine::new(), &stimulus);
+    println!("Ran {} values through the modulo machine ({} ticks)", count, rows.len());
+
+    let start = Instant::now();
+    let trace = CommittedTrace::commit(&prover, &rows)?;
+    println!("Committed the trace in {:.2?}", start.elapsed());
+
+    // Open rows challenged from the commitment and the trace digest
+    for row in trace.commitment().challenge_rows(&TraceRow::digest(&rows), open) {
+        let opening = trace.open_trace_row(row).expect("row inside the trace");
+        let valid = trace.commitment().verify_row(&verifier, &opening) && opening.decode().as_ref() == Some(&rows[row]);
+        verified(valid, "trace row opening")?;
+        println!("Row {}: output {:#x} ✓ PASSED", row, rows[row].output);
+    }
+    Ok(())
+}
+
+fn sign_vectors(args: &[String]) -> Result<()> {
+    let (mut seed, mut random, mut log_n) = (None, None, None);
+    let flags = &mut [("seed", &mut seed), ("random", &mut random), ("log-n", &mut log_n)];
+    let out = match parse_flags(args, flags)[..] {
+        [] => None,
+        [out] => Some(out.to_string()),
+        _ => usage(),
+    };
+
+    // The vectors match `vector-archive --seed` with the same root
+    let root = seed.unwrap_or_else(Seed::random_root);
+    let seed = root_seed(Some(root));
+    let set = vectors::generate(root, random.unwrap_or(64) as usize);
+    // Smallest domain holding one point per vector
+    let log_n = log_n.unwrap_or_else(|| set.vectors.len().next_power_of_two().trailing_zeros().max(2) as u64 - 1);
+    let setup_seed = seed.derive("setup");
+    let prover = Prover::new(setup(log_n, &seed));
+    let signed = SignedVectors::sign(&prover, setup_seed, set)?;
+
+    let out = out.unwrap_or_else(|| format!("{}-signed.tar", signed.set.archive_name()));
+    signed.write_archive(BufWriter::new(File::create(&out)?))?;
+    println!("Signed {} vectors (log_n {}) to {}", signed.set.vectors.len(), log_n, out);
+    Ok(())
+}
+
+fn verify_archive(args: &[String]) -> Result<()> {
+    let [path] = args else {
+        usage();
+    };
+    let signed = SignedVectors::read_archive(BufReader::new(File::open(path)?))?;
+    let start = Instant::now();
+    let verifier = Verifier::new(signed.setup());
+    signed.verify(&verifier)?;
+    println!("All {} vector outputs verified in {:.2?}", signed.set.vectors.len(), start.elapsed());
+    Ok(())
+}
+
+fn serve(args: &[String]) -> Result<()> {
+    let (mut port, mut window, mut log_n, mut seed) = (None, None, None, None);
+    let flags = &mut [
+        ("port", &mut port),
+        ("window", &mut window),
+        ("log-n", &mut log_n),
+        ("seed", &mut seed),
+    ];
+    if !parse_flags(args, flags).is_empty() {
+        usage();
+    }
+    let port = u16::try_from(port.unwrap_or(DEFAULT_PORT)).unwrap_or_else(|_| usage());
+
+    let setup = setup(log_n.unwrap_or(DEFAULT_LOG_N), &root_seed(seed));
+    // By default the window covers the whole domain
+    let window = window.map_or(setup.config.two_n(), |w| w as usize);
+    let session = AttestedSession::new(RollingCommitter::new(setup, window)?);
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    println!("Serving attested JSON-RPC on {} (window of {} outputs)", listener.local_addr()?, window);
+    service::serve(listener, session)?;
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("modsim") => modsim(&args[1..]),
+        Some("kzg") => kzg(&args[1..]),
+        Some("pipeline") => exit_on_error(pipeline(&args[1..])),
+        Some("sign-vectors") => exit_on_error(sign_vectors(&args[1..])),
+        Some("verify-archive") => exit_on_error(verify_archive(&args[1..])),
+        Some("serve") => exit_on_error(serve(&args[1..])),
+        _ => usage(),
+    }
+}
+
+fn exit_on_error(result: Result<()>) {
+    if let Err(e) = result {
+        eprintln!("❌ {}", e);
+        process::exit(1);
+    }
+}
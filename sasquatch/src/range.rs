@@ -0,0 +1,292 @@
+//! Range proofs: every evaluation of a committed polynomial lies in
+//! `[0, 2^k)`.
+//!
+//! A column (the evaluations of a committed polynomial over the prover's
+//! domain) is decomposed into `k` committed bit columns. The verifier checks
+//! `Σ 2^i·bit_i = column` directly on the commitments, and that every bit
+//! column is boolean through a single quotient
+//! `t = Σ α^j·b_j·(b_j − 1) / Z` opened at a random point. Any number of
+//! columns, each with its own `k`, share one quotient and one batched
+//! opening.
+
+use crate::kzg;
+use crate::transcript::Transcript;
+use ark_ff::BigInteger;
+use bls12_381_prover::{
+    DensePolynomial, DenseUVPolynomial, EvaluationDomain, Fr, G1Affine, OpeningProof, PrimeField, Prover, Verifier,
+    Zero,
+};
+use std::fmt;
+
+/// Widest supported range; `2^252` is below the scalar field modulus, so the
+/// bit recomposition cannot wrap
+pub const MAX_BITS: u32 = 252;
+
+/// Reason a range proof could not be produced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RangeError {
+    /// Range wider than [`MAX_BITS`]
+    TooWide { bits: u32 },
+    /// More values than the prover's domain has points
+    TooManyValues { values: usize, capacity: usize },
+    /// A value outside its column's range
+    OutOfRange { column: usize, index: usize },
+}
+
+impl fmt::Display for RangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RangeError::TooWide { bits } => write!(f, "{}-bit range exceeds the {}-bit maximum", bits, MAX_BITS),
+            RangeError::TooManyValues { values, capacity } => {
+                write!(f, "{} values, the domain holds {}", values, capacity)
+            }
+            RangeError::OutOfRange { column, index } => {
+                write!(f, "value {} of column {} is outside its range", index, column)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RangeError {}
+
+/// Proof that each of a set of committed columns lies in its range
+#[derive(Debug, Clone)]
+pub struct RangeProof {
+    /// Bit columns, least significant first, column after column
+    bits: Vec<G1Affine>,
+    /// Booleanity quotient
+    quotient: G1Affine,
+    /// Bit columns then the quotient, at the challenge point
+    evaluations: Vec<Fr>,
+    opening: OpeningProof,
+}
+
+impl RangeProof {
+    /// Prove every `(evaluations, k)` column lies in `[0, 2^k)`
+    ///
+    /// The evaluations are those the columns were committed with (shorter
+    /// vectors are zero-padded, as by `commit_evaluations`). The caller must
+    /// already have absorbed the column commitments into `transcript`.
+    pub fn prove(prover: &Prover, transcript: &mut Transcript, columns: &[(&[Fr], u32)]) -> Result<Self, RangeError> {
+        let domain_size = prover.config().two_n();
+        let mut commitments = Vec::new();
+        let mut bit_evals = Vec::new();
+        for (c, &(values, bits)) in columns.iter().enumerate() {
+            if bits > MAX_BITS {
+                return Err(RangeError::TooWide { bits });
+            }
+            if values.len() > domain_size {
+                return Err(RangeError::TooManyValues {
+                    values: values.len(),
+                    capacity: domain_size,
+                });
+            }
+            let values: Vec<_> = values.iter().map(|v| v.into_bigint()).collect();
+            if let Some(index) = values.iter().position(|v| v.num_bits() > bits) {
+                return Err(RangeError::OutOfRange { column: c, index });
+            }
+            for i in 0..bits as usize {
+                let column: Vec<Fr> = values.iter().map(|v| Fr::from(v.get_bit(i))).collect();
+                let (commitment, evals) = prover.commit_evaluations(&column);
+                commitments.push(commitment);
+                bit_evals.push(evals);
+            }
+        }
+
+        transcript.append_points(&commitments);
+        let alpha = transcript.challenge();
+        let quotient_evals = booleanity_quotient(&bit_evals, alpha, domain_size);
+        let quotient = prover.commit_evaluations(&quotient_evals).0;
+        transcript.append_point(&quotient);
+        let zeta = transcript.challenge();
+
+        let mut polynomials: Vec<&[Fr]> = bit_evals.iter().map(Vec::as_slice).collect();
+        polynomials.push(&quotient_evals);
+        let lagrange = kzg::domain(domain_size).evaluate_all_lagrange_coefficients(zeta);
+        let evaluations: Vec<Fr> = polynomials
+            .iter()
+            .map(|evals| evals.iter().zip(&lagrange).map(|(e, l)| *e * l).sum())
+            .collect();
+        for evaluation in &evaluations {
+            transcript.append_scalar(evaluation);
+        }
+        let gamma = transcript.challenge();
+        let opening = kzg::batch_open(prover, &polynomials, zeta, gamma);
+
+        Ok(Self {
+            bits: commitments,
+            quotient,
+            evaluations,
+            opening,
+        })
+    }
+
+    /// Check the proof against `(commitment, k)` columns, with `transcript`
+    /// in the state [`RangeProof::prove`] was given
+    pub fn verify(&self, verifier: &Verifier, transcript: &mut Transcript, columns: &[(G1Affine, u32)]) -> bool {
+        if columns.iter().any(|&(_, bits)| bits > MAX_BITS) {
+            return false;
+        }
+        let total = columns.iter().try_fold(0usize, |total, &(_, bits)| total.checked_add(bits as usize));
+        if total != Some(self.bits.len()) || self.evaluations.len() != self.bits.len() + 1 {
+            return false;
+        }
+
+        let mut bits = self.bits.as_slice();
+        for (column, width) in columns {
+            let (column_bits, rest) = bits.split_at(*width as usize);
+            if !recomposes(verifier, column, column_bits) {
+                return false;
+            }
+            bits = rest;
+        }
+
+        transcript.append_points(&self.bits);
+        let alpha = transcript.challenge();
+        transcript.append_point(&self.quotient);
+        let zeta = transcript.challenge();
+        for evaluation in &self.evaluations {
+            transcript.append_scalar(evaluation);
+        }
+        let gamma = transcript.challenge();
+
+        let domain_size = verifier.setup().config.two_n();
+        let (bit_values, quotient_value) = self.evaluations.split_at(self.bits.len());
+        let mut opened = self.bits.clone();
+        opened.push(self.quotient);
+        booleanity_holds(bit_values, quotient_value[0], alpha, zeta, domain_size)
+            && kzg::batch_verify(verifier, &opened, &self.evaluations, zeta, gamma, &self.opening)
+    }
+}
+
+/// Commit to `values` and prove each lies in `[0, 2^bits)`
+pub fn prove_range(prover: &Prover, values: &[Fr], bits: u32) -> Result<(G1Affine, RangeProof), RangeError> {
+    let capacity = prover.config().two_n();
+    if values.len() > capacity {
+        return Err(RangeError::TooManyValues {
+            values: values.len(),
+            capacity,
+        });
+    }
+    let commitment = prover.commit_evaluations(values).0;
+    let mut transcript = standalone_transcript(&commitment, bits);
+    let proof = RangeProof::prove(prover, &mut transcript, &[(values, bits)])?;
+    Ok((commitment, proof))
+}
+
+/// Check a [`prove_range`] proof
+pub fn verify_range(verifier: &Verifier, commitment: &G1Affine, bits: u32, proof: &RangeProof) -> bool {
+    let mut transcript = standalone_transcript(commitment, bits);
+    proof.verify(verifier, &mut transcript, &[(*commitment, bits)])
+}
+
+fn standalone_transcript(commitment: &G1Affine, bits: u32) -> Transcript {
+    let mut transcript = Transcript::new(b"sasquatch-range-v1");
+    transcript.append_u64(bits as u64);
+    transcript.append_point(commitment);
+    transcript
+}
+
+/// Whether the bit commitments recompose to `column`
+fn recomposes(verifier: &Verifier, column: &G1Affine, bits: &[G1Affine]) -> bool {
+    let mut terms = vec![(*column, -Fr::from(1u64))];
+    let mut weight = Fr::from(1u64);
+    for bit in bits {
+        terms.push((*bit, weight));
+        weight += weight;
+    }
+    kzg::combination_vanishes(verifier, &terms, Fr::zero())
+}
+
+/// Evaluations over the commitment domain of `Σ α^j·b_j·(b_j − 1) / Z`
+fn booleanity_quotient(columns: &[Vec<Fr>], alpha: Fr, domain_size: usize) -> Vec<Fr> {
+    let domain = kzg::domain(domain_size);
+    // b·(b − 1) has twice the degree, so accumulate over a domain twice as big
+    let wide = kzg::domain(2 * domain_size);
+    let mut numerator = vec![Fr::zero(); wide.size()];
+    let mut scale = Fr::from(1u64);
+    for evals in columns {
+        let mut values = domain.ifft(evals);
+        wide.fft_in_place(&mut values);
+        for (n, b) in numerator.iter_mut().zip(&values) {
+            *n += scale * b * (*b - Fr::from(1u64));
+        }
+        scale *= alpha;
+    }
+    wide.ifft_in_place(&mut numerator);
+
+    let (quotient, remainder) = DensePolynomial::from_coefficients_vec(numerator)
+        .divide_by_vanishing_poly(domain)
+        .unwrap();
+    debug_assert!(remainder.is_zero(), "bit columns are boolean");
+    let mut evals = quotient.coeffs;
+    evals.resize(domain_size, Fr::zero());
+    domain.fft_in_place(&mut evals);
+    evals
+}
+
+/// Check the booleanity identity at `point` from the opened values
+fn booleanity_holds(bits: &[Fr], quotient: Fr, alpha: Fr, point: Fr, domain_size: usize) -> bool {
+    let mut scale = Fr::from(1u64);
+    let mut lhs = Fr::zero();
+    for b in bits {
+        lhs += scale * b * (*b - Fr::from(1u64));
+        scale *= alpha;
+    }
+    lhs == kzg::domain(domain_size).evaluate_vanishing_polynomial(point) * quotient
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls12_381_prover::{Config, Setup};
+
+    fn setup() -> (Prover, Verifier) {
+        let setup = Setup::new(Config { log_n: 2 });
+        (Prover::new(setup.clone()), Verifier::new(setup))
+    }
+
+    #[test]
+    fn test_range_proof() {
+        let (prover, verifier) = setup();
+        let values: Vec<Fr> = [0u64, 1, 200, 255].map(Fr::from).to_vec();
+        let (commitment, proof) = prove_range(&prover, &values, 8).unwrap();
+        assert!(verify_range(&verifier, &commitment, 8, &proof));
+
+        // The same proof says nothing about another range or column
+        assert!(!verify_range(&verifier, &commitment, 9, &proof));
+        let (other, _) = prove_range(&prover, &values[..2], 8).unwrap();
+        assert!(!verify_range(&verifier, &other, 8, &proof));
+    }
+
+    #[test]
+    fn test_out_of_range_values_are_refused() {
+        let (prover, _) = setup();
+        let values = [Fr::from(256u64)];
+        assert_eq!(
+            prove_range(&prover, &values, 8).err(),
+            Some(RangeError::OutOfRange { column: 0, index: 0 })
+        );
+        // -1 is the largest field element, not a small negative number
+        assert!(prove_range(&prover, &[-Fr::from(1u64)], MAX_BITS).is_err());
+        assert_eq!(
+            prove_range(&prover, &values, MAX_BITS + 1).err(),
+            Some(RangeError::TooWide { bits: MAX_BITS + 1 })
+        );
+    }
+
+    #[test]
+    fn test_batched_columns() {
+        let (prover, verifier) = setup();
+        let a: Vec<Fr> = [3u64, 1].map(Fr::from).to_vec();
+        let b: Vec<Fr> = [1u64 << 40, 7].map(Fr::from).to_vec();
+        let (ca, cb) = (prover.commit_evaluations(&a).0, prover.commit_evaluations(&b).0);
+
+        let proof = RangeProof::prove(&prover, &mut Transcript::new(b"batch"), &[(&a, 2), (&b, 41)]).unwrap();
+        assert!(proof.verify(&verifier, &mut Transcript::new(b"batch"), &[(ca, 2), (cb, 41)]));
+        assert!(!proof.verify(&verifier, &mut Transcript::new(b"other"), &[(ca, 2), (cb, 41)]));
+        assert!(!proof.verify(&verifier, &mut Transcript::new(b"batch"), &[(cb, 2), (ca, 41)]));
+        // Widths whose sum overflows a u32 are refused, not summed
+        assert!(!proof.verify(&verifier, &mut Transcript::new(b"batch"), &[(ca, u32::MAX), (cb, 2)]));
+    }
+}
@@ -0,0 +1,437 @@
+//! Proof that committed outputs are the committed inputs reduced mod P.
+//!
+//! Every value is split into 64-bit limbs, one committed column per limb.
+//! The prover additionally commits to the quotient `q`, the carries of
+//! `q·P + r` and the limbs and borrows of `d = P − 1 − r`. Because P is
+//! public, both identities
+//!
+//! - `x = q·P + r` (limb by limb, with carries) and
+//! - `P − 1 − r = d` (limb by limb, with borrows, so `r < P`)
+//!
+//! are linear in the columns and are checked directly on the commitments.
+//! Every column is range-checked by bit decomposition, so no limb
+//! identity can wrap around the scalar field (see [`crate::range`]).
+//!
+//! Rows past the end of the run are filled with the valid row `x = r = 0`,
+//! so the identities hold on the whole commitment domain.
+
+use crate::convert::integer_to_words;
+use crate::kzg;
+use crate::range::RangeProof;
+use crate::transcript::Transcript;
+use bls12_381_prover::{Fr, G1Affine, Prover, Verifier};
+use modulo_machine::fixed::X_LIMBS;
+use modulo_machine::{ModuloMachine, X_BITS};
+use rug::integer::Order;
+use rug::Integer;
+use std::fmt;
+
+const LIMB_BITS: u32 = 64;
+/// Limbs of r, d and the modulus
+const R_LIMBS: usize = 4;
+/// Enough for a carry out of four 128-bit products plus the previous carry
+const CARRY_BITS: u32 = 67;
+
+/// Reason a reduction could not be proved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReductionError {
+    /// Modulus below 2 or wider than 256 bits
+    UnsupportedModulus,
+    /// Different numbers of inputs and outputs
+    LengthMismatch { inputs: usize, outputs: usize },
+    /// More rows than the prover's domain has points
+    TooManyRows { rows: usize, capacity: usize },
+    /// Input negative or wider than the 300-bit bus
+    InputOutOfRange { row: usize },
+    /// Output is not the input reduced mod P
+    NotReduced { row: usize },
+}
+
+impl fmt::Display for ReductionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReductionError::UnsupportedModulus => write!(f, "modulus must lie in [2, 2^256)"),
+            ReductionError::LengthMismatch { inputs, outputs } => {
+                write!(f, "{} inputs but {} outputs", inputs, outputs)
+            }
+            ReductionError::TooManyRows { rows, capacity } => {
+                write!(f, "{} rows, the domain holds {}", rows, capacity)
+            }
+            ReductionError::InputOutOfRange { row } => write!(f, "input {} is outside the 300-bit bus", row),
+            ReductionError::NotReduced { row } => write!(f, "output {} is not its input reduced mod P", row),
+        }
+    }
+}
+
+impl std::error::Error for ReductionError {}
+
+/// Public commitments to the inputs and outputs, one per 64-bit limb
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReductionCommitment {
+    pub modulus: Integer,
+    pub rows: usize,
+    pub domain_size: usize,
+    /// Input limbs, least significant first
+    pub x: Vec<G1Affine>,
+    /// Output limbs, least significant first
+    pub r: Vec<G1Affine>,
+}
+
+/// Proof that every committed output is its committed input mod P
+#[derive(Debug, Clone)]
+pub struct ReductionProof {
+    q: Vec<G1Affine>,
+    carries: Vec<G1Affine>,
+    d: Vec<G1Affine>,
+    borrows: Vec<G1Affine>,
+    /// Every column, in [`Layout::widths`] order, lies in its range
+    range: RangeProof,
+}
+
+/// Column counts and limb widths, which depend only on the modulus
+struct Layout {
+    modulus_limbs: [u64; R_LIMBS],
+    q_limbs: usize,
+    /// Limb positions of `q·P + r`; the last carry out must be zero
+    positions: usize,
+    x_widths: Vec<u32>,
+    r_widths: Vec<u32>,
+    q_widths: Vec<u32>,
+}
+
+impl Layout {
+    fn new(p: &Integer) -> Result<Self, ReductionError> {
+        let bits = p.significant_bits();
+        if *p < 2 || bits > R_LIMBS as u32 * LIMB_BITS {
+            return Err(ReductionError::UnsupportedModulus);
+        }
+        // q <= (2^300 - 1) / p < 2^(301 - bits)
+        let q_bits = X_BITS + 1 - bits;
+        let q_limbs = q_bits.div_ceil(LIMB_BITS) as usize;
+        let mut modulus_limbs = [0; R_LIMBS];
+        p.write_digits(&mut modulus_limbs, Order::Lsf);
+
+        Ok(Self {
+            modulus_limbs,
+            q_limbs,
+            positions: X_LIMBS.max(q_limbs + R_LIMBS - 1),
+            x_widths: limb_widths(X_BITS, X_LIMBS),
+            r_widths: limb_widths(bits, R_LIMBS),
+            q_widths: limb_widths(q_bits, q_limbs),
+        })
+    }
+
+    fn carries(&self) -> usize {
+        self.positions - 1
+    }
+
+    /// Bit width of every range-checked column: x, r, q, carries, d, borrows
+    fn widths(&self) -> Vec<u32> {
+        let mut widths = [self.x_widths.clone(), self.r_widths.clone(), self.q_widths.clone()].concat();
+        widths.extend(std::iter::repeat_n(CARRY_BITS, self.carries()));
+        widths.extend(&self.r_widths);
+        widths.extend(std::iter::repeat_n(1, R_LIMBS - 1));
+        widths
+    }
+
+    /// Limbs of `P − 1`
+    fn bound_limbs(&self) -> [u64; R_LIMBS] {
+        let mut limbs = self.modulus_limbs;
+        let mut i = 0;
+        while limbs[i] == 0 {
+            limbs[i] = u64::MAX;
+            i += 1;
+        }
+        limbs[i] -= 1;
+        limbs
+    }
+}
+
+fn limb_widths(bits: u32, limbs: usize) -> Vec<u32> {
+    (0..limbs as u32)
+        .map(|i| bits.saturating_sub(i * LIMB_BITS).min(LIMB_BITS))
+        .collect()
+}
+
+/// Witness columns for one row
+struct Row {
+    x: Vec<u128>,
+    r: Vec<u128>,
+    q: Vec<u128>,
+    carries: Vec<u128>,
+    d: Vec<u128>,
+    borrows: Vec<u128>,
+}
+
+impl Row {
+    fn new(layout: &Layout, p: &Integer, x: &Integer, r: &Integer) -> Self {
+        let q = Integer::from(x - r) / p;
+        let limbs = |v: &Integer, n: usize| integer_to_words(v, LIMB_BITS, n).expect("checked by the caller");
+        let (x, r, q) = (limbs(x, X_LIMBS), limbs(r, R_LIMBS), limbs(&q, layout.q_limbs));
+
+        // Carries of q·P + r, position by position
+        let mut carries = Vec::with_capacity(layout.carries());
+        let mut carry = Integer::new();
+        for k in 0..layout.carries() {
+            let mut sum = carry + Integer::from(r.get(k).copied().unwrap_or(0));
+            for (i, qi) in q.iter().enumerate() {
+                if let Some(pj) = k.checked_sub(i).and_then(|j| layout.modulus_limbs.get(j)) {
+                    sum += Integer::from(*qi) * *pj;
+                }
+            }
+            carry = sum >> LIMB_BITS;
+            carries.push(carry.to_u128().expect("carry fits its range"));
+        }
+
+        // Borrows of (P - 1) - r
+        let bound = layout.bound_limbs();
+        let (mut d, mut borrows) = (Vec::with_capacity(R_LIMBS), Vec::with_capacity(R_LIMBS - 1));
+        let mut borrow = 0u128;
+        for j in 0..R_LIMBS {
+            let (diff, under1) = (bound[j] as u128).overflowing_sub(r[j]);
+            let (diff, under2) = diff.overflowing_sub(borrow);
+            d.push(diff & u64::MAX as u128);
+            borrow = (under1 || under2) as u128;
+            if j + 1 < R_LIMBS {
+                borrows.push(borrow);
+            }
+        }
+
+        Row {
+            x,
+            r,
+            q,
+            carries,
+            d,
+            borrows,
+        }
+    }
+
+    fn columns(&self) -> impl Iterator<Item = u128> + '_ {
+        self.x
+            .iter()
+            .chain(&self.r)
+            .chain(&self.q)
+            .chain(&self.carries)
+            .chain(&self.d)
+            .chain(&self.borrows)
+            .copied()
+    }
+}
+
+fn transcript(commitment: &ReductionCommitment) -> Transcript {
+    let mut transcript = Transcript::new(b"sasquatch-reduction-v1");
+    transcript.append_bytes(&commitment.modulus.to_digits::<u8>(Order::Lsf));
+    transcript.append_u64(commitment.rows as u64);
+    transcript.append_u64(commitment.domain_size as u64);
+    transcript.append_points(commitment.x.iter().chain(&commitment.r));
+    transcript
+}
+
+/// Commit to `inputs` and `outputs` and prove `outputs[i] = inputs[i] mod p`
+pub fn prove_reduction(
+    prover: &Prover,
+    p: &Integer,
+    inputs: &[Integer],
+    outputs: &[Integer],
+) -> Result<(ReductionCommitment, ReductionProof), ReductionError> {
+    let layout = Layout::new(p)?;
+    if inputs.len() != outputs.len() {
+        return Err(ReductionError::LengthMismatch {
+            inputs: inputs.len(),
+            outputs: outputs.len(),
+        });
+    }
+    let domain_size = prover.config().two_n();
+    if inputs.len() > domain_size {
+        return Err(ReductionError::TooManyRows {
+            rows: inputs.len(),
+            capacity: domain_size,
+        });
+    }
+
+    let mut rows = Vec::with_capacity(domain_size);
+    for (i, (x, r)) in inputs.iter().zip(outputs).enumerate() {
+        if *x < 0 || x.significant_bits() > X_BITS {
+            return Err(ReductionError::InputOutOfRange { row: i });
+        }
+        if *r < 0 || r >= p || !Integer::from(x - r).is_divisible(p) {
+            return Err(ReductionError::NotReduced { row: i });
+        }
+        rows.push(Row::new(&layout, p, x, r));
+    }
+    let zero = Integer::new();
+    rows.resize_with(domain_size, || Row::new(&layout, p, &zero, &zero));
+
+    let widths = layout.widths();
+    let columns: Vec<Vec<Fr>> = (0..widths.len())
+        .map(|c| rows.iter().map(|row| Fr::from(row.columns().nth(c).unwrap())).collect())
+        .collect();
+    let commitments: Vec<G1Affine> = columns.iter().map(|column| prover.commit_evaluations(column).0).collect();
+
+    let mut committed = commitments.into_iter();
+    let mut take = |n: usize| committed.by_ref().take(n).collect::<Vec<_>>();
+    let commitment = ReductionCommitment {
+        modulus: p.clone(),
+        rows: inputs.len(),
+        domain_size,
+        x: take(X_LIMBS),
+        r: take(R_LIMBS),
+    };
+    let (q, carries, d, borrows) = (take(layout.q_limbs), take(layout.carries()), take(R_LIMBS), take(R_LIMBS - 1));
+
+    let mut transcript = transcript(&commitment);
+    transcript.append_points(q.iter().chain(&carries).chain(&d).chain(&borrows));
+    let ranges: Vec<(&[Fr], u32)> = columns.iter().map(Vec::as_slice).zip(widths).collect();
+    let range = RangeProof::prove(prover, &mut transcript, &ranges).expect("witness columns fit their ranges");
+
+    let proof = ReductionProof {
+        q,
+        carries,
+        d,
+        borrows,
+        range,
+    };
+    Ok((commitment, proof))
+}
+
+/// Run `values` through `machine` (one rising edge each) and prove the
+/// outputs are the inputs reduced by the machine's modulus
+pub fn prove_machine_run(
+    prover: &Prover,
+    machine: &mut ModuloMachine,
+    values: &[Integer],
+) -> Result<(Vec<Integer>, ReductionCommitment, ReductionProof), ReductionError> {
+    let outputs = machine.process_values(values);
+    let (commitment, proof) = prove_reduction(prover, machine.get_prime(), values, &outputs)?;
+    Ok((outputs, commitment, proof))
+}
+
+/// Check a reduction proof against its commitment
+pub fn verify_reduction(verifier: &Verifier, commitment: &ReductionCommitment, proof: &ReductionProof) -> bool {
+    let Ok(layout) = Layout::new(&commitment.modulus) else {
+        return false;
+    };
+    let domain_size = commitment.domain_size;
+    if domain_size != verifier.setup().config.two_n()
+        || commitment.rows > domain_size
+        || commitment.x.len() != X_LIMBS
+        || commitment.r.len() != R_LIMBS
+        || proof.q.len() != layout.q_limbs
+        || proof.carries.len() != layout.carries()
+        || proof.d.len() != R_LIMBS
+        || proof.borrows.len() != R_LIMBS - 1
+    {
+        return false;
+    }
+
+    // x = q·P + r, limb position by limb position
+    let base = Fr::from(1u128 << LIMB_BITS);
+    for k in 0..layout.positions {
+        let mut terms = Vec::new();
+        for (i, qi) in proof.q.iter().enumerate() {
+            if let Some(pj) = k.checked_sub(i).and_then(|j| layout.modulus_limbs.get(j)) {
+                terms.push((*qi, Fr::from(*pj)));
+            }
+        }
+        if let Some(rk) = commitment.r.get(k) {
+            terms.push((*rk, Fr::from(1u64)));
+        }
+        if let Some(carry_in) = k.checked_sub(1).and_then(|j| proof.carries.get(j)) {
+            terms.push((*carry_in, Fr::from(1u64)));
+        }
+        if let Some(xk) = commitment.x.get(k) {
+            terms.push((*xk, -Fr::from(1u64)));
+        }
+        if let Some(carry_out) = proof.carries.get(k) {
+            terms.push((*carry_out, -base));
+        }
+        if !kzg::combination_vanishes(verifier, &terms, Fr::from(0u64)) {
+            return false;
+        }
+    }
+
+    // (P - 1) - r = d with borrows, so r < P
+    for (j, bound) in layout.bound_limbs().iter().enumerate() {
+        let mut terms = vec![(proof.d[j], Fr::from(1u64)), (commitment.r[j], Fr::from(1u64))];
+        if let Some(borrow_in) = j.checked_sub(1).map(|i| proof.borrows[i]) {
+            terms.push((borrow_in, Fr::from(1u64)));
+        }
+        if let Some(borrow_out) = proof.borrows.get(j) {
+            terms.push((*borrow_out, -base));
+        }
+        if !kzg::combination_vanishes(verifier, &terms, -Fr::from(*bound)) {
+            return false;
+        }
+    }
+
+    // Every column lies in its range
+    let columns = [&commitment.x, &commitment.r, &proof.q, &proof.carries, &proof.d, &proof.borrows]
+        .into_iter()
+        .flatten()
+        .copied();
+    let ranges: Vec<(G1Affine, u32)> = columns.zip(layout.widths()).collect();
+    let mut transcript = transcript(commitment);
+    transcript.append_points(proof.q.iter().chain(&proof.carries).chain(&proof.d).chain(&proof.borrows));
+    proof.range.verify(verifier, &mut transcript, &ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls12_381_prover::{Config, Setup};
+
+    fn setup() -> (Prover, Verifier) {
+        let setup = Setup::new(Config { log_n: 2 });
+        (Prover::new(setup.clone()), Verifier::new(setup))
+    }
+
+    #[test]
+    fn test_machine_run_verifies() {
+        let (prover, verifier) = setup();
+        let mut machine = ModuloMachine::new();
+        let p = machine.get_prime().clone();
+        let values = [
+            Integer::from(&p + 42u32),
+            ModuloMachine::create_large_input(300, 0) - 1u32,
+            Integer::from(&p - 1u32),
+        ];
+
+        let (outputs, commitment, proof) = prove_machine_run(&prover, &mut machine, &values).unwrap();
+        assert_eq!(outputs[0], 42);
+        assert!(verify_reduction(&verifier, &commitment, &proof));
+
+        // Swapping output columns between proofs breaks the identities
+        let other = [Integer::from(7u32), Integer::new(), Integer::from(1u32)];
+        let (other_commitment, _) = prove_reduction(&prover, &p, &other, &other).unwrap();
+        let mut forged = commitment.clone();
+        forged.r = other_commitment.r;
+        assert!(!verify_reduction(&verifier, &forged, &proof));
+    }
+
+    #[test]
+    fn test_small_modulus() {
+        // A narrow modulus needs a multi-limb quotient
+        let (prover, verifier) = setup();
+        let p = Integer::from(1_000_003u32);
+        let inputs = [ModuloMachine::create_large_input(299, 12345), Integer::from(999u32)];
+        let outputs: Vec<Integer> = inputs.iter().map(|x| Integer::from(x % &p)).collect();
+        let (commitment, proof) = prove_reduction(&prover, &p, &inputs, &outputs).unwrap();
+        assert!(verify_reduction(&verifier, &commitment, &proof));
+    }
+
+    #[test]
+    fn test_rejects_false_statements() {
+        let (prover, _) = setup();
+        let p = Integer::from(97u32);
+        let prove = |x: u32, r: u32| prove_reduction(&prover, &p, &[Integer::from(x)], &[Integer::from(r)]).err();
+        assert_eq!(prove(100, 4), Some(ReductionError::NotReduced { row: 0 }));
+        // Congruent but not reduced
+        assert_eq!(prove(100, 100), Some(ReductionError::NotReduced { row: 0 }));
+        assert_eq!(prove(100, 3), None);
+        assert_eq!(
+            prove_reduction(&prover, &Integer::from(1u32), &[], &[]).err(),
+            Some(ReductionError::UnsupportedModulus)
+        );
+    }
+}
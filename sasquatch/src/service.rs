@@ -0,0 +1,330 @@
+//! Live attested simulation: a machine driven over JSON-RPC while a rolling
+//! commitment covers its last N outputs.
+//!
+//! [`RollingCommitter`] keeps the SHA-256 of each output
+//! ([`WitnessEncoding::Hash`]) in a ring of `window` slots over the prover's
+//! domain; output `seq` lives at domain point `ω^(seq mod window)`. A
+//! commitment in the Lagrange basis is linear in the evaluations, so each new
+//! output updates it with one scalar multiplication instead of a full MSM.
+//!
+//! [`AttestedSession`] serves the modulo-machine JSON-RPC methods (see
+//! `modulo_machine::rpc`), pushing every output returned by `tick`, `batch`,
+//! `process_values` and `reset`, plus two methods of its own:
+//!
+//! | Method       | Params  | Result                                                   |
+//! |--------------|---------|----------------------------------------------------------|
+//! | `commitment` | none    | `{commitment, window, domain_size, first, len, pushed}`  |
+//! | `open`       | `{seq}` | `{seq, output, proof, commitment}`                       |
+//!
+//! Points and proofs travel as hex of their compressed encodings.
+
+use crate::convert::bytes_to_hex;
+use crate::witness::{TraceWitness, WitnessEncoding};
+use crate::ConvertError;
+use ark_serialize::CanonicalSerialize;
+use bls12_381_prover::{
+    CurveGroup, EvaluationDomain, Fr, G1Affine, G1Projective, OpeningProof, Prover, Radix2EvaluationDomain, Setup,
+    Verifier, Zero,
+};
+use modulo_machine::rpc::{self, RpcError, RpcSession};
+use rug::Integer;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Reason a committer could not be created
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceError {
+    /// The window is empty or larger than the prover's domain
+    BadWindow { window: usize, capacity: usize },
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceError::BadWindow { window, capacity } => {
+                write!(f, "window of {} outputs must lie in [1, {}]", window, capacity)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ServiceError {}
+
+/// Commitment to the outputs currently in the window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowCommitment {
+    pub point: G1Affine,
+    pub window: usize,
+    pub domain_size: usize,
+    /// Sequence number of the oldest output in the window
+    pub first: u64,
+    pub len: usize,
+}
+
+/// One output of the window and its opening proof
+#[derive(Debug, Clone)]
+pub struct WindowOpening {
+    pub seq: u64,
+    pub output: Integer,
+    pub proof: OpeningProof,
+}
+
+impl WindowCommitment {
+    /// Check `opening` is output `seq` of the window, at its slot's domain
+    /// point
+    pub fn verify(&self, verifier: &Verifier, opening: &WindowOpening) -> bool {
+        if opening.seq < self.first || opening.seq - self.first >= self.len as u64 {
+            return false;
+        }
+        let domain = Radix2EvaluationDomain::<Fr>::new(self.domain_size).unwrap();
+        let point = domain.element((opening.seq % self.window as u64) as usize);
+        let expected = TraceWitness::new(WitnessEncoding::Hash).from_machine_outputs(std::slice::from_ref(&opening.output));
+        opening.proof.point == point
+            && expected.is_ok_and(|e| e[0] == opening.proof.evaluation)
+            && verifier.verify_opening(&self.point, &opening.proof)
+    }
+}
+
+/// Rolling commitment to the last `window` outputs
+pub struct RollingCommitter {
+    prover: Prover,
+    /// Lagrange basis of the domain, as the prover commits with
+    lagrange: Vec<G1Affine>,
+    /// Output hashes by slot, zero-padded to the domain
+    evals: Vec<Fr>,
+    outputs: VecDeque<Integer>,
+    window: usize,
+    pushed: u64,
+    commitment: G1Projective,
+}
+
+impl RollingCommitter {
+    pub fn new(setup: Setup, window: usize) -> Result<Self, ServiceError> {
+        let capacity = setup.config.two_n();
+        if !(1..=capacity).contains(&window) {
+            return Err(ServiceError::BadWindow { window, capacity });
+        }
+        let lagrange = G1Projective::normalize_batch(&setup.srs_lagrange_g1);
+        Ok(Self {
+            prover: Prover::new(setup),
+            lagrange,
+            evals: vec![Fr::zero(); capacity],
+            outputs: VecDeque::with_capacity(window),
+            window,
+            pushed: 0,
+            commitment: G1Projective::zero(),
+        })
+    }
+
+    /// Add the next output, evicting the oldest once the window is full
+    pub fn push(&mut self, output: &Integer) -> Result<(), ConvertError> {
+        let hash = TraceWitness::new(WitnessEncoding::Hash).from_machine_outputs(std::slice::from_ref(output))?[0];
+        let slot = (self.pushed % self.window as u64) as usize;
+        self.commitment += self.lagrange[slot] * (hash - self.evals[slot]);
+        self.evals[slot] = hash;
+        if self.outputs.len() == self.window {
+            self.outputs.pop_front();
+        }
+        self.outputs.push_back(output.clone());
+        self.pushed += 1;
+        Ok(())
+    }
+
+    /// Outputs pushed since the committer was created
+    pub fn pushed(&self) -> u64 {
+        self.pushed
+    }
+
+    pub fn commitment(&self) -> WindowCommitment {
+        WindowCommitment {
+            point: self.commitment.into_affine(),
+            window: self.window,
+            domain_size: self.evals.len(),
+            first: self.pushed - self.outputs.len() as u64,
+            len: self.outputs.len(),
+        }
+    }
+
+    /// Open output `seq`, or `None` if it is not in the window
+    pub fn open(&self, seq: u64) -> Option<WindowOpening> {
+        let first = self.pushed - self.outputs.len() as u64;
+        let output = self.outputs.get(usize::try_from(seq.checked_sub(first)?).ok()?)?.clone();
+        let domain = Radix2EvaluationDomain::<Fr>::new(self.evals.len()).unwrap();
+        let point = domain.element((seq % self.window as u64) as usize);
+        let proof = self.prover.create_opening_proof(&self.evals, point);
+        Some(WindowOpening { seq, output, proof })
+    }
+}
+
+/// A JSON-RPC machine session whose outputs feed a [`RollingCommitter`]
+pub struct AttestedSession {
+    session: RpcSession,
+    committer: RollingCommitter,
+}
+
+impl AttestedSession {
+    pub fn new(committer: RollingCommitter) -> Self {
+        Self {
+            session: RpcSession::new(),
+            committer,
+        }
+    }
+
+    pub fn committer(&self) -> &RollingCommitter {
+        &self.committer
+    }
+
+    /// Handle one request line and produce the response line
+    pub fn handle_line(&mut self, line: &str) -> String {
+        rpc::respond(line, |method, params| self.dispatch(method, params))
+    }
+
+    fn dispatch(&mut self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        match method {
+            "commitment" => {
+                let c = self.committer.commitment();
+                Ok(json!({
+                    "commitment": compressed_hex(&c.point),
+                    "window": c.window,
+                    "domain_size": c.domain_size,
+                    "first": c.first,
+                    "len": c.len,
+                    "pushed": self.committer.pushed(),
+                }))
+            }
+            "open" => {
+                let seq = params
+                    .get("seq")
+                    .and_then(Value::as_u64)
+                    .ok_or_else(|| RpcError::invalid_params("'seq' must be an unsigned integer"))?;
+                let opening = self
+                    .committer
+                    .open(seq)
+                    .ok_or_else(|| RpcError::invalid_params(format!("output {} is not in the window", seq)))?;
+                let mut proof = Vec::new();
+                opening.proof.write(&mut proof).expect("serializing to memory cannot fail");
+                Ok(json!({
+                    "seq": seq,
+                    "output": opening.output.to_string(),
+                    "proof": bytes_to_hex(&proof),
+                    "commitment": compressed_hex(&self.committer.commitment().point),
+                }))
+            }
+            _ => {
+                let result = self.session.dispatch(method, params)?;
+                if matches!(method, "tick" | "batch" | "process_values" | "reset") {
+                    let outputs = result.get("outputs").and_then(Value::as_array).map_or_else(
+                        || result.get("output").into_iter().collect::<Vec<_>>(),
+                        |outputs| outputs.iter().collect(),
+                    );
+                    for output in outputs {
+                        let output = rpc::parse_integer(output)?;
+                        self.committer.push(&output).expect("machine outputs fit the output bus");
+                    }
+                }
+                Ok(result)
+            }
+        }
+    }
+}
+
+fn compressed_hex(point: &G1Affine) -> String {
+    let mut bytes = Vec::new();
+    point.serialize_compressed(&mut bytes).expect("serializing to memory cannot fail");
+    bytes_to_hex(&bytes)
+}
+
+/// Serve `session` to every connection; all clients drive the same machine
+/// and commitment
+pub fn serve(listener: TcpListener, session: AttestedSession) -> io::Result<()> {
+    let session = Arc::new(Mutex::new(session));
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let session = Arc::clone(&session);
+        thread::spawn(move || {
+            let result = rpc::serve_stream(stream, |line| session.lock().unwrap().handle_line(line));
+            if let Err(e) = result {
+                eprintln!("Connection closed with error: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::bytes_from_hex;
+    use bls12_381_prover::Config;
+
+    fn call(session: &mut AttestedSession, method: &str, params: Value) -> Result<Value, RpcError> {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        rpc::parse_response(&session.handle_line(&request.to_string()))
+    }
+
+    #[test]
+    fn test_rolling_commitment_matches_full_commit() {
+        let setup = Setup::new(Config { log_n: 2 });
+        let prover = Prover::new(setup.clone());
+        let verifier = Verifier::new(setup.clone());
+        let mut committer = RollingCommitter::new(setup.clone(), 5).unwrap();
+
+        let outputs: Vec<Integer> = (0..12u32).map(|i| Integer::from(i * 1000 + 7)).collect();
+        for output in &outputs {
+            committer.push(output).unwrap();
+        }
+        let commitment = committer.commitment();
+        assert_eq!((commitment.first, commitment.len), (7, 5));
+        assert_eq!(commitment.point, prover.commit_evaluations(&committer.evals).0);
+
+        let opening = committer.open(9).unwrap();
+        assert_eq!(opening.output, outputs[9]);
+        assert!(commitment.verify(&verifier, &opening));
+        assert!(committer.open(6).is_none() && committer.open(12).is_none());
+
+        // A different claimed output, or an evicted sequence number, fails
+        let mut forged = opening.clone();
+        forged.output += 1u32;
+        assert!(!commitment.verify(&verifier, &forged));
+        let mut evicted = opening;
+        evicted.seq = 4;
+        assert!(!commitment.verify(&verifier, &evicted));
+
+        assert_eq!(
+            RollingCommitter::new(setup, 9).err(),
+            Some(ServiceError::BadWindow { window: 9, capacity: 8 })
+        );
+    }
+
+    #[test]
+    fn test_attested_session_over_rpc() {
+        let setup = Setup::new(Config { log_n: 2 });
+        let verifier = Verifier::new(setup.clone());
+        let mut session = AttestedSession::new(RollingCommitter::new(setup, 4).unwrap());
+
+        call(&mut session, "process_values", json!({ "values": [5, 6, 7] })).unwrap();
+        call(&mut session, "tick", json!({ "clk": false, "x": "9" })).unwrap();
+        call(&mut session, "snapshot", Value::Null).unwrap();
+        let commitment = call(&mut session, "commitment", Value::Null).unwrap();
+        assert_eq!(commitment["pushed"], 4);
+        assert_eq!(commitment["first"], 0);
+
+        let opened = call(&mut session, "open", json!({ "seq": 2 })).unwrap();
+        assert_eq!(opened["output"], "7");
+        assert_eq!(opened["commitment"], commitment["commitment"]);
+        let opening = WindowOpening {
+            seq: 2,
+            output: Integer::from(7),
+            proof: OpeningProof::read(&bytes_from_hex(opened["proof"].as_str().unwrap()).unwrap()[..]).unwrap(),
+        };
+        assert!(session.committer().commitment().verify(&verifier, &opening));
+
+        let error = call(&mut session, "open", json!({ "seq": 4 })).unwrap_err();
+        assert_eq!(error.code, rpc::INVALID_PARAMS);
+    }
+}
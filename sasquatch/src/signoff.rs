@@ -0,0 +1,210 @@
+//! Proof-carrying test-vector archives for hardware sign-off.
+//!
+//! A signed archive is a `modulo_machine::vectors` archive plus
+//! `proofs.json`: a KZG commitment to the SHA-256 of every expected output
+//! (the [`WitnessEncoding::Hash`] encoding, one domain point per vector) and
+//! an opening proof per vector. A third party checks a vendor's claimed
+//! outputs against the commitment without rerunning the model.
+//!
+//! The setup is rebuilt from the seed recorded in `proofs.json`, as with
+//! every setup in this workspace; a real sign-off would distribute the
+//! verifier's SRS from a trusted ceremony instead.
+
+use crate::convert::{bytes_from_hex, bytes_to_hex};
+use crate::error::{verified, Result};
+use crate::seed::Seed;
+use crate::witness::{TraceWitness, WitnessEncoding};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use bls12_381_prover::{Config, EvaluationDomain, Fr, G1Affine, OpeningProof, Prover, Radix2EvaluationDomain, Setup, Verifier};
+use modulo_machine::vectors::{self, VectorSet};
+use serde_json::{json, Value};
+use std::fmt;
+use std::io::{Read, Write};
+
+const PROOFS_FILE: &str = "proofs.json";
+/// Bumped whenever `proofs.json` changes
+pub const PROOFS_VERSION: u32 = 1;
+
+/// Reason a set cannot be signed or a signed archive cannot be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignoffError {
+    /// More vectors than the prover's domain has points
+    TooManyVectors { vectors: usize, capacity: usize },
+    /// The archive has no `proofs.json`
+    MissingProofs,
+    /// A `proofs.json` field that does not decode
+    Malformed(&'static str),
+}
+
+impl fmt::Display for SignoffError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignoffError::TooManyVectors { vectors, capacity } => {
+                write!(f, "{} vectors exceed the domain size {}", vectors, capacity)
+            }
+            SignoffError::MissingProofs => write!(f, "archive has no {}", PROOFS_FILE),
+            SignoffError::Malformed(field) => write!(f, "malformed {} in {}", field, PROOFS_FILE),
+        }
+    }
+}
+
+impl std::error::Error for SignoffError {}
+
+/// A vector set with a commitment to its expected outputs and one opening
+/// per vector
+#[derive(Debug, Clone)]
+pub struct SignedVectors {
+    pub set: VectorSet,
+    pub log_n: usize,
+    /// Seed the setup is drawn from
+    pub setup_seed: Seed,
+    pub commitment: G1Affine,
+    pub openings: Vec<OpeningProof>,
+}
+
+impl SignedVectors {
+    /// Commit to `set`'s expected outputs and open every one; `prover` must
+    /// use the setup drawn from `setup_seed`
+    pub fn sign(prover: &Prover, setup_seed: Seed, set: VectorSet) -> Result<Self> {
+        let capacity = prover.config().two_n();
+        if set.vectors.len() > capacity {
+            return Err(SignoffError::TooManyVectors {
+                vectors: set.vectors.len(),
+                capacity,
+            }
+            .into());
+        }
+        let outputs: Vec<_> = set.vectors.iter().map(|v| v.o.clone()).collect();
+        let hashes = TraceWitness::new(WitnessEncoding::Hash).from_machine_outputs(&outputs)?;
+        let (commitment, evals) = prover.commit_evaluations(&hashes);
+        let domain = Radix2EvaluationDomain::<Fr>::new(capacity).unwrap();
+        let openings = (0..hashes.len())
+            .map(|i| prover.create_opening_proof(&evals, domain.element(i)))
+            .collect();
+        Ok(SignedVectors {
+            set,
+            log_n: prover.config().log_n,
+            setup_seed,
+            commitment,
+            openings,
+        })
+    }
+
+    /// The setup the archive was signed under
+    pub fn setup(&self) -> Setup {
+        Setup::from_rng(Config { log_n: self.log_n }, &mut self.setup_seed.rng())
+    }
+
+    /// Check each vector's claimed output opens from the commitment at its
+    /// own domain point
+    pub fn verify(&self, verifier: &Verifier) -> Result<()> {
+        let config = &verifier.setup().config;
+        verified(config.log_n == self.log_n, "setup size")?;
+        verified(self.openings.len() == self.set.vectors.len(), "opening count")?;
+        let domain = Radix2EvaluationDomain::<Fr>::new(config.two_n()).unwrap();
+        let witness = TraceWitness::new(WitnessEncoding::Hash);
+        for (i, (vector, proof)) in self.set.vectors.iter().zip(&self.openings).enumerate() {
+            let expected = witness.from_machine_outputs(std::slice::from_ref(&vector.o))?;
+            let valid = proof.point == domain.element(i) && proof.evaluation == expected[0];
+            verified(valid && verifier.verify_opening(&self.commitment, proof), "vector output opening")?;
+        }
+        Ok(())
+    }
+
+    pub fn write_archive<W: Write>(&self, writer: W) -> Result<()> {
+        let mut commitment = Vec::new();
+        self.commitment.serialize_compressed(&mut commitment).expect("serializing to memory cannot fail");
+        let openings: Vec<String> = self
+            .openings
+            .iter()
+            .map(|proof| {
+                let mut bytes = Vec::new();
+                proof.write(&mut bytes).expect("serializing to memory cannot fail");
+                bytes_to_hex(&bytes)
+            })
+            .collect();
+        let proofs = json!({
+            "version": PROOFS_VERSION,
+            "log_n": self.log_n,
+            "setup_seed": self.setup_seed.to_string(),
+            "encoding": "sha256",
+            "commitment": bytes_to_hex(&commitment),
+            "openings": openings,
+        });
+        let proofs = serde_json::to_string_pretty(&proofs).expect("JSON values serialize") + "\n";
+        self.set.write_archive_with(writer, &[(PROOFS_FILE, proofs)])?;
+        Ok(())
+    }
+
+    /// Read a signed archive; the vector files are checked against the
+    /// manifest, the proofs only decoded
+    pub fn read_archive<R: Read>(reader: R) -> Result<Self> {
+        let (set, extra) = vectors::read_archive(reader)?;
+        let (_, proofs) = extra.iter().find(|(name, _)| name == PROOFS_FILE).ok_or(SignoffError::MissingProofs)?;
+        let proofs: Value = serde_json::from_str(proofs).map_err(|_| SignoffError::Malformed("JSON"))?;
+        if proofs["version"] != PROOFS_VERSION || proofs["encoding"] != "sha256" {
+            return Err(SignoffError::Malformed("version").into());
+        }
+
+        let field = |name: &'static str| proofs[name].as_str().ok_or(SignoffError::Malformed(name));
+        let bytes = |name: &'static str, text: &str| bytes_from_hex(text).map_err(|_| SignoffError::Malformed(name));
+        let log_n = proofs["log_n"].as_u64().ok_or(SignoffError::Malformed("log_n"))? as usize;
+        let setup_seed = field("setup_seed")?.parse().map_err(|_| SignoffError::Malformed("setup_seed"))?;
+        let commitment = G1Affine::deserialize_compressed(&bytes("commitment", field("commitment")?)?[..])
+            .map_err(|_| SignoffError::Malformed("commitment"))?;
+        let openings = proofs["openings"]
+            .as_array()
+            .ok_or(SignoffError::Malformed("openings"))?
+            .iter()
+            .map(|proof| {
+                let proof = bytes("openings", proof.as_str().unwrap_or_default())?;
+                OpeningProof::read(&proof[..]).map_err(|_| SignoffError::Malformed("openings"))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(SignedVectors {
+            set,
+            log_n,
+            setup_seed,
+            commitment,
+            openings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn test_signed_archive_round_trip_and_tamper() {
+        let setup_seed = Seed::new(1215).derive("setup");
+        let setup = Setup::from_rng(Config { log_n: 4 }, &mut setup_seed.rng());
+        let prover = Prover::new(setup.clone());
+        let verifier = Verifier::new(setup);
+
+        let signed = SignedVectors::sign(&prover, setup_seed, vectors::generate(3, 4)).unwrap();
+        let mut bytes = Vec::new();
+        signed.write_archive(&mut bytes).unwrap();
+        let read = SignedVectors::read_archive(&bytes[..]).unwrap();
+        assert_eq!(read.set.vectors, signed.set.vectors);
+        assert_eq!(read.setup_seed, setup_seed);
+        read.verify(&Verifier::new(read.setup())).unwrap();
+
+        // A vendor claiming a different output, even with a consistent
+        // manifest, fails verification
+        let mut forged = read.clone();
+        forged.set.vectors[2].o += 1u32;
+        let mut bytes = Vec::new();
+        forged.write_archive(&mut bytes).unwrap();
+        let forged = SignedVectors::read_archive(&bytes[..]).unwrap();
+        assert!(matches!(forged.verify(&verifier), Err(Error::Verification(_))));
+
+        // 21 corner cases plus 12 random vectors
+        let too_many = vectors::generate(3, 12);
+        assert!(matches!(
+            SignedVectors::sign(&prover, setup_seed, too_many),
+            Err(Error::Signoff(SignoffError::TooManyVectors { vectors: 33, capacity: 32 }))
+        ));
+    }
+}
@@ -0,0 +1,303 @@
+//! Commit to a modulo-machine execution trace with the KZG prover.
+//!
+//! Each tick becomes one row of seven field elements: clk, reset, the input
+//! in three 128-bit limbs and the output in two. P and the 300-bit inputs
+//! are wider than Fr, so limbs keep the encoding lossless. Every column is
+//! committed separately over the prover's 2n-point domain, so row `i` opens
+//! at the domain element ω^i in every column.
+//!
+//! Which rows to open is a Fiat–Shamir challenge over the commitment and the
+//! trace digest ([`TraceCommitment::challenge_rows`]), so the prover cannot
+//! steer openings away from the simulated data.
+
+use crate::convert::{fr_to_integer, integer_from_limbs, integer_to_limbs};
+use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+use bls12_381_prover::{Fr, G1Affine, OpeningProof, Prover, Verifier};
+use modulo_machine::{trace_file, ModuloMachine, TickInput};
+use rug::Integer;
+use std::fmt;
+use transcript::Transcript;
+
+/// Width of each limb column
+pub const LIMB_BITS: u32 = 128;
+const X_LIMBS: usize = 3;
+const O_LIMBS: usize = 2;
+/// clk, reset, input limbs, output limbs
+pub const COLUMNS: usize = 2 + X_LIMBS + O_LIMBS;
+
+/// Reason a trace could not be committed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceError {
+    /// More rows than the prover's domain has points
+    TooManyRows { rows: usize, capacity: usize },
+    /// A value that is negative or wider than its limb columns
+    Unencodable { row: usize },
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceError::TooManyRows { rows, capacity } => {
+                write!(f, "trace has {} rows, the domain holds {}", rows, capacity)
+            }
+            TraceError::Unencodable { row } => write!(f, "row {} has a value outside its limb columns", row),
+        }
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+/// One tick of a recorded run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceRow {
+    pub clk: bool,
+    pub reset: bool,
+    pub x: Integer,
+    /// Output after the tick
+    pub output: Integer,
+}
+
+impl TraceRow {
+    /// Run `stimulus` through `machine`, recording one row per tick
+    pub fn record(machine: &mut ModuloMachine, stimulus: &[TickInput]) -> Vec<TraceRow> {
+        stimulus
+            .iter()
+            .map(|t| TraceRow {
+                clk: t.clk,
+                reset: t.reset,
+                x: t.x.clone(),
+                output: machine.tick(t.clk, t.reset, &t.x).clone(),
+            })
+            .collect()
+    }
+
+    /// The stimulus of this tick
+    pub fn input(&self) -> TickInput {
+        TickInput::new(self.clk, self.reset, self.x.clone())
+    }
+
+    /// `trace_file::digest` of the rows
+    pub fn digest(rows: &[TraceRow]) -> [u8; 32] {
+        let inputs: Vec<TickInput> = rows.iter().map(TraceRow::input).collect();
+        trace_file::digest(inputs.iter().zip(rows.iter().map(|r| &r.output)))
+    }
+
+    fn to_fields(&self) -> Option<[Fr; COLUMNS]> {
+        let mut fields = [Fr::from(0u64); COLUMNS];
+        fields[0] = Fr::from(self.clk);
+        fields[1] = Fr::from(self.reset);
+        fields[2..2 + X_LIMBS].copy_from_slice(&integer_to_limbs::<X_LIMBS>(&self.x, LIMB_BITS).ok()?);
+        fields[2 + X_LIMBS..].copy_from_slice(&integer_to_limbs::<O_LIMBS>(&self.output, LIMB_BITS).ok()?);
+        Some(fields)
+    }
+
+    fn from_fields(fields: &[Fr]) -> Option<Self> {
+        let bit = |f: &Fr| match fr_to_integer(f).to_u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        };
+        Some(TraceRow {
+            clk: bit(&fields[0])?,
+            reset: bit(&fields[1])?,
+            x: integer_from_limbs(&fields[2..2 + X_LIMBS], LIMB_BITS).ok()?,
+            output: integer_from_limbs(&fields[2 + X_LIMBS..COLUMNS], LIMB_BITS).ok()?,
+        })
+    }
+}
+
+/// Public commitment to a trace: one KZG commitment per column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceCommitment {
+    pub rows: usize,
+    /// Size of the evaluation domain the columns were committed over
+    pub domain_size: usize,
+    pub columns: [G1Affine; COLUMNS],
+}
+
+impl TraceCommitment {
+    /// Check every column's proof for `opening`, including that each opens
+    /// at the domain point of the claimed row
+    pub fn verify_row(&self, verifier: &Verifier, opening: &RowOpening) -> bool {
+        let domain = Radix2EvaluationDomain::<Fr>::new(self.domain_size).unwrap();
+        let point = domain.element(opening.row);
+        opening.row < self.rows
+            && opening.proofs.len() == COLUMNS
+            && opening
+                .proofs
+                .iter()
+                .zip(&self.columns)
+                .all(|(proof, column)| proof.point == point && verifier.verify_opening(column, proof))
+    }
+
+    /// `count` rows to open, derived from this commitment and the digest of
+    /// the trace it claims to commit to ([`TraceRow::digest`])
+    pub fn challenge_rows(&self, trace_digest: &[u8; 32], count: usize) -> Vec<usize> {
+        if self.rows == 0 {
+            return Vec::new();
+        }
+        let mut transcript = Transcript::new(b"sasquatch-trace-rows-v1");
+        transcript.append_u64(self.rows as u64);
+        transcript.append_u64(self.domain_size as u64);
+        transcript.append_points(&self.columns);
+        transcript.append_trace_digest(trace_digest);
+        (0..count).map(|_| transcript.challenge_index(self.rows)).collect()
+    }
+}
+
+/// Opening of every column at one row
+#[derive(Debug, Clone)]
+pub struct RowOpening {
+    pub row: usize,
+    /// One proof per column, in column order
+    pub proofs: Vec<OpeningProof>,
+}
+
+impl RowOpening {
+    /// The row the proofs claim, or `None` if an evaluation is not a valid
+    /// encoding
+    pub fn decode(&self) -> Option<TraceRow> {
+        if self.proofs.len() != COLUMNS {
+            return None;
+        }
+        let fields: Vec<Fr> = self.proofs.iter().map(|p| p.evaluation).collect();
+        TraceRow::from_fields(&fields)
+    }
+}
+
+/// A trace committed by the prover, able to open any row
+pub struct CommittedTrace<'a> {
+    prover: &'a Prover,
+    /// Padded evaluations per column
+    columns: Vec<Vec<Fr>>,
+    commitment: TraceCommitment,
+}
+
+impl<'a> CommittedTrace<'a> {
+    pub fn commit(prover: &'a Prover, rows: &[TraceRow]) -> Result<Self, TraceError> {
+        let capacity = prover.config().two_n();
+        if rows.len() > capacity {
+            return Err(TraceError::TooManyRows {
+                rows: rows.len(),
+                capacity,
+            });
+        }
+
+        let mut values: Vec<Vec<Fr>> = (0..COLUMNS).map(|_| Vec::with_capacity(rows.len())).collect();
+        for (i, row) in rows.iter().enumerate() {
+            let fields = row.to_fields().ok_or(TraceError::Unencodable { row: i })?;
+            for (column, field) in values.iter_mut().zip(fields) {
+                column.push(field);
+            }
+        }
+
+        let mut columns = Vec::with_capacity(COLUMNS);
+        let mut commitments = [G1Affine::default(); COLUMNS];
+        for (commitment, column) in commitments.iter_mut().zip(&values) {
+            let (c, evals) = prover.commit_evaluations(column);
+            *commitment = c;
+            columns.push(evals);
+        }
+
+        Ok(Self {
+            prover,
+            columns,
+            commitment: TraceCommitment {
+                rows: rows.len(),
+                domain_size: capacity,
+                columns: commitments,
+            },
+        })
+    }
+
+    pub fn commitment(&self) -> &TraceCommitment {
+        &self.commitment
+    }
+
+    /// Prove the contents of row `i`, or `None` past the end of the trace
+    pub fn open_trace_row(&self, i: usize) -> Option<RowOpening> {
+        if i >= self.commitment.rows {
+            return None;
+        }
+        let domain = Radix2EvaluationDomain::<Fr>::new(self.commitment.domain_size).unwrap();
+        let point = domain.element(i);
+        let proofs = self
+            .columns
+            .iter()
+            .map(|evals| self.prover.create_opening_proof(evals, point))
+            .collect();
+        Some(RowOpening { row: i, proofs })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls12_381_prover::{Config, Setup};
+
+    fn stimulus() -> Vec<TickInput> {
+        let p = ModuloMachine::new().get_prime().clone();
+        let x = Integer::from(&p + 42u32);
+        vec![
+            TickInput::new(false, true, Integer::new()),
+            TickInput::new(false, false, x.clone()),
+            TickInput::new(true, false, x),
+            TickInput::new(false, false, ModuloMachine::create_large_input(299, 7)),
+            TickInput::new(true, false, ModuloMachine::create_large_input(299, 7)),
+        ]
+    }
+
+    #[test]
+    fn test_open_trace_row() {
+        let setup = Setup::new(Config { log_n: 4 });
+        let prover = Prover::new(setup.clone());
+        let verifier = Verifier::new(setup);
+
+        let rows = TraceRow::record(&mut ModuloMachine::new(), &stimulus());
+        let trace = CommittedTrace::commit(&prover, &rows).unwrap();
+        let commitment = trace.commitment();
+
+        for i in [2, 4] {
+            let opening = trace.open_trace_row(i).unwrap();
+            assert!(commitment.verify_row(&verifier, &opening));
+            assert_eq!(opening.decode().unwrap(), rows[i]);
+        }
+        assert_eq!(rows[2].output, 42);
+        assert!(trace.open_trace_row(rows.len()).is_none());
+
+        // An opening relabelled as another row fails
+        let mut opening = trace.open_trace_row(2).unwrap();
+        opening.row = 1;
+        assert!(!commitment.verify_row(&verifier, &opening));
+
+        // Challenged rows follow the simulated data, not just the commitment
+        let digest = TraceRow::digest(&rows);
+        let challenged = commitment.challenge_rows(&digest, 4);
+        assert_eq!(challenged, commitment.challenge_rows(&digest, 4));
+        assert!(challenged.iter().all(|&i| i < rows.len()));
+        let mut other = rows.clone();
+        other[4].output += 1u32;
+        assert_ne!(challenged, commitment.challenge_rows(&TraceRow::digest(&other), 4));
+    }
+
+    #[test]
+    fn test_commit_rejects_bad_traces() {
+        let prover = Prover::new(Setup::new(Config { log_n: 1 }));
+        let rows = TraceRow::record(&mut ModuloMachine::new(), &stimulus());
+        assert_eq!(
+            CommittedTrace::commit(&prover, &rows).err(),
+            Some(TraceError::TooManyRows { rows: 5, capacity: 4 })
+        );
+
+        let negative = TraceRow {
+            clk: true,
+            reset: false,
+            x: Integer::from(-1),
+            output: Integer::new(),
+        };
+        assert_eq!(
+            CommittedTrace::commit(&prover, &[negative]).err(),
+            Some(TraceError::Unencodable { row: 0 })
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Field-element witnesses from machine outputs.
+//!
+//! Outputs are below P, a 256-bit modulus, while Fr's modulus r is just
+//! under 2^255, so an output does not always fit one field element. Rather
+//! than reduce silently, [`TraceWitness`] makes the caller pick how outputs
+//! are encoded.
+
+use crate::convert::{fr_to_integer, integer_from_limbs, integer_to_bytes, integer_to_fr, integer_to_limbs, ConvertError};
+use bls12_381_prover::{Fr, PrimeField};
+use rug::Integer;
+use sha2::{Digest, Sha256};
+
+/// Output bus width
+const O_BITS: u32 = 256;
+
+/// How each machine output becomes field elements
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WitnessEncoding {
+    /// One element per output; outputs `>= r` are an error. Lossless.
+    Strict,
+    /// Two elements per output, the low then the high 128 bits. Lossless
+    /// for every output.
+    Limbs,
+    /// One element per output, `output mod r`. Outputs in `[r, P)` collide
+    /// with smaller ones, so the witness cannot be decoded.
+    ReduceModR,
+    /// SHA-256 of the 32-byte big-endian output, reduced mod r. For
+    /// committing to outputs without revealing them; cannot be decoded.
+    Hash,
+}
+
+/// Adapter from machine outputs to prover witnesses under one encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceWitness {
+    encoding: WitnessEncoding,
+}
+
+impl TraceWitness {
+    pub fn new(encoding: WitnessEncoding) -> Self {
+        Self { encoding }
+    }
+
+    pub fn encoding(&self) -> WitnessEncoding {
+        self.encoding
+    }
+
+    /// Field elements each output occupies
+    pub fn width(&self) -> usize {
+        match self.encoding {
+            WitnessEncoding::Limbs => 2,
+            _ => 1,
+        }
+    }
+
+    /// Encode `outputs`; outputs must fit the 256-bit output bus
+    pub fn from_machine_outputs(&self, outputs: &[Integer]) -> Result<Vec<Fr>, ConvertError> {
+        let mut witness = Vec::with_capacity(outputs.len() * self.width());
+        for output in outputs {
+            let bytes: [u8; 32] = integer_to_bytes(output)?;
+            match self.encoding {
+                WitnessEncoding::Strict => witness.push(integer_to_fr(output)?),
+                WitnessEncoding::Limbs => witness.extend(integer_to_limbs::<2>(output, O_BITS / 2)?),
+                WitnessEncoding::ReduceModR => witness.push(Fr::from_be_bytes_mod_order(&bytes)),
+                WitnessEncoding::Hash => witness.push(Fr::from_be_bytes_mod_order(&Sha256::digest(bytes))),
+            }
+        }
+        Ok(witness)
+    }
+
+    /// Decode a witness back to outputs; `None` for the lossy encodings or a
+    /// witness that is not a valid encoding
+    pub fn to_machine_outputs(&self, witness: &[Fr]) -> Option<Vec<Integer>> {
+        match self.encoding {
+            WitnessEncoding::Strict => Some(witness.iter().map(fr_to_integer).collect()),
+            WitnessEncoding::Limbs => {
+                if !witness.len().is_multiple_of(2) {
+                    return None;
+                }
+                witness
+                    .chunks(2)
+                    .map(|limbs| integer_from_limbs(limbs, O_BITS / 2).ok())
+                    .collect()
+            }
+            WitnessEncoding::ReduceModR | WitnessEncoding::Hash => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::fr_modulus;
+    use modulo_machine::ModuloMachine;
+
+    #[test]
+    fn test_round_trips() {
+        let mut machine = ModuloMachine::new();
+        let p = machine.get_prime().clone();
+        let outputs = machine.process_values(&[Integer::from(12345), Integer::from(&p - 1u32)]);
+        // P - 1 is above r, as is any output in [r, P)
+        assert!(outputs[1] >= fr_modulus());
+
+        let limbs = TraceWitness::new(WitnessEncoding::Limbs);
+        let witness = limbs.from_machine_outputs(&outputs).unwrap();
+        assert_eq!(witness.len(), 4);
+        assert_eq!(limbs.to_machine_outputs(&witness), Some(outputs.clone()));
+
+        let strict = TraceWitness::new(WitnessEncoding::Strict);
+        let small = strict.from_machine_outputs(&outputs[..1]).unwrap();
+        assert_eq!(strict.to_machine_outputs(&small).as_deref(), Some(&outputs[..1]));
+        assert!(strict.from_machine_outputs(&outputs).is_err());
+    }
+
+    #[test]
+    fn test_lossy_encodings_do_not_decode() {
+        let r = fr_modulus();
+        let reduce = TraceWitness::new(WitnessEncoding::ReduceModR);
+        let witness = reduce.from_machine_outputs(&[Integer::from(&r + 3u32)]).unwrap();
+        assert_eq!(witness, [Fr::from(3u64)]);
+        assert_eq!(reduce.to_machine_outputs(&witness), None);
+
+        let hash = TraceWitness::new(WitnessEncoding::Hash);
+        let a = hash.from_machine_outputs(&[Integer::from(3)]).unwrap();
+        assert_ne!(a, [Fr::from(3u64)]);
+        assert_eq!(hash.to_machine_outputs(&a), None);
+
+        // Nothing wider than the output bus is encoded, whatever the policy
+        let wide = Integer::from(1) << 256u32;
+        for encoding in [WitnessEncoding::Limbs, WitnessEncoding::ReduceModR, WitnessEncoding::Hash] {
+            let witness = TraceWitness::new(encoding);
+            assert!(witness.from_machine_outputs(std::slice::from_ref(&wide)).is_err());
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Fiat–Shamir transcript over SHA-256, shared by every protocol in the
+//! workspace.
+//!
+//! Besides prover messages it absorbs modulo-machine trace digests
+//! (`modulo_machine::trace_file::digest`), so challenges that pick which
+//! trace rows to open are derived from the simulated data itself. The crate
+//! depends on neither subsystem, so either can use it.
+
+use ark_bls12_381::{Fr, G1Affine};
+use ark_ff::{BigInteger, PrimeField};
+use ark_serialize::CanonicalSerialize;
+use sha2::{Digest, Sha256};
+
+/// Running hash of everything the prover has sent; challenges are derived
+/// from it so the proof is non-interactive
+#[derive(Clone)]
+pub struct Transcript {
+    hasher: Sha256,
+}
+
+impl Transcript {
+    /// Start a transcript under a protocol label, so challenges of different
+    /// protocols never coincide
+    pub fn new(label: &[u8]) -> Self {
+        let mut transcript = Self { hasher: Sha256::new() };
+        transcript.append_bytes(label);
+        transcript
+    }
+
+    /// Absorb length-prefixed bytes
+    pub fn append_bytes(&mut self, bytes: &[u8]) {
+        self.hasher.update((bytes.len() as u64).to_le_bytes());
+        self.hasher.update(bytes);
+    }
+
+    pub fn append_u64(&mut self, value: u64) {
+        self.append_bytes(&value.to_le_bytes());
+    }
+
+    pub fn append_scalar(&mut self, scalar: &Fr) {
+        self.append_serialized(scalar);
+    }
+
+    pub fn append_point(&mut self, point: &G1Affine) {
+        self.append_serialized(point);
+    }
+
+    pub fn append_points<'a>(&mut self, points: impl IntoIterator<Item = &'a G1Affine>) {
+        for point in points {
+            self.append_point(point);
+        }
+    }
+
+    /// Absorb the SHA-256 digest of a machine trace, tagged so it cannot be
+    /// confused with other 32-byte messages
+    pub fn append_trace_digest(&mut self, digest: &[u8; 32]) {
+        self.append_bytes(b"trace-digest");
+        self.append_bytes(digest);
+    }
+
+    /// Derive a challenge and absorb it, so successive challenges differ
+    pub fn challenge(&mut self) -> Fr {
+        let digest = self.hasher.clone().finalize();
+        let challenge = Fr::from_be_bytes_mod_order(&digest);
+        self.append_scalar(&challenge);
+        challenge
+    }
+
+    /// Derive a challenge index in `[0, bound)`; the bias is below
+    /// `bound / 2^64`
+    pub fn challenge_index(&mut self, bound: usize) -> usize {
+        assert!(bound > 0, "empty index range");
+        let bytes = self.challenge().into_bigint().to_bytes_le();
+        let low = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+        (low % bound as u64) as usize
+    }
+
+    fn append_serialized(&mut self, value: &impl CanonicalSerialize) {
+        let mut bytes = Vec::new();
+        value.serialize_compressed(&mut bytes).expect("serializing to memory cannot fail");
+        self.append_bytes(&bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_challenges_depend_on_everything_absorbed() {
+        let mut a = Transcript::new(b"test");
+        let mut b = Transcript::new(b"test");
+        assert_eq!(a.challenge(), b.challenge());
+        assert_ne!(a.challenge(), Transcript::new(b"test").challenge());
+
+        a.append_scalar(&Fr::from(1u64));
+        b.append_scalar(&Fr::from(2u64));
+        assert_ne!(a.challenge(), b.challenge());
+    }
+
+    #[test]
+    fn test_trace_digests_bind_challenge_indices() {
+        let indices = |digest: &[u8; 32]| {
+            let mut t = Transcript::new(b"test");
+            t.append_trace_digest(digest);
+            (0..8).map(|_| t.challenge_index(1000)).collect::<Vec<_>>()
+        };
+        assert_eq!(indices(&[1; 32]), indices(&[1; 32]));
+        assert_ne!(indices(&[1; 32]), indices(&[2; 32]));
+        assert!(indices(&[1; 32]).iter().all(|&i| i < 1000));
+
+        // A digest is not interchangeable with the same raw bytes
+        let mut a = Transcript::new(b"test");
+        let mut b = Transcript::new(b"test");
+        a.append_trace_digest(&[3; 32]);
+        b.append_bytes(&[3; 32]);
+        assert_ne!(a.challenge(), b.challenge());
+    }
+}